@@ -0,0 +1,385 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Packing several files and directories into a single archive encoded as one video.
+//! The archive starts with an index of its entries, directly following the header frames,
+//! followed by the contents of every entry in index order:
+//! - Bytes 0-3: Number of entries (little-endian)
+//! - Per entry: Path length (u16, little-endian), UTF-8 path with `/` separators,
+//!   content length (u64, little-endian)
+//! - Contents of every entry, concatenated
+//!
+//! Only files are stored, directories are recreated from the paths of the files in them.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    constants::{BYTES_U32, BYTES_U64},
+    filesys::write_output_file,
+};
+
+/// Bytes of the path length before every path in the index.
+const PATH_LEN_BYTES: usize = (u16::BITS / u8::BITS) as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// File stored in an archive, as listed in its index.
+pub struct ArchiveEntry {
+    /// Path of the file relative to the directory the archive is unpacked into,
+    /// components separated by `/`.
+    pub path: String,
+    /// Offset of the file contents in the archive.
+    pub offset: usize,
+    /// Length of the file contents in bytes.
+    pub len: usize,
+}
+
+/// Packs files and directories into an archive.
+/// Files are stored under their file name, directories with all files below them,
+/// under paths starting with the name of the directory.
+///
+/// # Arguments
+/// * `paths` - Files and directories to pack.
+///
+/// # Errors
+/// Fails if a path can not be read, has no UTF-8 file name,
+/// or two files would be stored under the same path.
+pub fn pack_paths(paths: &[PathBuf]) -> Result<Vec<u8>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            bail!(
+                "Unable to archive {}, its name is missing or not valid UTF-8.",
+                path.display()
+            );
+        };
+        collect_files(path, name.to_string(), &mut files)?;
+    }
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if let Some(pair) = files.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        bail!("Two archived files would be stored as {}.", pair[0].0);
+    }
+
+    let mut index = Vec::new();
+    let mut contents = Vec::new();
+    index.extend_from_slice(&u32::try_from(files.len())?.to_le_bytes());
+    for (archive_path, path) in files {
+        let data = fs::read(&path)
+            .with_context(|| format!("Unable to read archived file {}.", path.display()))?;
+        let Ok(path_len) = u16::try_from(archive_path.len()) else {
+            bail!("Archived path {archive_path} is too long.");
+        };
+        index.extend_from_slice(&path_len.to_le_bytes());
+        index.extend_from_slice(archive_path.as_bytes());
+        index.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        contents.extend_from_slice(&data);
+    }
+    index.extend_from_slice(&contents);
+    Ok(index)
+}
+
+/// Adds the file at `path` or all files below the directory at `path` to `files`,
+/// paired with the path they are stored under in the archive.
+/// Entries that are neither files nor directories are skipped with a warning.
+///
+/// # Arguments
+/// * `path` - File or directory to add.
+/// * `archive_path` - Path `path` is stored under in the archive.
+/// * `files` - Files collected so far.
+fn collect_files(
+    path: &Path,
+    archive_path: String,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<()> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("Unable to read {}.", path.display()))?;
+    if metadata.is_file() {
+        files.push((archive_path, path.to_path_buf()));
+    } else if metadata.is_dir() {
+        let entries = fs::read_dir(path)
+            .with_context(|| format!("Unable to read directory {}.", path.display()))?;
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("Unable to read directory {}.", path.display()))?;
+            let Ok(name) = entry.file_name().into_string() else {
+                bail!(
+                    "Unable to archive {}, its name is not valid UTF-8.",
+                    entry.path().display()
+                );
+            };
+            collect_files(&entry.path(), format!("{archive_path}/{name}"), files)?;
+        }
+    } else {
+        eprintln!(
+            "Skipping {}, it is neither a file nor a directory.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Reads the index at the start of an archive created by `pack_paths`.
+///
+/// # Arguments
+/// * `archive` - The whole archive.
+///
+/// # Errors
+/// Fails if the index is truncated, holds a path that is not valid UTF-8
+/// or an entry reaches past the end of the archive.
+pub fn read_index(archive: &[u8]) -> Result<Vec<ArchiveEntry>> {
+    let mut position = 0;
+    let mut take = |len: usize| -> Result<&[u8]> {
+        let Some(bytes) = archive.get(position..position + len) else {
+            bail!("Archive index is truncated.");
+        };
+        position += len;
+        Ok(bytes)
+    };
+    let entry_count = u32::from_le_bytes(take(BYTES_U32)?.try_into()?);
+    let mut paths_and_lens = Vec::new();
+    for _ in 0..entry_count {
+        let path_len = u16::from_le_bytes(take(PATH_LEN_BYTES)?.try_into()?);
+        let path = String::from_utf8(take(path_len as usize)?.to_vec())
+            .context("Archived path is not valid UTF-8.")?;
+        let len = usize::try_from(u64::from_le_bytes(take(BYTES_U64)?.try_into()?))?;
+        paths_and_lens.push((path, len));
+    }
+
+    let mut offset = position;
+    let mut entries = Vec::with_capacity(paths_and_lens.len());
+    for (path, len) in paths_and_lens {
+        if offset
+            .checked_add(len)
+            .is_none_or(|end| end > archive.len())
+        {
+            bail!("Archived file {path} reaches past the end of the archive.");
+        }
+        entries.push(ArchiveEntry { path, offset, len });
+        offset += len;
+    }
+    Ok(entries)
+}
+
+/// Unpacks an archive created by `pack_paths` into the directory at `dir`,
+/// creating it and any directories below it as needed.
+/// Every path is checked before anything is written,
+/// so a crafted archive can not write outside of `dir`.
+/// Returns the paths of the written files.
+///
+/// # Arguments
+/// * `archive` - The whole archive.
+/// * `dir` - Directory to unpack into.
+/// * `overwrite` - If existing files may be overwritten.
+///
+/// # Errors
+/// Fails if the index is invalid, a path would leave `dir`, a file exists
+/// and overwrite is not enabled, or a file can not be written.
+pub fn unpack_archive(archive: &[u8], dir: &Path, overwrite: bool) -> Result<Vec<PathBuf>> {
+    let entries = read_index(archive)?;
+    let mut targets = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let relative = Path::new(&entry.path);
+        // An empty path would name `dir` itself.
+        if entry.path.contains('\\')
+            || relative.components().next().is_none()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!(
+                "Archived path {} would leave the output directory.",
+                entry.path
+            );
+        }
+        let target = dir.join(relative);
+        if !overwrite && target.exists() {
+            bail!(
+                "File at {} exists and overwrite is not enabled.",
+                target.display()
+            );
+        }
+        targets.push(target);
+    }
+
+    for (entry, target) in entries.iter().zip(&targets) {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create directory {}.", parent.display()))?;
+        }
+        write_output_file(target, &archive[entry.offset..entry.offset + entry.len])?;
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory for the files of a single test, removed when dropped.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        /// Creates the directory, named after the test so parallel tests do not share one.
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("vortexkey_archive_{name}_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Archive holding `files` as (path, contents), written without the checks of `pack_paths`
+    /// like a crafted archive would be.
+    fn raw_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = u32::try_from(files.len()).unwrap().to_le_bytes().to_vec();
+        for (path, contents) in files {
+            archive.extend_from_slice(&u16::try_from(path.len()).unwrap().to_le_bytes());
+            archive.extend_from_slice(path.as_bytes());
+            archive.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        }
+        for (_, contents) in files {
+            archive.extend_from_slice(contents);
+        }
+        archive
+    }
+
+    #[test]
+    fn directory_round_trips() {
+        let dir = TestDir::new("round_trip");
+        let source = dir.0.join("docs");
+        fs::create_dir_all(source.join("nested/deeper")).unwrap();
+        fs::write(source.join("readme.txt"), b"read me").unwrap();
+        fs::write(source.join("nested/empty.bin"), b"").unwrap();
+        fs::write(source.join("nested/deeper/data.bin"), [7u8; 300]).unwrap();
+        let single = dir.0.join("single.txt");
+        fs::write(&single, b"single file").unwrap();
+
+        let archive = pack_paths(&[source, single]).unwrap();
+        let paths: Vec<_> = read_index(&archive)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "docs/nested/deeper/data.bin",
+                "docs/nested/empty.bin",
+                "docs/readme.txt",
+                "single.txt",
+            ]
+        );
+
+        let output = dir.0.join("output");
+        let written = unpack_archive(&archive, &output, false).unwrap();
+        assert_eq!(written.len(), 4);
+        assert_eq!(
+            fs::read(output.join("docs/readme.txt")).unwrap(),
+            b"read me"
+        );
+        assert_eq!(fs::read(output.join("docs/nested/empty.bin")).unwrap(), b"");
+        assert_eq!(
+            fs::read(output.join("docs/nested/deeper/data.bin")).unwrap(),
+            [7u8; 300]
+        );
+        assert_eq!(fs::read(output.join("single.txt")).unwrap(), b"single file");
+    }
+
+    #[test]
+    fn empty_directories_are_not_stored() {
+        let dir = TestDir::new("empty_dirs");
+        let source = dir.0.join("tree");
+        fs::create_dir_all(source.join("empty")).unwrap();
+        fs::create_dir_all(source.join("full")).unwrap();
+        fs::write(source.join("full/file.txt"), b"content").unwrap();
+
+        let archive = pack_paths(std::slice::from_ref(&source)).unwrap();
+        let entries = read_index(&archive).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "tree/full/file.txt");
+        let output = dir.0.join("output");
+        unpack_archive(&archive, &output, false).unwrap();
+        assert!(output.join("tree/full/file.txt").is_file());
+        assert!(!output.join("tree/empty").exists());
+
+        // A directory holding no files at all packs to an archive without entries.
+        let archive = pack_paths(&[source.join("empty")]).unwrap();
+        assert_eq!(archive, 0u32.to_le_bytes());
+        assert!(unpack_archive(&archive, &output, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn paths_leaving_the_output_directory_are_rejected() {
+        let dir = TestDir::new("traversal");
+        let output = dir.0.join("output");
+        let outside = dir.0.join("outside.txt");
+        for path in [
+            "../outside.txt",
+            "nested/../../outside.txt",
+            "./outside.txt",
+            outside.to_str().unwrap(),
+            "/etc/vortexkey_archive_test",
+            "..\\outside.txt",
+            "",
+        ] {
+            // A valid entry first, so nothing may be written before the bad path is found.
+            let archive = raw_archive(&[("valid.txt", b"valid"), (path, b"escaped")]);
+            let err = unpack_archive(&archive, &output, true).unwrap_err();
+            assert!(
+                err.to_string().contains("would leave the output directory"),
+                "{path:?}: {err}"
+            );
+            assert!(!output.join("valid.txt").exists(), "{path:?}");
+        }
+        assert!(!outside.exists());
+    }
+
+    #[test]
+    fn existing_files_are_only_overwritten_if_enabled() {
+        let dir = TestDir::new("overwrite");
+        fs::write(dir.0.join("file.txt"), b"existing").unwrap();
+        let archive = raw_archive(&[("file.txt", b"unpacked")]);
+        assert!(unpack_archive(&archive, &dir.0, false).is_err());
+        assert_eq!(fs::read(dir.0.join("file.txt")).unwrap(), b"existing");
+        unpack_archive(&archive, &dir.0, true).unwrap();
+        assert_eq!(fs::read(dir.0.join("file.txt")).unwrap(), b"unpacked");
+    }
+
+    #[test]
+    fn truncated_archives_are_rejected() {
+        let archive = raw_archive(&[("a.txt", b"first"), ("b.txt", b"second")]);
+        for len in 0..archive.len() {
+            assert!(read_index(&archive[..len]).is_err(), "{len} bytes");
+        }
+        assert_eq!(read_index(&archive).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn duplicate_paths_are_rejected() {
+        let dir = TestDir::new("duplicates");
+        let first = dir.0.join("first/same.txt");
+        let second = dir.0.join("second/same.txt");
+        for path in [&first, &second] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"same name").unwrap();
+        }
+        assert!(pack_paths(&[first, second]).is_err());
+    }
+}
@@ -0,0 +1,663 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Video backends used to turn frames into a video and back.
+
+use std::{
+    fmt::{self, Debug},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use image::DynamicImage;
+
+use crate::{
+    constants::{
+        AV1_CRF, COLOR_RANGE, COLORSPACE, H264_CRF, H264_MAX_CRF, H264_PRESET, UPSCALE_ALGORITHM,
+        VP9_AV1_MAX_CRF, VP9_CRF,
+    },
+    ffmpeg::FfmpegBackend,
+    filesys::FrameBuffer,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Parameters a backend needs to combine data frames into a video.
+pub struct EncodeSettings {
+    /// Rate at which the data frames are read.
+    pub data_fps: u32,
+    /// Framerate of the output video.
+    pub video_fps: u32,
+    /// Width of the output video in pixels.
+    pub frame_width: u32,
+    /// Height of the output video in pixels.
+    pub frame_height: u32,
+    /// Width of the data frames in pixels.
+    pub data_width: u32,
+    /// Height of the data frames in pixels.
+    pub data_height: u32,
+    /// If an existing output file is overwritten instead of failing the encode.
+    pub overwrite: bool,
+    /// If the frames hold data in the alpha channel, which the video has to keep.
+    pub alpha: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Codec and quality settings of the video encoder.
+/// The CRF decides how much detail survives compression
+/// and with it how robust the data blocks are.
+pub struct EncoderOptions {
+    /// Codec the video is encoded with.
+    pub codec: VideoCodec,
+    /// Constant rate factor, 0 is lossless and `VideoCodec::max_crf` the worst quality.
+    pub crf: u32,
+    /// Encoder preset, trading encoding speed for file size.
+    pub preset: EncoderPreset,
+    /// Hardware encoder used instead of the software encoder of `codec`, if any.
+    pub hwaccel: Option<HwAccel>,
+    /// Scaling algorithm used to upscale the data frames to the video resolution.
+    pub scale_algorithm: &'static str,
+    /// Colorspace and range the frames are converted to and the video is tagged with.
+    pub color: VideoColor,
+}
+
+impl Default for EncoderOptions {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::H264,
+            crf: H264_CRF,
+            preset: H264_PRESET,
+            hwaccel: None,
+            scale_algorithm: UPSCALE_ALGORITHM,
+            color: VideoColor::default(),
+        }
+    }
+}
+
+impl EncoderOptions {
+    /// Creates encoder options.
+    ///
+    /// # Arguments
+    /// * `codec` - Codec the video is encoded with.
+    /// * `crf` - Constant rate factor, the default of `codec` if `None`.
+    /// * `preset` - Encoder preset, trading encoding speed for file size.
+    /// * `hwaccel` - Hardware encoder to use, the software encoder if `None`.
+    ///
+    /// # Errors
+    /// Fails if `crf` is above the highest CRF of `codec`.
+    pub fn new(
+        codec: VideoCodec,
+        crf: Option<u32>,
+        preset: EncoderPreset,
+        hwaccel: Option<HwAccel>,
+    ) -> Result<Self> {
+        let crf = crf.unwrap_or(codec.default_crf());
+        if crf > codec.max_crf() {
+            bail!(
+                "CRF {crf} is out of range 0-{} for {codec:?}.",
+                codec.max_crf()
+            );
+        }
+        Ok(Self {
+            codec,
+            crf,
+            preset,
+            hwaccel,
+            scale_algorithm: UPSCALE_ALGORITHM,
+            color: VideoColor::default(),
+        })
+    }
+
+    #[must_use]
+    /// Sets the scaling algorithm used to upscale the data frames to the video resolution,
+    /// `UPSCALE_ALGORITHM` by default.
+    ///
+    /// # Arguments
+    /// * `scale_algorithm` - Name of the ffmpeg scaling algorithm, one of `SCALE_ALGORITHMS`.
+    pub fn with_scale_algorithm(mut self, scale_algorithm: &'static str) -> Self {
+        self.scale_algorithm = scale_algorithm;
+        self
+    }
+
+    #[must_use]
+    /// Sets the colorspace and range of the video, `VideoColor::default` by default.
+    ///
+    /// # Arguments
+    /// * `color` - Colorspace and range the frames are converted to.
+    pub fn with_color(mut self, color: VideoColor) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Color properties of a video, deciding how the RGB frames are converted to YUV and back.
+/// Decoding has to convert back with the same ones, otherwise every level is read shifted.
+pub struct VideoColor {
+    /// Colorspace of the video.
+    pub colorspace: Colorspace,
+    /// Range of pixel values the video uses.
+    pub range: ColorRange,
+}
+
+impl Default for VideoColor {
+    fn default() -> Self {
+        Self {
+            colorspace: COLORSPACE,
+            range: COLOR_RANGE,
+        }
+    }
+}
+
+impl fmt::Display for VideoColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} range", self.colorspace.name(), self.range.name())
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Colorspaces a video can be encoded in, each setting the
+/// matrix, primaries and transfer characteristics together.
+pub enum Colorspace {
+    /// ITU-R BT.709, the HD colorspace video platforms expect.
+    Bt709,
+    #[value(alias = "smpte170m")]
+    /// ITU-R BT.601, the SD colorspace, which ffmpeg assumes for untagged videos.
+    Bt601,
+    /// ITU-R BT.2020 with the non-constant luminance matrix, used for UHD.
+    Bt2020,
+}
+
+impl Colorspace {
+    #[must_use]
+    /// Name of the colorspace as accepted by `--colorspace`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Colorspace::Bt709 => "bt709",
+            Colorspace::Bt601 => "bt601",
+            Colorspace::Bt2020 => "bt2020",
+        }
+    }
+
+    #[must_use]
+    /// Value of the ffmpeg `-colorspace` option, the YUV matrix the video is tagged with.
+    pub fn matrix(self) -> &'static str {
+        match self {
+            Colorspace::Bt709 => "bt709",
+            Colorspace::Bt601 => "smpte170m",
+            Colorspace::Bt2020 => "bt2020nc",
+        }
+    }
+
+    #[must_use]
+    /// Value of the ffmpeg `-color_primaries` option.
+    pub fn primaries(self) -> &'static str {
+        match self {
+            Colorspace::Bt709 => "bt709",
+            Colorspace::Bt601 => "smpte170m",
+            Colorspace::Bt2020 => "bt2020",
+        }
+    }
+
+    #[must_use]
+    /// Value of the ffmpeg `-color_trc` option.
+    pub fn transfer(self) -> &'static str {
+        match self {
+            Colorspace::Bt709 => "bt709",
+            Colorspace::Bt601 => "smpte170m",
+            Colorspace::Bt2020 => "bt2020-10",
+        }
+    }
+
+    #[must_use]
+    /// Value of the `in_color_matrix` and `out_color_matrix` options of the ffmpeg scale filter,
+    /// which converts between RGB and YUV.
+    pub fn scale_matrix(self) -> &'static str {
+        match self {
+            Colorspace::Bt709 => "bt709",
+            Colorspace::Bt601 => "bt601",
+            Colorspace::Bt2020 => "bt2020",
+        }
+    }
+
+    #[must_use]
+    /// Colorspace of a video tagged with the YUV matrix `matrix` as reported by ffprobe,
+    /// `None` for untagged videos and other matrices.
+    ///
+    /// # Arguments
+    /// * `matrix` - The `color_space` ffprobe reports for the stream.
+    pub fn from_matrix(matrix: &str) -> Option<Self> {
+        match matrix {
+            "bt709" => Some(Colorspace::Bt709),
+            // PAL videos are tagged bt470bg, which uses the same matrix.
+            "smpte170m" | "bt470bg" => Some(Colorspace::Bt601),
+            "bt2020nc" => Some(Colorspace::Bt2020),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    /// Value identifying the colorspace in the header.
+    /// BT.709 is zero, so headers written before the colorspace was stored read as BT.709.
+    pub fn to_code(self) -> u8 {
+        match self {
+            Colorspace::Bt709 => 0,
+            Colorspace::Bt601 => 1,
+            Colorspace::Bt2020 => 2,
+        }
+    }
+
+    /// Reads the colorspace identified by `code` as written by `to_code`.
+    ///
+    /// # Arguments
+    /// * `code` - Value read from the header.
+    ///
+    /// # Errors
+    /// Fails if `code` does not name a known colorspace.
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(Colorspace::Bt709),
+            1 => Ok(Colorspace::Bt601),
+            2 => Ok(Colorspace::Bt2020),
+            _ => bail!("Unknown colorspace {code}."),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Range of pixel values a video uses.
+pub enum ColorRange {
+    #[value(alias = "limited", alias = "mpeg")]
+    /// Limited range, luma from 16 to 235 and chroma from 16 to 240.
+    Tv,
+    #[value(alias = "full", alias = "jpeg")]
+    /// Full range, every value from 0 to 255.
+    Pc,
+}
+
+impl ColorRange {
+    #[must_use]
+    /// Name of the range as accepted by the ffmpeg `-color_range` option and scale filter.
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorRange::Tv => "tv",
+            ColorRange::Pc => "pc",
+        }
+    }
+
+    #[must_use]
+    /// Range of a video tagged with `range` as reported by ffprobe,
+    /// `None` for untagged videos.
+    ///
+    /// # Arguments
+    /// * `range` - The `color_range` ffprobe reports for the stream.
+    pub fn from_tag(range: &str) -> Option<Self> {
+        match range {
+            "tv" => Some(ColorRange::Tv),
+            "pc" => Some(ColorRange::Pc),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    /// Value identifying the range in the header.
+    /// Limited range is zero, so headers written before the range was stored read as limited.
+    pub fn to_code(self) -> u8 {
+        match self {
+            ColorRange::Tv => 0,
+            ColorRange::Pc => 1,
+        }
+    }
+
+    /// Reads the range identified by `code` as written by `to_code`.
+    ///
+    /// # Arguments
+    /// * `code` - Value read from the header.
+    ///
+    /// # Errors
+    /// Fails if `code` does not name a known range.
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(ColorRange::Tv),
+            1 => Ok(ColorRange::Pc),
+            _ => bail!("Unknown color range {code}."),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Video codecs the data frames can be encoded with.
+/// Decoding works the same for all of them.
+pub enum VideoCodec {
+    #[default]
+    /// H.264, accepted by every platform.
+    H264,
+    /// VP9, used by many platforms for transcoded videos.
+    Vp9,
+    /// AV1, smallest files but slow to encode.
+    Av1,
+}
+
+impl VideoCodec {
+    #[must_use]
+    /// Constant rate factor used if none is given.
+    pub fn default_crf(self) -> u32 {
+        match self {
+            VideoCodec::H264 => H264_CRF,
+            VideoCodec::Vp9 => VP9_CRF,
+            VideoCodec::Av1 => AV1_CRF,
+        }
+    }
+
+    #[must_use]
+    /// Highest constant rate factor, the worst possible quality.
+    pub fn max_crf(self) -> u32 {
+        match self {
+            VideoCodec::H264 => H264_MAX_CRF,
+            VideoCodec::Vp9 | VideoCodec::Av1 => VP9_AV1_MAX_CRF,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Hardware video encoders.
+/// They are much faster than the software encoders on large resolutions,
+/// but quantize differently, so the same CRF keeps a different amount of detail.
+pub enum HwAccel {
+    /// NVIDIA NVENC. Supports H.264 and AV1 on recent GPUs.
+    Nvenc,
+    /// VA-API on Linux, e.g. Intel and AMD GPUs.
+    Vaapi,
+    /// Intel Quick Sync Video.
+    Qsv,
+}
+
+impl HwAccel {
+    #[must_use]
+    /// Name of the hardware encoder family for messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            HwAccel::Nvenc => "NVENC",
+            HwAccel::Vaapi => "VA-API",
+            HwAccel::Qsv => "Quick Sync",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Encoder preset, named after the H.264 presets.
+/// Slower presets produce smaller files at the same quality.
+/// VP9 and AV1 use the closest speed setting of their encoder.
+pub enum EncoderPreset {
+    /// Fastest encoding, largest files.
+    Ultrafast,
+    /// Second fastest preset.
+    Superfast,
+    /// Third fastest preset.
+    Veryfast,
+    /// Faster than the ffmpeg default.
+    Faster,
+    /// Slightly faster than the ffmpeg default.
+    Fast,
+    /// The ffmpeg default.
+    Medium,
+    /// Slightly slower than the ffmpeg default.
+    Slow,
+    /// Slower than the ffmpeg default.
+    Slower,
+    /// Slowest preset worth using, smallest files.
+    Veryslow,
+}
+
+impl EncoderPreset {
+    #[must_use]
+    /// Name of the preset as the H.264 encoder expects it.
+    pub fn name(self) -> &'static str {
+        match self {
+            EncoderPreset::Ultrafast => "ultrafast",
+            EncoderPreset::Superfast => "superfast",
+            EncoderPreset::Veryfast => "veryfast",
+            EncoderPreset::Faster => "faster",
+            EncoderPreset::Fast => "fast",
+            EncoderPreset::Medium => "medium",
+            EncoderPreset::Slow => "slow",
+            EncoderPreset::Slower => "slower",
+            EncoderPreset::Veryslow => "veryslow",
+        }
+    }
+
+    #[must_use]
+    /// Speed setting of the VP9 and AV1 encoders closest to the preset,
+    /// from 8 for the fastest down to 0 for the slowest.
+    pub fn speed(self) -> u32 {
+        match self {
+            EncoderPreset::Ultrafast => 8,
+            EncoderPreset::Superfast => 7,
+            EncoderPreset::Veryfast => 6,
+            EncoderPreset::Faster => 5,
+            EncoderPreset::Fast => 4,
+            EncoderPreset::Medium => 3,
+            EncoderPreset::Slow => 2,
+            EncoderPreset::Slower => 1,
+            EncoderPreset::Veryslow => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Parameters a backend needs to split a video back into frames.
+pub struct SplitSettings {
+    /// Rate at which frames are extracted from the video.
+    pub data_fps: u32,
+    /// Width the extracted frames are scaled to.
+    pub split_width: u32,
+    /// Height the extracted frames are scaled to.
+    pub split_height: u32,
+    /// Scaling algorithm used to downscale the frames.
+    pub scale_algorithm: String,
+    /// Section of the video to extract.
+    pub window: VideoWindow,
+    /// Rotation the video underwent, undone before scaling.
+    pub rotation: Rotation,
+    /// If the alpha channel of the video is extracted along with the colors.
+    pub alpha: bool,
+    /// Colorspace and range the video is converted back to RGB from,
+    /// those it is tagged with if `None`.
+    pub color: Option<VideoColor>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Section of a video to extract frames from.
+/// Timestamps use ffmpeg time duration syntax, e.g. `90.5` or `00:01:30`.
+pub struct VideoWindow {
+    /// Position to start extracting at, the start of the video if `None`.
+    pub seek: Option<String>,
+    /// Length of the extracted section, up to the end of the video if `None`.
+    pub duration: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Properties of the first video stream of a file, read before decoding it.
+pub struct VideoProbe {
+    /// Width of the video in pixels, as stored before any rotation is undone.
+    pub width: u32,
+    /// Height of the video in pixels, as stored before any rotation is undone.
+    pub height: u32,
+    /// Framerate of the video, `None` if the container does not state it.
+    pub fps: Option<f64>,
+    /// Number of frames in the video, `None` if the container does not state it.
+    pub frame_count: Option<u64>,
+    /// Colorspace the video is tagged with, `None` if untagged or unknown.
+    pub colorspace: Option<Colorspace>,
+    /// Color range the video is tagged with, `None` if untagged.
+    pub color_range: Option<ColorRange>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Clockwise rotation applied to the pixels of a video after it was encoded,
+/// for example by a platform turning a mobile upload upright.
+/// Rotation only recorded in the container metadata is already undone by ffmpeg.
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    /// Not rotated.
+    None,
+    #[value(name = "90")]
+    /// Rotated by 90 degrees clockwise.
+    Clockwise90,
+    #[value(name = "180")]
+    /// Rotated by 180 degrees.
+    Clockwise180,
+    #[value(name = "270")]
+    /// Rotated by 270 degrees clockwise, 90 degrees counterclockwise.
+    Clockwise270,
+}
+
+/// Turns a series of frame images into a video and back.
+pub trait VideoBackend: Debug {
+    /// Combine the frames saved in `frame_buffer` into a video at `output_file`,
+    /// in the order `FrameBuffer::list_combine_frames` returns them.
+    ///
+    /// # Arguments
+    /// * `settings` - Framerates, data frame and output resolution.
+    /// * `frame_buffer` - Directory holding the frames.
+    /// * `output_file` - Path the video is written to.
+    ///   Overwritten if it exists and `settings.overwrite` is set.
+    ///
+    /// # Errors
+    /// Fails if a frame can not be read, the encoder can not be started or exits unsuccessfully,
+    /// including if the output file exists and `settings.overwrite` is not set.
+    fn encode(
+        &self,
+        settings: &EncodeSettings,
+        frame_buffer: &FrameBuffer,
+        output_file: &Path,
+    ) -> Result<()>;
+
+    /// Start encoding a video at `output_file` from frames written one at a time,
+    /// without saving them to disk first.
+    /// The video is complete once `FrameWriter::finish` returned.
+    ///
+    /// # Arguments
+    /// * `settings` - Framerates, data frame and output resolution.
+    /// * `output_file` - Path the video is written to.
+    ///   Overwritten if it exists and `settings.overwrite` is set.
+    ///
+    /// # Errors
+    /// Fails if the encoder can not be started.
+    fn encode_stream(
+        &self,
+        settings: &EncodeSettings,
+        output_file: &Path,
+    ) -> Result<Box<dyn FrameWriter>>;
+
+    /// Split the video at `input_file` into frames saved in `frame_buffer`
+    /// at `FrameBuffer::frame_path_split`.
+    ///
+    /// # Arguments
+    /// * `settings` - Extraction framerate and frame resolution.
+    /// * `input_file` - Video to split.
+    /// * `frame_buffer` - Directory the frames are saved to.
+    ///
+    /// # Errors
+    /// Fails if the decoder can not be started or exits unsuccessfully,
+    /// or a frame can not be saved.
+    fn split(
+        &self,
+        settings: &SplitSettings,
+        input_file: &Path,
+        frame_buffer: &FrameBuffer,
+    ) -> Result<()>;
+
+    /// Read the resolution, framerate and frame count of the video at `input_file`.
+    /// Returns `None` if the backend has no way to determine them.
+    ///
+    /// # Arguments
+    /// * `input_file` - Video to inspect.
+    ///
+    /// # Errors
+    /// Fails if the probing tool exits unsuccessfully, the file has no video stream
+    /// or the output of the tool can not be parsed.
+    fn probe(&self, input_file: &Path) -> Result<Option<VideoProbe>>;
+
+    /// Re-encode the video at `input_file` to `output_file` at a fixed bitrate,
+    /// the way a platform transcodes uploads, overwriting `output_file`.
+    ///
+    /// # Arguments
+    /// * `input_file` - Video to re-encode.
+    /// * `output_file` - Path the re-encoded video is written to.
+    /// * `bitrate_kbps` - Target bitrate in kbit/s.
+    ///
+    /// # Errors
+    /// Fails if the encoder can not be started or exits unsuccessfully.
+    fn recompress(&self, input_file: &Path, output_file: &Path, bitrate_kbps: u32) -> Result<()>;
+}
+
+/// Receives the frames of a video being encoded by `VideoBackend::encode_stream`.
+pub trait FrameWriter {
+    /// Append `frame` to the video. Frames are at data resolution,
+    /// RGBA if `EncodeSettings::alpha` is set and RGB otherwise.
+    ///
+    /// # Arguments
+    /// * `frame` - Next frame of the video.
+    ///
+    /// # Errors
+    /// Fails if the frame can not be passed to the encoder.
+    fn write_frame(&mut self, frame: &DynamicImage) -> Result<()>;
+
+    /// Finish encoding after the last frame was written.
+    ///
+    /// # Errors
+    /// Fails if the encoder exits unsuccessfully.
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Available video backends.
+pub enum BackendKind {
+    #[default]
+    /// Shell out to the ffmpeg executable.
+    Ffmpeg,
+}
+
+impl BackendKind {
+    /// Create the backend this kind refers to.
+    ///
+    /// # Arguments
+    /// * `ffmpeg_path` - ffmpeg path explicitly requested by the user.
+    /// * `ffprobe_path` - ffprobe path explicitly requested by the user.
+    /// * `encoder` - Codec and quality settings used when encoding videos.
+    /// * `timeout` - Longest a single run of an external tool may take, unlimited if `None`.
+    ///
+    /// # Errors
+    /// Fails if the executables the backend needs can not be found.
+    pub fn create(
+        self,
+        ffmpeg_path: Option<&Path>,
+        ffprobe_path: Option<&Path>,
+        encoder: EncoderOptions,
+        timeout: Option<Duration>,
+    ) -> Result<Box<dyn VideoBackend>> {
+        match self {
+            BackendKind::Ffmpeg => Ok(Box::new(FfmpegBackend::new(
+                ffmpeg_path,
+                ffprobe_path,
+                encoder,
+                timeout,
+            )?)),
+        }
+    }
+}
@@ -9,12 +9,45 @@
 
 //! cli - Command line interface tooling.
 
-use std::path::PathBuf;
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use anyhow::{Result, bail};
-use clap::{Parser, ValueEnum};
+use anyhow::{Context, Result, bail};
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum, parser::ValueSource};
+use console::Term;
+use serde::Deserialize;
 
-use crate::{constants::resolutions, converter::Converter};
+use vortexkey::{
+    archive::{pack_paths, read_index},
+    backend::{
+        BackendKind, ColorRange, Colorspace, EncoderOptions, EncoderPreset, HwAccel, Rotation,
+        VideoBackend, VideoCodec, VideoColor, VideoWindow,
+    },
+    compression::compress,
+    constants::{
+        AUTO_COLOR_BITS, AUTO_DATA_PIXEL_SIZES, COLOR_CHANNELS, COLOR_RANGE, COLORSPACE,
+        DOWNSAMPLE_SCALER, FOUNTAIN_OVERHEAD_PERCENT, H264_PRESET, PASSPHRASE_ENV,
+        POSTBUFFER_FRAMES, PREBUFFER_FRAMES, SCALE_ALGORITHMS, SIMULATED_RECOMPRESS_KBPS,
+        STDIO_PATH, UPSCALE_ALGORITHM, VP9_AV1_MAX_CRF, resolutions,
+    },
+    converter::Converter,
+    encryption::encrypt,
+    error_correction::FecKind,
+    filesys::{
+        FrameBufferGuard, FrameFormat, InputData, read_input_file, resolve_workdir,
+        scoped_framebuffer,
+    },
+    hashing::HashAlgorithm,
+    header::HeaderData,
+    levels::LevelMapping,
+    utils::{format_bytes, hex_string_to_bytes},
+};
 
 #[derive(ValueEnum, Clone, Debug, PartialEq)]
 #[value(rename_all = "lower")]
@@ -22,6 +55,14 @@ use crate::{constants::resolutions, converter::Converter};
 /// - dtv (Data to Video)  
 /// - vtd (Video to Data)
 /// - split (Data to Frames)
+/// - compare (Decode two videos and compare the results)
+/// - capacities (Print the capacity of every preset resolution)
+/// - estimate (Print the size and length of the video for a file)
+/// - validate (Check the converter settings)
+/// - inspect (Print the header of a video)
+/// - reheader (Regenerate the header frames of existing frames)
+/// - verify (Encode and decode in memory to check the settings)
+/// - bench (Measure the throughput of every phase)
 pub enum OperatingMode {
     #[value(name = "dtv")]
     /// Encode a file to a video.
@@ -32,17 +73,182 @@ pub enum OperatingMode {
     #[value(name = "split")]
     /// Turn data into a series of frames.
     Split,
+    #[value(name = "compare")]
+    /// Decode two videos and compare the results byte for byte.
+    /// The output file is used as the second video.
+    Compare,
+    #[value(name = "capacities")]
+    /// Print how much data every preset resolution holds with the given settings.
+    /// Neither input nor output file are needed.
+    Capacities,
+    #[value(name = "estimate")]
+    /// Print the frame count, length and approximate size of the video
+    /// the input file or `--file-size` would be encoded as, without running ffmpeg.
+    Estimate,
+    #[value(name = "validate")]
+    /// Check if the converter settings are valid and print the resolved geometry.
+    /// Neither input nor output file are needed.
+    Validate,
+    #[value(name = "inspect")]
+    /// Print the header of a video without decoding its data.
+    Inspect,
+    #[value(name = "reheader")]
+    /// Regenerate only the header frames of the frames left in the framebuffer
+    /// by a previous dtv or split run for the input file, then combine them into a video.
+    Reheader,
+    #[value(name = "verify")]
+    /// Encode the input file to frames in memory and decode them again,
+    /// checking the settings round trip without running ffmpeg.
+    /// No output file is needed.
+    Verify,
+    #[value(name = "bench")]
+    /// Encode synthetic data of `--file-size` bytes through ffmpeg,
+    /// optionally decode it again, and report the throughput of every phase.
+    /// Neither input nor output file are needed.
+    Bench,
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
+#[allow(clippy::struct_excessive_bools)]
 /// Command line argument handler.
 pub struct Args {
     /// Output file (video file or reconstructed data).
-    pub outputfile: PathBuf,
+    /// In vtd mode a directory restores the data under its original file name.
+    /// In compare mode the second video to decode.
+    /// `-` writes the reconstructed data to stdout in vtd mode.
+    pub outputfile: Option<PathBuf>,
     #[arg(short = 'i')]
     /// Input file (video file or data to convert).
-    pub inputfile: PathBuf,
+    /// `-` reads the data to convert from stdin in dtv, split, reheader and estimate mode.
+    /// A directory is packed into an archive, like `archive`.
+    pub inputfile: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["inputfile", "message"],
+        help = "Pack this file or directory into an archive and encode it instead of an input file. Repeat to pack several, e.g. --archive docs --archive notes.txt. An input file that is a directory is packed the same way. Decoding unpacks the archive into the output directory. (dtv, split, reheader, estimate, verify)"
+    )]
+    /// Files and directories packed into an archive and encoded instead of an input file.
+    /// Decoding unpacks the archive into the output directory.
+    pub archive: Option<Vec<PathBuf>>,
+    #[arg(
+        long,
+        conflicts_with = "inputfile",
+        help = "Encode this text instead of an input file. Decoding prints it to stdout. (dtv, split)"
+    )]
+    /// Encode this text instead of an input file.
+    /// Decoding prints it to stdout instead of writing a file.
+    pub message: Option<String>,
+    #[arg(
+        long,
+        help = "Short note stored in the header, e.g. how to decode the video. Shown by inspect. (dtv, split)"
+    )]
+    /// Short note stored in the header, e.g. how to decode the video.
+    /// Shown by the inspect mode.
+    pub note: Option<String>,
+    #[arg(
+        long,
+        help = "Compress the data with zstd before encoding. Stored uncompressed if that would not make it smaller. (dtv, split)"
+    )]
+    /// Compress the data with zstd before encoding.
+    /// Stored uncompressed if that would not make it smaller.
+    pub compress: bool,
+    #[arg(
+        long,
+        help = "Map the input file into memory instead of reading it completely before encoding, lowering peak memory use for large files. Falls back to reading the file if it can not be mapped. The file must not be changed while encoding. (dtv, split, verify, estimate)"
+    )]
+    /// Map the input file into memory instead of reading it, see `read_input_file`.
+    pub mmap: bool,
+    #[arg(
+        long,
+        help = "Encrypt the data with AES-256-GCM using a key derived from a passphrase. The passphrase is read from --passphrase-file, the environment variable VORTEXKEY_PASSPHRASE or asked for on the terminal. The file name is then not stored, the note stays readable. (dtv, split)"
+    )]
+    /// Encrypt the data with AES-256-GCM using a key derived from the passphrase, see `Args::passphrase`.
+    /// The file name is then not stored, the note stays readable.
+    pub encrypt: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Read the passphrase for --encrypt or for decoding an encrypted video from the first line of this file. (dtv, split, vtd)"
+    )]
+    /// File whose first line is the passphrase, see `Args::passphrase`.
+    pub passphrase_file: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LevelMapping::Midpoint,
+        help = "How color channel levels are mapped to pixel values. even spreads them across the full range for larger error margins. Recorded in the header. (dtv, split)"
+    )]
+    /// How color channel levels are mapped to pixel values.
+    /// Recorded in the header, so decoding picks it up automatically.
+    pub level_mapping: LevelMapping,
+    #[arg(
+        long,
+        value_name = "R,G,B",
+        allow_hyphen_values = true,
+        help = "Offset added to the pixel value of every level in each color channel, comma separated, e.g. 4,0,-2. Counters a platform that darkens or brightens videos, decoding prints a suggestion measured from the video. (dtv, split)"
+    )]
+    /// Offset added to the pixel value of every level in each color channel,
+    /// comma separated. (RGB)
+    quantization_bias: Option<String>,
+    #[arg(
+        long,
+        help = "Encode a calibration frame showing every level of each color channel before the data. Decoding measures where the levels ended up and reads the data against those, correcting gamma and white balance shifts of cameras and screen captures. Recorded in the header. (dtv, split, reheader)"
+    )]
+    /// Encode a calibration frame showing every level of each color channel before the data.
+    /// Recorded in the header, so decoding picks it up automatically.
+    pub calibration_frame: bool,
+    #[arg(
+        long,
+        help = "Encode gray frames, copying the green level of every data unit to red and blue so all data is carried in the luminance. Survives chroma subsampling and aggressive re-encoding much better, but only the green bits hold data, so set the red and blue bits to 0, e.g. --bits 0,3,0. Recorded in the header. (dtv, verify)"
+    )]
+    /// Encode gray frames with all data in the luminance.
+    /// Recorded in the header, so decoding picks it up automatically.
+    luma: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = FecKind::Hamming,
+        help = "Error correction code protecting the data. The hamming variants correct a single bit per code word, smaller ones at more overhead, hamming is Hamming(31,26). rs corrects up to 16 damaged bytes per 223, more robust against the burst errors of video compression. Recorded in the header. (dtv, split, capacities)"
+    )]
+    /// Error correction code protecting the data.
+    /// Recorded in the header, so decoding picks it up automatically.
+    pub fec: FecKind,
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Interleave the symbols of this many error correction code words, spreading a burst of damaged pixels across them. 1 disables interleaving. Recorded in the header. (dtv, split)"
+    )]
+    /// Interleave the symbols of this many error correction code words,
+    /// spreading a burst of damaged pixels across them.
+    /// 1 disables interleaving.
+    pub interleave: u16,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = HashAlgorithm::Sha256,
+        help = "Hash of the data stored in the header and checked after decoding. blake3 is much faster than sha256 on large files, crc32 only catches accidental damage and none skips the check. Recorded in the header. (dtv, split)"
+    )]
+    /// Hash of the data stored in the header and checked after decoding.
+    /// Recorded in the header, so decoding picks it up automatically.
+    pub hash: HashAlgorithm,
+    #[arg(
+        long,
+        help = "File size in bytes to calculate the video length for. Defaults to the size of the input file. In bench mode the size of the synthetic data, 16 MiB by default. (capacities, estimate, bench)"
+    )]
+    /// File size in bytes to calculate the video length for.
+    /// Defaults to the size of the input file.
+    /// In bench mode the size of the synthetic data, `BENCH_BYTES` by default.
+    pub file_size: Option<u64>,
+    #[arg(
+        long,
+        help = "Also split and decode the benchmark video, reporting the throughput of those phases too. (bench)"
+    )]
+    /// Also split and decode the benchmark video.
+    pub bench_decode: bool,
     #[arg(
         short = 'y',
         help = "If output file should be overwritten if it exists.",
@@ -50,22 +256,349 @@ pub struct Args {
     )]
     /// If output file should be overwritten if it exists.
     pub overwrite: bool,
+    #[arg(
+        long,
+        help = "Debug: Save the averaged frames the decoder reads into this directory. (vtd only)"
+    )]
+    /// Debug: Save the averaged frames the decoder reads into this directory.
+    pub dump_frames: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Write per frame decode statistics (errors, mean channel values) to this CSV file. (vtd only)"
+    )]
+    /// Write per frame decode statistics to this CSV file.
+    pub stats_csv: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Print a histogram of the errors found in each frame and the positions of the affected code words. (vtd only)",
+        default_value_t = false
+    )]
+    /// Print a histogram of the errors found in each frame
+    /// and the positions of the affected code words after decoding.
+    pub error_report: bool,
+    #[arg(
+        long,
+        help = "Print a summary of the run as a single JSON object on stdout: bytes read and written, frame count, durations and, when decoding, the error correction report. Can not be combined with writing the decoded data to stdout. In bench mode the throughput of every phase. (dtv, vtd, bench)"
+    )]
+    /// Print a summary of the run as a single JSON object on stdout, see `RunSummary`.
+    pub json: bool,
+    #[arg(
+        long,
+        value_name = "HEX",
+        help = "SHA256 of the original file, 64 hex digits with optional 0x prefix. The decoded data is checked against it independently of the hash stored in the video header, exiting with code 3 on a mismatch. (vtd)"
+    )]
+    /// SHA256 of the original file the decoded data is checked against,
+    /// independently of the hash stored in the possibly corrupted header.
+    /// Parsed by `expected_sha256`.
+    expect_sha256: Option<String>,
+    #[arg(
+        long,
+        value_name = "KBPS",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "After encoding, re-encode a copy of the video at this bitrate in kbit/s like a platform transcoding the upload, then decode it and report the errors. Without a value 2500 kbit/s is used, roughly YouTube at 1080p. The encoded video itself is left unchanged. (dtv)"
+    )]
+    #[allow(clippy::option_option)]
+    /// After encoding, re-encode a copy of the video at this bitrate in kbit/s
+    /// like a platform would, then decode it and report the errors.
+    /// `Some(None)` if given without a bitrate, see `simulated_bitrate`.
+    simulate_recompress: Option<Option<u32>>,
+    #[arg(
+        long,
+        help = "Start decoding at this timestamp, e.g. 90.5 or 00:01:30. The window must contain the header frames and the hash will not match unless it covers all data. (vtd, compare)"
+    )]
+    /// Start decoding at this timestamp.
+    /// Decoding a window is meant for salvaging a section of a damaged video:
+    /// The window must contain the header frames, missing data frames
+    /// are only filled in when parity frames are enabled
+    /// and the whole file hash will not match unless all data is covered.
+    pub seek: Option<String>,
+    #[arg(
+        long,
+        help = "Only decode this much of the video, e.g. 30 or 00:00:30. See --seek. (vtd, compare)"
+    )]
+    /// Only decode this much of the video, see `seek`.
+    pub duration: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Rotation::None,
+        help = "Clockwise rotation the video underwent after encoding, e.g. by a platform turning an upload upright. Undone before decoding. (vtd, compare, inspect)"
+    )]
+    /// Clockwise rotation the video underwent after encoding,
+    /// e.g. by a platform turning an upload upright. Undone before decoding.
+    pub rotate: Rotation,
+    #[arg(
+        long,
+        help = "Place finder patterns in the corners of every frame, so video filmed off a screen can be decoded. Decoding locates them and undoes the perspective, which is slower than reading frames at their exact resolution. Needed for encoding and decoding. (dtv, split, vtd, compare, inspect, capacities)"
+    )]
+    /// Place finder patterns in the corners of every frame for camera capture.
+    /// Changes the frame layout, so it is needed for encoding and decoding.
+    pub camera: bool,
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        value_name = "FRACTION",
+        help = "Fraction of the width and height of every data unit sampled around its center when decoding. Below 1 the edges of the units are ignored, where neighbouring units bleed in when the scaler shifts the frame by part of a pixel, and frames are split at a higher resolution. (split, vtd, compare, inspect)"
+    )]
+    /// Fraction of the width and height of every data unit sampled around its center when decoding.
+    /// Below 1 the edges, where neighbouring units bleed in, are ignored.
+    pub sample_core: f64,
+    #[arg(
+        long,
+        default_value_t = DOWNSAMPLE_SCALER,
+        value_name = "SCALER",
+        value_parser = clap::value_parser!(u32).range(1..=16),
+        help = "Multiple of the data resolution frames are scaled to when splitting a video, before the pixels of every data unit are averaged. Higher values average out more compression noise, but the frame resolution must be at least this multiple of the data resolution. Stored in the video, decoding uses the value it was encoded with. (dtv, split)"
+    )]
+    /// Multiple of the data resolution frames are scaled to when splitting a video.
+    /// Stored in the header, so decoding uses the value the video was encoded with.
+    downsample: u32,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If the decoded hash does not match, retry splitting the video with every scale algorithm and keep the best result. (vtd only)"
+    )]
+    /// If the decoded hash does not match, retry splitting the video
+    /// with every scale algorithm and keep the best result.
+    pub auto_filter: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "config",
+        help = "If no header is found, search the start of the video for it with common data pixel sizes and color bits, and decode with the first combination that finds it. The frame resolution and framerates must still match. (vtd only)"
+    )]
+    /// If no header is found, search for it with common data pixel sizes and color bits
+    /// and decode with the first combination that finds it.
+    pub auto: bool,
+    #[arg(
+        long,
+        value_name = "ALGORITHM",
+        value_parser = clap::builder::PossibleValuesParser::new(SCALE_ALGORITHMS),
+        help = "ffmpeg scaling algorithm used to upscale the data frames when encoding and to downscale the video when splitting it. Defaults to neighbor for encoding, keeping the data units sharp, and area for splitting, averaging the pixels of every unit. The best choice depends on the codec and content. --auto-filter tries this one first. (dtv, reheader, vtd, compare, inspect)"
+    )]
+    /// ffmpeg scaling algorithm used for encoding and splitting, one of `SCALE_ALGORITHMS`.
+    /// Defaults to `UPSCALE_ALGORITHM` for encoding and the first of `SCALE_ALGORITHMS` for splitting.
+    scale_flags: Option<String>,
     #[arg(
         short,
-        value_enum, 
+        long,
+        help = "Hide the progress bars shown while encoding and decoding frames. They are also hidden if stderr is not a terminal. (dtv, split, vtd, compare, inspect)"
+    )]
+    /// Hide the progress bars shown while encoding and decoding frames.
+    pub quiet: bool,
+    #[arg(
+        short,
+        value_enum,
         default_value_t = OperatingMode::DataToVideo,
-        help = "Operating mode dtv (Data to Video), vtd (Video to Data) or split (Data to Frames)"
+        help = "Operating mode dtv (Data to Video), vtd (Video to Data), split (Data to Frames), compare (Compare decoded videos), capacities (Capacity of preset resolutions), validate (Check settings), inspect (Print video header), reheader (Regenerate header frames), verify (Round trip in memory) or bench (Measure throughput)"
         )]
-    /// Operating mode dtv (Data to Video), vtd (Video to Data) or split (Data to Frames)
+    /// Operating mode dtv (Data to Video), vtd (Video to Data), split (Data to Frames), compare (Compare decoded videos), capacities (Capacity of preset resolutions), validate (Check settings), inspect (Print video header), reheader (Regenerate header frames), verify (Round trip in memory) or bench (Measure throughput)
     pub mode: OperatingMode,
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..=16),
+        help = "Number of redundant header blocks, each starting on a new frame. (dtv/split only)"
+        )]
+    /// Number of redundant header blocks, each starting on a new frame.
+    pub header_frames: u32,
+    #[arg(
+        long,
+        value_name = "FRAMES",
+        default_value_t = 0,
+        help = "Repeat a header block after every N data frames, so the video still decodes if its start is cut off or damaged. Decoding votes over all header blocks found. Only a single copy can be encoded this way. 0 only puts the header at the start. Recorded in the header. (dtv, split, reheader)"
+    )]
+    /// Repeat a header block after every N data frames,
+    /// so the video still decodes if its start is cut off or damaged.
+    /// 0 only puts the header at the start.
+    pub header_interval: u16,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Insert a parity frame after every N data frames, allowing one lost frame per group to be rebuilt. 0 disables parity frames. (dtv/split only)"
+    )]
+    /// Insert a parity frame after every N data frames,
+    /// allowing one lost frame per group to be rebuilt.
+    /// 0 disables parity frames.
+    pub parity_interval: u16,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u16).range(1..),
+        conflicts_with = "parity_interval",
+        help = "Follow the data frames with fountain droplet frames, each the XOR of a pseudo random set of data frames. Any data frames dropped anywhere in the video are rebuilt as long as enough frames survive overall. The value is the number of droplets per hundred data frames, 25 without a value. Recorded in the header. (dtv, split, estimate, verify)"
+    )]
+    #[allow(clippy::option_option)]
+    /// Follow the data frames with this many fountain droplet frames per hundred data frames.
+    /// `Some(None)` if given without a value, see `fountain_overhead`.
+    fountain: Option<Option<u16>>,
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..=16),
+        help = "Number of copies of the header and data frames in the video. Decoding falls back to the next copy if one fails the hash check. (dtv/split only)"
+        )]
+    /// Number of copies of the header and data frames in the video.
+    /// Decoding falls back to the next copy if one fails the hash check.
+    pub repeat: u32,
+    #[arg(
+        long,
+        value_name = "FRAMES",
+        default_value_t = PREBUFFER_FRAMES,
+        help = "Number of blank frames before the first header frame. Raise it for platforms trimming the start of a video. Recorded in the header. (dtv/split only)"
+    )]
+    /// Number of blank frames before the first header frame,
+    /// e.g. for platforms trimming the start of a video.
+    pub prebuffer: u16,
+    #[arg(
+        long,
+        value_name = "FRAMES",
+        default_value_t = POSTBUFFER_FRAMES,
+        help = "Number of blank frames after the last data frame. Raise it for platforms trimming the end of a video. Recorded in the header. (dtv/split only)"
+    )]
+    /// Number of blank frames after the last data frame,
+    /// e.g. for platforms trimming the end of a video.
+    pub postbuffer: u16,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Refuse to encode data into a video longer than this many seconds. (dtv/split only)"
+    )]
+    /// Refuse to encode data into a video longer than this many seconds.
+    pub max_duration: Option<u64>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BackendKind::Ffmpeg,
+        help = "Video backend used to combine frames into a video and split it back."
+    )]
+    /// Video backend used to combine frames into a video and split it back.
+    pub backend: BackendKind,
+    #[arg(
+        long,
+        help = "Path to the ffmpeg executable. Defaults to $VORTEXKEY_FFMPEG, /bin/ffmpeg or ffmpeg on PATH."
+    )]
+    /// Path to the ffmpeg executable.
+    /// Defaults to `$VORTEXKEY_FFMPEG`, /bin/ffmpeg or ffmpeg on PATH.
+    pub ffmpeg_path: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Path to the ffprobe executable. Defaults to $VORTEXKEY_FFPROBE, /bin/ffprobe or ffprobe on PATH."
+    )]
+    /// Path to the ffprobe executable.
+    /// Defaults to `$VORTEXKEY_FFPROBE`, /bin/ffprobe or ffprobe on PATH.
+    pub ffprobe_path: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Keep the frame directories of the run instead of deleting them afterwards, and print where they are. Split always keeps its frames. (vtd, compare, inspect)"
+    )]
+    /// Keep the frame directories of the run, see `FrameBufferGuard`.
+    pub keep_frames: bool,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Frame directory printed by split, whose header frames are regenerated. (reheader)"
+    )]
+    /// Frame directory printed by split, whose header frames are regenerated.
+    pub frames_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Stop ffmpeg if a single run of it takes longer than this many seconds, failing the run. Unlimited by default."
+    )]
+    /// Stop ffmpeg if a single run of it takes longer than this many seconds.
+    pub ffmpeg_timeout: Option<u64>,
+    #[arg(
+        long,
+        help = "Directory the intermediate frames are stored in, which needs room for all frames of a video. Must exist and be writable. Defaults to $VORTEXKEY_WORKDIR or the temp directory, which is often a small tmpfs. (dtv, split, vtd, verify, compare, inspect)"
+    )]
+    /// Directory the intermediate frames are stored in.
+    /// Defaults to `$VORTEXKEY_WORKDIR` or the temp directory.
+    pub workdir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = VideoCodec::H264,
+        help = "Codec the video is encoded with. Matching the codec a platform transcodes to can reduce re-encoding artifacts. vp9 and av1 also fit into .webm files. Decoding detects the codec automatically. (dtv, reheader)"
+    )]
+    /// Codec the video is encoded with.
+    /// Matching the codec a platform transcodes to can reduce re-encoding artifacts.
+    pub codec: VideoCodec,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(0..=i64::from(VP9_AV1_MAX_CRF)),
+        help = "Constant rate factor of the encoder, 0 is lossless and 51 (h264) or 63 (vp9, av1) the worst quality. Lower values keep the data blocks more intact at the cost of larger files. Defaults to 20 for h264 and 24 for vp9 and av1. (dtv, reheader)"
+    )]
+    /// Constant rate factor of the encoder, 0 is lossless.
+    /// Defaults to `VideoCodec::default_crf`.
+    pub crf: Option<u32>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = H264_PRESET,
+        help = "Encoder preset, named after the H.264 presets. Slower presets produce smaller files at the same quality. vp9 and av1 use the closest speed setting. (dtv, reheader)"
+    )]
+    /// Encoder preset, trading encoding speed for file size.
+    pub preset: EncoderPreset,
+    #[arg(
+        long,
+        value_enum,
+        help = "Encode on the GPU with NVENC, VA-API or Quick Sync instead of the software encoder. Much faster for 4K and 8K, but hardware encoders keep less detail at the same --crf, so check the decoded errors and lower --crf if needed. (dtv, reheader)"
+    )]
+    /// Encode on the GPU instead of with the software encoder.
+    /// Hardware encoders keep less detail at the same CRF.
+    pub hwaccel: Option<HwAccel>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Colorspace the frames are converted to and the video is tagged with. Defaults to bt709, which platforms expect. Recorded in the header. When decoding, overrides the colorspace the video is tagged with. (dtv, reheader, vtd, inspect, compare)"
+    )]
+    /// Colorspace of the video, see `video_color` and `split_color`.
+    pub colorspace: Option<Colorspace>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Range of pixel values of the video, tv (limited, 16-235) or pc (full, 0-255). Defaults to tv, which every platform and player handles. Recorded in the header. When decoding, overrides the range the video is tagged with. (dtv, reheader, vtd, inspect, compare)"
+    )]
+    /// Color range of the video, see `video_color` and `split_color`.
+    pub color_range: Option<ColorRange>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = FrameFormat::Png,
+        help = "Image format of the frames kept on disk while splitting a video or between split and reheader. bmp and ppm are uncompressed, so they are written and read faster but take more space in the temp directory. Only png keeps the alpha channel. (split, reheader, vtd, verify, compare, inspect, dtv with --simulate-recompress)"
+    )]
+    /// Image format of the frames kept on disk, see `frame_format`.
+    frame_format: FrameFormat,
     #[arg(
         short,
+        long,
         default_value_t = 121,
         value_parser = clap::value_parser!(u32).range(111..=888),
-        help = "Number of bits encoded in each color channel. (RGB)"
+        help = "Number of bits encoded in each color channel packed as one digit per channel. (RGB) Prefer --bits."
         )]
     /// Number of bits encoded in each color channel. (RGB)
+    /// Packs one digit per channel, kept for compatibility, `bits` is preferred.
     colorbits: u32,
+    #[arg(
+        long,
+        value_name = "R,G,B",
+        conflicts_with = "colorbits",
+        help = "Number of bits encoded in each color channel, comma separated, e.g. 1,2,1 or 5,6,5. Replaces --colorbits."
+    )]
+    /// Number of bits encoded in each color channel, comma separated. (RGB)
+    /// Replaces `colorbits`.
+    bits: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u32).range(0..=8),
+        help = "Number of bits encoded in the alpha channel of every data unit, adding a fourth channel to the frames. Needs a video keeping the alpha channel: --codec vp9 without --hwaccel into a .webm or .mkv file. Platforms converting uploads to plain 4:2:0 video discard it. Needed for encoding and decoding. (dtv, split, vtd, verify, compare, inspect, capacities)"
+    )]
+    /// Number of bits encoded in the alpha channel of every data unit, zero for RGB only frames.
+    /// Changes the frame layout, so it is needed for encoding and decoding.
+    alpha_bits: u32,
     #[arg(
         long,
         default_value_t = 30,
@@ -82,6 +615,15 @@ pub struct Args {
         )]
     /// Data framerate.
     data_fps: u32,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..=60),
+        conflicts_with = "video_fps",
+        help = "Framerate of the target platform. Used as video fps, so it can not be given with --video-fps, and picks a data fps that maps cleanly onto it."
+        )]
+    /// Framerate of the target platform.
+    /// Used as video fps instead of `video_fps` and picks a data fps that maps cleanly onto it.
+    target_fps: Option<u32>,
     #[arg(
         short,
         long,
@@ -100,36 +642,798 @@ pub struct Args {
         )]
     /// Size of data block in pixels.
     data_pixel_size: u32,
+    #[arg(
+        long,
+        help = "Fail if the frame resolution is not a whole multiple of the data pixel size. By default the video is cut to the largest even multiple, dropping the left over edge pixels with a warning."
+    )]
+    /// Fail if the frame resolution is not a whole multiple of the data pixel size,
+    /// instead of cutting the video to the largest multiple.
+    strict_dimensions: bool,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of data blocks in each row. Overrides the width given by data pixel size, e.g. for wider blocks."
+        )]
+    /// Number of data blocks in each row.
+    /// Overrides the width given by `data_pixel_size`.
+    data_width: Option<u32>,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of data blocks in each column. Overrides the height given by data pixel size."
+        )]
+    /// Number of data blocks in each column.
+    /// Overrides the height given by `data_pixel_size`.
+    data_height: Option<u32>,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        conflicts_with_all = ["colorbits", "bits", "video_fps", "data_fps", "target_fps", "frame_resolution", "data_pixel_size", "data_width", "data_height"],
+        help = "Converter settings token as printed in validate mode. Replaces the color bits, framerate, resolution and data size options."
+    )]
+    /// Converter settings token as printed in validate mode.
+    /// Replaces the color bits, framerate, resolution and data size options.
+    config: Option<String>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "TOML file with default settings, keyed by the long option names, e.g. bits = \"1,2,1\", fec = \"rs\" or crf = 18. Options given on the command line take precedence over the file, which takes precedence over the built in defaults."
+    )]
+    /// TOML file with default settings, see `ConfigFile`.
+    /// Precedence is built in defaults < config file < command line.
+    config_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+/// Converter settings tried by `--auto`.
+pub struct AutoCandidate {
+    /// Size of a data unit in pixels.
+    pub data_pixel_size: u32,
+    /// Number of bits in each color channel.
+    pub color_bits: [u32; COLOR_CHANNELS],
+    /// Converter using these settings.
+    pub converter: Converter,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+/// Settings read from the TOML file given with `--config-file`.
+/// Keys are the long names of the command line options they stand in for
+/// and take the same values, so they are validated by the same parser.
+struct ConfigFile {
+    /// See `Args::bits`.
+    bits: Option<String>,
+    /// See `Args::alpha_bits`.
+    alpha_bits: Option<u32>,
+    /// See `Args::video_fps`.
+    video_fps: Option<u32>,
+    /// See `Args::data_fps`.
+    data_fps: Option<u32>,
+    /// See `Args::target_fps`.
+    target_fps: Option<u32>,
+    /// See `Args::frame_resolution`.
+    frame_resolution: Option<String>,
+    /// See `Args::data_pixel_size`.
+    data_pixel_size: Option<u32>,
+    /// See `Args::data_width`.
+    data_width: Option<u32>,
+    /// See `Args::data_height`.
+    data_height: Option<u32>,
+    /// See `Args::level_mapping`.
+    level_mapping: Option<String>,
+    /// See `Args::quantization_bias`.
+    quantization_bias: Option<String>,
+    /// See `Args::sample_core`.
+    sample_core: Option<f64>,
+    /// See `Args::downsample`.
+    downsample: Option<u32>,
+    /// See `Args::fec`.
+    fec: Option<String>,
+    /// See `Args::interleave`.
+    interleave: Option<u16>,
+    /// See `Args::hash`.
+    hash: Option<String>,
+    /// See `Args::header_frames`.
+    header_frames: Option<u32>,
+    /// See `Args::header_interval`.
+    header_interval: Option<u16>,
+    /// See `Args::parity_interval`.
+    parity_interval: Option<u16>,
+    /// See `Args::repeat`.
+    repeat: Option<u32>,
+    /// See `Args::prebuffer`.
+    prebuffer: Option<u16>,
+    /// See `Args::postbuffer`.
+    postbuffer: Option<u16>,
+    /// See `Args::max_duration`.
+    max_duration: Option<u64>,
+    /// See `Args::backend`.
+    backend: Option<String>,
+    /// See `Args::ffmpeg_path`.
+    ffmpeg_path: Option<PathBuf>,
+    /// See `Args::ffprobe_path`.
+    ffprobe_path: Option<PathBuf>,
+    /// See `Args::ffmpeg_timeout`.
+    ffmpeg_timeout: Option<u64>,
+    /// See `Args::workdir`.
+    workdir: Option<PathBuf>,
+    /// See `Args::codec`.
+    codec: Option<String>,
+    /// See `Args::crf`.
+    crf: Option<u32>,
+    /// See `Args::preset`.
+    preset: Option<String>,
+    /// See `Args::hwaccel`.
+    hwaccel: Option<String>,
+    /// See `Args::colorspace`.
+    colorspace: Option<String>,
+    /// See `Args::color_range`.
+    color_range: Option<String>,
+    /// See `Args::scale_flags`.
+    scale_flags: Option<String>,
+    /// See `Args::frame_format`.
+    frame_format: Option<String>,
+}
+
+impl ConfigFile {
+    /// Reads and deserializes the config file at `path`.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the TOML file.
+    fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read config file {}.", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}.", path.display()))
+    }
+
+    /// Settings given in the file as pairs of the id of the option they stand in for,
+    /// the ids of the options overriding them and their value.
+    /// Converter settings are also overridden by a `--config` token, `bits` by `--colorbits`
+    /// and `video_fps` and `target_fps` by each other.
+    fn values(&self) -> Vec<(&'static str, &'static [&'static str], OsString)> {
+        /// Options overriding a converter setting besides itself.
+        const CONVERTER: &[&str] = &["config"];
+        /// Command line form of a setting.
+        fn arg<T: ToString>(value: Option<&T>) -> Option<OsString> {
+            value.map(|value| OsString::from(value.to_string()))
+        }
+        let path = |value: &Option<PathBuf>| value.clone().map(PathBuf::into_os_string);
+        [
+            (
+                "bits",
+                &["config", "colorbits"][..],
+                arg(self.bits.as_ref()),
+            ),
+            ("alpha_bits", &[], arg(self.alpha_bits.as_ref())),
+            (
+                "video_fps",
+                &["config", "target_fps"][..],
+                arg(self.video_fps.as_ref()),
+            ),
+            ("data_fps", CONVERTER, arg(self.data_fps.as_ref())),
+            (
+                "target_fps",
+                &["config", "video_fps"][..],
+                arg(self.target_fps.as_ref()),
+            ),
+            (
+                "frame_resolution",
+                CONVERTER,
+                arg(self.frame_resolution.as_ref()),
+            ),
+            (
+                "data_pixel_size",
+                CONVERTER,
+                arg(self.data_pixel_size.as_ref()),
+            ),
+            ("data_width", CONVERTER, arg(self.data_width.as_ref())),
+            ("data_height", CONVERTER, arg(self.data_height.as_ref())),
+            ("level_mapping", &[], arg(self.level_mapping.as_ref())),
+            (
+                "quantization_bias",
+                &[],
+                arg(self.quantization_bias.as_ref()),
+            ),
+            ("sample_core", &[], arg(self.sample_core.as_ref())),
+            ("downsample", &[], arg(self.downsample.as_ref())),
+            ("fec", &[], arg(self.fec.as_ref())),
+            ("interleave", &[], arg(self.interleave.as_ref())),
+            ("hash", &[], arg(self.hash.as_ref())),
+            ("header_frames", &[], arg(self.header_frames.as_ref())),
+            ("header_interval", &[], arg(self.header_interval.as_ref())),
+            ("parity_interval", &[], arg(self.parity_interval.as_ref())),
+            ("repeat", &[], arg(self.repeat.as_ref())),
+            ("prebuffer", &[], arg(self.prebuffer.as_ref())),
+            ("postbuffer", &[], arg(self.postbuffer.as_ref())),
+            ("max_duration", &[], arg(self.max_duration.as_ref())),
+            ("backend", &[], arg(self.backend.as_ref())),
+            ("ffmpeg_path", &[], path(&self.ffmpeg_path)),
+            ("ffprobe_path", &[], path(&self.ffprobe_path)),
+            ("ffmpeg_timeout", &[], arg(self.ffmpeg_timeout.as_ref())),
+            ("workdir", &[], path(&self.workdir)),
+            ("codec", &[], arg(self.codec.as_ref())),
+            ("crf", &[], arg(self.crf.as_ref())),
+            ("preset", &[], arg(self.preset.as_ref())),
+            ("hwaccel", &[], arg(self.hwaccel.as_ref())),
+            ("colorspace", &[], arg(self.colorspace.as_ref())),
+            ("color_range", &[], arg(self.color_range.as_ref())),
+            ("scale_flags", &[], arg(self.scale_flags.as_ref())),
+            ("frame_format", &[], arg(self.frame_format.as_ref())),
+        ]
+        .into_iter()
+        .filter_map(|(id, overridden_by, value)| Some((id, overridden_by, value?)))
+        .collect()
+    }
 }
 
 impl Args {
+    /// Parses the command line arguments like `parse()`,
+    /// filling in options not given on the command line from `--config-file`.
+    /// The file values are passed through the command line parser as well,
+    /// so they are validated exactly like options given directly.
+    pub fn parse_with_config_file() -> Result<Self> {
+        let cli_args: Vec<OsString> = env::args_os().collect();
+        let matches = Self::command().get_matches_from(&cli_args);
+        let args = Self::from_arg_matches(&matches)?;
+        let Some(config_path) = &args.config_file else {
+            return Ok(args);
+        };
+        let config_file = ConfigFile::read(config_path)?;
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        // Values from the file are placed before the command line arguments
+        // and only if no option overriding them was given.
+        let mut merged_args = cli_args[..1].to_vec();
+        for (id, overridden_by, value) in config_file.values() {
+            if given(id) || overridden_by.iter().any(|id| given(id)) {
+                continue;
+            }
+            let mut arg = OsString::from(format!("--{}=", id.replace('_', "-")));
+            arg.push(value);
+            merged_args.push(arg);
+        }
+        merged_args.extend_from_slice(&cli_args[1..]);
+        Self::try_parse_from(merged_args)
+            .with_context(|| format!("Invalid setting in config file {}.", config_path.display()))
+    }
+
+    /// Video backend selected by `--backend`, using the configured executables.
+    pub fn video_backend(&self) -> Result<Box<dyn VideoBackend>> {
+        self.backend.create(
+            self.ffmpeg_path.as_deref(),
+            self.ffprobe_path.as_deref(),
+            EncoderOptions::new(self.codec, self.crf, self.preset, self.hwaccel)?
+                .with_scale_algorithm(self.scale_flags().unwrap_or(UPSCALE_ALGORITHM))
+                .with_color(self.video_color()),
+            self.ffmpeg_timeout.map(Duration::from_secs),
+        )
+    }
+
+    /// New framebuffer for this run, deleted when dropped unless `--keep-frames` was given.
+    pub fn frame_buffer(&self) -> Result<FrameBufferGuard> {
+        scoped_framebuffer(&self.workdir()?, self.frame_format()?, self.keep_frames)
+    }
+
+    /// Colorspace and range a video is encoded with,
+    /// `COLORSPACE` and `COLOR_RANGE` unless given.
+    pub fn video_color(&self) -> VideoColor {
+        VideoColor {
+            colorspace: self.colorspace.unwrap_or(COLORSPACE),
+            range: self.color_range.unwrap_or(COLOR_RANGE),
+        }
+    }
+
+    /// Colorspace and range a video is split with,
+    /// `None` to use those it is tagged with unless one of them was given.
+    pub fn split_color(&self) -> Option<VideoColor> {
+        (self.colorspace.is_some() || self.color_range.is_some()).then(|| self.video_color())
+    }
+
+    /// Scaling algorithm selected with `--scale-flags`, `None` if not given.
+    fn scale_flags(&self) -> Option<&'static str> {
+        // The parser only accepts entries of `SCALE_ALGORITHMS`.
+        SCALE_ALGORITHMS
+            .into_iter()
+            .find(|&algorithm| self.scale_flags.as_deref() == Some(algorithm))
+    }
+
+    /// Scaling algorithm used when splitting a video,
+    /// the one selected with `--scale-flags` or the first of `SCALE_ALGORITHMS`.
+    pub fn split_scale_algorithm(&self) -> &'static str {
+        self.scale_flags().unwrap_or(SCALE_ALGORITHMS[0])
+    }
+
+    /// Scaling algorithms tried in order when splitting a video:
+    /// `split_scale_algorithm` first, followed by all others if `--auto-filter` is set.
+    pub fn split_scale_algorithms(&self) -> Vec<&'static str> {
+        let first = self.split_scale_algorithm();
+        let mut algorithms = vec![first];
+        if self.auto_filter {
+            algorithms.extend(
+                SCALE_ALGORITHMS
+                    .into_iter()
+                    .filter(|&algorithm| algorithm != first),
+            );
+        }
+        algorithms
+    }
+
+    /// Section of the input video selected by `--seek` and `--duration`.
+    pub fn video_window(&self) -> VideoWindow {
+        VideoWindow {
+            seek: self.seek.clone(),
+            duration: self.duration.clone(),
+        }
+    }
+
+    /// Input file, required by every mode that reads a file or video.
+    pub fn input_file(&self) -> Result<&Path> {
+        self.inputfile
+            .as_deref()
+            .context("An input file (-i) is required in this mode.")
+    }
+
+    /// Files and directories to pack into an archive, given with `--archive`
+    /// or as an input file that is a directory. `None` if a single file is encoded.
+    pub fn archive_paths(&self) -> Option<Vec<PathBuf>> {
+        self.archive.clone().or_else(|| {
+            self.inputfile
+                .as_ref()
+                .filter(|inputfile| inputfile.is_dir())
+                .map(|inputfile| vec![inputfile.clone()])
+        })
+    }
+
+    /// If the input is read from stdin, given as `-i -`.
+    pub fn input_is_stdin(&self) -> bool {
+        self.inputfile
+            .as_deref()
+            .is_some_and(|inputfile| inputfile == Path::new(STDIO_PATH))
+    }
+
+    /// If the output is written to stdout, given as `-`.
+    pub fn output_is_stdout(&self) -> bool {
+        self.outputfile
+            .as_deref()
+            .is_some_and(|outputfile| outputfile == Path::new(STDIO_PATH))
+    }
+
+    /// Data to encode and its header, read from the input file or taken from `--message`.
+    /// The header records the settings of `converter`, so decoding can check them.
+    ///
+    /// # Arguments
+    /// * `converter` - Converter the data will be encoded with.
+    pub fn payload(&self, converter: &Converter) -> Result<(InputData, HeaderData)> {
+        let mut is_archive = false;
+        let (data, is_message, file_name) = if let Some(message) = &self.message {
+            (InputData::Read(message.as_bytes().to_vec()), true, None)
+        } else if let Some(paths) = self.archive_paths() {
+            let archive = pack_paths(&paths)?;
+            eprintln!(
+                "Packed {} files into an archive of {}.",
+                read_index(&archive)?.len(),
+                format_bytes(archive.len() as u64)
+            );
+            is_archive = true;
+            (InputData::Read(archive), false, None)
+        } else if self.input_is_stdin() {
+            // The header needs the length and hash of the whole data,
+            // so stdin is read completely before encoding starts.
+            let mut data = Vec::new();
+            io::stdin()
+                .lock()
+                .read_to_end(&mut data)
+                .context("Unable to read data from stdin.")?;
+            (InputData::Read(data), false, None)
+        } else {
+            let inputfile = self.input_file()?;
+            let data = read_input_file(inputfile, self.mmap)?;
+            let file_name = inputfile.file_name().and_then(|name| name.to_str());
+            if file_name.is_none() {
+                eprintln!("Input file name is not valid UTF-8 and will not be stored.");
+            }
+            (data, false, file_name)
+        };
+        let compressed = if self.compress {
+            compress(&data)?
+        } else {
+            None
+        };
+        let (data, is_compressed) = if let Some(compressed) = compressed {
+            eprintln!(
+                "Compressed {} to {}.",
+                format_bytes(data.len() as u64),
+                format_bytes(compressed.len() as u64)
+            );
+            (InputData::Read(compressed), true)
+        } else {
+            if self.compress {
+                eprintln!("Data is not compressible, storing it uncompressed.");
+            }
+            (data, false)
+        };
+        let (data, encryption) = if self.encrypt {
+            let (ciphertext, params) = encrypt(&data, &self.passphrase(true)?)?;
+            (InputData::Read(ciphertext), Some(params))
+        } else {
+            (data, None)
+        };
+        let mut header = self.data_header(converter, &data);
+        header.is_message = is_message;
+        header.is_archive = is_archive;
+        header.is_compressed = is_compressed;
+        header.encryption = encryption;
+        // The header is not encrypted, so the file name would give away what the data is.
+        if let Some(file_name) = file_name
+            && header.encryption.is_none()
+        {
+            header = header.with_file_name(file_name)?;
+        }
+        if let Some(note) = &self.note {
+            header = header.with_note(note)?;
+        }
+        Ok((data, header))
+    }
+
+    /// Header describing `data` with the encoding settings of these arguments and `converter`,
+    /// without anything about where the data came from.
+    ///
+    /// # Arguments
+    /// * `converter` - Converter the data will be encoded with.
+    /// * `data` - Data to encode, after compression and encryption.
+    pub fn data_header(&self, converter: &Converter, data: &[u8]) -> HeaderData {
+        let mut header = HeaderData::with_hash_algorithm(data, self.parity_interval, self.hash);
+        header.fountain_overhead = self.fountain_overhead();
+        header.level_mapping = self.level_mapping;
+        header.color = self.video_color();
+        header.fec = self.fec;
+        header.interleave_depth = self.interleave;
+        [header.prebuffer_frames, header.postbuffer_frames] = self.buffer_frames();
+        header.converter_config = Some(converter.config_bytes());
+        header
+    }
+
+    /// Number of blank frames before and after the data, see `prebuffer` and `postbuffer`.
+    pub fn buffer_frames(&self) -> [u16; 2] {
+        [self.prebuffer, self.postbuffer]
+    }
+
+    /// Output file, required by every mode that writes a file or video.
+    pub fn output_file(&self) -> Result<&Path> {
+        self.outputfile
+            .as_deref()
+            .context("An output file is required in this mode.")
+    }
+
     /// Use command line arguments to constuct converter instance.
     pub fn to_converter_config(&self) -> Result<Converter> {
-        let video_resolution = match self.frame_resolution.as_str() {
-            "240p" => resolutions::SD_240,
-            "360p" => resolutions::SD_360,
-            "480p" => resolutions::SD_480,
-            "720p" => resolutions::HD_720,
-            "1080p" => resolutions::HD_1080,
-            "1440p" => resolutions::QHD_1440,
-            "4k" => resolutions::UHD_4K,
-            "8k" => resolutions::UHD_8K,
-            _ => bail!("Invalid resolution specified."),
+        let converter = if let Some(config) = &self.config {
+            Converter::from_base64(config)?
+                .with_finder_patterns(self.camera)?
+                .with_alpha_bits(self.alpha_bits)?
+        } else {
+            let Some(&(_, video_resolution)) = resolutions::ALL
+                .iter()
+                .find(|(name, _)| *name == self.frame_resolution)
+            else {
+                bail!("Invalid resolution specified.");
+            };
+            let (data_fps, video_fps) = self.framerates();
+            self.converter_for_resolution(video_resolution, data_fps, video_fps)?
         };
-        let data_resolution = [
-            video_resolution[0] / self.data_pixel_size,
-            video_resolution[1] / self.data_pixel_size,
-        ];
-        Converter::new(
-            [
+        converter
+            .with_quantization_bias(self.quantization_bias()?)?
+            .with_calibration_frame(self.calibration_frame)?
+            .with_luma_only(self.luma)?
+            .with_downsample_scaler(self.downsample)?
+            .with_sample_core(self.sample_core)
+            .map(|converter| {
+                converter
+                    .with_header_interval(self.header_interval)
+                    .with_progress(!self.quiet)
+            })
+    }
+
+    /// Converters `--auto` tries when no header is found, built from these arguments
+    /// with every combination of `AUTO_DATA_PIXEL_SIZES` and `AUTO_COLOR_BITS`
+    /// the frame resolution allows, grouped by data pixel size.
+    /// The combination of these arguments is left out, as it was already tried.
+    pub fn auto_candidates(&self) -> Vec<AutoCandidate> {
+        let current = (self.data_pixel_size, self.color_bits().ok());
+        let mut candidates = Vec::new();
+        for data_pixel_size in AUTO_DATA_PIXEL_SIZES {
+            for color_bits in AUTO_COLOR_BITS {
+                if current == (data_pixel_size, Some(color_bits)) {
+                    continue;
+                }
+                let mut args = self.clone();
+                args.data_pixel_size = data_pixel_size;
+                args.data_width = None;
+                args.data_height = None;
+                args.bits = Some(
+                    color_bits
+                        .iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                );
+                // Combinations the resolution or the other settings do not allow are skipped.
+                if let Ok(converter) = args.to_converter_config() {
+                    candidates.push(AutoCandidate {
+                        data_pixel_size,
+                        color_bits,
+                        converter,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Droplet frames per hundred data frames requested with `--fountain`,
+    /// zero if no droplets are used.
+    pub fn fountain_overhead(&self) -> u16 {
+        self.fountain
+            .map_or(0, |overhead| overhead.unwrap_or(FOUNTAIN_OVERHEAD_PERCENT))
+    }
+
+    /// Image format of the frames kept on disk, from `--frame-format`.
+    ///
+    /// # Errors
+    /// Fails if data is encoded in the alpha channel and the format can not store it.
+    pub fn frame_format(&self) -> Result<FrameFormat> {
+        if self.alpha_bits > 0 && self.frame_format != FrameFormat::Png {
+            bail!(
+                "Only PNG frames keep the alpha channel, use --frame-format png with --alpha-bits."
+            );
+        }
+        Ok(self.frame_format)
+    }
+
+    /// Directory framebuffers are created in, from `--workdir`,
+    /// `$VORTEXKEY_WORKDIR` or the temp directory.
+    ///
+    /// # Errors
+    /// Fails if a configured directory does not exist or is not writable.
+    pub fn workdir(&self) -> Result<PathBuf> {
+        resolve_workdir(self.workdir.as_deref())
+    }
+
+    /// Passphrase to encrypt or decrypt the data with. In order of precedence
+    /// read from the first line of `--passphrase-file`, the environment variable `PASSPHRASE_ENV`
+    /// or asked for on the terminal without echoing it.
+    /// It is never taken from the command line, where other users and the shell history could see it.
+    ///
+    /// # Arguments
+    /// * `confirm` - If a passphrase typed on the terminal is asked for twice, used when encrypting.
+    ///
+    /// # Errors
+    /// Fails if the file can not be read, the passphrase is empty,
+    /// the confirmation differs or there is no terminal to ask on.
+    pub fn passphrase(&self, confirm: bool) -> Result<String> {
+        let passphrase = if let Some(path) = &self.passphrase_file {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Unable to read passphrase file {}.", path.display()))?;
+            contents.lines().next().unwrap_or_default().to_owned()
+        } else if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+            passphrase
+        } else {
+            let term = Term::stderr();
+            if !term.is_term() {
+                bail!(
+                    "No terminal to ask for the passphrase on, set {PASSPHRASE_ENV} or use --passphrase-file."
+                );
+            }
+            term.write_str("Passphrase: ")?;
+            let passphrase = term.read_secure_line()?;
+            if confirm {
+                term.write_str("Repeat passphrase: ")?;
+                if term.read_secure_line()? != passphrase {
+                    bail!("Passphrases do not match.");
+                }
+            }
+            passphrase
+        };
+        if passphrase.is_empty() {
+            bail!("Passphrase is empty.");
+        }
+        Ok(passphrase)
+    }
+
+    /// Bitrate in kbit/s `--simulate-recompress` re-encodes the video at,
+    /// `None` if no simulation was requested.
+    pub fn simulated_bitrate(&self) -> Option<u32> {
+        self.simulate_recompress
+            .map(|bitrate| bitrate.unwrap_or(SIMULATED_RECOMPRESS_KBPS))
+    }
+
+    /// SHA256 the decoded data must match, parsed from `--expect-sha256`.
+    /// `None` if not set.
+    pub fn expected_sha256(&self) -> Result<Option<[u8; 32]>> {
+        let Some(hex) = &self.expect_sha256 else {
+            return Ok(None);
+        };
+        let bytes = hex_string_to_bytes(hex.trim()).context("Invalid --expect-sha256.")?;
+        let Ok(hash) = <[u8; 32]>::try_from(bytes) else {
+            bail!("--expect-sha256 expects 64 hex digits, got {hex:?}.");
+        };
+        Ok(Some(hash))
+    }
+
+    /// Constructs the converter from the settings stored in a video header,
+    /// applying the decoding options not stored with them.
+    ///
+    /// # Arguments
+    /// * `converter_config` - Converter settings stored in the header.
+    /// * `downsample_scaler` - Downsample scaler stored in the header,
+    ///   zero to use `--downsample` instead.
+    pub fn header_converter(
+        &self,
+        converter_config: &[u8],
+        downsample_scaler: u32,
+    ) -> Result<Converter> {
+        let downsample_scaler = if downsample_scaler == 0 {
+            self.downsample
+        } else {
+            downsample_scaler
+        };
+        Converter::from_config_bytes(converter_config)?
+            .with_finder_patterns(self.camera)?
+            .with_alpha_bits(self.alpha_bits)?
+            .with_downsample_scaler(downsample_scaler)?
+            .with_sample_core(self.sample_core)
+            .map(|converter| converter.with_progress(!self.quiet))
+    }
+
+    /// Constructs a converter for every preset resolution using the remaining settings.
+    /// Resolutions the settings are invalid for map to the error explaining why.
+    pub fn converter_per_resolution(&self) -> Vec<(&'static str, Result<Converter>)> {
+        let (data_fps, video_fps) = self.framerates();
+        resolutions::ALL
+            .iter()
+            .map(|&(name, video_resolution)| {
+                (
+                    name,
+                    self.converter_for_resolution(video_resolution, data_fps, video_fps),
+                )
+            })
+            .collect()
+    }
+
+    /// Data and video framerate to use, respecting `--target-fps`.
+    fn framerates(&self) -> (u32, u32) {
+        match self.target_fps {
+            Some(target_fps) => {
+                let data_fps = Converter::clean_data_fps(target_fps, self.data_fps);
+                if data_fps != self.data_fps {
+                    eprintln!(
+                        "Data fps ({}) does not map cleanly onto target fps ({}). Using data fps {} instead.",
+                        self.data_fps, target_fps, data_fps
+                    );
+                }
+                (data_fps, target_fps)
+            }
+            None => (self.data_fps, self.video_fps),
+        }
+    }
+
+    /// Frame size and number of data blocks along one axis of the frame,
+    /// the blocks either set explicitly or given by the data pixel size.
+    /// If the frame is not a whole multiple of the data pixel size,
+    /// it is cut to the largest even multiple with a warning, as the remaining edge pixels
+    /// could not hold data, or fails with `--strict-dimensions`.
+    /// Explicit sizes are checked by `Converter::new` instead.
+    ///
+    /// # Arguments
+    /// * `dimension` - Name of the axis used in messages.
+    /// * `size` - Size of the frame along the axis in pixels.
+    /// * `explicit_size` - Size set by `--data-width` or `--data-height`.
+    fn data_size(
+        &self,
+        dimension: &str,
+        size: u32,
+        explicit_size: Option<u32>,
+    ) -> Result<(u32, u32)> {
+        if let Some(explicit_size) = explicit_size {
+            return Ok((size, explicit_size));
+        }
+        let remainder = size % self.data_pixel_size;
+        if remainder == 0 {
+            return Ok((size, size / self.data_pixel_size));
+        }
+        if self.strict_dimensions {
+            bail!(
+                "Frame {dimension} ({size}) is not a whole multiple of the data pixel size ({}), {remainder} pixels would be left over.",
+                self.data_pixel_size
+            );
+        }
+        let mut blocks = size / self.data_pixel_size;
+        // Encoders subsample colors in 2x2 pixel blocks and reject odd frame sizes.
+        if !(blocks * self.data_pixel_size).is_multiple_of(2) {
+            blocks -= 1;
+        }
+        let cut_size = blocks * self.data_pixel_size;
+        eprintln!(
+            "Warning: Frame {dimension} ({size}) is not a whole multiple of the data pixel size ({}), cutting it to {cut_size} pixels.",
+            self.data_pixel_size
+        );
+        Ok((cut_size, blocks))
+    }
+
+    /// Number of bits encoded in each color channel,
+    /// parsed from `--bits` or unpacked from the digits of `--colorbits`.
+    fn color_bits(&self) -> Result<[u32; COLOR_CHANNELS]> {
+        let Some(bits) = &self.bits else {
+            return Ok([
                 (self.colorbits / 100),
                 (self.colorbits % 100) / 10,
                 self.colorbits % 10,
-            ],
-            self.data_fps,
-            self.video_fps,
-            video_resolution,
-            data_resolution,
-        )
+            ]);
+        };
+        let values = bits
+            .split(',')
+            .map(|value| {
+                value
+                    .trim()
+                    .parse::<u32>()
+                    .with_context(|| format!("Invalid channel bit count {value:?} in --bits."))
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        let Ok(color_bits) = <[u32; COLOR_CHANNELS]>::try_from(values) else {
+            bail!("--bits expects {COLOR_CHANNELS} comma separated values (R,G,B), got {bits:?}.");
+        };
+        if let Some(value) = color_bits.iter().find(|&&x| x > u8::BITS) {
+            bail!("Channel bit count {value} in --bits is out of range 0-8.");
+        }
+        Ok(color_bits)
+    }
+
+    /// Offset added to the pixel value of every level in each color channel,
+    /// parsed from `--quantization-bias`. Zero if not set.
+    fn quantization_bias(&self) -> Result<[i8; COLOR_CHANNELS]> {
+        let Some(bias) = &self.quantization_bias else {
+            return Ok([0; COLOR_CHANNELS]);
+        };
+        let values = bias
+            .split(',')
+            .map(|value| {
+                value.trim().parse::<i8>().with_context(|| {
+                    format!("Invalid channel offset {value:?} in --quantization-bias.")
+                })
+            })
+            .collect::<Result<Vec<i8>>>()?;
+        let Ok(quantization_bias) = <[i8; COLOR_CHANNELS]>::try_from(values) else {
+            bail!(
+                "--quantization-bias expects {COLOR_CHANNELS} comma separated values (R,G,B), got {bias:?}."
+            );
+        };
+        Ok(quantization_bias)
+    }
+
+    /// Constructs a converter for `video_resolution` using the remaining settings.
+    ///
+    /// # Arguments
+    /// * `video_resolution` - (Width, Height) of the output video.
+    /// * `data_fps` - Data framerate.
+    /// * `video_fps` - Output video framerate.
+    fn converter_for_resolution(
+        &self,
+        video_resolution: [u32; 2],
+        data_fps: u32,
+        video_fps: u32,
+    ) -> Result<Converter> {
+        let (frame_width, data_width) =
+            self.data_size("width", video_resolution[0], self.data_width)?;
+        let (frame_height, data_height) =
+            self.data_size("height", video_resolution[1], self.data_height)?;
+        Converter::new(
+            self.color_bits()?,
+            data_fps,
+            video_fps,
+            [frame_width, frame_height],
+            [data_width, data_height],
+        )?
+        .with_finder_patterns(self.camera)?
+        .with_alpha_bits(self.alpha_bits)
     }
 }
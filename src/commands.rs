@@ -0,0 +1,653 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Operating modes of the command line program other than video to data,
+//! see `decode` for that one. Each runs on the parsed `Args`
+//! and prints its results to stdout.
+
+use std::{ffi::OsStr, fs, path::Path, time};
+
+use anyhow::{Context, Result, bail};
+
+use crate::cli::{Args, OperatingMode};
+use vortexkey::{
+    backend::{Rotation, VideoBackend, VideoWindow},
+    constants::{BENCH_BYTES, MAX_FRAME_COUNT, SECONDS_PER_MINUTE},
+    converter::{Converter, FileReport},
+    filesys::FrameBuffer,
+    stats::{BenchSummary, PhaseTiming, RunSummary},
+    utils::{base64_encode, bytes_to_hex_string, compare_bytes, format_bytes, format_duration},
+};
+
+/// Check the input file and the paths to archive exist, or that the mode can read its input from stdin,
+/// and that only vtd mode writes its output to stdout.
+///
+/// # Errors
+/// Fails if a path is missing or the mode can not read stdin or write stdout.
+pub fn check_io_paths(args: &Args) -> Result<()> {
+    if args.output_is_stdout() && args.mode != OperatingMode::VideoToData {
+        bail!("Writing the output to stdout is only supported in vtd mode.");
+    }
+    if args.output_is_stdout() && args.json {
+        bail!("The JSON summary is printed to stdout, write the output to a file instead.");
+    }
+    if args.input_is_stdin() {
+        if !matches!(
+            args.mode,
+            OperatingMode::DataToVideo
+                | OperatingMode::Split
+                | OperatingMode::Reheader
+                | OperatingMode::Estimate
+                | OperatingMode::Verify
+        ) {
+            bail!(
+                "Reading the input from stdin is only supported in dtv, split, reheader, estimate and verify mode."
+            );
+        }
+    } else if let Some(inputfile) = &args.inputfile
+        && !inputfile.exists()
+    {
+        bail!("Provided input file at {inputfile:?} could not be found.");
+    }
+    for path in args.archive.iter().flatten() {
+        if !path.exists() {
+            bail!("Provided path to archive at {path:?} could not be found.");
+        }
+    }
+    Ok(())
+}
+
+/// Check the converter settings in `args` and print them with their config token.
+///
+/// # Errors
+/// Fails if the settings are invalid.
+pub fn validate_settings(args: &Args) -> Result<()> {
+    let converter = args
+        .to_converter_config()
+        .context("Invalid converter settings.")?;
+    println!("Converter settings are valid.");
+    println!("{converter}");
+    println!("Config token: {}", converter.to_base64());
+    Ok(())
+}
+
+/// Generate the frames for the input file or message in a kept frame directory,
+/// whose path is printed, so they can be combined with `reheader_video` later.
+///
+/// # Errors
+/// Fails if the input can not be read, the video would be too long
+/// or the frames can not be written.
+pub fn split_frames(args: &Args, converter: &Converter) -> Result<()> {
+    let (data, header) = args.payload(converter)?;
+    check_video_length(args, converter, data.len())?;
+    let mut frame_buffer = args.frame_buffer()?;
+    timed_block!("frame generation", {
+        converter.deconstruct_bytes(
+            &frame_buffer,
+            &data,
+            &header,
+            args.header_frames as usize,
+            args.repeat as usize,
+        )?;
+    });
+    // Printed on drop, so it can be passed to reheader with --frames-dir.
+    frame_buffer.keep();
+    Ok(())
+}
+
+/// Regenerate the header frames in the frame directory given with `--frames-dir`
+/// for the input file or message, then combine the frames into a video at `args.outputfile`.
+///
+/// # Errors
+/// Fails if the data is encrypted, no frame directory was given,
+/// or the header can not be written or the frames combined.
+pub fn reheader_video(args: &Args, converter: &Converter) -> Result<()> {
+    // A fresh salt and nonce would not match the ciphertext in the existing data frames.
+    if args.encrypt {
+        bail!("Encrypted videos can not be reheadered, encode the data again instead.");
+    }
+    let backend = args.video_backend()?;
+    let (_, header) = args.payload(converter)?;
+    let Some(frames_dir) = &args.frames_dir else {
+        bail!("Reheader needs the frame directory printed by split, pass it with --frames-dir.");
+    };
+    let frame_buffer = FrameBuffer::open(frames_dir)?.with_format(args.frame_format()?);
+    timed_block!("header regeneration", {
+        converter.regenerate_header(
+            &frame_buffer,
+            &header,
+            args.header_frames as usize,
+            args.repeat as usize,
+        )?;
+    });
+    timed_block!("frame combination", {
+        converter.combine_frames(
+            backend.as_ref(),
+            &frame_buffer,
+            args.output_file()?,
+            args.overwrite,
+        )?;
+    });
+    Ok(())
+}
+
+/// Encode the data at `args.inputfile` into a video at `args.outputfile`.
+/// Prints a summary as JSON afterwards if `args.json` is set.
+///
+/// # Errors
+/// Fails if the output exists without `--overwrite`, the input can not be read,
+/// the video would be too long or encoding fails.
+pub fn encode_video(args: &Args, converter: &Converter) -> Result<()> {
+    let backend = args.video_backend()?;
+    let output_file = args.output_file()?;
+    // Checked before encoding, so no time is spent on a video that can not be saved.
+    if !args.overwrite && output_file.exists() {
+        bail!("File at video output path exists and overwrite is not enabled.");
+    }
+    let start = time::Instant::now();
+    let (data, header) = args.payload(converter)?;
+    let data_len = data.len();
+    let (frames, duration) = check_video_length(args, converter, data_len)?;
+    timed_block!("video encoding", {
+        converter.encode_to_video(
+            backend.as_ref(),
+            &data,
+            &header,
+            args.header_frames as usize,
+            args.repeat as usize,
+            output_file,
+        )?;
+    });
+    let simulated_report = match args.simulated_bitrate() {
+        Some(bitrate_kbps) => simulate_recompress(
+            converter,
+            backend.as_ref(),
+            output_file,
+            bitrate_kbps,
+            &*args.frame_buffer()?,
+            &*args.frame_buffer()?,
+            args.split_scale_algorithm(),
+        )?,
+        None => None,
+    };
+    if args.json {
+        RunSummary {
+            mode: "dtv",
+            bytes_read: data_len as u64,
+            bytes_written: fs::metadata(output_file)
+                .context("Unable to read size of the encoded video.")?
+                .len(),
+            frames,
+            video_seconds: Some(duration.as_secs_f64()),
+            elapsed_seconds: start.elapsed().as_secs_f64(),
+            message: None,
+            report: simulated_report.as_ref(),
+            expected_hash_match: None,
+        }
+        .print()?;
+    }
+    Ok(())
+}
+
+/// Re-encode a copy of the video at `video_file` at `bitrate_kbps` like a platform would,
+/// then decode it and print the errors found.
+/// Returns the report of the decoded copy, `None` if it could not be decoded at all.
+///
+/// # Arguments
+/// * `converter` - Converter the video was encoded with.
+/// * `backend` - Video backend used to re-encode and split the copy.
+/// * `video_file` - Encoded video, left unchanged.
+/// * `bitrate_kbps` - Bitrate in kbit/s of the re-encoded copy.
+/// * `recompress_dir` - Directory the copy is stored in.
+/// * `frame_buffer` - Framebuffer the frames of the copy are split into.
+///   Splitting clears it, so it must not be `recompress_dir`.
+/// * `scale_algorithm` - Scaling algorithm the copy is split with.
+fn simulate_recompress(
+    converter: &Converter,
+    backend: &dyn VideoBackend,
+    video_file: &Path,
+    bitrate_kbps: u32,
+    recompress_dir: &FrameBuffer,
+    frame_buffer: &FrameBuffer,
+    scale_algorithm: &str,
+) -> Result<Option<FileReport>> {
+    let extension = video_file.extension().unwrap_or(OsStr::new("mp4"));
+    let recompressed_file = recompress_dir
+        .path()
+        .join("recompressed")
+        .with_extension(extension);
+    timed_block!(format!("recompression at {bitrate_kbps} kbit/s"), {
+        backend.recompress(video_file, &recompressed_file, bitrate_kbps)?;
+    });
+    converter.split_video(
+        backend,
+        frame_buffer,
+        &recompressed_file,
+        scale_algorithm,
+        &VideoWindow::default(),
+        Rotation::None,
+        None,
+    )?;
+    let report = match converter.reconstruct_to_vec(frame_buffer, None) {
+        Ok((_, report)) => report,
+        Err(err) => {
+            eprintln!(
+                "Recompressed at {bitrate_kbps} kbit/s the video can not be decoded: {err:#}"
+            );
+            return Ok(None);
+        }
+    };
+    eprintln!(
+        "Recompressed at {bitrate_kbps} kbit/s: Corrected: {}  Uncorrectable: {}  Lost frames: {}  Hash match: {}",
+        report.corrected_errors, report.uncorrected_errors, report.lost_frames, report.hash_match
+    );
+    if !report.hash_match {
+        eprintln!(
+            "The data does not survive this bitrate, try fewer bits per channel, larger data units or a stronger error correction."
+        );
+    }
+    Ok(Some(report))
+}
+
+/// Encode the input file or message to frames in memory with the settings in `args`
+/// and decode them again, without a video backend.
+/// Prints PASS if the data comes back unchanged and FAIL otherwise.
+///
+/// # Errors
+/// Fails if the data can not be encoded, or decoding fails or returns different data.
+pub fn verify_round_trip(args: &Args, converter: &Converter) -> Result<()> {
+    let (data, header) = args.payload(converter)?;
+    let frames = converter.encode_to_frames(
+        &data,
+        &header,
+        args.header_frames as usize,
+        args.repeat as usize,
+    )?;
+    let (decoded, report) = match converter.decode_from_frames(&frames) {
+        Ok(decoded) => decoded,
+        Err(err) => {
+            println!("FAIL: {} frames could not be decoded.", frames.len());
+            return Err(err);
+        }
+    };
+    eprintln!(
+        "Errors during file reconstruction: Corrected: {}  Uncorrectable: {}",
+        report.corrected_errors, report.uncorrected_errors
+    );
+    if !report.hash_match || decoded != *data {
+        println!(
+            "FAIL: {} bytes in {} frames did not decode to the encoded data.",
+            data.len(),
+            frames.len()
+        );
+        bail!("Round trip verification failed.");
+    }
+    println!(
+        "PASS: {} bytes in {} frames decoded to the encoded data.",
+        data.len(),
+        frames.len()
+    );
+    Ok(())
+}
+
+/// Encode `args.file_size` bytes of synthetic data, `BENCH_BYTES` by default,
+/// into a video in the work directory and print the throughput of every phase.
+/// Frames are generated to disk and combined separately, so both phases are timed on their own.
+/// With `args.bench_decode` set the video is split and decoded again as well.
+///
+/// # Errors
+/// Fails if any phase fails or the video would be too long.
+pub fn run_bench(args: &Args, converter: &Converter) -> Result<()> {
+    let backend = args.video_backend()?;
+    let bytes = args.file_size.unwrap_or(BENCH_BYTES);
+    let data = bench_data(usize::try_from(bytes)?);
+    let header = args.data_header(converter, &data);
+    let (frames, _) = check_video_length(args, converter, data.len())?;
+    let frame_buffer = args.frame_buffer()?;
+    // Kept apart from the frames, as splitting clears their frame buffer.
+    let video_dir = args.frame_buffer()?;
+    // Matroska takes every codec, with or without an alpha channel.
+    let video_file = video_dir.path().join("bench.mkv");
+    let mut phases = Vec::new();
+    timed_block!("frame generation", elapsed, {
+        converter.deconstruct_bytes(
+            &frame_buffer,
+            &data,
+            &header,
+            args.header_frames as usize,
+            args.repeat as usize,
+        )?;
+    });
+    phases.push(PhaseTiming::new("frame generation", elapsed, bytes));
+    timed_block!("frame combination", elapsed, {
+        converter.combine_frames(backend.as_ref(), &frame_buffer, &video_file, true)?;
+    });
+    phases.push(PhaseTiming::new("frame combination", elapsed, bytes));
+    let mut data_match = None;
+    if args.bench_decode {
+        timed_block!("video splitting", elapsed, {
+            converter.split_video(
+                backend.as_ref(),
+                &frame_buffer,
+                &video_file,
+                args.split_scale_algorithm(),
+                &VideoWindow::default(),
+                Rotation::None,
+                args.split_color(),
+            )?;
+        });
+        phases.push(PhaseTiming::new("video splitting", elapsed, bytes));
+        timed_block!("file reconstruction", elapsed, {
+            let (decoded, _) = converter.reconstruct_to_vec(&frame_buffer, None)?;
+            data_match = Some(decoded == data);
+        });
+        phases.push(PhaseTiming::new("file reconstruction", elapsed, bytes));
+    }
+    BenchSummary {
+        mode: "bench",
+        bytes,
+        frames,
+        video_bytes: fs::metadata(&video_file)
+            .context("Unable to read size of the benchmark video.")?
+            .len(),
+        phases,
+        data_match,
+    }
+    .print(args.json)
+}
+
+/// Synthetic data of `len` bytes for bench mode, the BLAKE3 output stream of a fixed input.
+/// It does not compress, like most real data, and is the same on every run,
+/// so benchmarks of different settings and machines are comparable.
+///
+/// # Arguments
+/// * `len` - Bytes of data to generate.
+fn bench_data(len: usize) -> Vec<u8> {
+    let mut data = vec![0; len];
+    blake3::Hasher::new()
+        .update(b"vortexkey bench")
+        .finalize_xof()
+        .fill(&mut data);
+    data
+}
+
+/// Check the video encoding `data_len` bytes stays within practical limits
+/// and print its frame count and length.
+/// Returns the frame count and length.
+fn check_video_length(
+    args: &Args,
+    converter: &Converter,
+    data_len: usize,
+) -> Result<(usize, time::Duration)> {
+    let (frames, duration) = converter.check_video_length(
+        data_len,
+        args.header_frames as usize,
+        args.parity_interval,
+        args.fountain_overhead(),
+        args.repeat as usize,
+        args.fec,
+        args.buffer_frames(),
+        args.max_duration.map(time::Duration::from_secs),
+    )?;
+    eprintln!(
+        "Video will have {frames} frames and be {} long.",
+        format_duration(duration)
+    );
+    Ok((frames, duration))
+}
+
+/// Split the video at `args.inputfile` and print its header.
+///
+/// # Errors
+/// Fails if the video can not be split or no header is found.
+pub fn inspect_video(args: &Args, converter: &Converter) -> Result<()> {
+    let backend = args.video_backend()?;
+    let frame_buffer = args.frame_buffer()?;
+    converter.split_video(
+        backend.as_ref(),
+        &frame_buffer,
+        args.input_file()?,
+        args.split_scale_algorithm(),
+        &args.video_window(),
+        args.rotate,
+        args.split_color(),
+    )?;
+    let (header, vote) = converter.inspect_header(&frame_buffer)?;
+    println!("Version:         {:?}", header.version_code);
+    println!("Data length:     {} bytes", header.data_len);
+    println!("Hash algorithm:  {:?}", header.hash_algorithm);
+    println!(
+        "Hash:            {}",
+        bytes_to_hex_string(&header.data_hash)
+    );
+    println!("Parity interval: {}", header.parity_interval);
+    println!("Fountain:        {}%", header.fountain_overhead);
+    println!("Downsample:      {}", header.downsample_scaler);
+    println!("Header interval: {}", header.header_interval);
+    println!("Sequenced:       {}", header.sequence_numbers);
+    println!("Frame CRC:       {}", header.frame_crc);
+    if let Some(data_frames) = header.data_frames {
+        println!("Data frames:     {data_frames}");
+    }
+    println!("Message:         {}", header.is_message);
+    println!("Archive:         {}", header.is_archive);
+    println!("Compressed:      {}", header.is_compressed);
+    println!("Encrypted:       {}", header.encryption.is_some());
+    println!("Level mapping:   {:?}", header.level_mapping);
+    println!("Colors:          {}", header.color);
+    println!("Gray code:       {}", header.gray_code);
+    let [red, green, blue] = header.quantization_bias;
+    println!("Level bias:      R {red} G {green} B {blue}");
+    println!("Calibration:     {}", header.calibration_frame);
+    println!("Finder patterns: {}", header.finder_patterns);
+    println!("Alpha bits:      {}", header.alpha_bits);
+    println!("Luma only:       {}", header.luma_only);
+    println!("FEC:             {:?}", header.fec);
+    println!("Interleave:      {}", header.interleave_depth);
+    println!(
+        "Buffer frames:   {} before, {} after",
+        header.prebuffer_frames, header.postbuffer_frames
+    );
+    println!("Note:            {}", header.note);
+    println!("File name:       {}", header.file_name);
+    if let Some(converter_config) = header.converter_config {
+        println!("Config token:    {}", base64_encode(&converter_config));
+    }
+    println!(
+        "Header copies:   {}  Agreeing: {}",
+        vote.copies, vote.agreeing_copies
+    );
+    Ok(())
+}
+
+/// Decode the videos at `args.inputfile` and `args.outputfile`
+/// and report where the decoded outputs differ.
+///
+/// # Errors
+/// Fails if either video is missing or can not be decoded.
+pub fn compare_videos(args: &Args, converter: &Converter) -> Result<()> {
+    let backend = args.video_backend()?;
+    let second_video = args.output_file()?;
+    if !second_video.exists() {
+        bail!("Provided second video at {second_video:?} could not be found.");
+    }
+    let frame_buffer = args.frame_buffer()?;
+    let mut decoded = Vec::with_capacity(2);
+    for video in [args.input_file()?, second_video] {
+        timed_block!(format!("decoding {}", video.display()), {
+            converter.split_video(
+                backend.as_ref(),
+                &frame_buffer,
+                video,
+                args.split_scale_algorithm(),
+                &args.video_window(),
+                args.rotate,
+                args.split_color(),
+            )?;
+            decoded.push(converter.reconstruct_to_vec(&frame_buffer, args.dump_frames.as_deref())?);
+        });
+    }
+    let (data_a, report_a) = &decoded[0];
+    let (data_b, report_b) = &decoded[1];
+    println!(
+        "First video:  {} bytes  Hash match: {}",
+        data_a.len(),
+        report_a.hash_match
+    );
+    println!(
+        "Second video: {} bytes  Hash match: {}",
+        data_b.len(),
+        report_b.hash_match
+    );
+    match compare_bytes(data_a, data_b) {
+        (None, _) => println!("Decoded outputs are identical."),
+        (Some(first_difference), differing_bytes) => println!(
+            "Decoded outputs differ. First differing byte: {first_difference}  Differing bytes: {differing_bytes}"
+        ),
+    }
+    Ok(())
+}
+
+/// Print how much data every preset resolution holds with the settings in `args`,
+/// and how long a video for `args.file_size` or the input file would be.
+///
+/// # Errors
+/// Fails if the size of the input file can not be read.
+pub fn print_capacities(args: &Args) -> Result<()> {
+    let file_size = match (args.file_size, &args.inputfile) {
+        (Some(file_size), _) => Some(usize::try_from(file_size)?),
+        (None, Some(inputfile)) => Some(usize::try_from(
+            fs::metadata(inputfile)
+                .with_context(|| format!("Unable to read size of {}.", inputfile.display()))?
+                .len(),
+        )?),
+        (None, None) => None,
+    };
+    let length_column = file_size.map_or(String::new(), |size| format!("Video for {size} bytes"));
+    let header = format!(
+        "{:<10} {:>12} {:>14} {:>16} {:>16} {:>16}  {}",
+        "Resolution",
+        "Data grid",
+        "Bytes/frame",
+        "Bytes/second",
+        "Raw/minute",
+        "Data/minute",
+        length_column
+    );
+    println!("{}", header.trim_end());
+    for (name, converter) in args.converter_per_resolution() {
+        let converter = match converter {
+            Ok(converter) => converter,
+            Err(err) => {
+                println!("{name:<10} Unsupported: {err}");
+                continue;
+            }
+        };
+        let [data_width, data_height] = converter.data_dimensions();
+        let length = match file_size {
+            Some(size) => format_duration(converter.encoded_duration(
+                size,
+                args.header_frames as usize,
+                args.parity_interval,
+                args.fountain_overhead(),
+                args.repeat as usize,
+                args.fec,
+                args.buffer_frames(),
+            )?),
+            None => String::new(),
+        };
+        let (raw_per_second, usable_per_second) = converter.capacity_per_second(args.fec);
+        let row = format!(
+            "{:<10} {:>12} {:>14} {:>16} {:>16} {:>16}  {}",
+            name,
+            format!("{data_width}x{data_height}"),
+            converter.data_bytes_per_frame(args.fec),
+            usable_per_second,
+            raw_per_second * SECONDS_PER_MINUTE,
+            usable_per_second * SECONDS_PER_MINUTE,
+            length
+        );
+        println!("{}", row.trim_end());
+    }
+    Ok(())
+}
+
+/// Print the frame count, length and approximate size of the video encoding
+/// `args.file_size` bytes, or the input file or message after compression and encryption,
+/// with the settings in `args`.
+///
+/// # Errors
+/// Fails if the settings are invalid, the input can not be read
+/// or no data size is known.
+pub fn print_estimate(args: &Args) -> Result<()> {
+    let converter = args
+        .to_converter_config()
+        .context("Invalid converter settings.")?;
+    let data_len = match args.file_size {
+        Some(file_size) => usize::try_from(file_size)?,
+        None if args.inputfile.is_some() || args.message.is_some() || args.archive.is_some() => {
+            args.payload(&converter)?.0.len()
+        }
+        None => bail!("Estimate needs an input file, a message or --file-size."),
+    };
+    let header_frames = args.header_frames as usize;
+    let repeat = args.repeat as usize;
+    let frames = converter.encoded_frame_count(
+        data_len,
+        header_frames,
+        args.parity_interval,
+        args.fountain_overhead(),
+        repeat,
+        args.fec,
+        args.buffer_frames(),
+    )?;
+    let duration = converter.encoded_duration(
+        data_len,
+        header_frames,
+        args.parity_interval,
+        args.fountain_overhead(),
+        repeat,
+        args.fec,
+        args.buffer_frames(),
+    )?;
+    let size = converter.estimated_video_size(
+        data_len,
+        header_frames,
+        args.parity_interval,
+        args.fountain_overhead(),
+        repeat,
+        args.fec,
+    )?;
+    println!("{converter}");
+    println!("Data:             {data_len} bytes");
+    println!(
+        "Frames:           {frames} ({} buffer frames)",
+        args.prebuffer + args.postbuffer
+    );
+    println!("Duration:         {}", format_duration(duration));
+    println!("Approximate size: {}", format_bytes(size));
+    let (raw_per_second, usable_per_second) = converter.capacity_per_second(args.fec);
+    println!(
+        "Capacity:         {} raw, {} of data per minute",
+        format_bytes((raw_per_second * SECONDS_PER_MINUTE) as u64),
+        format_bytes((usable_per_second * SECONDS_PER_MINUTE) as u64)
+    );
+    if frames > MAX_FRAME_COUNT {
+        println!("Too many frames, at most {MAX_FRAME_COUNT} are supported.");
+    }
+    if let Some(max_duration) = args.max_duration
+        && duration.as_secs() > max_duration
+    {
+        println!(
+            "Longer than the maximum duration of {}.",
+            format_duration(time::Duration::from_secs(max_duration))
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Optional zstd compression of the data before it is encoded.
+//! Compression happens before the Hamming encoding,
+//! so the error correction protects the compressed stream.
+
+use anyhow::{Context, Result};
+
+use crate::constants::ZSTD_LEVEL;
+
+/// Compresses `data` with zstd.
+/// Returns `None` if the compressed data would not be smaller,
+/// so incompressible data is stored as is.
+///
+/// # Arguments
+/// * `data` - Data to compress.
+///
+/// # Errors
+/// Fails if zstd is unable to compress the data.
+pub fn compress(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut encoder = zstd::Encoder::new(Vec::new(), ZSTD_LEVEL)?;
+    // The frame checksum detects corruption the Hamming code could not correct.
+    encoder.include_checksum(true)?;
+    std::io::Write::write_all(&mut encoder, data)?;
+    let compressed = encoder.finish().context("Unable to compress data.")?;
+    Ok((compressed.len() < data.len()).then_some(compressed))
+}
+
+/// Decompresses data compressed by `compress`.
+///
+/// # Arguments
+/// * `data` - Compressed data read from a video.
+///
+/// # Errors
+/// Fails if the data is not valid zstd data or its checksum does not match.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(data).context("Unable to decompress data, it may be corrupted.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo random bytes zstd can not compress.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        (0..len)
+            .map(|_| {
+                // xorshift64, any spread of bit patterns will do.
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compressed_data_round_trips() -> Result<()> {
+        let data = b"vortexkey ".repeat(1000);
+        let compressed = compress(&data)?.expect("repetitive data compresses");
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(decompress(&compressed)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn incompressible_data_is_not_compressed() -> Result<()> {
+        assert_eq!(compress(&noise(10_000))?, None);
+        assert_eq!(compress(b"")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_stream_fails_to_decompress() -> Result<()> {
+        let data = b"vortexkey ".repeat(1000);
+        let compressed = compress(&data)?.expect("repetitive data compresses");
+        let mut corrupted = compressed.clone();
+        let middle = corrupted.len() / 2;
+        corrupted[middle] ^= 0xFF;
+        assert!(decompress(&corrupted).is_err());
+        assert!(decompress(&compressed[..compressed.len() - 1]).is_err());
+        assert!(decompress(&noise(100)).is_err());
+        Ok(())
+    }
+}
@@ -9,50 +9,126 @@
 
 //! Global constants.
 
+use crate::backend::{ColorRange, Colorspace, EncoderPreset};
+
 // === Configuration Constants ===
 #[allow(clippy::doc_markdown)]
 /// Folder where intermediate frames are stored
 /// before being stiched into a video.
-/// This folder is created in the temp directory
-/// determined using `env::temp_dir()`.
+/// This folder is created in the work directory,
+/// the temp directory determined using `env::temp_dir()` unless configured.
+/// Every run uses a folder of its own whose name starts with this.
 /// Default: "vortexkey_framebuffer"
 pub const FRAME_DIR: &str = "vortexkey_framebuffer";
 
+/// Environment variable overriding the work directory intermediate frames are stored in.
+/// Default: "`VORTEXKEY_WORKDIR`"
+pub const WORKDIR_ENV: &str = "VORTEXKEY_WORKDIR";
+
+/// Environment variable holding the passphrase for `--encrypt`,
+/// so it does not have to be passed on the command line.
+/// Default: "`VORTEXKEY_PASSPHRASE`"
+pub const PASSPHRASE_ENV: &str = "VORTEXKEY_PASSPHRASE";
+
 /// Path to ffmpeg executable.
+/// Used if `FFMPEG_PATH_ENV` is unset and the file exists,
+/// otherwise ffmpeg is searched for on `PATH`.
 /// Default: "/bin/ffmpeg"
 pub const FFMPEG_EXCUTABLE_PATH: &str = "/bin/ffmpeg";
 
+/// Environment variable overriding the ffmpeg executable path.
+/// Default: "`VORTEXKEY_FFMPEG`"
+pub const FFMPEG_PATH_ENV: &str = "VORTEXKEY_FFMPEG";
+
+/// Path to ffprobe executable.
+/// Used if neither `--ffprobe-path` nor `FFPROBE_PATH_ENV` is set and the file exists,
+/// otherwise ffprobe is searched for on `PATH`.
+/// Default: "/bin/ffprobe"
+pub const FFPROBE_EXECUTABLE_PATH: &str = "/bin/ffprobe";
+
+/// Environment variable overriding the ffprobe executable path.
+/// Default: "`VORTEXKEY_FFPROBE`"
+pub const FFPROBE_PATH_ENV: &str = "VORTEXKEY_FFPROBE";
+
 #[allow(clippy::doc_markdown)]
-/// H.264 ConstantRateFactor  
+/// H.264 ConstantRateFactor used unless `--crf` is given.  
 /// Allowed values: 0-51  
 /// 0 -> Lossless, 23 -> ffmpeg default, 51 -> worst possible  
 /// Subjectively sane range is 17–28
 /// Default: 20
 pub const H264_CRF: u32 = 20;
 
-/// H.264 Preset  
+/// Highest H.264 constant rate factor, the worst possible quality.
+pub const H264_MAX_CRF: u32 = 51;
+
+/// Encoder preset used unless `--preset` is given.  
 /// Controls encoder speed to compression ratio
 /// Slower -> Smaler file size.  
-/// Default: faster  
-/// Valid:
-/// - ultrafast
-/// - superfast
-/// - veryfast
-/// - faster
-/// - fast
-/// - medium
-/// - slow
-/// - slower
-/// - veryslow  
-pub const H264_PRESET: &str = "veryfast";
-
-/// How many fully blank buffer frames to add before the main data stream.  
+/// Default: veryfast  
+pub const H264_PRESET: EncoderPreset = EncoderPreset::Veryfast;
+
+/// VP9 constant rate factor used unless `--crf` is given.  
+/// Allowed values: 0-63, higher values are worse.  
+/// Default: 24
+pub const VP9_CRF: u32 = 24;
+
+/// AV1 constant rate factor used unless `--crf` is given.  
+/// Allowed values: 0-63, higher values are worse.  
+/// Default: 24
+pub const AV1_CRF: u32 = 24;
+
+/// Highest VP9 and AV1 constant rate factor, the worst possible quality.
+pub const VP9_AV1_MAX_CRF: u32 = 63;
+
+#[allow(clippy::doc_markdown)]
+/// Bitrate in kbit/s `--simulate-recompress` re-encodes the video at if none is given,
+/// roughly what YouTube streams 1080p H.264 video at.  
+/// Default: 2500
+pub const SIMULATED_RECOMPRESS_KBPS: u32 = 2500;
+
+/// Fountain droplet frames sent per hundred data frames if `--fountain` is given without a value,
+/// enough to rebuild a few dropped frames of every stretch of the video.  
+/// Default: 25
+pub const FOUNTAIN_OVERHEAD_PERCENT: u16 = 25;
+
+/// VA-API render device used by `--hwaccel vaapi`.  
+/// Default: "/dev/dri/renderD128"
+pub const VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// Rough number of bits H.264 spends per pixel on a frame full of data blocks
+/// at `H264_CRF`, used to estimate video file sizes without running ffmpeg.
+/// Repeated video frames showing the same data frame cost next to nothing.
+/// Real sizes vary with the data, block size and encoder version.
+/// Default: 0.5
+pub const ESTIMATED_BITS_PER_PIXEL: f64 = 0.5;
+
+/// zstd compression level used with `--compress`.
+/// Allowed values: 1-22, higher is smaller but slower.
+/// Default: 10
+pub const ZSTD_LEVEL: i32 = 10;
+
+/// How many fully blank buffer frames to add before the main data stream
+/// unless `--prebuffer` is given.  
 /// Default: 3
-pub const PREBUFFER_FRAMES: usize = 3;
+pub const PREBUFFER_FRAMES: u16 = 3;
 
-/// How many fully blank buffer frames to add after the main data stream.  
+/// How many fully blank buffer frames to add after the main data stream
+/// unless `--postbuffer` is given.  
 /// Default: 3
-pub const POSTBUFFER_FRAMES: usize = 3;
+pub const POSTBUFFER_FRAMES: u16 = 3;
+
+/// Videos longer than this many seconds produce a warning before encoding.
+/// Matches the longest video Youtube accepts for upload.
+/// Default: 43200 (12 hours)
+pub const LONG_VIDEO_WARNING_SECONDS: u64 = 12 * 60 * 60;
+
+/// Width in characters of the longest bar printed by `--error-report`.
+/// Default: 40
+pub const ERROR_HISTOGRAM_WIDTH: usize = 40;
+
+/// Most uncorrectable code word positions listed by `--error-report`.
+/// Default: 32
+pub const ERROR_REPORT_MAX_OFFSETS: usize = 32;
 
 /// When reprocessing the frames extracted from a video file
 /// they are scaled down to `downsample_scaler * data_resolution` first
@@ -60,14 +136,87 @@ pub const POSTBUFFER_FRAMES: usize = 3;
 /// Default: 2
 pub const DOWNSAMPLE_SCALER: u32 = 2;
 
-/// What colorspace to encode video as.
+/// Frames of videos decoded with `--camera` are scaled to
+/// `camera_downsample_scaler * data_resolution` instead,
+/// leaving enough pixels to locate the finder patterns
+/// and sample data units shrunk by the perspective.  
+/// Default: 4
+pub const CAMERA_DOWNSAMPLE_SCALER: u32 = 4;
+
+/// Frames of videos decoded with a `--sample-core` below one are scaled to
+/// `core_downsample_scaler * data_resolution` instead,
+/// so every data unit has an outer ring of pixels that can be left out.  
+/// Default: 4
+pub const CORE_DOWNSAMPLE_SCALER: u32 = 4;
+
+/// Scaling algorithm ffmpeg uses to upscale the data frames to the video resolution.
+/// Nearest neighbour keeps the edges between data units sharp.  
+/// Default: "neighbor"
+pub const UPSCALE_ALGORITHM: &str = "neighbor";
+
+/// Scaling algorithms ffmpeg offers, accepted by `--scale-flags`.
+/// The first entry is used by default for downscaling when splitting a video back into frames,
+/// `--auto-filter` tries them in order.
+// Errors measured for one test video:
+// - neighbor          3 errors
+// - area              2 errors
+// - fast_bilinear     3 errors
+// - bicubic           3 errors
+// - bicublin          3 errors
+// - lanczos           3 errors
+// - experimental      4 errors
+// - bilinear          6 errors
+// - gauss             6 errors
+// - sinc (slow)       3 errors
+// - spline (slow)     3 errors
+pub const SCALE_ALGORITHMS: [&str; 11] = [
+    "area",
+    "neighbor",
+    "fast_bilinear",
+    "bicubic",
+    "bicublin",
+    "lanczos",
+    "experimental",
+    "bilinear",
+    "gauss",
+    "sinc",
+    "spline",
+];
+
+/// Data pixel sizes `--auto` tries when the header can not be found,
+/// most common first. Sizes not dividing the frame resolution are skipped.  
+/// Default: 10, 8, 12, 16, 20, 5, 6, 4, 2, 24, 30, 40
+pub const AUTO_DATA_PIXEL_SIZES: [u32; 12] = [10, 8, 12, 16, 20, 5, 6, 4, 2, 24, 30, 40];
+
+/// Color bits `--auto` tries for every data pixel size, most common first.  
+/// Default: 1,2,1 1,1,1 2,2,2 1,2,2 2,3,2 0,3,0 3,3,3
+pub const AUTO_COLOR_BITS: [[u32; COLOR_CHANNELS]; 7] = [
+    [1, 2, 1],
+    [1, 1, 1],
+    [2, 2, 2],
+    [1, 2, 2],
+    [2, 3, 2],
+    [0, 3, 0],
+    [3, 3, 3],
+];
+
+/// Seconds from the start of the video `--auto` splits to look for the header,
+/// unless `--duration` is given.  
+/// Default: 60
+pub const AUTO_PROBE_SECONDS: u32 = 60;
+
+/// Bytes of synthetic data bench mode encodes, unless `--file-size` is given.  
+/// Default: 16 MiB
+pub const BENCH_BYTES: u64 = 16 * 1024 * 1024;
+
+/// What colorspace to encode video as, unless `--colorspace` is given.
 /// bt709 is reccomended for Youtube.  
-/// Default: "bt709"
-pub const COLORSPACE: &str = "bt709";
+/// Default: bt709
+pub const COLORSPACE: Colorspace = Colorspace::Bt709;
 
-/// Video encoding color range.  
-/// Default: "tv"
-pub const COLOR_RANGE: &str = "tv";
+/// Video encoding color range, unless `--color-range` is given.  
+/// Default: tv
+pub const COLOR_RANGE: ColorRange = ColorRange::Tv;
 
 // === Fixed Constants ===
 // DO NOT CHANGE THESE
@@ -91,35 +240,69 @@ pub mod resolutions {
     pub const UHD_4K: [u32; 2] = [3840, 2160];
     /// Ultra HD (8K, 4320p)
     pub const UHD_8K: [u32; 2] = [7680, 4320];
+
+    /// All preset resolutions with the names used on the command line.
+    pub const ALL: [(&str, [u32; 2]); 8] = [
+        ("240p", SD_240),
+        ("360p", SD_360),
+        ("480p", SD_480),
+        ("720p", HD_720),
+        ("1080p", HD_1080),
+        ("1440p", QHD_1440),
+        ("4k", UHD_4K),
+        ("8k", UHD_8K),
+    ];
 }
 
 /// How many color channels we use: red, green, blue
 pub const COLOR_CHANNELS: usize = 3;
 
-/// Positions of the data bits for a Hamming(31,26) code.
-pub const HAMMING_DATA_POSITIONS_31_26: [usize; 26] = [
-    2, 4, 5, 6, 8, 9, 10, 11, 12, 13, 14, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29,
-    30,
-];
+/// Index of the alpha channel in the data units of frames carrying data in it,
+/// following the color channels.
+pub const ALPHA_CHANNEL: usize = COLOR_CHANNELS;
 
-/// Positions of the parity bits for a Hamming(31,26) code.
-pub const HAMMING_PARITY_POSITIONS_31_26: [u32; 5] = [1, 2, 4, 8, 16];
+/// How many data bytes in a RS(255,223) code word.
+pub const RS_CHUNK_BYTES_255_223: usize = 223;
 
-/// How many data bits in a Hamming(31,26) encoding
-pub const HAMMING_DATA_BITS_31_26: usize = 26;
+/// How many bytes in a RS(255,223) code word including parity.
+pub const RS_CHUNK_BYTES_TOTAL_255_223: usize = 255;
 
-/// Masks of the lower 26 bits.
-pub const BIT_MASK_26: u32 = (1 << 26) - 1;
+/// How many parity bytes in a RS(255,223) code word.
+/// Up to half as many damaged bytes can be corrected.
+pub const RS_PARITY_BYTES_255_223: usize = RS_CHUNK_BYTES_TOTAL_255_223 - RS_CHUNK_BYTES_255_223;
 
-/// Masks of the lower 31 bits.
-pub const BIT_MASK_31: u32 = (1 << 31) - 1;
+/// Primitive polynomial x^8 + x^4 + x^3 + x^2 + 1 generating GF(256).
+pub const RS_PRIMITIVE_POLYNOMIAL: u16 = 0x11d;
 
-/// How many bytes in a data chunk for hamming encoding.
-/// lcm(8 bits/byte,26 bits) / 8bits/byte = 13
-pub const HAMMING_CHUNK_BYTES_31_26: usize = 13;
+/// Most frames a video can have.
+/// Intermediate frames are numbered with twelve digits, far more than this.
+pub const MAX_FRAME_COUNT: usize = 999_999_999;
 
-/// How many bytes in a chunk when parity is added.
-pub const HAMMING_CHUNK_BYTES_TOAL_31_26: usize = 16;
+/// Frames stored in each subdirectory of a framebuffer,
+/// so no directory grows too large to be listed quickly.
+pub const FRAMES_PER_SHARD: usize = 10_000;
+
+/// Length of the converter settings blob in bytes:
+/// Version, three channel bit counts and six u32 fields.
+pub const CONVERTER_CONFIG_LEN: usize = 1 + COLOR_CHANNELS + 6 * BYTES_U32;
 
 /// Bytes in a u32
 pub const BYTES_U32: usize = (u32::BITS / u8::BITS) as usize;
+
+/// Bytes in a u64
+pub const BYTES_U64: usize = (u64::BITS / u8::BITS) as usize;
+
+/// Input or output path that reads the data from stdin
+/// or writes it to stdout instead of a file.
+pub const STDIO_PATH: &str = "-";
+
+/// Seconds in a minute, used to report capacities per minute of video.
+pub const SECONDS_PER_MINUTE: usize = 60;
+
+/// Exit code when the decoded data does not match the hash passed with `--expect-sha256`,
+/// distinguishing it from the code 1 of every other failure.
+pub const EXPECTED_HASH_MISMATCH_EXIT_CODE: u8 = 3;
+
+/// Exit code when the run was interrupted with Ctrl-C,
+/// 128 plus the signal number of SIGINT as shells report it.
+pub const INTERRUPTED_EXIT_CODE: u8 = 130;
@@ -9,72 +9,186 @@
 
 //! Tools to encode and decode data from and into bitmap images.
 
-use std::{
-    fs,
-    path::Path,
-    process::{Command, Stdio},
-};
+use std::{array, borrow::Cow, fmt, fs, path::Path, time::Duration};
 
 use anyhow::{Context, Result, bail};
-use glob::glob;
-use image::{GenericImageView, ImageBuffer, Pixel, RgbImage};
-use sha2::{Digest, Sha256};
+use image::{DynamicImage, ImageBuffer, Pixel, RgbImage, RgbaImage};
+use rayon::prelude::*;
+use serde::Serialize;
 
 use crate::{
+    archive::unpack_archive,
+    backend::{EncodeSettings, Rotation, SplitSettings, VideoBackend, VideoColor, VideoWindow},
     constants::{
-        COLOR_CHANNELS, COLOR_RANGE, COLORSPACE, DOWNSAMPLE_SCALER, FFMPEG_EXCUTABLE_PATH,
-        H264_CRF, H264_PRESET, HAMMING_CHUNK_BYTES_31_26, HAMMING_CHUNK_BYTES_TOAL_31_26,
-        POSTBUFFER_FRAMES, PREBUFFER_FRAMES,
+        ALPHA_CHANNEL, BYTES_U32, CAMERA_DOWNSAMPLE_SCALER, COLOR_CHANNELS, CONVERTER_CONFIG_LEN,
+        CORE_DOWNSAMPLE_SCALER, DOWNSAMPLE_SCALER, ESTIMATED_BITS_PER_PIXEL,
+        LONG_VIDEO_WARNING_SECONDS, MAX_FRAME_COUNT, resolutions,
     },
-    error_correction::{HammingReport, decode_with_hamming_31_26, encode_with_hamming_31_26},
+    error_correction::{FecKind, HammingReport},
     filesys::{
-        clear_framebuffer_folder, frame_path_combine, frame_path_wildcard_combine,
-        frame_path_wildcard_split, get_framebuffer_folder,
+        FrameBuffer, commit_partial_output, discard_partial_output, frame_path_dump,
+        partial_output_path, write_output_file,
+    },
+    finder::{FINDER_RESERVED, finder_unit, reserved_units, sample_grid},
+    framing::{
+        FRAME_CRC_LEN, FramePosition, FramingReport, append_frame_crc, build_frames, frame_count,
+        frame_crc_matches, frame_position, payload_len, reassemble_stream,
+    },
+    hashing::HashAlgorithm,
+    header::{
+        BLOCK_LEN, BLOCK_LEN_V1, CALIBRATION_MAGIC, HEADER_BLOCK_FRAMES, HEADER_FRAME_BYTES,
+        HEADER_GRID, HeaderData, HeaderVote, VERSION_CODE, VERSION_CODE_V1, VERSION_CODE_V2,
+        VERSION_CODE_V3,
+    },
+    interleaving::{deinterleave, interleave},
+    interrupt::check_interrupted,
+    levels::{LevelClassifier, LevelMapping, calibration_level, gray_decode, measure_level_values},
+    stats::{FrameKind, FrameStats, mean_channels, mean_level_offsets},
+    utils::{
+        base64_decode, base64_encode, bytes_to_hex_string, format_bytes, format_duration,
+        progress_bar,
     },
-    utils::bytes_to_hex_string,
 };
 
-#[derive(Debug, PartialEq)]
-/// Data read from a decoded videos header.
-struct HeaderData {
-    /// Identifying what version the used converter is.  
-    /// Also used as a "magic" number to identify the beginnig of
-    /// the first data frame.
-    version_code: [u8; 8],
-    /// Number of bytes that were encoded into the video.
-    data_len: usize,
-    /// SHA256 hash over the data.
-    sha256_hash: [u8; 32],
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 /// Result of error correction while decoding a file from video
 pub struct FileReport {
     /// Single bit errors found and corrected.
     pub corrected_errors: u32,
     /// Double bit errors found and unable to be corrected.
     pub uncorrected_errors: u32,
+    /// Byte offsets within the error corrected data stream
+    /// of the code words errors were corrected in.
+    pub corrected_offsets: Vec<usize>,
+    /// Byte offsets within the error corrected data stream
+    /// of the code words with uncorrectable errors.
+    pub uncorrected_offsets: Vec<usize>,
     /// If the read hash matched the calculated hash over the entire file.
+    /// Always true if the video stores no hash.
     pub hash_match: bool,
+    /// Number of redundant header copies read from the header frames.
+    pub header_copies: usize,
+    /// Number of header copies identical to the majority voted header.
+    pub agreeing_header_copies: usize,
+    /// Data frames rebuilt from their parity group.
+    pub recovered_frames: usize,
+    /// Data frames that could neither be read nor rebuilt.
+    pub lost_frames: usize,
+    /// Frames skipped as they were read more than once or had an unreadable sequence number.
+    pub discarded_frames: usize,
+    /// Data and parity frames the header says a copy holds, `None` if not stored.
+    pub expected_data_frames: Option<usize>,
+    /// Data and parity frames read from the video, including discarded ones.
+    /// Differs from `expected_data_frames` if the video was trimmed or resampled.
+    pub read_data_frames: usize,
+    /// Data, parity and droplet frames whose CRC did not match,
+    /// `None` if the frames carry no CRC.
+    pub crc_failed_frames: Option<usize>,
+    #[serde(skip)]
+    /// Header read from the video.
+    pub header: HeaderData,
+    #[serde(skip)]
+    /// Statistics of every frame read from the video, written out by `--stats-csv`.
+    pub frame_stats: Vec<FrameStats>,
+    /// Which copy of the data in a video encoded with `--repeat` was used, starting at 1.
+    pub copy: usize,
 }
 
 impl FileReport {
+    #[must_use]
     /// Extend a hamming report into a file decoding report
     /// adding the information if file hash matched.
     ///
     /// # Arguments
     /// * `base_report` - Hamming report to extend
     /// * `hash_match` - If the calculated file hash matched the expected one.
-    pub fn from_hamming_report(base_report: &HammingReport, hash_match: bool) -> Self {
+    /// * `header_vote` - Result of the majority vote over the header copies.
+    /// * `framing_report` - Frame level problems found while reading the data frames.
+    /// * `header` - Header read from the video.
+    pub fn from_hamming_report(
+        base_report: HammingReport,
+        hash_match: bool,
+        header_vote: HeaderVote,
+        framing_report: FramingReport,
+        header: HeaderData,
+    ) -> Self {
         FileReport {
             corrected_errors: base_report.corrected_errors,
             uncorrected_errors: base_report.uncorrected_errors,
+            corrected_offsets: base_report.corrected_offsets,
+            uncorrected_offsets: base_report.uncorrected_offsets,
             hash_match,
+            header_copies: header_vote.copies,
+            agreeing_header_copies: header_vote.agreeing_copies,
+            recovered_frames: framing_report.recovered,
+            lost_frames: framing_report.lost,
+            discarded_frames: framing_report.discarded,
+            expected_data_frames: header.data_frames,
+            read_data_frames: framing_report.read,
+            crc_failed_frames: None,
+            header,
+            frame_stats: Vec::new(),
+            copy: 1,
         }
     }
+
+    #[must_use]
+    /// If this decoding is better than `other`, used to pick between
+    /// the copies of a repeated video or the results of several scale algorithms.
+    /// Passing the hash check beats failing it, however few errors the failing one had.
+    /// Otherwise fewer uncorrected errors win.
+    ///
+    /// # Arguments
+    /// * `other` - Report of the best decoding so far.
+    pub fn is_better_than(&self, other: &FileReport) -> bool {
+        self.hash_match || (!other.hash_match && self.uncorrected_errors < other.uncorrected_errors)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error returned when decoding finds no frame starting a header block,
+/// which usually means the video was decoded with the wrong converter settings.
+pub struct NoHeaderFound {
+    /// Number of frames searched.
+    pub frames: usize,
+}
+
+impl fmt::Display for NoHeaderFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No data frame with a valid version code found across {} frames. The video may not have been made by vortexkey, or the frame resolution, data pixel size and color bits differ from those it was encoded with.",
+            self.frames
+        )
+    }
+}
+
+impl std::error::Error for NoHeaderFound {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error returned when the header of a video records other color bits or data dimensions
+/// than those it is decoded with, so its data frames can not be read.
+/// Decoding again with the settings it holds reads them.
+pub struct DataLayoutDiffers {
+    /// Converter settings recorded in the header, see `Converter::config_bytes`.
+    pub converter_config: [u8; CONVERTER_CONFIG_LEN],
+    /// Downsample scaler recorded in the header, zero if not recorded.
+    pub downsample_scaler: u32,
+}
+
+impl fmt::Display for DataLayoutDiffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Video was encoded with other color bits or data dimensions than it is decoded with, as recorded in its header."
+        )
+    }
 }
 
+impl std::error::Error for DataLayoutDiffers {}
+
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)]
 /// Used to convert arbitrary data to video.
 /// Manages methods and parameters for that purpose.
 pub struct Converter {
@@ -90,11 +204,18 @@ pub struct Converter {
     blue_bits: u32,
     /// Mask over the in `blue_bits` defined number of bits: `(1 << blue_bits) - 1`
     blue_mask: u32,
+    /// How many bits of data should be encoded in the alpha channel for each data unit.
+    /// Zero if frames are RGB only.
+    /// Not part of `config_bytes`, the decoder has to know the frame layout
+    /// before it can read the header, so it is always given explicitly.
+    alpha_bits: u32,
+    /// Mask over the in `alpha_bits` defined number of bits: `(1 << alpha_bits) - 1`
+    alpha_mask: u32,
     /// Total numer of bits encoded in each data unit:
-    /// `red_bits + green_bits + blue_bits`
+    /// `red_bits + green_bits + blue_bits + alpha_bits`
     total_bits: u32,
     /// Mask over the in `total_bits` defined number of bits: `(1 << total_bits) - 1`
-    total_mask: u32,
+    total_mask: u64,
     /// How many data frames per second should be encoded in the output video.
     data_fps: u32,
     /// Framerate the output video will be encoded as.  
@@ -114,20 +235,357 @@ pub struct Converter {
     /// How many bytes will be encoded in each data frame:
     /// `(frame_data_unit_count * total_bits) / u8::MAX`
     frame_data_byte_count: usize,
+    /// Offset added to the pixel value of every level when encoding. Order: RGB
+    /// Not part of `config_bytes`, as decoding does not depend on it.
+    quantization_bias: [i8; COLOR_CHANNELS],
+    /// If a calibration frame is encoded after the header frames of every copy.
+    /// Not part of `config_bytes`, as the decoder learns it from the header.
+    calibration_frame: bool,
+    /// If the corners of every frame hold finder patterns instead of data,
+    /// which the decoder locates to undo the perspective of a camera.
+    /// Not part of `config_bytes`, the decoder has to know the layout
+    /// before it can read the header, so it is always given explicitly.
+    finder_patterns: bool,
+    /// If the green level of every data unit is copied to its red and blue channel,
+    /// encoding all data in the luminance of gray frames.
+    /// Not part of `config_bytes`, as the decoder learns it from the header.
+    luma_only: bool,
+    /// Fraction of the width and height of every data unit sampled around its center
+    /// when decoding, between zero exclusive and one.
+    /// Not part of `config_bytes`, as encoding does not depend on it.
+    sample_core: f64,
+    /// Multiple of the data resolution frames are scaled to when splitting a video,
+    /// before every block of `downsample_scaler` by `downsample_scaler` pixels is averaged.
+    /// Not part of `config_bytes`, the header stores it so decoding uses the same value.
+    downsample_scaler: u32,
+    /// Number of data, parity and droplet frames after which a header block is repeated,
+    /// zero if the header is only encoded at the start of every copy.
+    /// Not part of `config_bytes`, as the decoder learns it from the header.
+    header_interval: u16,
+    /// If progress bars are shown while encoding and decoding frames.
+    /// Not part of `config_bytes`, as it only affects the output on the terminal.
+    progress: bool,
 }
 
-impl Converter {
-    /// Identifying what version this converter is.  
-    /// Also used as a "magic" number to identify the beginnig of
-    /// the first data frame.
-    /// Encoded into the first frame of the output video.
-    const VERSION_CODE: [u8; 8] = [68, 65, 67, 79, 0, 255, 0, 1];
+impl fmt::Display for Converter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Video resolution: {}x{} at {} fps",
+            self.frame_width, self.frame_height, self.video_fps
+        )?;
+        writeln!(
+            f,
+            "Data grid:        {}x{} units of {}x{} pixels at {} fps",
+            self.data_width,
+            self.data_height,
+            self.frame_width / self.data_width,
+            self.frame_height / self.data_height,
+            self.data_fps
+        )?;
+        if self.alpha_bits > 0 {
+            writeln!(
+                f,
+                "Color bits:       R {} G {} B {} A {} ({} bits per unit)",
+                self.red_bits, self.green_bits, self.blue_bits, self.alpha_bits, self.total_bits
+            )?;
+        } else {
+            writeln!(
+                f,
+                "Color bits:       R {} G {} B {} ({} bits per unit)",
+                self.red_bits, self.green_bits, self.blue_bits, self.total_bits
+            )?;
+        }
+        write!(
+            f,
+            "Frame capacity:   {} bytes raw, {} bytes of data",
+            self.frame_data_byte_count,
+            self.data_bytes_per_frame(FecKind::default())
+        )?;
+        if self.quantization_bias != [0; COLOR_CHANNELS] {
+            let [red, green, blue] = self.quantization_bias;
+            write!(f, "\nLevel bias:       R {red} G {green} B {blue}")?;
+        }
+        if self.calibration_frame {
+            write!(f, "\nCalibration:      frame before the data")?;
+        }
+        if self.finder_patterns {
+            write!(
+                f,
+                "\nFinder patterns:  in the frame corners, for camera capture"
+            )?;
+        }
+        if self.luma_only {
+            write!(f, "\nLuma only:        gray frames, data in the luminance")?;
+        }
+        if self.sample_core < 1.0 {
+            write!(
+                f,
+                "\nSample core:      inner {:.0}% of every unit",
+                self.sample_core * 100.0
+            )?;
+        }
+        if self.downsample_scaler != DOWNSAMPLE_SCALER {
+            write!(
+                f,
+                "\nDownsample:       {}x{} pixels averaged per unit",
+                self.downsample_scaler, self.downsample_scaler
+            )?;
+        }
+        if self.header_interval > 0 {
+            write!(
+                f,
+                "\nHeader repeat:    after every {} data frames",
+                self.header_interval
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Builds a `Converter` from named settings, see `Converter::builder`.
+/// Settings not set keep the defaults of the command line interface:
+/// 1 red, 2 green and 1 blue bit, 1 data frame per second in a 30 fps 1080p video
+/// and data blocks of 10x10 pixels.
+pub struct ConverterBuilder {
+    /// How many bits should be encoded in each color channel. Order: RGB
+    color_bits: [u32; COLOR_CHANNELS],
+    /// How many data frames per second should be encoded in the output video.
+    data_fps: u32,
+    /// Final framerate of the output video.
+    video_fps: u32,
+    /// (Width, Height) Resolution of the final output video.
+    resolution: [u32; 2],
+    /// Size of a square data block in pixels, used unless `data_dimensions` is set.
+    data_block_size: u32,
+    /// (Width, Height) How many data units each frame should contain, if set explicitly.
+    data_dimensions: Option<[u32; 2]>,
+    /// Offset added to the pixel value of every level when encoding. Order: RGB
+    quantization_bias: [i8; COLOR_CHANNELS],
+    /// If a calibration frame is encoded after the header frames.
+    calibration_frame: bool,
+    /// If the corners of every frame hold finder patterns.
+    finder_patterns: bool,
+}
+
+impl Default for ConverterBuilder {
+    fn default() -> Self {
+        Self {
+            color_bits: [1, 2, 1],
+            data_fps: 1,
+            video_fps: 30,
+            resolution: resolutions::HD_1080,
+            data_block_size: 10,
+            data_dimensions: None,
+            quantization_bias: [0; COLOR_CHANNELS],
+            calibration_frame: false,
+            finder_patterns: false,
+        }
+    }
+}
+
+impl ConverterBuilder {
+    #[must_use]
+    /// Sets how many bits are encoded in each color channel.
+    ///
+    /// # Arguments
+    /// * `color_bits` - Bits per channel, 0 to 8 each. Order: RGB
+    pub fn color_bits(mut self, color_bits: [u32; COLOR_CHANNELS]) -> Self {
+        self.color_bits = color_bits;
+        self
+    }
+
+    #[must_use]
+    /// Sets how many data frames per second are encoded in the output video.
+    ///
+    /// # Arguments
+    /// * `data_fps` - Data framerate, must divide the video framerate.
+    pub fn data_fps(mut self, data_fps: u32) -> Self {
+        self.data_fps = data_fps;
+        self
+    }
+
+    #[must_use]
+    /// Sets the framerate of the output video.
+    ///
+    /// # Arguments
+    /// * `video_fps` - Output video framerate.
+    pub fn video_fps(mut self, video_fps: u32) -> Self {
+        self.video_fps = video_fps;
+        self
+    }
+
+    #[must_use]
+    /// Sets the resolution of the output video.
+    ///
+    /// # Arguments
+    /// * `resolution` - (Width, Height) in pixels, e.g. one of `constants::resolutions`.
+    pub fn resolution(mut self, resolution: [u32; 2]) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    #[must_use]
+    /// Sets the size of the square data blocks.
+    /// Overridden by `data_dimensions`.
+    ///
+    /// # Arguments
+    /// * `data_block_size` - Edge length of a data block in pixels.
+    pub fn data_block_size(mut self, data_block_size: u32) -> Self {
+        self.data_block_size = data_block_size;
+        self.data_dimensions = None;
+        self
+    }
+
+    #[must_use]
+    /// Sets how many data units each frame holds, allowing non-square data blocks.
+    /// Overrides `data_block_size`.
+    ///
+    /// # Arguments
+    /// * `data_dimensions` - (Width, Height) Number of data units in each frame.
+    pub fn data_dimensions(mut self, data_dimensions: [u32; 2]) -> Self {
+        self.data_dimensions = Some(data_dimensions);
+        self
+    }
+
+    #[must_use]
+    /// Sets the offset added to the pixel value of every level when encoding,
+    /// see `Converter::with_quantization_bias`.
+    ///
+    /// # Arguments
+    /// * `quantization_bias` - Offset per color channel. Order: RGB
+    pub fn quantization_bias(mut self, quantization_bias: [i8; COLOR_CHANNELS]) -> Self {
+        self.quantization_bias = quantization_bias;
+        self
+    }
+
+    #[must_use]
+    /// Sets if a calibration frame is encoded after the header frames,
+    /// see `Converter::with_calibration_frame`.
+    ///
+    /// # Arguments
+    /// * `calibration_frame` - If the calibration frame is encoded.
+    pub fn calibration_frame(mut self, calibration_frame: bool) -> Self {
+        self.calibration_frame = calibration_frame;
+        self
+    }
+
+    #[must_use]
+    /// Sets if the corners of every frame hold finder patterns,
+    /// see `Converter::with_finder_patterns`.
+    ///
+    /// # Arguments
+    /// * `finder_patterns` - If the finder patterns are used.
+    pub fn finder_patterns(mut self, finder_patterns: bool) -> Self {
+        self.finder_patterns = finder_patterns;
+        self
+    }
+
+    /// Number of data units in each frame given by the data block size.
+    /// Fails if the resolution is not a whole multiple of it,
+    /// as the remaining edge pixels could not hold data.
+    fn block_data_dimensions(&self) -> Result<[u32; 2]> {
+        if self.data_block_size == 0 {
+            bail!("Data block size must not be zero.");
+        }
+        for (dimension, size) in ["width", "height"].into_iter().zip(self.resolution) {
+            let remainder = size % self.data_block_size;
+            if remainder != 0 {
+                bail!(
+                    "Frame {dimension} ({size}) is not a whole multiple of the data block size ({}), {remainder} pixels would be left over.",
+                    self.data_block_size
+                );
+            }
+        }
+        Ok(self.resolution.map(|size| size / self.data_block_size))
+    }
+
+    /// Builds the converter, validating the settings like `Converter::new`.
+    ///
+    /// # Errors
+    /// Fails if the resolution is not a whole multiple of the data block size
+    /// or the settings are rejected by `Converter::new`, `Converter::with_quantization_bias`,
+    /// `Converter::with_finder_patterns` or `Converter::with_calibration_frame`.
+    pub fn build(self) -> Result<Converter> {
+        let data_dimensions = self
+            .data_dimensions
+            .map_or_else(|| self.block_data_dimensions(), Ok)?;
+        Converter::new(
+            self.color_bits,
+            self.data_fps,
+            self.video_fps,
+            self.resolution,
+            data_dimensions,
+        )?
+        .with_quantization_bias(self.quantization_bias)?
+        .with_finder_patterns(self.finder_patterns)?
+        .with_calibration_frame(self.calibration_frame)
+    }
+}
 
+impl Converter {
     /// Lowest `data_fps` value allowed.
     const MIN_FPS: u32 = 1;
 
-    /// Lenght in bytes of the header that will be endcoded into the first frame.
-    const HEADER_LEN: usize = 48;
+    /// Layout version of the blob returned by `config_bytes`.
+    /// Never zero, so a zeroed blob marks missing settings.
+    const CONFIG_VERSION: u8 = 1;
+
+    /// Places the data units of a frame around the finder patterns,
+    /// returning the units of the whole data grid in row order.
+    /// Returns `frame_data_units` unchanged without finder patterns.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Data units of the frame, one byte per channel.
+    fn insert_finder_units(&self, frame_data_units: Vec<u8>) -> Vec<u8> {
+        if !self.finder_patterns {
+            return frame_data_units;
+        }
+        let grid = [self.data_width, self.data_height];
+        let mut data_units = frame_data_units.chunks_exact(COLOR_CHANNELS);
+        let mut grid_units = Vec::with_capacity(
+            self.data_width as usize * self.data_height as usize * COLOR_CHANNELS,
+        );
+        for y in 0..self.data_height {
+            for x in 0..self.data_width {
+                match finder_unit(x, y, grid) {
+                    Some(value) => grid_units.extend([value; COLOR_CHANNELS]),
+                    None => grid_units.extend_from_slice(data_units.next().unwrap_or_default()),
+                }
+            }
+        }
+        grid_units
+    }
+
+    /// Removes the finder pattern units from the units of a whole data grid,
+    /// reverting `insert_finder_units`.
+    ///
+    /// # Arguments
+    /// * `grid_units` - Units of the whole data grid in row order, one byte per channel.
+    fn remove_finder_units(&self, grid_units: Vec<u8>) -> Vec<u8> {
+        if !self.finder_patterns {
+            return grid_units;
+        }
+        let grid = [self.data_width, self.data_height];
+        (0..self.data_height)
+            .flat_map(|y| (0..self.data_width).map(move |x| (x, y)))
+            .zip(grid_units.chunks_exact(COLOR_CHANNELS))
+            .filter(|&((x, y), _)| finder_unit(x, y, grid).is_none())
+            .flat_map(|(_, data_unit)| data_unit.iter().copied())
+            .collect()
+    }
+
+    /// Index of the first data unit of the level ramp in a calibration frame,
+    /// following the units holding one bit of `CALIBRATION_MAGIC` each.
+    const CALIBRATION_RAMP_START: usize = CALIBRATION_MAGIC.len() * u8::BITS as usize;
+
+    #[must_use]
+    /// Starts building a converter from named settings,
+    /// which is harder to get wrong than the positional arguments of `new`.
+    pub fn builder() -> ConverterBuilder {
+        ConverterBuilder::default()
+    }
 
     /// Generates a new Converter.
     ///
@@ -136,6 +594,9 @@ impl Converter {
     /// * `video_fps` - Final framerate of the output video. Must be larger or equal to and and multiple of `data_fps`.
     /// * `frame_dimensions` - (Width, Height) Resolution of the final output video. Must be multiples of the `data_dimensions`.
     /// * `data_dimensions` - (Width, Height) How many data units each frame should contain.
+    ///
+    /// # Errors
+    /// Fails if the settings are inconsistent, as described for each argument.
     pub fn new(
         color_bits: [u32; COLOR_CHANNELS],
         data_fps: u32,
@@ -147,6 +608,14 @@ impl Converter {
             bail!("Color channel bit counts must be one byte or smaller.");
         }
 
+        if color_bits.iter().all(|&x| x == 0) {
+            bail!("At least one color channel must encode data.");
+        }
+
+        if data_dimensions.contains(&0) {
+            bail!("Data width and height must not be zero.");
+        }
+
         if !(Self::MIN_FPS..=video_fps).contains(&data_fps) {
             bail!(
                 "Data fps ({}) must be between {} and video fps ({}).",
@@ -156,7 +625,7 @@ impl Converter {
             );
         }
 
-        if video_fps % data_fps != 0 {
+        if !video_fps.is_multiple_of(data_fps) {
             bail!(
                 "Video fps ({}) is not whole multiple of data fps ({}).",
                 video_fps,
@@ -164,14 +633,14 @@ impl Converter {
             );
         }
 
-        if frame_dimensions[0] % data_dimensions[0] != 0 {
+        if !frame_dimensions[0].is_multiple_of(data_dimensions[0]) {
             bail!(
                 "Frame width ({}) is not whole multiple of data width ({}).",
                 frame_dimensions[0],
                 data_dimensions[0]
             );
         }
-        if frame_dimensions[1] % data_dimensions[1] != 0 {
+        if !frame_dimensions[1].is_multiple_of(data_dimensions[1]) {
             bail!(
                 "Frame height ({}) is not whole multiple of data height ({}).",
                 frame_dimensions[1],
@@ -179,28 +648,12 @@ impl Converter {
             );
         }
 
-        if frame_dimensions[0] < DOWNSAMPLE_SCALER * data_dimensions[0] {
-            bail!(
-                "Frame width ({}) can not be smaller than data width ({}) multiplied by downsample scaler ({}).",
-                frame_dimensions[0],
-                data_dimensions[0],
-                DOWNSAMPLE_SCALER
-            );
-        }
-
-        if frame_dimensions[1] < DOWNSAMPLE_SCALER * data_dimensions[1] {
-            bail!(
-                "Frame height ({}) can to be smaller than data height ({}) multiplied by downsample scaler ({}).",
-                frame_dimensions[1],
-                data_dimensions[1],
-                DOWNSAMPLE_SCALER
-            );
-        }
+        Self::check_downsample_scaler(frame_dimensions, data_dimensions, DOWNSAMPLE_SCALER)?;
         let total_bits = color_bits.iter().sum();
         let frame_data_unit_count: usize =
             data_dimensions[0] as usize * data_dimensions[1] as usize;
         let frame_data_bit_count = total_bits as usize * frame_data_unit_count;
-        if frame_data_bit_count % (u8::BITS as usize) != 0 {
+        if !frame_data_bit_count.is_multiple_of(u8::BITS as usize) {
             bail!(
                 "Frame must encode whole number of bytes. Trying to encode {} bits.",
                 frame_data_bit_count
@@ -214,6 +667,8 @@ impl Converter {
             green_mask: (1 << color_bits[1]) - 1,
             blue_bits: color_bits[2],
             blue_mask: (1 << color_bits[2]) - 1,
+            alpha_bits: 0,
+            alpha_mask: 0,
             total_bits,
             total_mask: (1 << total_bits) - 1,
             data_fps,
@@ -224,62 +679,440 @@ impl Converter {
             frame_width: frame_dimensions[0],
             frame_data_unit_count,
             frame_data_byte_count,
+            quantization_bias: [0; COLOR_CHANNELS],
+            calibration_frame: false,
+            finder_patterns: false,
+            luma_only: false,
+            sample_core: 1.0,
+            downsample_scaler: DOWNSAMPLE_SCALER,
+            header_interval: 0,
+            progress: true,
         })
     }
 
+    /// Sets the offset added to the pixel value of every level when encoding,
+    /// countering platforms that consistently darken or brighten videos,
+    /// e.g. as returned by `suggested_quantization_bias`.
+    /// Decoding is unaffected, levels are still read as the closest unshifted value.
+    ///
+    /// # Arguments
+    /// * `quantization_bias` - Offset per color channel. Order: RGB
+    ///
+    /// # Errors
+    /// Fails if an offset would move the pixel values halfway
+    /// to the neighbouring level of the midpoint mapping or further.
+    pub fn with_quantization_bias(
+        mut self,
+        quantization_bias: [i8; COLOR_CHANNELS],
+    ) -> Result<Self> {
+        for ((channel, bits), bias) in ["red", "green", "blue"]
+            .into_iter()
+            .zip([self.red_bits, self.green_bits, self.blue_bits])
+            .zip(quantization_bias)
+        {
+            let max_bias = Self::max_quantization_bias(bits);
+            if u32::from(bias.unsigned_abs()) > max_bias {
+                bail!(
+                    "Quantization bias {bias} of the {channel} channel is out of range, at most {max_bias} fits between its {bits} bit levels."
+                );
+            }
+        }
+        self.quantization_bias = quantization_bias;
+        Ok(self)
+    }
+
+    /// Sets if a calibration frame showing every level of each color channel
+    /// is encoded after the header frames of every copy.
+    /// The decoder measures where the levels ended up in it and reads the data frames
+    /// against those values, correcting gamma and white balance shifts
+    /// of cameras and screen captures.
+    ///
+    /// # Arguments
+    /// * `calibration_frame` - If the calibration frame is encoded.
+    ///
+    /// # Errors
+    /// Fails if a frame is too small to show every level after the calibration magic,
+    /// or the frames hold data in the alpha channel.
+    pub fn with_calibration_frame(mut self, calibration_frame: bool) -> Result<Self> {
+        if calibration_frame && self.alpha_bits > 0 {
+            bail!(
+                "Calibration frames only cover the color channels, not data in the alpha channel."
+            );
+        }
+        let max_levels = 1 << self.red_bits.max(self.green_bits).max(self.blue_bits);
+        if calibration_frame
+            && self.frame_data_unit_count < Self::CALIBRATION_RAMP_START + max_levels
+        {
+            bail!(
+                "Frames of {} data units are too small for a calibration frame showing all {max_levels} levels.",
+                self.frame_data_unit_count
+            );
+        }
+        self.calibration_frame = calibration_frame;
+        Ok(self)
+    }
+
+    /// Sets if the corners of every frame hold QR code style finder patterns instead of data.
+    /// Decoding locates them and undoes the perspective of video filmed off a screen,
+    /// instead of expecting the frames at their exact resolution.
+    /// Must be set the same way for encoding and decoding, as it changes the frame layout.
+    ///
+    /// # Arguments
+    /// * `finder_patterns` - If the finder patterns are used.
+    ///
+    /// # Errors
+    /// Fails if the data grid is too small for the finder patterns,
+    /// the remaining data units do not encode a whole number of bytes
+    /// or the frames hold data in the alpha channel, which a camera can not see.
+    pub fn with_finder_patterns(mut self, finder_patterns: bool) -> Result<Self> {
+        if finder_patterns && self.alpha_bits > 0 {
+            bail!("Finder patterns are for camera capture, which does not see the alpha channel.");
+        }
+        let grid_units = self.data_width as usize * self.data_height as usize;
+        let frame_data_unit_count = if finder_patterns {
+            if self.data_width < 2 * FINDER_RESERVED || self.data_height < 2 * FINDER_RESERVED {
+                bail!(
+                    "Data grid of {}x{} units is too small for finder patterns, at least {} units are needed in each direction.",
+                    self.data_width,
+                    self.data_height,
+                    2 * FINDER_RESERVED
+                );
+            }
+            grid_units - reserved_units()
+        } else {
+            grid_units
+        };
+        let frame_data_bit_count = self.total_bits as usize * frame_data_unit_count;
+        if !frame_data_bit_count.is_multiple_of(u8::BITS as usize) {
+            bail!(
+                "Frame must encode whole number of bytes. Trying to encode {frame_data_bit_count} bits around the finder patterns."
+            );
+        }
+        self.frame_data_unit_count = frame_data_unit_count;
+        self.frame_data_byte_count = frame_data_bit_count / u8::BITS as usize;
+        self.finder_patterns = finder_patterns;
+        Ok(self)
+    }
+
+    /// Sets the fraction of the width and height of every data unit sampled around its center
+    /// when decoding. Below one the edges of the units are ignored,
+    /// where neighbouring units bleed in when the scaler shifts a frame by part of a pixel,
+    /// and frames are split at `CORE_DOWNSAMPLE_SCALER` times the data resolution,
+    /// so the edges span whole pixels.
+    ///
+    /// # Arguments
+    /// * `sample_core` - Sampled fraction, one samples the whole unit.
+    ///
+    /// # Errors
+    /// Fails if `sample_core` is not above zero and at most one.
+    pub fn with_sample_core(mut self, sample_core: f64) -> Result<Self> {
+        if !(sample_core > 0.0 && sample_core <= 1.0) {
+            bail!("Sample core {sample_core} is out of range, it must be above 0 and at most 1.");
+        }
+        self.sample_core = sample_core;
+        Ok(self)
+    }
+
+    /// Sets how many bits are encoded in the alpha channel of every data unit,
+    /// adding it as a fourth channel to the frames.
+    /// Only videos keeping the alpha channel can hold such frames, see `EncodeSettings::alpha`.
+    /// Must be set the same way for encoding and decoding, as it changes the frame layout.
+    ///
+    /// # Arguments
+    /// * `alpha_bits` - Bits encoded in the alpha channel, zero for RGB only frames.
+    ///
+    /// # Errors
+    /// Fails if `alpha_bits` is larger than a byte, the data units no longer encode
+    /// a whole number of bytes per frame, or finder patterns or a calibration frame are used.
+    pub fn with_alpha_bits(mut self, alpha_bits: u32) -> Result<Self> {
+        if alpha_bits > u8::BITS {
+            bail!("Alpha channel bit count must be one byte or smaller.");
+        }
+        if alpha_bits > 0 && self.finder_patterns {
+            bail!("Finder patterns are for camera capture, which does not see the alpha channel.");
+        }
+        if alpha_bits > 0 && self.calibration_frame {
+            bail!(
+                "Calibration frames only cover the color channels, not data in the alpha channel."
+            );
+        }
+        let total_bits = self.red_bits + self.green_bits + self.blue_bits + alpha_bits;
+        let frame_data_bit_count = total_bits as usize * self.frame_data_unit_count;
+        if !frame_data_bit_count.is_multiple_of(u8::BITS as usize) {
+            bail!(
+                "Frame must encode whole number of bytes. Trying to encode {frame_data_bit_count} bits with {alpha_bits} alpha bits."
+            );
+        }
+        self.alpha_bits = alpha_bits;
+        self.alpha_mask = (1 << alpha_bits) - 1;
+        self.total_bits = total_bits;
+        self.total_mask = (1 << total_bits) - 1;
+        self.frame_data_byte_count = frame_data_bit_count / u8::BITS as usize;
+        Ok(self)
+    }
+
+    /// Sets if every data unit is encoded gray, copying its green level
+    /// to the red and blue channel so all data is carried in the luminance.
+    /// Chroma subsampling halves the resolution of the color information,
+    /// so gray frames survive `yuv420p` and aggressive re-encoding much better,
+    /// at the cost of the capacity of the red and blue channel.
+    /// The decoder learns it from the header and reads the luma of the data units.
+    ///
+    /// # Arguments
+    /// * `luma_only` - If the frames are encoded gray.
+    ///
+    /// # Errors
+    /// Fails if bits are encoded in the red or blue channel,
+    /// as gray units only have one level to carry them.
+    pub fn with_luma_only(mut self, luma_only: bool) -> Result<Self> {
+        if luma_only && (self.red_bits > 0 || self.blue_bits > 0) {
+            bail!(
+                "Luma only frames carry all bits in the green level, but {} red and {} blue bits are set. Use e.g. --bits 0,{},0.",
+                self.red_bits,
+                self.blue_bits,
+                self.red_bits + self.green_bits + self.blue_bits
+            );
+        }
+        self.luma_only = luma_only;
+        Ok(self)
+    }
+
+    /// Checks frames scaled down to `downsample_scaler` times the data resolution
+    /// are not larger than the frames themselves.
+    ///
+    /// # Arguments
+    /// * `frame_dimensions` - (Width, Height) Resolution of the video.
+    /// * `data_dimensions` - (Width, Height) Data units in each frame.
+    /// * `downsample_scaler` - Multiple of the data resolution frames are scaled to.
+    fn check_downsample_scaler(
+        frame_dimensions: [u32; 2],
+        data_dimensions: [u32; 2],
+        downsample_scaler: u32,
+    ) -> Result<()> {
+        for ((dimension, frame), data) in ["width", "height"]
+            .into_iter()
+            .zip(frame_dimensions)
+            .zip(data_dimensions)
+        {
+            if downsample_scaler
+                .checked_mul(data)
+                .is_none_or(|scaled| frame < scaled)
+            {
+                bail!(
+                    "Frame {dimension} ({frame}) can not be smaller than data {dimension} ({data}) multiplied by downsample scaler ({downsample_scaler})."
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the multiple of the data resolution frames are scaled to when splitting a video.
+    /// Averaging more pixels per data unit evens out compression noise,
+    /// but needs a frame resolution of at least that multiple of the data resolution.
+    /// Stored in the header, so decoding uses the value the video was encoded with.
+    ///
+    /// # Arguments
+    /// * `downsample_scaler` - Pixels averaged along each side of a data unit.
+    ///
+    /// # Errors
+    /// Fails if `downsample_scaler` is zero or the frame resolution is smaller
+    /// than the data resolution multiplied by it.
+    pub fn with_downsample_scaler(mut self, downsample_scaler: u32) -> Result<Self> {
+        if downsample_scaler == 0 {
+            bail!("Downsample scaler must not be zero.");
+        }
+        Self::check_downsample_scaler(
+            [self.frame_width, self.frame_height],
+            [self.data_width, self.data_height],
+            downsample_scaler,
+        )?;
+        self.downsample_scaler = downsample_scaler;
+        Ok(self)
+    }
+
+    /// Number of channels every data unit is stored in,
+    /// four if data is encoded in the alpha channel and three otherwise.
+    fn channels(&self) -> usize {
+        if self.alpha_bits > 0 {
+            COLOR_CHANNELS + 1
+        } else {
+            COLOR_CHANNELS
+        }
+    }
+
+    #[must_use]
+    /// Sets after how many data, parity and droplet frames a header block is repeated,
+    /// so the header survives the start of a video being cut off or damaged.
+    /// The decoder takes a majority vote over all header blocks it finds
+    /// and reads the data frames around them.
+    /// Only a single copy of the data can be encoded with repeated headers.
+    ///
+    /// # Arguments
+    /// * `header_interval` - Data frames between repeated header blocks, zero to disable.
+    pub fn with_header_interval(mut self, header_interval: u16) -> Self {
+        self.header_interval = header_interval;
+        self
+    }
+
+    #[must_use]
+    /// Sets if progress bars are shown on stderr while encoding and decoding frames.
+    /// They are never shown if stderr is not a terminal.
+    ///
+    /// # Arguments
+    /// * `progress` - If the progress bars are shown.
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Largest quantization bias keeping pixel values closer to their own level
+    /// than to its neighbours with the midpoint mapping, which has the smaller level spacing.
+    ///
+    /// # Arguments
+    /// * `bits` - Number of bits encoded in the color channel.
+    fn max_quantization_bias(bits: u32) -> u32 {
+        (1u32 << (u8::BITS - 1))
+            .checked_shr(bits)
+            .unwrap_or(0)
+            .saturating_sub(1)
+    }
+
+    #[must_use]
+    /// Quantization bias countering the mean level offsets measured in the data frames
+    /// of a decoded video, limited to what `with_quantization_bias` accepts.
+    /// Takes the bias the video was encoded with into account,
+    /// so only the shift caused by the platform is countered.
+    /// Encoding with it for the same platform moves the values read back onto their levels.
+    ///
+    /// # Arguments
+    /// * `report` - Report of the decoded video.
+    pub fn suggested_quantization_bias(&self, report: &FileReport) -> [i8; COLOR_CHANNELS] {
+        let data_frames: Vec<&FrameStats> = report
+            .frame_stats
+            .iter()
+            .filter(|frame| frame.kind == FrameKind::Data)
+            .collect();
+        let mut bias = [0; COLOR_CHANNELS];
+        if data_frames.is_empty() {
+            return bias;
+        }
+        let encoded_bias = report.header.quantization_bias;
+        for (channel, (bias, bits)) in bias
+            .iter_mut()
+            .zip([self.red_bits, self.green_bits, self.blue_bits])
+            .enumerate()
+        {
+            // Channels without data bits carry no levels to shift,
+            // or only copies of the green level in luma only frames.
+            if bits == 0 {
+                continue;
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let mean_offset = data_frames
+                .iter()
+                .map(|frame| frame.mean_level_offsets[channel])
+                .sum::<f64>()
+                / data_frames.len() as f64;
+            let max_bias = f64::from(Self::max_quantization_bias(bits));
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                *bias = (f64::from(encoded_bias[channel]) - mean_offset)
+                    .round()
+                    .clamp(-max_bias, max_bias) as i8;
+            }
+        }
+        bias
+    }
+
+    #[must_use]
+    /// Picks the data framerate closest to `data_fps` that maps onto `video_fps`
+    /// by duplicating every data frame a whole number of times.
+    /// Using the platform framerate as `video_fps` with such a data framerate
+    /// avoids re-timing, which would blend neighbouring data frames.
+    /// Returns `data_fps` unchanged if it already maps cleanly,
+    /// otherwise the largest clean data framerate below it.
+    ///
+    /// # Arguments
+    /// * `video_fps` - Framerate of the target platform.
+    /// * `data_fps` - Requested data framerate.
+    pub fn clean_data_fps(video_fps: u32, data_fps: u32) -> u32 {
+        (Self::MIN_FPS..=data_fps.min(video_fps))
+            .rev()
+            .find(|&candidate| video_fps.is_multiple_of(candidate))
+            .unwrap_or(Self::MIN_FPS)
+    }
+
     /// Take a slice of bytes and encode it into a bitmap image.
     /// The lenght of the supplied data should be equivalent to
     /// the amount of bytes than can be encoded into each frame (`frame_data_byte_count`).
     ///
     /// * `data` - Arbitrary bytes to encode into frame.
-    fn data_to_frame(&self, data: &[u8]) -> Vec<u8> {
+    /// * `level_mapping` - How channel levels are mapped to pixel values.
+    /// * `gray_code` - If data values are shown as the level they are the Gray code of.
+    fn data_to_frame(&self, data: &[u8], level_mapping: LevelMapping, gray_code: bool) -> Vec<u8> {
         assert_eq!(data.len(), self.frame_data_byte_count);
         let mut encoded_data_units =
-            Vec::with_capacity(self.frame_data_unit_count * COLOR_CHANNELS);
-        let mut bit_buffer: u32 = 0;
+            Vec::with_capacity(self.frame_data_unit_count * self.channels());
+        let mut bit_buffer: u64 = 0;
         let mut bit_count: u32 = 0;
 
         for &data_byte in data {
             // Move new byte into bit_buffer
-            bit_buffer = (bit_buffer << u8::BITS) | data_byte as u32;
+            bit_buffer = (bit_buffer << u8::BITS) | data_byte as u64;
             bit_count += u8::BITS;
 
             // Extract data units until not enough bits left.
             while bit_count >= self.total_bits {
-                let data_unit_bits: u32 =
-                    (bit_buffer >> (bit_count - self.total_bits)) & self.total_mask;
+                // Alpha bits follow the color bits, so they are the lowest bits of the unit.
+                #[allow(clippy::cast_possible_truncation)]
+                let data_unit_bits =
+                    ((bit_buffer >> (bit_count - self.total_bits)) & self.total_mask) as u32;
                 bit_count -= self.total_bits;
+                #[allow(clippy::cast_possible_truncation)]
+                let alpha = (data_unit_bits & self.alpha_mask) as u8;
+                let data_unit_bits = data_unit_bits >> self.alpha_bits;
 
                 #[allow(clippy::cast_possible_truncation)]
-                let mut red =
+                let red =
                     ((data_unit_bits >> (self.green_bits + self.blue_bits)) & self.red_mask) as u8;
-                red <<= u8::BITS - self.red_bits;
-                if self.red_bits < 8 {
-                    red |= 1 << (u8::BITS - self.red_bits - 1);
-                }
-
                 #[allow(clippy::cast_possible_truncation)]
-                let mut green = ((data_unit_bits >> (self.blue_bits)) & self.green_mask) as u8;
-                green <<= u8::BITS - self.green_bits;
-                if self.green_bits < 8 {
-                    green |= 1 << (u8::BITS - self.green_bits - 1);
-                }
-
+                let green = ((data_unit_bits >> (self.blue_bits)) & self.green_mask) as u8;
                 #[allow(clippy::cast_possible_truncation)]
-                let mut blue = (data_unit_bits & self.blue_mask) as u8;
-                blue <<= u8::BITS - self.blue_bits;
-                if self.blue_bits < 8 {
-                    blue |= 1 << (u8::BITS - self.blue_bits - 1);
+                let blue = (data_unit_bits & self.blue_mask) as u8;
+                let [red, green, blue] = if gray_code {
+                    [red, green, blue].map(gray_decode)
+                } else {
+                    [red, green, blue]
+                };
+
+                let [red_bias, green_bias, blue_bias] = self.quantization_bias;
+                let green = level_mapping
+                    .encode(green, self.green_bits)
+                    .saturating_add_signed(green_bias);
+                if self.luma_only {
+                    encoded_data_units.extend([green; COLOR_CHANNELS]);
+                } else {
+                    encoded_data_units.push(
+                        level_mapping
+                            .encode(red, self.red_bits)
+                            .saturating_add_signed(red_bias),
+                    );
+                    encoded_data_units.push(green);
+                    encoded_data_units.push(
+                        level_mapping
+                            .encode(blue, self.blue_bits)
+                            .saturating_add_signed(blue_bias),
+                    );
+                }
+                if self.alpha_bits > 0 {
+                    let alpha = if gray_code { gray_decode(alpha) } else { alpha };
+                    encoded_data_units.push(level_mapping.encode(alpha, self.alpha_bits));
                 }
-
-                encoded_data_units.push(red);
-                encoded_data_units.push(green);
-                encoded_data_units.push(blue);
             }
         }
         assert_eq!(
             encoded_data_units.len(),
-            self.frame_data_unit_count * COLOR_CHANNELS
+            self.frame_data_unit_count * self.channels()
         );
         encoded_data_units
     }
@@ -288,25 +1121,30 @@ impl Converter {
     /// The image should be of dimensions (`data_width`, `data_height`) and contain the correct number of bytes.
     ///
     /// * `frame_data_units` - Image to decode data from.
-    fn frame_to_data(&self, frame_data_units: &[u8]) -> Vec<u8> {
+    /// * `classifier` - Classifies pixel values as channel levels, see `level_classifier`.
+    fn frame_to_data(&self, frame_data_units: &[u8], classifier: &LevelClassifier) -> Vec<u8> {
         assert_eq!(
             frame_data_units.len(),
-            self.frame_data_unit_count * COLOR_CHANNELS
+            self.frame_data_unit_count * self.channels()
         );
 
         let mut decoded_bytes = Vec::with_capacity(self.frame_data_byte_count);
-        let mut bit_buffer: u32 = 0;
+        let mut bit_buffer: u64 = 0;
         let mut bit_count: u32 = 0;
 
-        // Each data unit is encoded as a byte triplett.
-        for data_unit in frame_data_units.chunks_exact(COLOR_CHANNELS) {
-            let red: u32 = (data_unit[0] >> (u8::BITS - self.red_bits)) as u32;
-            let green: u32 = (data_unit[1] >> (u8::BITS - self.green_bits)) as u32;
-            let blue: u32 = (data_unit[2] >> (u8::BITS - self.blue_bits)) as u32;
-            let data_unit_bits =
+        // Each data unit is encoded as a byte triplett, or quadruplet with alpha.
+        for data_unit in frame_data_units.chunks_exact(self.channels()) {
+            let red: u32 = classifier.classify(0, data_unit[0]) as u32;
+            let green: u32 = classifier.classify(1, data_unit[1]) as u32;
+            let blue: u32 = classifier.classify(2, data_unit[2]) as u32;
+            let mut data_unit_bits =
                 blue | (green << self.blue_bits) | (red << (self.blue_bits + self.green_bits));
+            if self.alpha_bits > 0 {
+                let alpha = classifier.classify(ALPHA_CHANNEL, data_unit[ALPHA_CHANNEL]) as u32;
+                data_unit_bits = (data_unit_bits << self.alpha_bits) | alpha;
+            }
 
-            bit_buffer = (bit_buffer << self.total_bits) | data_unit_bits;
+            bit_buffer = (bit_buffer << self.total_bits) | data_unit_bits as u64;
             bit_count += self.total_bits;
 
             while bit_count >= u8::BITS {
@@ -320,69 +1158,29 @@ impl Converter {
         decoded_bytes
     }
 
-    /// Generates a redundant header for data frames with the following structure:
-    ///
-    /// - Bytes 0-7:    `VERSION_CODE`
-    /// - Bytes 8-15:    Data length in bytes (little-endian)
-    /// - Bytes 16-47:   SHA256 hash of the data
-    ///
-    /// The header is triplicated for redundancy.
-    ///
-    /// # Arguments
-    /// * `data` - The data to generate a header for
-    fn data_block_header(data: &[u8]) -> [u8; Self::HEADER_LEN * 3] {
-        // Create and populate the single header
-        let mut header = [0u8; Self::HEADER_LEN];
-
-        header[0..8].copy_from_slice(&Self::VERSION_CODE);
-        header[8..16].copy_from_slice(&(data.len() as u64).to_le_bytes());
-        let data_hash = Sha256::digest(data);
-        header[16..48].copy_from_slice(&data_hash);
-
-        // Triplicate the header for redundancy
-        std::array::from_fn(|i| header[i % Self::HEADER_LEN])
+    /// Builds a frame where all encoded bytes are zero.
+    fn buffer_frame_image(&self) -> Result<DynamicImage> {
+        self.data_frame_image(
+            &vec![0; self.frame_data_byte_count],
+            LevelMapping::Midpoint,
+            false,
+        )
     }
 
-    /// Takes in a triple redundant header generated by `data_block_header`
-    /// Converts it into a single header by majority vote of the three copies
-    /// and decodes the contents.
+    /// Builds the image of a data frame at data resolution,
+    /// padding `frame_data` with zeros to a full frame.
+    /// The image is RGBA if data is encoded in the alpha channel and RGB otherwise.
     ///
     /// # Arguments
-    /// * `header` - Triple redundant header bytes read from file
-    fn read_data_header(header: &[u8; Self::HEADER_LEN * 3]) -> Result<HeaderData> {
-        // Split into the three redundant headers
-        let (part1, rest) = header.split_at(Self::HEADER_LEN);
-        let (part2, part3) = rest.split_at(Self::HEADER_LEN);
-
-        // Perform majority vote over three redundant copies.
-        let mut majority: [u8; Self::HEADER_LEN] = [0; Self::HEADER_LEN];
-        for i in 0..Self::HEADER_LEN {
-            majority[i] = (part1[i] & part2[i]) | (part2[i] & part3[i]) | (part1[i] & part3[i]);
-        }
-
-        let version_code: [u8; 8] = majority[0..8].try_into()?;
-        let data_len: usize = u64::from_le_bytes(majority[8..16].try_into()?)
-            .try_into()
-            .context("Read data lenght wont fit into pointer type.")?;
-        let sha256_hash: [u8; 32] = majority[16..48].try_into()?;
-        Ok(HeaderData {
-            version_code,
-            data_len,
-            sha256_hash,
-        })
-    }
-
-    /// Saves a frame at the specified location where all encoded bytes are zero.
-    ///
-    /// # Arguments
-    /// * `frame_path` - Path where the frame should be saved.
-    fn save_buffer_frame(&self, path: &Path) -> Result<()> {
-        self.save_data_frame(&vec![0; self.frame_data_byte_count], path)?;
-        Ok(())
-    }
-
-    /// Helper function to save an data frame
-    fn save_data_frame(&self, frame_data: &[u8], path: &Path) -> Result<()> {
+    /// * `frame_data` - Bytes to encode, at most `frame_data_byte_count`.
+    /// * `level_mapping` - How channel levels are mapped to pixel values.
+    /// * `gray_code` - If data values are shown as the level they are the Gray code of.
+    fn data_frame_image(
+        &self,
+        frame_data: &[u8],
+        level_mapping: LevelMapping,
+        gray_code: bool,
+    ) -> Result<DynamicImage> {
         let received_data_len = frame_data.len();
         if received_data_len > self.frame_data_byte_count {
             bail!(
@@ -394,328 +1192,2735 @@ impl Converter {
         let img_data = if received_data_len < self.frame_data_byte_count {
             let mut frame_buffer: Vec<u8> = frame_data.to_vec();
             frame_buffer.resize(self.frame_data_byte_count, 0);
-            self.data_to_frame(&frame_buffer)
+            self.data_to_frame(&frame_buffer, level_mapping, gray_code)
         } else {
-            self.data_to_frame(frame_data)
+            self.data_to_frame(frame_data, level_mapping, gray_code)
         };
+        let img_data = self.insert_finder_units(img_data);
 
-        let img_buffer: RgbImage =
-            ImageBuffer::from_raw(self.data_width, self.data_height, img_data)
-                .context("Unable to create image buffer from frame data")?;
-        img_buffer
-            .save(path)
-            .context("Unable to save frame as PNG")?;
-        Ok(())
+        // `from_raw` only reports a mismatch as `None`, so check the geometry here
+        // to point at the exact lengths involved.
+        assert_eq!(
+            img_data.len(),
+            self.data_width as usize * self.data_height as usize * self.channels(),
+            "Frame image data length does not match data width ({}) * data height ({}) * channels ({}).",
+            self.data_width,
+            self.data_height,
+            self.channels()
+        );
+        self.units_to_image(img_data)
+            .context("Unable to create image buffer from frame data")
     }
 
-    /// Read a file at the supplied path and encodes its contents it into as many frames as needed.
-    /// Also prepends a header generated using `data_block_header` before generating.
-    /// Saves all generated frames in the directory specified using `constants::FRAME_DIR_PATH`.
+    /// Wraps the units of a whole data grid in an image at data resolution,
+    /// RGBA if data is encoded in the alpha channel and RGB otherwise.
+    /// Returns `None` if the number of units does not match the data resolution.
     ///
     /// # Arguments
-    /// * `path` - Path where the file to read is located.
-    pub fn deconstruct_file(&self, path: &Path) -> Result<()> {
-        // This whole process could be optimized to not require loading the entire file into memory.
-        // I didnt.
-        let mut file_data = fs::read(path).context("Unable to read source file")?;
-        let header = Self::data_block_header(&file_data).to_vec();
+    /// * `grid_units` - Units of the whole data grid in row order, one byte per channel.
+    fn units_to_image(&self, grid_units: Vec<u8>) -> Option<DynamicImage> {
+        if self.alpha_bits > 0 {
+            RgbaImage::from_raw(self.data_width, self.data_height, grid_units)
+                .map(DynamicImage::ImageRgba8)
+        } else {
+            RgbImage::from_raw(self.data_width, self.data_height, grid_units)
+                .map(DynamicImage::ImageRgb8)
+        }
+    }
 
-        clear_framebuffer_folder()?;
+    /// Builds the calibration frame, starting with `CALIBRATION_MAGIC`
+    /// followed by a ramp cycling every color channel through all its levels,
+    /// see `calibration_level`. Levels are never Gray coded.
+    /// Every bit of the magic takes a whole data unit, shown at the lowest
+    /// or highest level of all channels, so it is still recognized
+    /// if the levels shifted too far to be read at their encoded values.
+    ///
+    /// # Arguments
+    /// * `level_mapping` - How channel levels are mapped to pixel values.
+    fn calibration_frame_image(&self, level_mapping: LevelMapping) -> Result<DynamicImage> {
+        let mut frame_data = vec![0; self.frame_data_byte_count];
+        let total_bits = self.total_bits as usize;
+        let color_bits = [self.red_bits, self.green_bits, self.blue_bits];
+        for unit in 0..self.frame_data_unit_count {
+            let [red, green, blue] = if unit < Self::CALIBRATION_RAMP_START {
+                let magic_bit = (CALIBRATION_MAGIC[unit / 8] >> (7 - unit % 8)) & 1;
+                color_bits.map(|bits| {
+                    u32::from(magic_bit) * (u32::MAX.checked_shr(u32::BITS - bits).unwrap_or(0))
+                })
+            } else {
+                color_bits.map(|bits| {
+                    u32::from(calibration_level(unit - Self::CALIBRATION_RAMP_START, bits))
+                })
+            };
+            let data_unit_bits =
+                blue | (green << self.blue_bits) | (red << (self.blue_bits + self.green_bits));
+            // Bits are numbered from the most significant bit of each byte,
+            // matching how `data_to_frame` reads them.
+            for bit in 0..total_bits {
+                if (data_unit_bits >> (total_bits - 1 - bit)) & 1 == 1 {
+                    let position = unit * total_bits + bit;
+                    frame_data[position / 8] |= 0x80 >> (position % 8);
+                }
+            }
+        }
+        self.data_frame_image(&frame_data, level_mapping, false)
+    }
 
-        // Generating prebuffer frames
-        for i in 0..PREBUFFER_FRAMES {
-            self.save_buffer_frame(&frame_path_combine(i)?)?;
+    /// Checks if a frame starts with the `CALIBRATION_MAGIC` of a calibration frame.
+    /// Magic bits are read by cutting the channels encoding data at mid-range,
+    /// which holds through any shift keeping the lowest level darker than the highest.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of the frame.
+    fn is_calibration_frame(&self, frame_data_units: &[u8]) -> bool {
+        let channels: Vec<usize> = [self.red_bits, self.green_bits, self.blue_bits]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &bits)| bits > 0)
+            .map(|(channel, _)| channel)
+            .collect();
+        #[allow(clippy::cast_possible_truncation)]
+        let threshold = u32::from(u8::MAX) * channels.len() as u32;
+        let mut magic = [0u8; CALIBRATION_MAGIC.len()];
+        for (unit, data_unit) in frame_data_units
+            .chunks_exact(self.channels())
+            .take(Self::CALIBRATION_RAMP_START)
+            .enumerate()
+        {
+            let sum: u32 = channels
+                .iter()
+                .map(|&channel| u32::from(data_unit[channel]))
+                .sum();
+            if sum * 2 > threshold {
+                magic[unit / 8] |= 0x80 >> (unit % 8);
+            }
         }
+        magic == CALIBRATION_MAGIC
+    }
 
-        // Pad with zero to whole number of hamming chunks to allow error correction.
-        file_data.resize(
-            file_data.len().div_ceil(HAMMING_CHUNK_BYTES_31_26) * HAMMING_CHUNK_BYTES_31_26,
-            0,
-        );
+    #[must_use]
+    /// Multiple of the data resolution frames are scaled to when splitting a video,
+    /// as set with `with_downsample_scaler`.
+    pub fn downsample_scaler(&self) -> u32 {
+        self.downsample_scaler
+    }
 
-        println!("Encoding {:?} bytes to video.", file_data.len());
+    #[must_use]
+    /// (Width, Height) Number of data units in each frame.
+    pub fn data_dimensions(&self) -> [u32; 2] {
+        [self.data_width, self.data_height]
+    }
+
+    #[must_use]
+    /// Number of user data bytes stored in each data frame,
+    /// after the error correction overhead.
+    ///
+    /// # Arguments
+    /// * `fec` - Error correction code protecting the data.
+    pub fn data_bytes_per_frame(&self, fec: FecKind) -> usize {
+        self.frame_data_byte_count * fec.chunk_bytes() / fec.chunk_bytes_total()
+    }
 
-        let mut file_data_with_correction = encode_with_hamming_31_26(&file_data)?;
-        file_data_with_correction.splice(0..0, header);
+    #[must_use]
+    /// Number of user data bytes stored per second of video.
+    ///
+    /// # Arguments
+    /// * `fec` - Error correction code protecting the data.
+    pub fn data_bytes_per_second(&self, fec: FecKind) -> usize {
+        self.data_bytes_per_frame(fec) * self.data_fps as usize
+    }
 
-        // Generating regular data frames
-        for (frame_index, frame_data) in file_data_with_correction
-            .chunks(self.frame_data_byte_count)
-            .enumerate()
-        {
-            self.save_data_frame(
-                frame_data,
-                &frame_path_combine(frame_index + PREBUFFER_FRAMES)?,
-            )?;
+    #[must_use]
+    /// Number of bytes stored per second of video as (raw, usable):
+    /// All bytes the data frames hold, and the user data left after the error correction overhead.
+    /// Header, parity and buffer frames are not accounted for.
+    ///
+    /// # Arguments
+    /// * `fec` - Error correction code protecting the data.
+    pub fn capacity_per_second(&self, fec: FecKind) -> (usize, usize) {
+        (
+            self.frame_data_byte_count * self.data_fps as usize,
+            self.data_bytes_per_second(fec),
+        )
+    }
+
+    /// Number of frames a single copy of a file occupies,
+    /// counting its header, calibration, data, parity and droplet frames
+    /// and the header blocks repeated between them.
+    ///
+    /// # Arguments
+    /// * `data_len` - Size of the file in bytes.
+    /// * `header_frames` - Number of redundant header blocks.
+    /// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+    /// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+    /// * `fec` - Error correction code protecting the data.
+    /// * `sequenced` - If every data frame starts with a sequence number and ends with a CRC,
+    ///   as newly encoded ones do.
+    fn copy_frame_count(
+        &self,
+        data_len: usize,
+        header_frames: usize,
+        parity_interval: u16,
+        fountain_overhead: u16,
+        fec: FecKind,
+        sequenced: bool,
+    ) -> Result<usize> {
+        let stream_len = fec.encoded_len(data_len);
+        let data_frames = frame_count(
+            stream_len,
+            self.frame_bytes(sequenced),
+            parity_interval as usize,
+            fountain_overhead,
+            sequenced,
+        )?;
+        Ok(
+            (header_frames + self.repeated_header_count(data_frames)) * HEADER_BLOCK_FRAMES
+                + usize::from(self.calibration_frame)
+                + data_frames,
+        )
+    }
+
+    /// Number of bytes of each data frame holding its sequence number and payload,
+    /// leaving out the CRC at its end if there is one.
+    ///
+    /// # Arguments
+    /// * `frame_crc` - If every data frame ends with a CRC.
+    fn frame_bytes(&self, frame_crc: bool) -> usize {
+        if frame_crc {
+            self.frame_data_byte_count.saturating_sub(FRAME_CRC_LEN)
+        } else {
+            self.frame_data_byte_count
         }
+    }
 
-        let last_frame_index = file_data_with_correction
-            .chunks(self.frame_data_byte_count)
-            .count();
-        let postbuffer_index_start = PREBUFFER_FRAMES + last_frame_index;
-        // Generating postbuffer frames
-        for i in postbuffer_index_start..postbuffer_index_start + POSTBUFFER_FRAMES {
-            self.save_buffer_frame(&frame_path_combine(i)?)?;
+    /// Number of header blocks repeated between `data_frames` data, parity and droplet frames,
+    /// one after every `header_interval` of them unless they are the last.
+    ///
+    /// # Arguments
+    /// * `data_frames` - Number of data, parity and droplet frames of a copy.
+    fn repeated_header_count(&self, data_frames: usize) -> usize {
+        if self.header_interval == 0 {
+            0
+        } else {
+            data_frames.saturating_sub(1) / self.header_interval as usize
         }
-        Ok(())
     }
 
-    /// Reads in a png image at `downsample_scaler` times the final data resolution
-    /// Averages `downsample_scaler * downsample_scaler` pixel blocks and returns the data as Vec<u8>.
+    /// Number of data, parity and droplet frames following the header frames of each copy.
     ///
     /// # Arguments
-    /// * `path` - Path where the frame to read is located.
-    fn average_blocks<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
-        let img = image::open(path).context("Failed to open image")?;
-        let (width, height) = img.dimensions();
+    /// * `header` - Header describing the encoded data.
+    fn data_frame_count(&self, header: &HeaderData) -> Result<usize> {
+        frame_count(
+            header.fec.encoded_len(header.data_len),
+            self.frame_bytes(header.frame_crc),
+            header.parity_interval as usize,
+            header.fountain_overhead,
+            header.sequence_numbers,
+        )
+    }
+
+    /// Total number of frames `deconstruct_bytes` generates for a file,
+    /// including buffer, header, parity and droplet frames.
+    /// Newly encoded data frames always carry a sequence number.
+    ///
+    /// # Arguments
+    /// * `data_len` - Size of the file in bytes.
+    /// * `header_frames` - Number of redundant header blocks.
+    /// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+    /// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+    /// * `repeat` - Number of copies of the data in the video.
+    /// * `fec` - Error correction code protecting the data.
+    /// * `buffer_frames` - Number of blank frames before and after the data.
+    ///
+    /// # Errors
+    /// Fails if a frame is too small to hold a sequence number.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encoded_frame_count(
+        &self,
+        data_len: usize,
+        header_frames: usize,
+        parity_interval: u16,
+        fountain_overhead: u16,
+        repeat: usize,
+        fec: FecKind,
+        buffer_frames: [u16; 2],
+    ) -> Result<usize> {
+        let [prebuffer_frames, postbuffer_frames] = buffer_frames;
+        Ok(prebuffer_frames as usize
+            + repeat
+                * self.copy_frame_count(
+                    data_len,
+                    header_frames,
+                    parity_interval,
+                    fountain_overhead,
+                    fec,
+                    true,
+                )?
+            + postbuffer_frames as usize)
+    }
+
+    /// Length of the video `deconstruct_bytes` and `combine_frames` produce for a file.
+    ///
+    /// # Arguments
+    /// * `data_len` - Size of the file in bytes.
+    /// * `header_frames` - Number of redundant header blocks.
+    /// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+    /// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+    /// * `repeat` - Number of copies of the data in the video.
+    /// * `fec` - Error correction code protecting the data.
+    /// * `buffer_frames` - Number of blank frames before and after the data.
+    ///
+    /// # Errors
+    /// Fails like `encoded_frame_count`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encoded_duration(
+        &self,
+        data_len: usize,
+        header_frames: usize,
+        parity_interval: u16,
+        fountain_overhead: u16,
+        repeat: usize,
+        fec: FecKind,
+        buffer_frames: [u16; 2],
+    ) -> Result<Duration> {
+        let frames = u64::try_from(self.encoded_frame_count(
+            data_len,
+            header_frames,
+            parity_interval,
+            fountain_overhead,
+            repeat,
+            fec,
+            buffer_frames,
+        )?)?;
+        Ok(Duration::from_millis(
+            frames * 1000 / u64::from(self.data_fps),
+        ))
+    }
 
-        if width != self.data_width * DOWNSAMPLE_SCALER {
+    /// Approximate size in bytes of the video `encode_to_video` produces for a file,
+    /// based on `ESTIMATED_BITS_PER_PIXEL`. Blank buffer frames are not counted.
+    ///
+    /// # Arguments
+    /// * `data_len` - Size of the file in bytes.
+    /// * `header_frames` - Number of redundant header blocks.
+    /// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+    /// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+    /// * `repeat` - Number of copies of the data in the video.
+    /// * `fec` - Error correction code protecting the data.
+    ///
+    /// # Errors
+    /// Fails like `encoded_frame_count`.
+    pub fn estimated_video_size(
+        &self,
+        data_len: usize,
+        header_frames: usize,
+        parity_interval: u16,
+        fountain_overhead: u16,
+        repeat: usize,
+        fec: FecKind,
+    ) -> Result<u64> {
+        let content_frames = repeat
+            * self.copy_frame_count(
+                data_len,
+                header_frames,
+                parity_interval,
+                fountain_overhead,
+                fec,
+                true,
+            )?;
+        let frame_pixels = u64::from(self.frame_width) * u64::from(self.frame_height);
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let size = (content_frames as f64 * frame_pixels as f64 * ESTIMATED_BITS_PER_PIXEL
+            / f64::from(u8::BITS)) as u64;
+        Ok(size)
+    }
+
+    /// Checks the video encoding a file would stay within practical limits before any frames
+    /// are generated, and returns its frame count and length.
+    /// Fails if the frames can not be numbered or the video is longer than `max_duration`,
+    /// and warns about videos too long to be uploaded.
+    ///
+    /// # Arguments
+    /// * `data_len` - Size of the file in bytes.
+    /// * `header_frames` - Number of redundant header blocks.
+    /// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+    /// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+    /// * `repeat` - Number of copies of the data in the video.
+    /// * `fec` - Error correction code protecting the data.
+    /// * `buffer_frames` - Number of blank frames before and after the data.
+    /// * `max_duration` - Longest video the user accepts, if limited.
+    ///
+    /// # Errors
+    /// Fails if the frames can not be numbered or the video is longer than `max_duration`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_video_length(
+        &self,
+        data_len: usize,
+        header_frames: usize,
+        parity_interval: u16,
+        fountain_overhead: u16,
+        repeat: usize,
+        fec: FecKind,
+        buffer_frames: [u16; 2],
+        max_duration: Option<Duration>,
+    ) -> Result<(usize, Duration)> {
+        let frames = self.encoded_frame_count(
+            data_len,
+            header_frames,
+            parity_interval,
+            fountain_overhead,
+            repeat,
+            fec,
+            buffer_frames,
+        )?;
+        let duration = self.encoded_duration(
+            data_len,
+            header_frames,
+            parity_interval,
+            fountain_overhead,
+            repeat,
+            fec,
+            buffer_frames,
+        )?;
+        if frames > MAX_FRAME_COUNT {
             bail!(
-                "Read image width ({}) is incorrect. Expected data_width * DOWNSAMPLE_SCALER ({}*{}={})",
-                width,
-                self.data_width,
-                DOWNSAMPLE_SCALER,
-                self.data_width * DOWNSAMPLE_SCALER
+                "Video would need {frames} frames, more than the supported {MAX_FRAME_COUNT}. Use a higher resolution or more color bits."
+            );
+        }
+        if let Some(max_duration) = max_duration
+            && duration > max_duration
+        {
+            bail!(
+                "Video would be {} long, exceeding the maximum duration of {}. Use a higher data-fps, resolution or more color bits.",
+                format_duration(duration),
+                format_duration(max_duration)
+            );
+        }
+        if duration.as_secs() > LONG_VIDEO_WARNING_SECONDS {
+            eprintln!(
+                "Warning: Video will be {} long, longer than most platforms accept. Consider a higher data-fps.",
+                format_duration(duration)
             );
         }
+        Ok((frames, duration))
+    }
+
+    /// Read a file at the supplied path and encodes its contents it into as many frames as needed.
+    /// The data frames are preceded by header frames generated using `HeaderData::to_block`.
+    /// Saves all generated frames in `frame_buffer`.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory the frames are saved to.
+    /// * `path` - Path where the file to read is located.
+    /// * `header_frames` - Number of redundant header blocks, each starting on a new frame.
+    /// * `parity_interval` - Insert a parity frame after every `parity_interval` data frames.
+    ///   Zero disables parity frames.
+    /// * `buffer_frames` - Number of blank frames before and after the data,
+    ///   e.g. `[PREBUFFER_FRAMES, POSTBUFFER_FRAMES]`.
+    ///
+    /// # Errors
+    /// Fails if the file can not be read or a frame can not be saved.
+    pub fn deconstruct_file(
+        &self,
+        frame_buffer: &FrameBuffer,
+        path: &Path,
+        header_frames: usize,
+        parity_interval: u16,
+        buffer_frames: [u16; 2],
+    ) -> Result<()> {
+        // This whole process could be optimized to not require loading the entire file into memory.
+        // I didnt.
+        let file_data = fs::read(path).context("Unable to read source file")?;
+        let mut header = HeaderData::new(&file_data, parity_interval);
+        [header.prebuffer_frames, header.postbuffer_frames] = buffer_frames;
+        self.deconstruct_bytes(frame_buffer, &file_data, &header, header_frames, 1)
+    }
+
+    /// Encodes `file_data` into as many frames as needed.
+    /// The data frames are preceded by header frames generated from `header`.
+    /// With `repeat` above one, the header and data frames are repeated,
+    /// giving the decoder further copies to fall back on if one is damaged.
+    /// Saves all generated frames in `frame_buffer`.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory the frames are saved to.
+    /// * `file_data` - The data to encode.
+    /// * `header` - Header describing `file_data`, as created by `HeaderData::new`.
+    /// * `header_frames` - Number of redundant header blocks, each starting on a new frame.
+    /// * `repeat` - Number of copies of the header and data frames.
+    ///
+    /// # Errors
+    /// Fails if the frame buffer can not be cleared or a frame can not be saved.
+    pub fn deconstruct_bytes(
+        &self,
+        frame_buffer: &FrameBuffer,
+        file_data: &[u8],
+        header: &HeaderData,
+        header_frames: usize,
+        repeat: usize,
+    ) -> Result<()> {
+        frame_buffer.clear()?;
+        let mut frame_index = 0;
+        self.generate_frames(file_data, header, header_frames, repeat, &mut |frame| {
+            frame
+                .save(frame_buffer.frame_path_combine(frame_index)?)
+                .context("Unable to save frame")?;
+            frame_index += 1;
+            Ok(())
+        })
+    }
+
+    /// Encodes `file_data` into the same frames `deconstruct_bytes` saves,
+    /// returning them in video order instead of writing them to disk.
+    /// Frames are at data resolution, one pixel per data unit,
+    /// RGBA if data is encoded in the alpha channel and RGB otherwise.
+    ///
+    /// # Arguments
+    /// * `file_data` - The data to encode.
+    /// * `header` - Header describing `file_data`, as created by `HeaderData::new`.
+    /// * `header_frames` - Number of redundant header blocks, each starting on a new frame.
+    /// * `repeat` - Number of copies of the header and data frames.
+    ///
+    /// # Errors
+    /// Fails if `header_frames` or `repeat` is zero or the data can not be encoded.
+    pub fn encode_to_frames(
+        &self,
+        file_data: &[u8],
+        header: &HeaderData,
+        header_frames: usize,
+        repeat: usize,
+    ) -> Result<Vec<DynamicImage>> {
+        let mut frames = Vec::new();
+        self.generate_frames(file_data, header, header_frames, repeat, &mut |frame| {
+            frames.push(frame.clone());
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    /// Encodes `file_data` directly into a video at `output_file`,
+    /// streaming every frame to the backend as it is generated
+    /// instead of saving the frames to disk first.
+    ///
+    /// # Arguments
+    /// * `backend` - Video backend used to encode the frames.
+    /// * `file_data` - The data to encode.
+    /// * `header` - Header describing `file_data`, as created by `HeaderData::new`.
+    /// * `header_frames` - Number of redundant header blocks, each starting on a new frame.
+    /// * `repeat` - Number of copies of the header and data frames.
+    /// * `output_file` - Path pointing to the video file. Overwritten if it exists.
+    ///
+    /// # Errors
+    /// Fails if a frame can not be generated or the backend fails to encode the video.
+    pub fn encode_to_video(
+        &self,
+        backend: &dyn VideoBackend,
+        file_data: &[u8],
+        header: &HeaderData,
+        header_frames: usize,
+        repeat: usize,
+        output_file: &Path,
+    ) -> Result<()> {
+        // Same as `combine_frames`, the video is only moved to `output_file`
+        // once encoding succeeded.
+        let partial_path = partial_output_path(output_file)?;
+        let result = backend
+            .encode_stream(&self.encode_settings(true), &partial_path)
+            .and_then(|mut writer| {
+                self.generate_frames(file_data, header, header_frames, repeat, &mut |frame| {
+                    writer.write_frame(frame)
+                })?;
+                writer.finish()
+            });
+        if let Err(err) = result {
+            discard_partial_output(&partial_path);
+            return Err(err);
+        }
+        commit_partial_output(&partial_path, output_file)
+    }
 
-        if height != self.data_height * DOWNSAMPLE_SCALER {
+    /// Encodes `file_data` into frames and passes them to `write_frame` in video order:
+    /// Prebuffer frames, `repeat` copies of the header and data frames and postbuffer frames.
+    ///
+    /// # Arguments
+    /// * `file_data` - The data to encode.
+    /// * `header` - Header describing `file_data`, as created by `HeaderData::new`.
+    /// * `header_frames` - Number of redundant header blocks, each starting on a new frame.
+    /// * `repeat` - Number of copies of the header and data frames.
+    /// * `write_frame` - Called with every frame in order.
+    fn generate_frames(
+        &self,
+        file_data: &[u8],
+        header: &HeaderData,
+        header_frames: usize,
+        repeat: usize,
+        write_frame: &mut dyn FnMut(&DynamicImage) -> Result<()>,
+    ) -> Result<()> {
+        if header_frames == 0 {
+            bail!("At least one header frame is required.");
+        }
+        if repeat == 0 {
+            bail!("At least one copy of the data is required.");
+        }
+        if repeat > 1 && self.header_interval > 0 {
             bail!(
-                "Read image height ({}) is incorrect. Expected data_height * DOWNSAMPLE_SCALER ({}*{}={})",
-                height,
-                self.data_height,
-                DOWNSAMPLE_SCALER,
-                self.data_height * DOWNSAMPLE_SCALER
+                "Repeated header blocks can not be told apart from the header of a further copy, encode a single copy with a header interval."
             );
         }
+        let buffer_frame = self.buffer_frame_image()?;
+        let header_images = self.header_frame_images(header, header_frames)?;
+        let calibration_image = if self.calibration_frame {
+            Some(self.calibration_frame_image(header.level_mapping)?)
+        } else {
+            None
+        };
+
+        // Generating prebuffer frames
+        for _ in 0..header.prebuffer_frames {
+            write_frame(&buffer_frame)?;
+        }
 
-        let mut output = Vec::with_capacity((self.data_width * self.data_height * 3) as usize);
+        // Padded with zero to whole number of error correction chunks.
+        let chunk_bytes = header.fec.chunk_bytes();
+        eprintln!(
+            "Encoding {} to video.",
+            format_bytes((file_data.len().div_ceil(chunk_bytes) * chunk_bytes) as u64)
+        );
 
-        for by in 0..self.data_height {
-            for bx in 0..self.data_width {
-                let mut r_sum = 0u32;
-                let mut g_sum = 0u32;
-                let mut b_sum = 0u32;
-
-                for y in 0..DOWNSAMPLE_SCALER {
-                    for x in 0..DOWNSAMPLE_SCALER {
-                        let px = img
-                            .get_pixel(bx * DOWNSAMPLE_SCALER + x, by * DOWNSAMPLE_SCALER + y)
-                            .to_rgb();
-                        r_sum += px[0] as u32;
-                        g_sum += px[1] as u32;
-                        b_sum += px[2] as u32;
+        let file_data_with_correction = interleave(
+            &header.fec.encode_padded(file_data)?,
+            header.fec.word_symbols(),
+            header.fec.symbol_bits(),
+            header.interleave_depth as usize,
+        )?;
+        let mut frames = build_frames(
+            &file_data_with_correction,
+            self.frame_bytes(header.frame_crc),
+            header.parity_interval as usize,
+            header.fountain_overhead,
+            header.sequence_numbers,
+        )?;
+        if header.frame_crc {
+            frames.iter_mut().for_each(append_frame_crc);
+        }
+
+        // The first block is repeated between the data frames.
+        let repeated_header = &header_images[..HEADER_BLOCK_FRAMES];
+        let copy_frames = header_images.len()
+            + usize::from(self.calibration_frame)
+            + self.repeated_header_count(frames.len()) * repeated_header.len()
+            + frames.len();
+        let progress = progress_bar("Encoding frames", repeat * copy_frames, self.progress);
+        for _ in 0..repeat {
+            // Generating header frames
+            for header_image in &header_images {
+                write_frame(header_image)?;
+                progress.inc(1);
+            }
+            if let Some(calibration_image) = &calibration_image {
+                write_frame(calibration_image)?;
+                progress.inc(1);
+            }
+
+            // Generating regular data frames
+            for (index, frame_data) in frames.iter().enumerate() {
+                check_interrupted()?;
+                if self.header_interval > 0
+                    && index > 0
+                    && index.is_multiple_of(self.header_interval as usize)
+                {
+                    for header_image in repeated_header {
+                        write_frame(header_image)?;
+                        progress.inc(1);
                     }
                 }
-                let block_size = DOWNSAMPLE_SCALER * DOWNSAMPLE_SCALER;
-                #[allow(clippy::cast_possible_truncation)]
-                output.push((r_sum / block_size) as u8);
-                #[allow(clippy::cast_possible_truncation)]
-                output.push((g_sum / block_size) as u8);
-                #[allow(clippy::cast_possible_truncation)]
-                output.push((b_sum / block_size) as u8);
+                write_frame(&self.data_frame_image(
+                    frame_data,
+                    header.level_mapping,
+                    header.gray_code,
+                )?)?;
+                progress.inc(1);
             }
         }
-        Ok(output)
+        progress.finish_and_clear();
+
+        // Generating postbuffer frames
+        for _ in 0..header.postbuffer_frames {
+            write_frame(&buffer_frame)?;
+        }
+        Ok(())
     }
 
-    /// Take all frames saved in `constants::FRAME_DIR_PATH` and decode them
-    /// Combining the extracted data back into a single file.
+    /// Overwrites the header frames of the frames already in `frame_buffer`,
+    /// including the header blocks repeated between the data frames,
+    /// leaving buffer and data frames untouched.
+    /// The frames must have been generated for the same data with the same
+    /// number of header frames, parity interval and copies.
     ///
     /// # Arguments
-    /// * `path` - Path where the file will be stored.
-    /// * `overwrite` - If the output file should be overwritten if it exists.
-    pub fn reconstruct_file<P: AsRef<Path>>(&self, path: P, overwrite: bool) -> Result<FileReport> {
-        let mut read_from_video: Vec<u8> = Vec::with_capacity(self.frame_data_byte_count);
-        let mut checked_header: HeaderData = HeaderData {
-            version_code: [0; 8],
-            data_len: 0,
-            sha256_hash: [0; 32],
-        };
+    /// * `frame_buffer` - Directory holding the frames.
+    /// * `header` - New header, describing the data the frames were generated from.
+    /// * `header_frames` - Number of redundant header blocks the frames were generated with.
+    /// * `repeat` - Number of copies the frames were generated with.
+    ///
+    /// # Errors
+    /// Fails if `header_frames` is zero, the frame buffer does not hold the frames expected
+    /// for this data and settings, or a frame can not be saved.
+    pub fn regenerate_header(
+        &self,
+        frame_buffer: &FrameBuffer,
+        header: &HeaderData,
+        header_frames: usize,
+        repeat: usize,
+    ) -> Result<()> {
+        if header_frames == 0 {
+            bail!("At least one header frame is required.");
+        }
+        let expected_frames = self.encoded_frame_count(
+            header.data_len,
+            header_frames,
+            header.parity_interval,
+            header.fountain_overhead,
+            repeat,
+            header.fec,
+            header.buffer_frames(),
+        )?;
+        let existing_frames = frame_buffer.list_combine_frames()?.len();
+        if existing_frames != expected_frames {
+            bail!(
+                "Framebuffer holds {existing_frames} frames, but {expected_frames} are expected for this data and settings."
+            );
+        }
 
-        let mut found_header_frame = false;
-        for frame_path in glob(&frame_path_wildcard_split()?.to_string_lossy())? {
-            let mut img_content = self.frame_to_data(&self.average_blocks(frame_path?)?);
-            debug_assert_eq!(img_content.len(), self.frame_data_byte_count);
-            if found_header_frame {
-                read_from_video.append(&mut img_content);
-                continue;
-            }
-            // Read three redundant header copies.
-            let header: [u8; Self::HEADER_LEN * 3] =
-                img_content[0..Self::HEADER_LEN * 3].try_into()?;
-            // If the header bytes are all zero we are still on a prebuffer frame.
-            if header.iter().any(|&x| x != 0) {
-                found_header_frame = true;
-                checked_header =
-                    Self::read_data_header(&header).context("Unable to decode header.")?;
-                // Dont include header in read_data.
-                read_from_video.append(&mut img_content[Self::HEADER_LEN * 3..].to_vec());
+        let copy_frames = self.copy_frame_count(
+            header.data_len,
+            header_frames,
+            header.parity_interval,
+            header.fountain_overhead,
+            header.fec,
+            header.sequence_numbers,
+        )?;
+        let repeated_headers = self.repeated_header_count(self.data_frame_count(header)?);
+        for copy in 0..repeat {
+            let mut frame_index = self.save_header_frames(
+                frame_buffer,
+                header,
+                header_frames,
+                header.prebuffer_frames as usize + copy * copy_frames,
+            )? + usize::from(self.calibration_frame);
+            for _ in 0..repeated_headers {
+                frame_index = self.save_header_frames(
+                    frame_buffer,
+                    header,
+                    1,
+                    frame_index + self.header_interval as usize,
+                )?;
             }
         }
+        Ok(())
+    }
 
-        println!("Read {:?} bytes from video.", read_from_video.len());
-
-        // Pad with zero to whole number of hamming chunks to allow error correction.
-        read_from_video.resize(
-            read_from_video
-                .len()
-                .div_ceil(HAMMING_CHUNK_BYTES_TOAL_31_26)
-                * HAMMING_CHUNK_BYTES_TOAL_31_26,
-            0,
-        );
-        let (mut corrected_data, report) = decode_with_hamming_31_26(&read_from_video)?;
+    /// Saves `header_frames` header blocks, each starting on a new frame,
+    /// beginning at frame `start`.
+    /// Returns the index of the first frame after the header.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory the frames are saved to.
+    /// * `header` - Header to save.
+    /// * `header_frames` - Number of redundant header blocks.
+    /// * `start` - Index of the first header frame.
+    fn save_header_frames(
+        &self,
+        frame_buffer: &FrameBuffer,
+        header: &HeaderData,
+        header_frames: usize,
+        start: usize,
+    ) -> Result<usize> {
+        let mut frame_index = start;
+        for header_image in self.header_frame_images(header, header_frames)? {
+            header_image
+                .save(frame_buffer.frame_path_combine(frame_index)?)
+                .context("Unable to save frame")?;
+            frame_index += 1;
+        }
+        Ok(frame_index)
+    }
 
-        if checked_header.version_code != Self::VERSION_CODE {
-            bail!("Unable to find correct VERSION_CODE. First data frame missing or corrupted.");
+    /// Builds the frames of `header_frames` header blocks, each starting on a new frame.
+    ///
+    /// # Arguments
+    /// * `header` - Header to encode.
+    /// * `header_frames` - Number of redundant header blocks.
+    fn header_frame_images(
+        &self,
+        header: &HeaderData,
+        header_frames: usize,
+    ) -> Result<Vec<DynamicImage>> {
+        let mut header = header.clone();
+        header.data_frames = Some(self.data_frame_count(&header)?);
+        header.quantization_bias = self.quantization_bias;
+        header.calibration_frame = self.calibration_frame;
+        header.finder_patterns = self.finder_patterns;
+        header.alpha_bits = self.alpha_bits;
+        header.luma_only = self.luma_only;
+        header.downsample_scaler = self.downsample_scaler;
+        header.header_interval = self.header_interval;
+        let block = header.to_block()?;
+        self.check_header_grid()?;
+        let mut images = Vec::new();
+        for _ in 0..header_frames {
+            for frame_data in block.chunks(HEADER_FRAME_BYTES) {
+                images.push(self.header_frame_image(frame_data)?);
+            }
         }
+        Ok(images)
+    }
 
-        if checked_header.data_len == 0 {
-            bail!("Expected size read as invalid value zero.");
+    /// Checks the data grid is at least as fine as `HEADER_GRID`,
+    /// so header frames can be shown in it.
+    fn check_header_grid(&self) -> Result<()> {
+        if self.data_width < HEADER_GRID[0] || self.data_height < HEADER_GRID[1] {
+            bail!(
+                "Data resolution ({}x{}) is below the {}x{} units of header frames. Use a smaller data pixel size.",
+                self.data_width,
+                self.data_height,
+                HEADER_GRID[0],
+                HEADER_GRID[1]
+            );
         }
+        Ok(())
+    }
 
-        if checked_header.data_len > corrected_data.len() {
+    /// Builds the image of a header frame in the fixed layout of `HEADER_GRID`,
+    /// independent of the color bits and data pixel size, so a decoder given other settings
+    /// still reads the header and learns them from it.
+    /// Every header unit shows one bit per color channel at the midpoint levels
+    /// and spans the data units nearest to it. Its corners stay dark for the finder patterns.
+    ///
+    /// # Arguments
+    /// * `frame_data` - Bytes to encode, at most `HEADER_FRAME_BYTES`.
+    fn header_frame_image(&self, frame_data: &[u8]) -> Result<DynamicImage> {
+        if frame_data.len() > HEADER_FRAME_BYTES {
             bail!(
-                "Read less data ({} bytes) than expected file size ({} bytes).",
-                corrected_data.len(),
-                checked_header.data_len
+                "Header frame data supplied ({} bytes) is longer than expected ({HEADER_FRAME_BYTES} bytes).",
+                frame_data.len()
             );
         }
+        let bit = |index: usize| {
+            frame_data
+                .get(index / 8)
+                .is_some_and(|byte| (byte >> (7 - index % 8)) & 1 == 1)
+        };
+        let header_units = Self::header_unit_indices();
+        let grid = [self.data_width, self.data_height];
+        let mut grid_units = Vec::with_capacity(
+            self.data_width as usize * self.data_height as usize * self.channels(),
+        );
+        for y in 0..self.data_height {
+            let header_y = y * HEADER_GRID[1] / self.data_height;
+            for x in 0..self.data_width {
+                let header_x = x * HEADER_GRID[0] / self.data_width;
+                let finder_value = if self.finder_patterns {
+                    finder_unit(x, y, grid)
+                } else {
+                    None
+                };
+                let unit = match (
+                    finder_value,
+                    header_units[(header_y * HEADER_GRID[0] + header_x) as usize],
+                ) {
+                    (Some(value), _) => [value; COLOR_CHANNELS],
+                    (None, Some(index)) => array::from_fn(|channel| {
+                        LevelMapping::Midpoint
+                            .encode(u8::from(bit(index * COLOR_CHANNELS + channel)), 1)
+                    }),
+                    (None, None) => [0; COLOR_CHANNELS],
+                };
+                grid_units.extend(unit);
+                if self.alpha_bits > 0 {
+                    grid_units.push(LevelMapping::Midpoint.encode(1, 1));
+                }
+            }
+        }
+        self.units_to_image(grid_units)
+            .context("Unable to create image buffer from header frame data")
+    }
+
+    /// Returns the index of every unit of `HEADER_GRID` among those holding header bits,
+    /// in row order, or `None` for the units its corners reserve for finder patterns.
+    fn header_unit_indices() -> Vec<Option<usize>> {
+        let mut next_index = 0;
+        let mut indices = Vec::with_capacity((HEADER_GRID[0] * HEADER_GRID[1]) as usize);
+        for y in 0..HEADER_GRID[1] {
+            for x in 0..HEADER_GRID[0] {
+                indices.push(finder_unit(x, y, HEADER_GRID).is_none().then(|| {
+                    next_index += 1;
+                    next_index - 1
+                }));
+            }
+        }
+        indices
+    }
 
-        // Resize to expected size.
-        corrected_data.resize(checked_header.data_len, 0);
+    /// Returns the data unit in the middle of those `header_frame_image` fills
+    /// with header unit `header_position`, along one axis of the grid.
+    ///
+    /// # Arguments
+    /// * `header_position` - Column or row of the header unit.
+    /// * `header_size` - Header units along the axis, see `HEADER_GRID`.
+    /// * `data_size` - Data units along the axis, at least `header_size`.
+    fn header_unit_center(header_position: u32, header_size: u32, data_size: u32) -> u32 {
+        let first = (header_position * data_size).div_ceil(header_size);
+        let end = ((header_position + 1) * data_size).div_ceil(header_size);
+        (first + end - 1) / 2
+    }
 
-        println!("Writing {:?} bytes to file.", corrected_data.len());
+    /// Classifier reading the units of header frames, one bit in every channel.
+    fn header_classifier() -> LevelClassifier {
+        LevelClassifier::new(LevelMapping::Midpoint, [1; COLOR_CHANNELS], false).with_alpha_levels(
+            LevelMapping::Midpoint,
+            1,
+            false,
+        )
+    }
 
-        if !overwrite & path.as_ref().exists() {
-            bail!("File at file output path exists and overwrite is not enabled.");
+    /// Reads every frame in the fixed header layout, see `header_frame_image`,
+    /// sampling the data unit in the middle of each header unit.
+    /// Returns `None` if the data grid is coarser than `HEADER_GRID`
+    /// and can not hold header frames.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    fn decode_header_frames(&self, frame_data_units: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+        self.check_header_grid().ok()?;
+        let classifier = Self::header_classifier();
+        let channels = self.channels();
+        let header_units = Self::header_unit_indices();
+        // Index of every header unit holding bits and the offset of the data unit sampled for it.
+        let mut samples = Vec::new();
+        for header_y in 0..HEADER_GRID[1] {
+            let y = Self::header_unit_center(header_y, HEADER_GRID[1], self.data_height);
+            for header_x in 0..HEADER_GRID[0] {
+                let x = Self::header_unit_center(header_x, HEADER_GRID[0], self.data_width);
+                if let Some(index) = header_units[(header_y * HEADER_GRID[0] + header_x) as usize] {
+                    samples.push((index, (y * self.data_width + x) as usize * channels));
+                }
+            }
         }
+        Some(
+            frame_data_units
+                .par_iter()
+                .map(|units| {
+                    let grid_units = self.insert_finder_units(units.clone());
+                    let mut frame = vec![0; HEADER_FRAME_BYTES];
+                    for &(index, offset) in &samples {
+                        for channel in 0..COLOR_CHANNELS {
+                            let bit = index * COLOR_CHANNELS + channel;
+                            if bit / 8 < HEADER_FRAME_BYTES
+                                && grid_units
+                                    .get(offset + channel)
+                                    .is_some_and(|&value| classifier.classify(channel, value) == 1)
+                            {
+                                frame[bit / 8] |= 0x80 >> (bit % 8);
+                            }
+                        }
+                    }
+                    frame
+                })
+                .collect(),
+        )
+    }
 
-        let computed_hash: [u8; 32] = Sha256::digest(&corrected_data).into();
-        fs::write(path, corrected_data).context("Unable to write output file.")?;
+    /// Decodes the header frames of a video and finds the first of them.
+    /// Tries the fixed header layout first, then the data layout of the midpoint mapping
+    /// videos before version 4 wrote their header frames in.
+    /// Returns the decoded frames and the index of the first header frame.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    fn locate_header(&self, frame_data_units: &[Vec<u8>]) -> Result<(Vec<Vec<u8>>, usize)> {
+        if let Some(header_frames) = self.decode_header_frames(frame_data_units)
+            && let Ok(header_start) = Self::find_header_start(&header_frames)
+        {
+            return Ok((header_frames, header_start));
+        }
+        let header_frames = self.decode_frames(
+            frame_data_units,
+            &self.level_classifier(LevelMapping::Midpoint, false),
+        );
+        let header_start = Self::find_header_start(&header_frames)?;
+        Ok((header_frames, header_start))
+    }
 
-        let report =
-            FileReport::from_hamming_report(&report, computed_hash == checked_header.sha256_hash);
-        if !report.hash_match {
-            eprintln!(
-                "Reconstructed file hash {} does not match expected hash {}.",
-                bytes_to_hex_string(&computed_hash),
-                bytes_to_hex_string(&checked_header.sha256_hash)
-            );
+    /// Checks the video was encoded with the color bits and data dimensions it is decoded with,
+    /// as far as its header records them.
+    /// Fails with `DataLayoutDiffers` otherwise, so the caller can decode again
+    /// with the settings from the header.
+    ///
+    /// # Arguments
+    /// * `header` - Header read from the copy.
+    fn check_data_layout(&self, header: &HeaderData) -> Result<()> {
+        let Some(converter_config) = header.converter_config else {
+            return Ok(());
+        };
+        let encoder = Self::from_config_bytes(&converter_config)
+            .context("Video header holds invalid converter settings.")?;
+        if [encoder.red_bits, encoder.green_bits, encoder.blue_bits]
+            != [self.red_bits, self.green_bits, self.blue_bits]
+            || [encoder.data_width, encoder.data_height] != [self.data_width, self.data_height]
+        {
+            return Err(DataLayoutDiffers {
+                converter_config,
+                downsample_scaler: header.downsample_scaler,
+            }
+            .into());
         }
-        Ok(report)
+        Ok(())
     }
 
-    /// Take all frames saved in `constants::FRAME_DIR_PATH` and combine them into a video.
-    /// Upscale video to `frame_height` x `frame_width` and save at specified path.
-    /// Also increase framerate to `constants::VIDEO_FPS`.
+    /// Multiple of the data resolution frames are scaled to when splitting a video.
+    /// Camera capture and core sampling need at least their own larger scalers.
+    fn split_scaler(&self) -> u32 {
+        if self.finder_patterns {
+            self.downsample_scaler.max(CAMERA_DOWNSAMPLE_SCALER)
+        } else if self.sample_core < 1.0 {
+            self.downsample_scaler.max(CORE_DOWNSAMPLE_SCALER)
+        } else {
+            self.downsample_scaler
+        }
+    }
+
+    /// Reads in a frame image at `downsample_scaler` times the final data resolution
+    /// Averages `downsample_scaler * downsample_scaler` pixel blocks and returns the data as Vec<u8>.
     ///
     /// # Arguments
-    /// * `output_file` - Path pointing to the combined video file.
-    /// * `overwrite` - If the output file should be overwritten if it exists.
-    pub fn combine_frames<P: AsRef<Path>>(&self, output_file: P, overwrite: bool) -> Result<()> {
-        if !overwrite & output_file.as_ref().exists() {
-            bail!("File at video output path exists and overwrite is not enabled.");
+    /// * `path` - Path where the frame to read is located.
+    fn average_blocks<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let img = image::open(path).context("Failed to open image")?;
+        if self.finder_patterns {
+            return self.sample_finder_frame(&img.to_rgb8());
         }
-        // Encoding parameters choosed as per youtube reccomendation:
-        // https://support.google.com/youtube/answer/1722171
-        // - mp4 Containter
-        // - H.264
-        // - Profile: High
-        // - CABAC enabled
-        // - bt709 colorspace
-        // - Chroma subsampling: 4:2:0
-        let ffmpeg_command = Command::new(FFMPEG_EXCUTABLE_PATH)
-            .args([
-                "-hide_banner",
-                "-loglevel",
-                "error",
-                "-framerate",
-                &format!("{}", self.data_fps),
-                "-pattern_type",
-                "glob",
-                "-i",
-                &frame_path_wildcard_combine()?.to_string_lossy(),
-                "-vf",
-                // Downscaling algorithm used when splitting video back into frames.
-                // Available:
-                // - fast_bilinear     3 errors
-                // - bilinear          6 errors
-                // - bicubic           3 errors
-                // - experimental      4 errors
-                // - neighbor          3 errors
-                // - area              2 errors
-                // - bicublin          3 errors
-                // - gauss             6 errors
-                // - sinc (slow)       3 errors
-                // - lanczos           3 errors
-                // - spline (slow)     3 errors
-                &format!(
-                    "scale={}:{}:flags=neighbor,format=yuv420p",
-                    self.frame_width, self.frame_height
-                ),
-                "-c:v",
-                "libx264",
-                "-preset",
-                H264_PRESET,
-                "-crf",
-                &format!("{H264_CRF}"),
-                "-profile:v",
-                "high",
-                "-colorspace:v",
-                COLORSPACE,
-                "-color_primaries:v",
-                COLORSPACE,
-                "-color_trc:v",
-                COLORSPACE,
-                "-color_range:v",
-                COLOR_RANGE,
-                "-r",
-                &format!("{}", self.video_fps),
-                "-y", // Overwrite if exists
-                &output_file.as_ref().to_string_lossy(),
-            ])
-            .stdout(Stdio::null())
-            //.stderr(Stdio::null())
-            .status()?;
-        if !ffmpeg_command.success() {
-            bail!("ffmpeg returned nonzero exit status.");
+        let (width, height) = (img.width(), img.height());
+        let scaler = self.split_scaler();
+
+        if width != self.data_width * scaler {
+            bail!(
+                "Read image width ({}) is incorrect. Expected data_width * downsample_scaler ({}*{}={})",
+                width,
+                self.data_width,
+                scaler,
+                self.data_width * scaler
+            );
         }
-        Ok(())
+
+        if height != self.data_height * scaler {
+            bail!(
+                "Read image height ({}) is incorrect. Expected data_height * downsample_scaler ({}*{}={})",
+                height,
+                self.data_height,
+                scaler,
+                self.data_height * scaler
+            );
+        }
+
+        Ok(self.average_channels(&img, [scaler, scaler]))
     }
 
-    /// Split a video back into individual frames.
-    /// Also scales down back to the data resolution.
+    /// Averages the pixels of every data unit of a frame held in memory.
+    /// The frame may be at any whole multiple of the data resolution,
+    /// such as the data resolution frames `encode_to_frames` returns.
     ///
     /// # Arguments
-    /// * `input_file` - Path pointing to the video file.
-    pub fn split_video<P: AsRef<Path>>(&self, input_file: P) -> Result<()> {
-        clear_framebuffer_folder()?;
-        let frame_dir = get_framebuffer_folder()?;
-        let frame_wildcard = frame_dir.join(Path::new("split%09d.png"));
-        let ffmpeg_command = Command::new(FFMPEG_EXCUTABLE_PATH)
-            .args([
-                "-hide_banner",
-                "-loglevel",
-                "error",
-                "-i",
-                &input_file.as_ref().to_string_lossy(),
-                "-vf",
-                &format!(
-                    "scale={}:{}:flags=neighbor",
-                    self.data_width * DOWNSAMPLE_SCALER,
-                    self.data_height * DOWNSAMPLE_SCALER,
-                ),
-                "-r",
-                &format!("{}", self.data_fps),
-                &frame_wildcard.to_string_lossy(),
-            ])
-            .stdout(Stdio::null())
-            //.stderr(Stdio::null())
-            .status()?;
-        if !ffmpeg_command.success() {
-            bail!("ffmpeg returned nonzero exit status.");
+    /// * `frame` - Frame to average.
+    fn average_frame(&self, frame: &DynamicImage) -> Result<Vec<u8>> {
+        if self.finder_patterns {
+            return self.sample_finder_frame(&frame.to_rgb8());
         }
+        let (width, height) = (frame.width(), frame.height());
+        if width == 0
+            || height == 0
+            || !width.is_multiple_of(self.data_width)
+            || !height.is_multiple_of(self.data_height)
+        {
+            bail!(
+                "Frame resolution {width}x{height} is not a whole multiple of the data resolution {}x{}.",
+                self.data_width,
+                self.data_height
+            );
+        }
+        Ok(self.average_channels(frame, [width / self.data_width, height / self.data_height]))
+    }
+
+    /// Samples the data units of a frame holding finder patterns,
+    /// locating them to undo the perspective of a camera, see `finder::sample_grid`.
+    ///
+    /// # Arguments
+    /// * `frame` - Frame at any resolution, the data grid may be shown at an angle.
+    fn sample_finder_frame(&self, frame: &RgbImage) -> Result<Vec<u8>> {
+        Ok(self.remove_finder_units(sample_grid(frame, [self.data_width, self.data_height])?))
+    }
+
+    /// Averages the data units of a frame like `average_image`,
+    /// reading the alpha channel along with the colors if data is encoded in it.
+    ///
+    /// # Arguments
+    /// * `img` - Frame at `scale` times the data resolution.
+    /// * `scale` - (Horizontal, Vertical) Pixels per data unit.
+    fn average_channels(&self, img: &DynamicImage, scale: [u32; 2]) -> Vec<u8> {
+        if self.alpha_bits > 0 {
+            match img.as_rgba8() {
+                Some(img) => self.average_image(img, scale),
+                None => self.average_image(&img.to_rgba8(), scale),
+            }
+        } else {
+            match img.as_rgb8() {
+                Some(img) => self.average_image(img, scale),
+                None => self.average_image(&img.to_rgb8(), scale),
+            }
+        }
+    }
+
+    /// Averages the `scale[0] * scale[1]` pixel block of `img` of each data unit,
+    /// and returns their values as Vec<u8>, one byte per channel.
+    /// Only the core of every block set by `sample_core` is averaged,
+    /// pixels partly inside it are weighted by the part that is.
+    ///
+    /// # Arguments
+    /// * `img` - Frame at `scale` times the data resolution.
+    /// * `scale` - (Horizontal, Vertical) Pixels per data unit.
+    fn average_image<P: Pixel<Subpixel = u8>>(
+        &self,
+        img: &ImageBuffer<P, Vec<u8>>,
+        scale: [u32; 2],
+    ) -> Vec<u8> {
+        let [scale_x, scale_y] = scale;
+        let weights_x = self.core_weights(scale_x);
+        let weights_y = self.core_weights(scale_y);
+        let block_weight: f64 = weights_x.iter().sum::<f64>() * weights_y.iter().sum::<f64>();
+        let channels = usize::from(P::CHANNEL_COUNT);
+        let mut output =
+            Vec::with_capacity((self.data_width * self.data_height) as usize * channels);
+
+        for by in 0..self.data_height {
+            for bx in 0..self.data_width {
+                let mut sums = [0.0f64; COLOR_CHANNELS + 1];
+
+                for (y, weight_y) in (0..scale_y).zip(&weights_y) {
+                    for (x, weight_x) in (0..scale_x).zip(&weights_x) {
+                        let px = img.get_pixel(bx * scale_x + x, by * scale_y + y);
+                        for (sum, &value) in sums.iter_mut().zip(px.channels()) {
+                            *sum += weight_x * weight_y * f64::from(value);
+                        }
+                    }
+                }
+                // Truncated like an integer average, which the whole block reproduces exactly.
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                output.extend(
+                    sums[..channels]
+                        .iter()
+                        .map(|sum| (sum / block_weight) as u8),
+                );
+            }
+        }
+        output
+    }
+
+    /// Weight of each pixel along one side of a data unit block,
+    /// the part of the pixel inside the sampled core centered in the block.
+    ///
+    /// # Arguments
+    /// * `scale` - Pixels along the side of the block.
+    fn core_weights(&self, scale: u32) -> Vec<f64> {
+        let size = f64::from(scale);
+        let core_start = size * (1.0 - self.sample_core) / 2.0;
+        let core_end = size - core_start;
+        (0..scale)
+            .map(|pixel| {
+                let pixel = f64::from(pixel);
+                (core_end.min(pixel + 1.0) - core_start.max(pixel)).max(0.0)
+            })
+            .collect()
+    }
+
+    /// Saves the averaged data units of a frame as an image at data resolution.
+    /// This is what the decoder sees before reading bits from the colors.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged frame as returned by `average_blocks`.
+    /// * `path` - Path where the image should be saved.
+    fn save_dump_frame(&self, frame_data_units: &[u8], path: &Path) -> Result<()> {
+        let img_buffer = self
+            .units_to_image(self.insert_finder_units(frame_data_units.to_vec()))
+            .context("Unable to create image buffer from averaged frame")?;
+        img_buffer
+            .save(path)
+            .context("Unable to save dumped frame as PNG")?;
+        Ok(())
+    }
+
+    /// Take all frames saved in `frame_buffer` and decode them
+    /// Combining the extracted data back into a single file.
+    /// An archive of several files is unpacked into `path` as a directory instead,
+    /// unless it is compressed or encrypted, which the caller has to undo first.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory holding the split frames.
+    /// * `path` - Path where the file, or the files of an archive, will be stored.
+    /// * `overwrite` - If the output file should be overwritten if it exists.
+    /// * `dump_dir` - If set, every averaged frame is also saved into this directory.
+    ///
+    /// # Errors
+    /// Fails like `reconstruct_to_vec`, or if the output file can not be written.
+    pub fn reconstruct_file<P: AsRef<Path>>(
+        &self,
+        frame_buffer: &FrameBuffer,
+        path: P,
+        overwrite: bool,
+        dump_dir: Option<&Path>,
+    ) -> Result<FileReport> {
+        if !overwrite && path.as_ref().exists() {
+            bail!("File at file output path exists and overwrite is not enabled.");
+        }
+
+        let (corrected_data, report) = self.reconstruct_to_vec(frame_buffer, dump_dir)?;
+
+        if report.header.is_archive
+            && !report.header.is_compressed
+            && report.header.encryption.is_none()
+        {
+            let files = unpack_archive(&corrected_data, path.as_ref(), overwrite)?;
+            eprintln!("Unpacked {} files into directory.", files.len());
+            return Ok(report);
+        }
+        eprintln!(
+            "Writing {} to file.",
+            format_bytes(corrected_data.len() as u64)
+        );
+
+        write_output_file(path.as_ref(), &corrected_data)?;
+        Ok(report)
+    }
+
+    /// Take all frames saved in `frame_buffer` and decode them
+    /// Combining the extracted data back into a single buffer.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory holding the split frames.
+    /// * `dump_dir` - If set, every averaged frame is also saved into this directory.
+    ///
+    /// # Errors
+    /// Fails if the frames can not be read, no valid header is found or less data than expected is read.
+    pub fn reconstruct_to_vec(
+        &self,
+        frame_buffer: &FrameBuffer,
+        dump_dir: Option<&Path>,
+    ) -> Result<(Vec<u8>, FileReport)> {
+        let frame_data_units = self.read_split_frames(frame_buffer, dump_dir)?;
+        self.decode_data_units(&frame_data_units)
+    }
+
+    /// Decodes frames held in memory, such as those returned by `encode_to_frames`,
+    /// like `reconstruct_to_vec` decodes the frames in a framebuffer.
+    /// Frames may be at any whole multiple of the data resolution.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames of the video in order.
+    ///
+    /// # Errors
+    /// Fails if a frame has the wrong resolution, no valid header is found
+    /// or less data than expected is read.
+    pub fn decode_from_frames(&self, frames: &[DynamicImage]) -> Result<(Vec<u8>, FileReport)> {
+        let frame_data_units = frames
+            .par_iter()
+            .map(|frame| self.average_frame(frame))
+            .collect::<Result<Vec<_>>>()?;
+        self.decode_data_units(&frame_data_units)
+    }
+
+    /// Decodes the averaged data units of all frames of a video,
+    /// trying every copy of the data until one passes the hash check.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    fn decode_data_units(&self, frame_data_units: &[Vec<u8>]) -> Result<(Vec<u8>, FileReport)> {
+        let (header_frames, mut header_start) = self.locate_header(frame_data_units)?;
+        // Videos encoded with `--repeat` contain several copies, each with its own header.
+        // They are tried in order until one passes the hash check.
+        let mut copy = 1;
+        let mut best: Option<(Vec<u8>, FileReport)> = None;
+        let mut first_error = None;
+        loop {
+            let next_search_start =
+                match self.decode_copy(frame_data_units, &header_frames, header_start) {
+                    Ok((data, mut report, copy_end)) => {
+                        report.copy = copy;
+                        let hash_match = report.hash_match;
+                        if best
+                            .as_ref()
+                            .is_none_or(|(_, best_report)| report.is_better_than(best_report))
+                        {
+                            best = Some((data, report));
+                        }
+                        if hash_match {
+                            break;
+                        }
+                        copy_end
+                    }
+                    Err(err) if err.is::<DataLayoutDiffers>() => return Err(err),
+                    Err(err) => {
+                        eprintln!("Unable to decode copy {copy}: {err:#}");
+                        first_error.get_or_insert(err);
+                        header_start + 1
+                    }
+                };
+            match Self::find_next_header(&header_frames, next_search_start) {
+                Some(next_header_start) => {
+                    header_start = next_header_start;
+                    copy += 1;
+                }
+                None => break,
+            }
+        }
+        match (best, first_error) {
+            (Some(best), _) => Ok(best),
+            (None, Some(err)) => Err(err),
+            (None, None) => bail!("No copy of the data found in video."),
+        }
+    }
+
+    /// Decodes a single copy of the data whose header starts at `header_start`.
+    /// Returns the data, the report and the index of the first frame after the copy.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    /// * `header_frames` - All frames decoded in the layout of their header frames, see `locate_header`.
+    /// * `header_start` - Index of the first header frame of the copy.
+    fn decode_copy(
+        &self,
+        frame_data_units: &[Vec<u8>],
+        header_frames: &[Vec<u8>],
+        header_start: usize,
+    ) -> Result<(Vec<u8>, FileReport, usize)> {
+        let (checked_header, header_vote, data_start, repeated_headers) =
+            Self::read_copy_header(header_frames, header_start)?;
+        self.check_alpha_bits(&checked_header)?;
+        self.check_data_layout(&checked_header)?;
+        let luma_units;
+        let frame_data_units = if checked_header.luma_only {
+            luma_units = self.luma_units(frame_data_units);
+            luma_units.as_slice()
+        } else {
+            frame_data_units
+        };
+        let (calibrated_classifier, data_start) =
+            self.read_calibration_frame(frame_data_units, &checked_header, data_start);
+        let calibration_frame = calibrated_classifier.as_ref().map(|_| data_start - 1);
+        let classifier = calibrated_classifier.unwrap_or_else(|| {
+            self.level_classifier(checked_header.level_mapping, checked_header.gray_code)
+        });
+        let decoded_frames = self.decode_frames(frame_data_units, &classifier);
+        let (data_start, frames) = Self::copy_frames(
+            &decoded_frames,
+            header_frames,
+            data_start,
+            &repeated_headers,
+        );
+        let stream_frames =
+            Self::stream_frames(frames, data_start, calibration_frame, &repeated_headers);
+
+        let (read_from_video, framing_report) =
+            self.read_stream(&stream_frames, &checked_header)?;
+        let read_from_video = Self::pad_stream(read_from_video, &checked_header)?;
+        // Errors are also counted per data frame for the frame statistics.
+        let (stream_offset, data_frame_len) = if checked_header.version_code == VERSION_CODE_V1 {
+            (BLOCK_LEN_V1, self.frame_data_byte_count)
+        } else {
+            (
+                0,
+                payload_len(
+                    self.frame_bytes(checked_header.frame_crc),
+                    checked_header.sequence_numbers,
+                )?,
+            )
+        };
+        let (mut corrected_data, report, data_frame_reports) =
+            checked_header
+                .fec
+                .decode_blocks(&read_from_video, data_frame_len, stream_offset)?;
+        let frame_stats = self.frame_stats(
+            frame_data_units,
+            frames,
+            data_start,
+            calibration_frame,
+            &repeated_headers,
+            &checked_header,
+            &data_frame_reports,
+        )?;
+
+        // An empty file is encoded with a length of zero and the hash of no data,
+        // any other hash means the length was misread.
+        let hash_algorithm = checked_header.hash_algorithm;
+        if checked_header.data_len == 0 && checked_header.data_hash != hash_algorithm.digest(&[]) {
+            bail!("Expected size read as invalid value zero.");
+        }
+
+        if checked_header.data_len > corrected_data.len() {
+            bail!(
+                "Read less data ({} bytes) than expected file size ({} bytes).",
+                corrected_data.len(),
+                checked_header.data_len
+            );
+        }
+
+        // The data was zero padded to whole code words before encoding, and the last data frame
+        // to a whole frame. Both paddings follow the data, so cutting the decoded data to the length
+        // from the header drops them, no matter how few bytes the last frame holds.
+        corrected_data.truncate(checked_header.data_len);
+
+        if hash_algorithm == HashAlgorithm::None {
+            eprintln!("Warning: The video stores no hash, the decoded data can not be checked.");
+        }
+        let computed_hash = hash_algorithm.digest(&corrected_data);
+        let hash_match = computed_hash == checked_header.data_hash;
+        if !hash_match {
+            eprintln!(
+                "Reconstructed file hash {} does not match expected hash {}.",
+                bytes_to_hex_string(&computed_hash),
+                bytes_to_hex_string(&checked_header.data_hash)
+            );
+        }
+        let mut report = FileReport::from_hamming_report(
+            report,
+            hash_match,
+            header_vote,
+            framing_report,
+            checked_header,
+        );
+        report.crc_failed_frames = report.header.frame_crc.then(|| {
+            frame_stats
+                .iter()
+                .filter(|frame| frame.crc_ok == Some(false))
+                .count()
+        });
+        report.frame_stats = frame_stats;
+        Ok((corrected_data, report, frames.len()))
+    }
+
+    /// Undoes the interleaving of the data stream read from a copy
+    /// and pads it with zeros to a whole number of error correction chunks.
+    ///
+    /// # Arguments
+    /// * `stream` - Data stream read from the copy.
+    /// * `header` - Header read from the copy.
+    fn pad_stream(mut stream: Vec<u8>, header: &HeaderData) -> Result<Vec<u8>> {
+        if header.interleave_depth > 1 {
+            // Missing frames are padded, so the groups line up with those of the encoder.
+            // The frame statistics count errors at the position of the code word
+            // before interleaving.
+            let stream_len = header.fec.encoded_len(header.data_len);
+            stream.resize(stream.len().max(stream_len), 0);
+            stream = deinterleave(
+                &stream,
+                header.fec.word_symbols(),
+                header.fec.symbol_bits(),
+                header.interleave_depth as usize,
+            )?;
+        }
+        let chunk_bytes_total = header.fec.chunk_bytes_total();
+        stream.resize(
+            stream.len().div_ceil(chunk_bytes_total) * chunk_bytes_total,
+            0,
+        );
+        Ok(stream)
+    }
+
+    /// Reads the header of the copy starting at `header_start`,
+    /// voting over all header blocks of the video if the header is repeated between the data frames.
+    /// Returns the header, the vote result, the index of the first data frame
+    /// and which frames hold a header block if the header is repeated, empty otherwise.
+    ///
+    /// # Arguments
+    /// * `header_frames` - All frames decoded in the layout of their header frames, see `locate_header`.
+    /// * `header_start` - Index of the first header frame of the copy.
+    fn read_copy_header(
+        header_frames: &[Vec<u8>],
+        header_start: usize,
+    ) -> Result<(HeaderData, HeaderVote, usize, Vec<bool>)> {
+        let (header, vote, data_start) = Self::read_header(header_frames, header_start)?;
+        if header.header_interval == 0 {
+            return Ok((header, vote, data_start, Vec::new()));
+        }
+        let (header, vote, repeated_headers) = Self::read_repeated_headers(header_frames)?;
+        Ok((header, vote, data_start, repeated_headers))
+    }
+
+    /// Returns the index of the first data frame and the frames of the copy
+    /// whose data starts at `data_start`, up to the header of the following copy.
+    /// With repeated headers the video holds a single copy, whose data frames are read
+    /// from the whole video, even in front of the header found.
+    ///
+    /// # Arguments
+    /// * `frames` - Decoded frames of the whole video.
+    /// * `header_frames` - All frames decoded in the layout of their header frames, see `locate_header`.
+    /// * `data_start` - Index of the frame the data starts in, after the calibration frame.
+    /// * `repeated_headers` - Which frames hold a header block if the header is repeated
+    ///   between the data frames, empty otherwise.
+    fn copy_frames<'a>(
+        frames: &'a [Vec<u8>],
+        header_frames: &[Vec<u8>],
+        data_start: usize,
+        repeated_headers: &[bool],
+    ) -> (usize, &'a [Vec<u8>]) {
+        if repeated_headers.is_empty() {
+            let copy_end =
+                Self::find_next_header(header_frames, data_start).unwrap_or(frames.len());
+            (data_start, &frames[..copy_end])
+        } else {
+            (0, frames)
+        }
+    }
+
+    /// Returns the frames of a copy the data stream is read from,
+    /// leaving out the header blocks repeated between them and the calibration frame.
+    ///
+    /// # Arguments
+    /// * `frames` - Decoded frames of the copy.
+    /// * `data_start` - Index of the frame the data starts in, see `copy_frames`.
+    /// * `calibration_frame` - Index of the calibration frame, if one was read.
+    /// * `repeated_headers` - Which frames hold a header block if the header is repeated
+    ///   between the data frames, empty otherwise.
+    fn stream_frames<'a>(
+        frames: &'a [Vec<u8>],
+        data_start: usize,
+        calibration_frame: Option<usize>,
+        repeated_headers: &[bool],
+    ) -> Cow<'a, [Vec<u8>]> {
+        if repeated_headers.is_empty() {
+            return Cow::Borrowed(&frames[data_start..]);
+        }
+        Cow::Owned(
+            frames
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| !repeated_headers[index] && calibration_frame != Some(index))
+                .map(|(_, frame)| frame.clone())
+                .collect(),
+        )
+    }
+
+    /// Checks the video was encoded with as many alpha bits as it is decoded with,
+    /// as the data frames can not be read with a different frame layout.
+    ///
+    /// # Arguments
+    /// * `header` - Header read from the copy.
+    fn check_alpha_bits(&self, header: &HeaderData) -> Result<()> {
+        if header.alpha_bits != self.alpha_bits {
+            bail!(
+                "Video holds {} bits in the alpha channel, but is decoded with {}. Pass --alpha-bits {0}.",
+                header.alpha_bits,
+                self.alpha_bits
+            );
+        }
+        Ok(())
+    }
+
+    /// Replaces the green channel of every data unit with its luma,
+    /// the BT.601 weighted sum of the three color channels `yuv420p` keeps at full resolution.
+    /// The frames of luma only videos are gray, so the luma is their green level
+    /// with less of the noise chroma subsampling adds to the single channels.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    fn luma_units(&self, frame_data_units: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let channels = self.channels();
+        frame_data_units
+            .iter()
+            .map(|units| {
+                let mut units = units.clone();
+                for unit in units.chunks_exact_mut(channels) {
+                    let luma = (299 * u32::from(unit[0])
+                        + 587 * u32::from(unit[1])
+                        + 114 * u32::from(unit[2])
+                        + 500)
+                        / 1000;
+                    #[allow(clippy::cast_possible_truncation)]
+                    {
+                        unit[1] = luma as u8;
+                    }
+                }
+                units
+            })
+            .collect()
+    }
+
+    /// Reads the calibration frame of a copy if its header announces one,
+    /// returning a classifier reading levels at the values measured in it
+    /// and the index of the first data frame.
+    /// Falls back to the encoded level values if the calibration frame is missing,
+    /// leaving the data start unchanged.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    /// * `header` - Header read from the copy.
+    /// * `data_start` - Index of the frame following the header frames.
+    fn read_calibration_frame(
+        &self,
+        frame_data_units: &[Vec<u8>],
+        header: &HeaderData,
+        data_start: usize,
+    ) -> (Option<LevelClassifier>, usize) {
+        if !header.calibration_frame || header.version_code == VERSION_CODE_V1 {
+            return (None, data_start);
+        }
+        let Some(units) = frame_data_units
+            .get(data_start)
+            .filter(|units| self.is_calibration_frame(units))
+        else {
+            eprintln!(
+                "Warning: Calibration frame missing, reading levels at their encoded values."
+            );
+            return (None, data_start);
+        };
+        let color_bits = [self.red_bits, self.green_bits, self.blue_bits];
+        let mut expected = color_bits.map(|bits| header.level_mapping.level_values(bits));
+        for (level_values, bias) in expected.iter_mut().zip(header.quantization_bias) {
+            for level_value in level_values {
+                *level_value = level_value.saturating_add_signed(bias);
+            }
+        }
+        let level_values = measure_level_values(
+            &units[Self::CALIBRATION_RAMP_START * COLOR_CHANNELS..],
+            color_bits,
+            expected,
+        );
+        eprintln!("Level thresholds calibrated from the calibration frame.");
+        (
+            Some(LevelClassifier::from_level_values(
+                level_values,
+                header.gray_code,
+            )),
+            data_start + 1,
+        )
+    }
+
+    /// Reads the data stream of a single copy from its frames,
+    /// warning if fewer or more data frames were read than the header expects.
+    ///
+    /// # Arguments
+    /// * `frames` - Decoded frames of the copy, starting with the frame the data starts in.
+    /// * `header` - Header read from the copy.
+    fn read_stream(
+        &self,
+        frames: &[Vec<u8>],
+        header: &HeaderData,
+    ) -> Result<(Vec<u8>, FramingReport)> {
+        // Length of the data stream after padding and error correction.
+        let stream_len = header.fec.encoded_len(header.data_len);
+        let (stream, framing_report) = if header.version_code == VERSION_CODE_V1 {
+            // Version 1 videos have the data directly following the header.
+            // The zeros padding the last frame are cut off like for later versions,
+            // so they are not decoded as further code words.
+            let mut stream = frames.concat().split_off(BLOCK_LEN_V1);
+            stream.truncate(stream_len);
+            (stream, FramingReport::default())
+        } else {
+            reassemble_stream(
+                frames,
+                stream_len,
+                self.frame_bytes(header.frame_crc),
+                header.parity_interval as usize,
+                header.fountain_overhead,
+                header.sequence_numbers,
+            )?
+        };
+
+        eprintln!("Read {} from video.", format_bytes(stream.len() as u64));
+        if let Some(expected_frames) = header.data_frames
+            && expected_frames != framing_report.read
+        {
+            eprintln!(
+                "Warning: Read {} data frames, but the header expects {expected_frames}. The video may have been trimmed or the data-fps misconfigured.",
+                framing_report.read
+            );
+        }
+        Ok((stream, framing_report))
+    }
+
+    /// Reads the averaged data units of all split frames in `frame_buffer`.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory holding the split frames.
+    /// * `dump_dir` - If set, every averaged frame is also saved into this directory.
+    fn read_split_frames(
+        &self,
+        frame_buffer: &FrameBuffer,
+        dump_dir: Option<&Path>,
+    ) -> Result<Vec<Vec<u8>>> {
+        if let Some(dump_dir) = dump_dir {
+            fs::create_dir_all(dump_dir).context("Unable to create frame dump directory.")?;
+        }
+        let frame_paths = frame_buffer.list_split_frames()?;
+        let frame_count = frame_paths.len();
+        // Frames are averaged in parallel. Collecting the indexed iterator keeps them in order,
+        // so header detection can scan the result afterwards.
+        let progress = progress_bar("Decoding frames", frame_count, self.progress);
+        let frame_data_units = frame_paths
+            .par_iter()
+            .enumerate()
+            .map(|(frame_index, frame_path)| {
+                check_interrupted()?;
+                let frame_data_units = self.average_blocks(frame_path)?;
+                if let Some(dump_dir) = dump_dir {
+                    self.save_dump_frame(
+                        &frame_data_units,
+                        &frame_path_dump(dump_dir, frame_index),
+                    )?;
+                }
+                progress.inc(1);
+                Ok(frame_data_units)
+            })
+            .collect();
+        progress.finish_and_clear();
+        frame_data_units
+    }
+
+    /// Decodes the data bytes of frames read using `read_split_frames`.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    /// * `classifier` - Classifies pixel values as channel levels, see `level_classifier`.
+    fn decode_frames(
+        &self,
+        frame_data_units: &[Vec<u8>],
+        classifier: &LevelClassifier,
+    ) -> Vec<Vec<u8>> {
+        frame_data_units
+            .par_iter()
+            .map(|units| self.frame_to_data(units, classifier))
+            .collect()
+    }
+
+    /// Builds the classifier reading channel levels from pixel values
+    /// using this converters color bits.
+    ///
+    /// # Arguments
+    /// * `level_mapping` - How channel levels were mapped to pixel values.
+    /// * `gray_code` - If data values are the Gray code of their level.
+    fn level_classifier(&self, level_mapping: LevelMapping, gray_code: bool) -> LevelClassifier {
+        LevelClassifier::new(
+            level_mapping,
+            [self.red_bits, self.green_bits, self.blue_bits],
+            gray_code,
+        )
+        .with_alpha_levels(level_mapping, self.alpha_bits, gray_code)
+    }
+
+    /// Finds and decodes the header in the decoded frames of a video.
+    /// Returns the header, the vote result and the index of the frame the data starts in.
+    /// For version 1 videos this is the header frame itself,
+    /// with the data following the header block.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames read from the video.
+    /// * `header_start` - Index of the first header frame.
+    fn read_header(
+        frames: &[Vec<u8>],
+        header_start: usize,
+    ) -> Result<(HeaderData, HeaderVote, usize)> {
+        let version_bytes = Self::version_bytes(frames, header_start);
+        let version_code = HeaderData::read_version_code(&version_bytes)?;
+
+        match version_code {
+            VERSION_CODE_V1 => {
+                let (header, vote) = HeaderData::from_blocks(&[&version_bytes], version_code)
+                    .context("Unable to decode header.")?;
+                Ok((header, vote, header_start))
+            }
+            VERSION_CODE | VERSION_CODE_V3 | VERSION_CODE_V2 => {
+                Self::read_header_frames(frames, header_start, version_code)
+            }
+            _ => {
+                bail!("Unable to find correct VERSION_CODE. First data frame missing or corrupted.")
+            }
+        }
+    }
+
+    /// Collects statistics for every frame read from a video.
+    ///
+    /// # Arguments
+    /// * `frame_data_units` - Averaged data units of each frame.
+    /// * `frames` - Decoded data of each frame.
+    /// * `data_start` - Index of the frame the data starts in, after the calibration frame.
+    /// * `calibration_frame` - Index of the calibration frame, if one was read.
+    /// * `repeated_headers` - Which frames hold a header block if the header is repeated
+    ///   between the data frames, empty otherwise.
+    /// * `header` - Header read from the video.
+    /// * `data_frame_reports` - Errors found in each data frame.
+    #[allow(clippy::too_many_arguments)]
+    fn frame_stats(
+        &self,
+        frame_data_units: &[Vec<u8>],
+        frames: &[Vec<u8>],
+        data_start: usize,
+        calibration_frame: Option<usize>,
+        repeated_headers: &[bool],
+        header: &HeaderData,
+        data_frame_reports: &[HammingReport],
+    ) -> Result<Vec<FrameStats>> {
+        let is_v1 = header.version_code == VERSION_CODE_V1;
+        let stream_len = header.fec.encoded_len(header.data_len);
+        // Version 1 videos have the data stream start inside the header frame.
+        let (stream_len, parity_interval, fountain_overhead, sequenced) = if is_v1 {
+            (BLOCK_LEN_V1 + stream_len, 0, 0, false)
+        } else {
+            (
+                stream_len,
+                header.parity_interval as usize,
+                header.fountain_overhead,
+                header.sequence_numbers,
+            )
+        };
+
+        let frame_bytes = self.frame_bytes(header.frame_crc);
+
+        // Only data, parity and droplet frames use the level mapping from the header,
+        // header frames of the current version their own fixed layout.
+        let header_classifier = if header.version_code == VERSION_CODE {
+            Self::header_classifier()
+        } else {
+            self.level_classifier(LevelMapping::Midpoint, false)
+        };
+        let data_classifier = self.level_classifier(header.level_mapping, header.gray_code);
+
+        let mut stats = Vec::with_capacity(frames.len());
+        for (index, (units, frame)) in frame_data_units.iter().zip(frames).enumerate() {
+            let in_header = repeated_headers
+                .get(index)
+                .is_some_and(|&in_header| in_header);
+            let position = match index.checked_sub(data_start) {
+                Some(offset) if !in_header => frame_position(
+                    frame,
+                    offset,
+                    stream_len,
+                    frame_bytes,
+                    parity_interval,
+                    fountain_overhead,
+                    sequenced,
+                )?,
+                _ => None,
+            };
+            let (kind, data_frame) = match position {
+                Some(FramePosition::Data(0)) if is_v1 => (FrameKind::Header, Some(0)),
+                Some(FramePosition::Data(data_frame)) => (FrameKind::Data, Some(data_frame)),
+                Some(FramePosition::Parity(_) | FramePosition::Droplet(_)) => {
+                    (FrameKind::Parity, None)
+                }
+                None if calibration_frame == Some(index) => (FrameKind::Calibration, None),
+                None if (in_header || index < data_start) && frame.iter().any(|&x| x != 0) => {
+                    (FrameKind::Header, None)
+                }
+                None => (FrameKind::Buffer, None),
+            };
+            let crc_ok = (header.frame_crc && position.is_some())
+                .then(|| frame_crc_matches(frame, frame_bytes));
+            let errors = data_frame
+                .and_then(|data_frame| data_frame_reports.get(data_frame))
+                .cloned()
+                .unwrap_or_default();
+            let classifier = match kind {
+                FrameKind::Data | FrameKind::Parity | FrameKind::Calibration => &data_classifier,
+                FrameKind::Header | FrameKind::Buffer => &header_classifier,
+            };
+            stats.push(FrameStats {
+                index,
+                kind,
+                data_frame,
+                mean_channels: mean_channels(units, self.channels()),
+                mean_level_offsets: mean_level_offsets(units, self.channels(), classifier),
+                corrected_errors: errors.corrected_errors,
+                uncorrected_errors: errors.uncorrected_errors,
+                crc_ok,
+            });
+        }
+        Ok(stats)
+    }
+
+    /// Returns the bytes a header version code is read from
+    /// if a header block starts at frame `start`.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames read from the video.
+    /// * `start` - Index of the frame the header block would start in.
+    fn version_bytes(frames: &[Vec<u8>], start: usize) -> Vec<u8> {
+        frames[start.min(frames.len())..]
+            .iter()
+            .flatten()
+            .take(BLOCK_LEN_V1)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the index of the first frame holding any data, where the header is expected.
+    /// Fails right away if no frame starts a header block of any version,
+    /// as then the frames were most likely decoded with the wrong settings.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames decoded in the layout of their header frames, see `locate_header`.
+    fn find_header_start(frames: &[Vec<u8>]) -> Result<usize> {
+        // If all bytes are zero we are still on a prebuffer frame.
+        let header_start = frames
+            .iter()
+            .position(|frame| frame.iter().any(|&x| x != 0))
+            .unwrap_or(frames.len());
+        let has_header = (header_start..frames.len()).any(|start| {
+            HeaderData::read_version_code(&Self::version_bytes(frames, start)).is_ok_and(
+                |version_code| {
+                    [
+                        VERSION_CODE,
+                        VERSION_CODE_V3,
+                        VERSION_CODE_V2,
+                        VERSION_CODE_V1,
+                    ]
+                    .contains(&version_code)
+                },
+            )
+        });
+        if !has_header {
+            return Err(NoHeaderFound {
+                frames: frames.len(),
+            }
+            .into());
+        }
+        Ok(header_start)
+    }
+
+    /// Returns the index of the first frame at or after `from`
+    /// that starts a header block of version 2 or later.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames decoded in the layout of their header frames, see `locate_header`.
+    /// * `from` - Index of the first frame to check.
+    fn find_next_header(frames: &[Vec<u8>], from: usize) -> Option<usize> {
+        (from..frames.len()).find(|&start| {
+            HeaderData::read_version_code(&Self::version_bytes(frames, start)).is_ok_and(
+                |version_code| {
+                    [VERSION_CODE, VERSION_CODE_V3, VERSION_CODE_V2].contains(&version_code)
+                },
+            )
+        })
+    }
+
+    /// Decodes the header of the video split into `frame_buffer`
+    /// without decoding the data.
+    ///
+    /// # Arguments
+    /// * `frame_buffer` - Directory holding the split frames.
+    ///
+    /// # Errors
+    /// Fails if the frames can not be read or no valid header is found.
+    pub fn inspect_header(&self, frame_buffer: &FrameBuffer) -> Result<(HeaderData, HeaderVote)> {
+        let frame_data_units = self.read_split_frames(frame_buffer, None)?;
+        let (frames, header_start) = self.locate_header(&frame_data_units)?;
+        let (header, vote, _) = Self::read_header(&frames, header_start)?;
+        if header.header_interval > 0 {
+            let (header, vote, _) = Self::read_repeated_headers(&frames)?;
+            return Ok((header, vote));
+        }
+        Ok((header, vote))
+    }
+
+    /// Reads every header block of a video encoded with a header interval,
+    /// those at its start and those repeated between the data frames,
+    /// and decodes the header by majority vote over all of them.
+    /// Returns the header, the vote result and which frames hold a header block.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames decoded in the layout of their header frames, see `locate_header`.
+    fn read_repeated_headers(frames: &[Vec<u8>]) -> Result<(HeaderData, HeaderVote, Vec<bool>)> {
+        let block_frames = Self::block_frames(frames);
+        let mut in_header = vec![false; frames.len()];
+        let mut blocks = Vec::new();
+        let mut search_start = 0;
+        while let Some(block_start) = Self::find_next_header(frames, search_start) {
+            let Some(block_frame_data) = frames.get(block_start..block_start + block_frames) else {
+                break;
+            };
+            blocks.push(block_frame_data.concat());
+            in_header[block_start..block_start + block_frames].fill(true);
+            search_start = block_start + block_frames;
+        }
+        let version_code = blocks.first().map_or(Ok(VERSION_CODE), |block| {
+            HeaderData::read_version_code(block)
+        })?;
+        let block_refs: Vec<&[u8]> = blocks.iter().map(Vec::as_slice).collect();
+        let (header, vote) = HeaderData::from_blocks(&block_refs, version_code)
+            .context("Unable to decode header.")?;
+        Ok((header, vote, in_header))
+    }
+
+    /// Reads all consecutive header blocks starting at `header_start`
+    /// and decodes the header by majority vote over all of them.
+    /// Returns the header, the vote result and the index of the first data frame.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames read from the video.
+    /// * `header_start` - Index of the first header frame.
+    /// * `version_code` - Version of the first header block, version 2 or later.
+    fn read_header_frames(
+        frames: &[Vec<u8>],
+        header_start: usize,
+        version_code: [u8; 8],
+    ) -> Result<(HeaderData, HeaderVote, usize)> {
+        let block_frames = Self::block_frames(frames);
+        let mut blocks: Vec<Vec<u8>> = Vec::new();
+        let mut data_start = header_start;
+        // Further blocks are only counted as header if their own version code matches.
+        while let Some(block_frame_data) = frames.get(data_start..data_start + block_frames) {
+            let block = block_frame_data.concat();
+            if !blocks.is_empty() && HeaderData::read_version_code(&block)? != version_code {
+                break;
+            }
+            blocks.push(block);
+            data_start += block_frames;
+        }
+        if blocks.is_empty() {
+            bail!(
+                "Video ends {} frames after the start of the header, but a header block spans {block_frames} frames. The video may have been trimmed.",
+                frames.len().saturating_sub(header_start)
+            );
+        }
+        let block_refs: Vec<&[u8]> = blocks.iter().map(Vec::as_slice).collect();
+        let (header, vote) = HeaderData::from_blocks(&block_refs, version_code)
+            .context("Unable to decode header.")?;
+        Ok((header, vote, data_start))
+    }
+
+    /// Number of frames a header block spans in the layout `frames` were decoded in,
+    /// see `locate_header`.
+    ///
+    /// # Arguments
+    /// * `frames` - All frames decoded in the layout of their header frames.
+    fn block_frames(frames: &[Vec<u8>]) -> usize {
+        BLOCK_LEN.div_ceil(frames.first().map_or(BLOCK_LEN, Vec::len).max(1))
+    }
+
+    #[must_use]
+    /// Encodes all parameters of this converter into a base64 token,
+    /// which `from_base64` turns back into an identical converter.
+    pub fn to_base64(self) -> String {
+        base64_encode(&self.config_bytes())
+    }
+
+    /// Creates a converter from a token generated by `to_base64`.
+    /// The parameters are checked the same way `new` checks them.
+    ///
+    /// # Arguments
+    /// * `token` - Base64 token holding the converter parameters.
+    ///
+    /// # Errors
+    /// Fails if the token is not valid base64 or holds invalid settings.
+    pub fn from_base64(token: &str) -> Result<Self> {
+        let blob = base64_decode(token).context("Config token is not valid base64.")?;
+        Self::from_config_bytes(&blob)
+    }
+
+    /// Serializes all parameters of this converter into a fixed layout blob.
+    // Blob layout, u32 fields little endian:
+    // 0       version
+    // 1..4    red, green, blue bits
+    // 4..8    data fps
+    // 8..12   video fps
+    // 12..20  frame width, height
+    // 20..28  data width, height
+    #[must_use]
+    pub fn config_bytes(self) -> [u8; CONVERTER_CONFIG_LEN] {
+        let mut blob = [0u8; CONVERTER_CONFIG_LEN];
+        blob[0] = Self::CONFIG_VERSION;
+        for (byte, bits) in blob[1..=COLOR_CHANNELS].iter_mut().zip([
+            self.red_bits,
+            self.green_bits,
+            self.blue_bits,
+        ]) {
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                *byte = bits as u8;
+            }
+        }
+        for (bytes, field) in blob[1 + COLOR_CHANNELS..].chunks_exact_mut(BYTES_U32).zip([
+            self.data_fps,
+            self.video_fps,
+            self.frame_width,
+            self.frame_height,
+            self.data_width,
+            self.data_height,
+        ]) {
+            bytes.copy_from_slice(&field.to_le_bytes());
+        }
+        blob
+    }
+
+    /// Creates a converter from a blob generated by `config_bytes`.
+    /// The parameters are checked the same way `new` checks them.
+    ///
+    /// # Arguments
+    /// * `blob` - Serialized converter parameters.
+    ///
+    /// # Errors
+    /// Fails if the blob has an unknown version or holds invalid settings.
+    pub fn from_config_bytes(blob: &[u8]) -> Result<Self> {
+        if blob.len() != CONVERTER_CONFIG_LEN {
+            bail!(
+                "Converter settings hold {} bytes, expected {}.",
+                blob.len(),
+                CONVERTER_CONFIG_LEN
+            );
+        }
+        if blob[0] != Self::CONFIG_VERSION {
+            bail!("Unsupported converter settings version {}.", blob[0]);
+        }
+        let field = |index: usize| {
+            let start = 1 + COLOR_CHANNELS + index * BYTES_U32;
+            let mut bytes = [0u8; BYTES_U32];
+            bytes.copy_from_slice(&blob[start..start + BYTES_U32]);
+            u32::from_le_bytes(bytes)
+        };
+        Self::new(
+            [blob[1] as u32, blob[2] as u32, blob[3] as u32],
+            field(0),
+            field(1),
+            [field(2), field(3)],
+            [field(4), field(5)],
+        )
+        .context("Invalid converter settings.")
+    }
+
+    /// Settings the video backend needs to encode frames of this converter.
+    ///
+    /// # Arguments
+    /// * `overwrite` - If the backend may overwrite an existing output file.
+    fn encode_settings(&self, overwrite: bool) -> EncodeSettings {
+        EncodeSettings {
+            data_fps: self.data_fps,
+            video_fps: self.video_fps,
+            frame_width: self.frame_width,
+            frame_height: self.frame_height,
+            data_width: self.data_width,
+            data_height: self.data_height,
+            overwrite,
+            alpha: self.alpha_bits > 0,
+        }
+    }
+
+    /// Take all frames saved in `frame_buffer` and combine them into a video.
+    /// Upscale video to `frame_height` x `frame_width` and save at specified path.
+    /// Also increase framerate to `constants::VIDEO_FPS`.
+    ///
+    /// # Arguments
+    /// * `backend` - Video backend used to encode the frames.
+    /// * `frame_buffer` - Directory holding the frames.
+    /// * `output_file` - Path pointing to the combined video file.
+    /// * `overwrite` - If the output file should be overwritten if it exists.
+    ///
+    /// # Errors
+    /// Fails if the output file exists and `overwrite` is not set, or the backend fails to encode the video.
+    pub fn combine_frames<P: AsRef<Path>>(
+        &self,
+        backend: &dyn VideoBackend,
+        frame_buffer: &FrameBuffer,
+        output_file: P,
+        overwrite: bool,
+    ) -> Result<()> {
+        if !overwrite && output_file.as_ref().exists() {
+            bail!("File at video output path exists and overwrite is not enabled.");
+        }
+        let settings = self.encode_settings(overwrite);
+        // The backend writes to a partial file that is only moved to `output_file`
+        // once encoding succeeded, so a killed encode never leaves a truncated video.
+        let partial_path = partial_output_path(output_file.as_ref())?;
+        // A partial file left behind by a killed run would make the encoder refuse to start.
+        discard_partial_output(&partial_path);
+        if let Err(err) = backend.encode(&settings, frame_buffer, &partial_path) {
+            discard_partial_output(&partial_path);
+            return Err(err);
+        }
+        // The output file may have been created by someone else while encoding.
+        if !overwrite && output_file.as_ref().exists() {
+            discard_partial_output(&partial_path);
+            bail!(
+                "File at video output path was created while encoding and overwrite is not enabled."
+            );
+        }
+        commit_partial_output(&partial_path, output_file.as_ref())
+    }
+
+    /// Checks a video before splitting it, so decoding with the wrong settings
+    /// fails loudly instead of garbling the data.
+    /// The video must hold at least one frame and its framerate must not be below the data framerate,
+    /// which would skip data frames.
+    /// Skipped if the backend can not probe the video.
+    ///
+    /// # Arguments
+    /// * `backend` - Video backend used to probe the video.
+    /// * `input_file` - Path pointing to the video file.
+    /// * `rotation` - Rotation the video underwent, swapping width and height for quarter turns.
+    /// * `check_resolution` - If the resolution is checked as well,
+    ///   which does not apply to camera footage.
+    fn check_video(
+        &self,
+        backend: &dyn VideoBackend,
+        input_file: &Path,
+        rotation: Rotation,
+        check_resolution: bool,
+    ) -> Result<()> {
+        let Some(probe) = backend.probe(input_file)? else {
+            return Ok(());
+        };
+        if probe.frame_count == Some(0) {
+            bail!("{} contains no video frames.", input_file.display());
+        }
+        // Platforms converting to NTSC rates slow the video down by 1000/1001, which still decodes.
+        if let Some(fps) = probe.fps
+            && fps * 1.001 < f64::from(self.data_fps) - 1e-3
+        {
+            bail!(
+                "Video framerate {fps:.2} is below the data framerate {}. Decode with the --data-fps the video was encoded with.",
+                self.data_fps
+            );
+        }
+        if check_resolution {
+            self.check_video_resolution(probe.width, probe.height, rotation)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the resolution of a video matches the frame resolution of this converter.
+    /// Videos rescaled by a platform keep their aspect ratio and still decode,
+    /// so they only fail if the data units would end up shifted by half a unit or more.
+    ///
+    /// # Arguments
+    /// * `width` - Width of the video as stored, before undoing `rotation`.
+    /// * `height` - Height of the video as stored, before undoing `rotation`.
+    /// * `rotation` - Rotation the video underwent, swapping width and height for quarter turns.
+    fn check_video_resolution(&self, width: u32, height: u32, rotation: Rotation) -> Result<()> {
+        let (width, height) = match rotation {
+            Rotation::Clockwise90 | Rotation::Clockwise270 => (height, width),
+            Rotation::None | Rotation::Clockwise180 => (width, height),
+        };
+        if (width, height) == (self.frame_width, self.frame_height) {
+            return Ok(());
+        }
+        // Height the video would have if it kept the aspect ratio of the frames,
+        // and how far that is off measured in data units.
+        let expected_height =
+            f64::from(width) * f64::from(self.frame_height) / f64::from(self.frame_width);
+        let unit_offset = (f64::from(height) - expected_height).abs() * f64::from(self.data_height)
+            / f64::from(height);
+        if unit_offset >= 0.5 {
+            bail!(
+                "Video resolution {width}x{height} does not match the frame resolution {}x{}. Decode with the --frame-resolution the video was encoded with, and --rotate if it was turned.",
+                self.frame_width,
+                self.frame_height
+            );
+        }
+        eprintln!(
+            "Video resolution {width}x{height} differs from the frame resolution {}x{}, the video was likely rescaled.",
+            self.frame_width, self.frame_height
+        );
+        Ok(())
+    }
+
+    /// Split a video back into individual frames.
+    /// Also scales down back to the data resolution.
+    ///
+    /// # Arguments
+    /// * `backend` - Video backend used to split the video.
+    /// * `frame_buffer` - Directory the frames are saved to.
+    /// * `input_file` - Path pointing to the video file.
+    /// * `scale_algorithm` - Scaling algorithm used for downscaling, one of `SCALE_ALGORITHMS`.
+    /// * `window` - Section of the video to extract.
+    /// * `rotation` - Rotation the video underwent, undone before scaling.
+    /// * `color` - Colorspace and range to convert the video from, its tags if `None`.
+    ///
+    /// # Errors
+    /// Fails if the frame buffer can not be cleared, the video does not fit the frame resolution
+    /// or data framerate or the backend fails to split the video.
+    #[allow(clippy::too_many_arguments)]
+    pub fn split_video<P: AsRef<Path>>(
+        &self,
+        backend: &dyn VideoBackend,
+        frame_buffer: &FrameBuffer,
+        input_file: P,
+        scale_algorithm: &str,
+        window: &VideoWindow,
+        rotation: Rotation,
+        color: Option<VideoColor>,
+    ) -> Result<()> {
+        // Camera footage has its own resolution, the finder patterns locate the frame in it.
+        self.check_video(
+            backend,
+            input_file.as_ref(),
+            rotation,
+            !self.finder_patterns,
+        )?;
+        let scaler = self.split_scaler();
+        frame_buffer.clear()?;
+        let settings = SplitSettings {
+            data_fps: self.data_fps,
+            split_width: self.data_width * scaler,
+            split_height: self.data_height * scaler,
+            scale_algorithm: scale_algorithm.to_string(),
+            window: window.clone(),
+            rotation,
+            alpha: self.alpha_bits > 0,
+            color,
+        };
+        backend.split(&settings, input_file.as_ref(), frame_buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+    use image::imageops::FilterType;
+
+    use super::*;
+    use crate::{
+        constants::RS_CHUNK_BYTES_TOTAL_255_223,
+        header::{BASE_LEN, HEADER_COPIES},
+    };
+
+    /// Converter for a small 256x144 video with 4x4 pixel data units and 2 bits per channel.
+    fn small_converter() -> Converter {
+        Converter::builder()
+            .color_bits([2, 2, 2])
+            .data_fps(1)
+            .video_fps(1)
+            .resolution([256, 144])
+            .data_block_size(4)
+            .build()
+            .unwrap()
+    }
+
+    /// Deterministic test data of `len` bytes.
+    fn test_data(len: usize) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        (0..len).map(|i| (i * 31 + i / 251) as u8).collect()
+    }
+
+    /// Scales `frame` up by `scale` and moves it right and down by `offset` pixels,
+    /// repeating the edge, like a scaler misplacing the frame.
+    fn offset_scaled_frame(frame: &DynamicImage, scale: u32, offset: u32) -> DynamicImage {
+        let scaled = frame.resize_exact(
+            frame.width() * scale,
+            frame.height() * scale,
+            FilterType::Nearest,
+        );
+        let scaled = scaled.to_rgb8();
+        let shifted = RgbImage::from_fn(scaled.width(), scaled.height(), |x, y| {
+            *scaled.get_pixel(x.saturating_sub(offset), y.saturating_sub(offset))
+        });
+        DynamicImage::ImageRgb8(shifted)
+    }
+
+    /// Number of channel values of `frames` read as a different level than from the unshifted frames
+    /// after offsetting them by one pixel at four times the data resolution.
+    fn misread_levels(converter: &Converter, frames: &[DynamicImage]) -> usize {
+        let classifier = converter.level_classifier(LevelMapping::Midpoint, false);
+        let mut misread = 0;
+        for frame in frames {
+            let clean = converter
+                .average_frame(&offset_scaled_frame(frame, 4, 0))
+                .unwrap();
+            let shifted = converter
+                .average_frame(&offset_scaled_frame(frame, 4, 1))
+                .unwrap();
+            misread += clean
+                .iter()
+                .zip(&shifted)
+                .enumerate()
+                .filter(|&(i, (&clean, &shifted))| {
+                    let channel = i % COLOR_CHANNELS;
+                    classifier.classify(channel, clean) != classifier.classify(channel, shifted)
+                })
+                .count();
+        }
+        misread
+    }
+
+    #[test]
+    fn core_sampling_reads_offset_frames() -> Result<()> {
+        let data = test_data(3000);
+        let converter = small_converter();
+        let frames = converter.encode_to_frames(&data, &HeaderData::new(&data, 0), 3, 1)?;
+
+        assert!(misread_levels(&converter, &frames) > 0);
+        let core_converter = small_converter().with_sample_core(0.5)?;
+        assert_eq!(misread_levels(&core_converter, &frames), 0);
+
+        let offset_frames: Vec<_> = frames
+            .iter()
+            .map(|frame| offset_scaled_frame(frame, 4, 1))
+            .collect();
+        let (decoded, report) = core_converter.decode_from_frames(&offset_frames)?;
+        assert_eq!(decoded, data);
+        assert_eq!(report.corrected_errors, 0);
+        Ok(())
+    }
+
+    /// Encodes `data` described by `header` to frames and decodes it again.
+    fn frame_round_trip(converter: &Converter, data: &[u8], header: &HeaderData) -> Vec<u8> {
+        let frames = converter.encode_to_frames(data, header, 1, 1).unwrap();
+        let (decoded, report) = converter.decode_from_frames(&frames).unwrap();
+        assert!(report.hash_match);
+        decoded
+    }
+
+    /// The first `count` data sizes filling their last data frame exactly,
+    /// one more byte needs another frame.
+    fn full_frame_sizes(converter: &Converter, fec: FecKind, count: usize) -> Vec<usize> {
+        let frames = |len| converter.copy_frame_count(len, 1, 0, 0, fec, true).unwrap();
+        (1..)
+            .filter(|&len| frames(len + 1) > frames(len))
+            .take(count)
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_sizes_around_frame_boundaries() {
+        let converter = small_converter();
+        // Every size up to two error correction chunks, then around the end of the first frames.
+        let mut sizes: Vec<_> = (0..=2 * FecKind::default().chunk_bytes()).collect();
+        for full in full_frame_sizes(&converter, FecKind::default(), 3) {
+            sizes.extend([full - 1, full, full + 1]);
+        }
+        for len in sizes {
+            let data = test_data(len);
+            let decoded = frame_round_trip(&converter, &data, &HeaderData::new(&data, 0));
+            assert!(decoded == data, "{len} bytes do not round trip");
+        }
+    }
+
+    #[test]
+    fn fountain_rebuilds_dropped_data_frames() -> Result<()> {
+        let data = test_data(20_000);
+        let converter = small_converter();
+        for overhead in [25, 50, 100] {
+            let mut header = HeaderData::new(&data, 0);
+            header.fountain_overhead = overhead;
+            let mut frames = converter.encode_to_frames(&data, &header, 1, 1)?;
+            // The first data frame, which the first droplet always covers.
+            let data_start = usize::from(header.prebuffer_frames)
+                + BLOCK_LEN.div_ceil(converter.frame_data_byte_count)
+                + usize::from(converter.calibration_frame);
+            frames.remove(data_start);
+            let (decoded, report) = converter.decode_from_frames(&frames)?;
+            assert!(report.hash_match, "{overhead}% overhead");
+            assert_eq!(decoded, data, "{overhead}% overhead");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rescaled_videos_pass_the_resolution_check() {
+        let converter = small_converter();
+        for (width, height, rotation) in [
+            (256, 144, Rotation::None),
+            (512, 288, Rotation::None),
+            (128, 72, Rotation::Clockwise180),
+            (144, 256, Rotation::Clockwise90),
+            // Rounded by the scaler, off by less than half a data unit.
+            (640, 361, Rotation::None),
+        ] {
+            assert!(
+                converter
+                    .check_video_resolution(width, height, rotation)
+                    .is_ok(),
+                "{width}x{height}"
+            );
+        }
+        for (width, height, rotation) in [
+            (256, 256, Rotation::None),
+            (144, 256, Rotation::None),
+            (256, 144, Rotation::Clockwise270),
+            (256, 150, Rotation::None),
+        ] {
+            assert!(
+                converter
+                    .check_video_resolution(width, height, rotation)
+                    .is_err(),
+                "{width}x{height}"
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_with_few_bytes_in_last_frame() {
+        let converter = small_converter();
+        for fec in FecKind::value_variants().iter().copied() {
+            for interleave_depth in [1, 4] {
+                for full in full_frame_sizes(&converter, fec, 2) {
+                    for extra in 1..=8 {
+                        let data = test_data(full + extra);
+                        let mut header = HeaderData::new(&data, 0);
+                        header.fec = fec;
+                        header.interleave_depth = interleave_depth;
+                        let decoded = frame_round_trip(&converter, &data, &header);
+                        assert!(
+                            decoded == data,
+                            "{} bytes with {fec:?} and interleave depth {interleave_depth} do not round trip",
+                            data.len()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cut_off_header_fails_without_panic() {
+        let converter = small_converter();
+        let data = test_data(500);
+        let header = HeaderData::new(&data, 0);
+        let frames = converter.encode_to_frames(&data, &header, 1, 1).unwrap();
+        const { assert!(HEADER_BLOCK_FRAMES > 1) };
+        let header_end = usize::from(header.prebuffer_frames) + HEADER_BLOCK_FRAMES;
+        // Every prefix must fail or succeed, none may panic.
+        for len in 0..frames.len() {
+            let result = converter.decode_from_frames(&frames[..len]);
+            if len < header_end {
+                assert!(result.is_err(), "{len} of {} frames", frames.len());
+            }
+        }
+    }
+
+    #[test]
+    fn too_small_first_frame_fails() {
+        let converter = small_converter();
+        let data = test_data(3000);
+        let mut frames = converter
+            .encode_to_frames(&data, &HeaderData::new(&data, 0), 1, 1)
+            .unwrap();
+        // The data resolution minus one row of data units.
+        frames[0] = frames[0].crop_imm(0, 0, frames[0].width(), frames[0].height() - 1);
+        let err = converter.decode_from_frames(&frames).unwrap_err();
+        assert!(err.to_string().contains("not a whole multiple"), "{err}");
+        frames[0] = DynamicImage::new_rgb8(0, 0);
+        assert!(converter.decode_from_frames(&frames).is_err());
+    }
+
+    #[test]
+    fn header_is_read_with_other_data_settings() -> Result<()> {
+        let encoder = small_converter();
+        let decoder = Converter::builder()
+            .color_bits([1, 2, 1])
+            .data_fps(1)
+            .video_fps(1)
+            .resolution([256, 144])
+            .data_block_size(2)
+            .build()?;
+        let data = test_data(3000);
+        let mut header = HeaderData::new(&data, 0);
+        header.converter_config = Some(encoder.config_bytes());
+        let frames = encoder.encode_to_frames(&data, &header, 1, 1)?;
+        // The frames as the decoder splits them, at its own data resolution.
+        let split_frames: Vec<DynamicImage> = frames
+            .iter()
+            .map(|frame| {
+                frame.resize_exact(decoder.data_width, decoder.data_height, FilterType::Nearest)
+            })
+            .collect();
+        let err = decoder.decode_from_frames(&split_frames).unwrap_err();
+        let differs = err.downcast::<DataLayoutDiffers>()?;
+        assert_eq!(differs.converter_config, encoder.config_bytes());
+
+        let header_converter = Converter::from_config_bytes(&differs.converter_config)?;
+        let (restored, _) = header_converter.decode_from_frames(&frames)?;
+        assert_eq!(restored, data);
+        Ok(())
+    }
+
+    #[test]
+    fn data_resolution_below_header_grid_fails_to_encode() {
+        let converter = Converter::builder()
+            .color_bits([2, 2, 2])
+            .data_fps(1)
+            .video_fps(1)
+            .resolution([128, 72])
+            .data_block_size(4)
+            .build()
+            .unwrap();
+        let data = test_data(500);
+        let err = converter
+            .encode_to_frames(&data, &HeaderData::new(&data, 0), 1, 1)
+            .unwrap_err();
+        assert!(err.to_string().contains("header frames"), "{err}");
+    }
+
+    /// Flips every bit of the data units holding bytes `start..start + len` of `frame`.
+    /// Both must be multiples of three, the bytes held by four units of 2, 2, 2 bits.
+    fn damage_bytes(frame: &mut RgbImage, start: usize, len: usize) {
+        let width = frame.width() as usize;
+        for unit in start * 4 / 3..(start + len) * 4 / 3 {
+            #[allow(clippy::cast_possible_truncation)]
+            let pixel = frame.get_pixel_mut((unit % width) as u32, (unit / width) as u32);
+            // Midpoint levels are mirrored around the center, so this flips both bits.
+            pixel.0 = pixel.0.map(|value| u8::MAX - value);
+        }
+    }
+
+    #[test]
+    fn header_survives_damage_defeating_majority_vote() -> Result<()> {
+        let converter = small_converter();
+        let data = test_data(3000);
+        let header = HeaderData::new(&data, 0);
+        let mut frames = converter.encode_to_frames(&data, &header, 3, 1)?;
+        let read_blocks = |frames: &[DynamicImage]| -> Result<Vec<(usize, Vec<u8>)>> {
+            let units = frames
+                .iter()
+                .map(|frame| converter.average_frame(frame))
+                .collect::<Result<Vec<_>>>()?;
+            let header_frames = converter.decode_header_frames(&units).unwrap();
+            Ok((0..header_frames.len())
+                .filter(|&index| {
+                    HeaderData::read_version_code(&header_frames[index])
+                        .is_ok_and(|code| code == VERSION_CODE)
+                })
+                .map(|start| {
+                    (
+                        start,
+                        header_frames[start..start + HEADER_BLOCK_FRAMES].concat(),
+                    )
+                })
+                .collect())
+        };
+        let header_blocks = read_blocks(&frames)?;
+        assert_eq!(header_blocks.len(), 3);
+
+        // Two of the three header blocks get the same damage. Six of the nine base copies
+        // differ alike, which a majority vote takes as the header.
+        // The Reed-Solomon copies get 15 damaged bytes in every code word.
+        let flip = |block: &mut [u8], start: usize, len: usize| {
+            block[start..start + len]
+                .iter_mut()
+                .for_each(|byte| *byte = !*byte);
+        };
+        for (start, mut block) in header_blocks.into_iter().take(2) {
+            for copy in 0..HEADER_COPIES {
+                flip(&mut block, copy * BASE_LEN + 9, 24);
+            }
+            let rs_words = (BLOCK_LEN - BLOCK_LEN_V1) / RS_CHUNK_BYTES_TOTAL_255_223;
+            for word in 0..rs_words {
+                flip(
+                    &mut block,
+                    BLOCK_LEN_V1 + word * RS_CHUNK_BYTES_TOTAL_255_223 + 21,
+                    15,
+                );
+            }
+            for (offset, frame_data) in block.chunks(HEADER_FRAME_BYTES).enumerate() {
+                frames[start + offset] = converter.header_frame_image(frame_data)?;
+            }
+        }
+
+        let blocks: Vec<Vec<u8>> = read_blocks(&frames)?
+            .into_iter()
+            .map(|(_, block)| block)
+            .collect();
+        let base_copies: Vec<&[u8]> = blocks
+            .iter()
+            .flat_map(|block| block[..BLOCK_LEN_V1].chunks_exact(BASE_LEN))
+            .collect();
+        let majority: Vec<u8> = (0..BASE_LEN)
+            .map(|byte| {
+                (0..u8::BITS).fold(0, |majority, bit| {
+                    let set = base_copies
+                        .iter()
+                        .filter(|copy| copy[byte] & (1 << bit) != 0)
+                        .count();
+                    majority | (u8::from(set * 2 > base_copies.len()) << bit)
+                })
+            })
+            .collect();
+        assert_ne!(majority, blocks[2][..BASE_LEN]);
+
+        let (decoded, report) = converter.decode_from_frames(&frames)?;
+        assert_eq!(decoded, data);
+        assert_eq!(report.header.data_hash, header.data_hash);
+        Ok(())
+    }
+
+    #[test]
+    fn damaged_data_frame_fails_its_crc() -> Result<()> {
+        let converter = small_converter();
+        let data = test_data(5000);
+        let header = HeaderData::new(&data, 0);
+        let mut frames = converter.encode_to_frames(&data, &header, 1, 1)?;
+        let (_, report) = converter.decode_from_frames(&frames)?;
+        assert_eq!(report.crc_failed_frames, Some(0));
+
+        let data_start = usize::from(header.prebuffer_frames)
+            + HEADER_BLOCK_FRAMES
+            + usize::from(converter.calibration_frame);
+        let mut frame = frames[data_start].to_rgb8();
+        // Inside the payload, so the sequence number still places the frame.
+        damage_bytes(&mut frame, 300, 3);
+        frames[data_start] = DynamicImage::ImageRgb8(frame);
+        let (_, report) = converter.decode_from_frames(&frames)?;
+        assert_eq!(report.crc_failed_frames, Some(1));
+        let failed: Vec<usize> = report
+            .frame_stats
+            .iter()
+            .filter(|frame| frame.crc_ok == Some(false))
+            .map(|frame| frame.index)
+            .collect();
+        assert_eq!(failed, [data_start]);
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_copy_passing_the_hash_check_is_preferred() -> Result<()> {
+        let converter = small_converter();
+        let data = test_data(3000);
+        let header = HeaderData::new(&data, 0);
+        // The first copy is other data of the same length under the same header.
+        // It decodes without errors, but fails the hash check.
+        let other: Vec<u8> = data.iter().map(|byte| byte ^ 0x5A).collect();
+        let mut frames = converter.encode_to_frames(&other, &header, 1, 1)?;
+        let intact = converter.encode_to_frames(&data, &header, 1, 1)?;
+        frames.extend_from_slice(&intact[usize::from(header.prebuffer_frames)..]);
+
+        let (decoded, report) = converter.decode_from_frames(&frames)?;
+        assert_eq!(decoded, data);
+        assert_eq!(report.copy, 2);
+        assert!(report.hash_match);
+        assert_eq!(report.uncorrected_errors, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn hash_match_outranks_fewer_errors() -> Result<()> {
+        let converter = small_converter();
+        let data = test_data(3000);
+        let frames = converter.encode_to_frames(&data, &HeaderData::new(&data, 0), 1, 1)?;
+        let report = || {
+            converter
+                .decode_from_frames(&frames)
+                .map(|(_, report)| report)
+        };
+        let (mut matching, mut damaged, mut wrong_hash) = (report()?, report()?, report()?);
+        matching.uncorrected_errors = 3;
+        damaged.hash_match = false;
+        damaged.uncorrected_errors = 5;
+        wrong_hash.hash_match = false;
+
+        assert!(matching.is_better_than(&wrong_hash));
+        assert!(!wrong_hash.is_better_than(&matching));
+        assert!(wrong_hash.is_better_than(&damaged));
+        assert!(!damaged.is_better_than(&wrong_hash));
         Ok(())
     }
 }
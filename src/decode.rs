@@ -0,0 +1,518 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Video to data mode of the command line program: splits and decodes a video
+//! with the settings in `Args`, retrying with the settings its header records,
+//! and writes or prints the decoded data.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time,
+};
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+
+use crate::cli::Args;
+use vortexkey::{
+    archive::unpack_archive,
+    backend::{VideoBackend, VideoColor},
+    compression::decompress,
+    constants::AUTO_PROBE_SECONDS,
+    converter::{Converter, DataLayoutDiffers, FileReport, NoHeaderFound},
+    encryption::decrypt,
+    filesys::write_output_file,
+    header::HeaderData,
+    interrupt::Interrupted,
+    stats::{RunSummary, print_error_report, write_stats_csv},
+    utils::{bytes_to_hex_string, format_bytes, format_duration},
+};
+
+/// Split the video at `args.inputfile`, decode it and write the result to `args.outputfile`,
+/// or to stdout if it is `-`.
+/// Messages encoded with `--message` are printed instead,
+/// and only written if an output file is given.
+/// With `args.auto_filter` set, decoding is retried with every scale algorithm
+/// until the hash matches, keeping the result with the fewest uncorrectable errors.
+/// With `args.auto` set and no header found, common converter settings are tried
+/// until one finds the header.
+///
+/// # Errors
+/// Fails if the video can not be decoded or the data can not be written.
+pub fn decode_video(args: &Args, converter: &Converter) -> Result<()> {
+    decode_video_with(args, converter, true, time::Instant::now())
+}
+
+/// Decode the video with `converter` as described in `decode_video`.
+/// If `use_header_settings` is set and the header records different converter settings,
+/// decoding is repeated once with those instead.
+/// `start` is when decoding began, for the summary printed with `--json`.
+fn decode_video_with(
+    args: &Args,
+    converter: &Converter,
+    use_header_settings: bool,
+    start: time::Instant,
+) -> Result<()> {
+    let backend = args.video_backend()?;
+    let expected_sha256 = args.expected_sha256()?;
+    // A directory is only resolved to a file once the header is read.
+    if let Some(outputfile) = &args.outputfile
+        && !args.output_is_stdout()
+        && !args.overwrite
+        && outputfile.exists()
+        && !outputfile.is_dir()
+    {
+        bail!("File at file output path exists and overwrite is not enabled.");
+    }
+    // Statistics are only written after decoding, which an existing file should not waste.
+    if let Some(stats_csv) = &args.stats_csv
+        && !args.overwrite
+        && stats_csv.exists()
+    {
+        bail!(
+            "File at {} exists and overwrite is not enabled.",
+            stats_csv.display()
+        );
+    }
+
+    let (scale_algorithm, data, report) = match decode_best(args, converter, backend.as_ref()) {
+        Err(err) if args.auto && err.is::<NoHeaderFound>() => {
+            eprintln!("{err:#}");
+            let auto_converter = find_auto_converter(args, backend.as_ref())?;
+            // The header may record the complete settings, which are then used as usual.
+            let mut args = args.clone();
+            args.auto = false;
+            return decode_video_with(&args, &auto_converter, true, start);
+        }
+        Err(err) if use_header_settings && err.is::<DataLayoutDiffers>() => {
+            let differs = err.downcast::<DataLayoutDiffers>()?;
+            return decode_with_header_settings(
+                args,
+                &differs.converter_config,
+                differs.downsample_scaler,
+                start,
+            );
+        }
+        result => result?,
+    };
+    let header_scaler = report.header.downsample_scaler;
+    if use_header_settings
+        && let Some(converter_config) = report.header.converter_config
+        && (converter_config != converter.config_bytes()
+            || (header_scaler != 0 && header_scaler != converter.downsample_scaler()))
+    {
+        return decode_with_header_settings(args, &converter_config, header_scaler, start);
+    }
+    if let Some(color) = header_color(args, backend.as_ref(), &report.header)? {
+        eprintln!(
+            "Video is not tagged with the colors it was encoded with, decoding again as {color} from its header."
+        );
+        let mut args = args.clone();
+        args.colorspace = Some(color.colorspace);
+        args.color_range = Some(color.range);
+        return decode_video_with(&args, converter, use_header_settings, start);
+    }
+    if args.auto_filter {
+        eprintln!("Using result of scale algorithm {scale_algorithm}.");
+    }
+    let data = if let Some(encryption) = &report.header.encryption {
+        if !report.hash_match {
+            eprintln!("Hash does not match, decryption will likely fail.");
+        }
+        let decrypted = decrypt(&data, &args.passphrase(false)?, encryption)?;
+        eprintln!("Decrypted {}.", format_bytes(decrypted.len() as u64));
+        decrypted
+    } else {
+        data
+    };
+    let data = if report.header.is_compressed {
+        let decompressed = decompress(&data)?;
+        eprintln!(
+            "Decompressed {} to {}.",
+            format_bytes(data.len() as u64),
+            format_bytes(decompressed.len() as u64)
+        );
+        decompressed
+    } else {
+        data
+    };
+    print_decode_diagnostics(args, converter, &report)?;
+    // Checked on the final data, as the header hash covers it compressed and encrypted.
+    let expected_hash_match = expected_sha256.map(|expected_hash| {
+        let computed_hash: [u8; 32] = Sha256::digest(&data).into();
+        if computed_hash != expected_hash {
+            eprintln!(
+                "Decoded data hash {} does not match the hash {} passed with --expect-sha256.",
+                bytes_to_hex_string(&computed_hash),
+                bytes_to_hex_string(&expected_hash)
+            );
+        }
+        computed_hash == expected_hash
+    });
+    write_decoded_data(args, &report, &data, start, expected_hash_match)?;
+    if expected_hash_match == Some(false) {
+        // The data is still written, as partially damaged data may be of use.
+        return Err(ExpectedHashMismatch.into());
+    }
+    Ok(())
+}
+
+/// Colors the header records for the video at `args.inputfile`, if the video is tagged
+/// with different ones or none, so it has to be split again converting from those.
+/// `None` if the tags match, the colors were given with `--colorspace` or `--color-range`
+/// or the tags can not be read.
+///
+/// # Arguments
+/// * `args` - Command line arguments of the run.
+/// * `backend` - Video backend used to read the tags.
+/// * `header` - Header read from the video.
+fn header_color(
+    args: &Args,
+    backend: &dyn VideoBackend,
+    header: &HeaderData,
+) -> Result<Option<VideoColor>> {
+    if args.split_color().is_some() {
+        return Ok(None);
+    }
+    let Some(probe) = backend.probe(args.input_file()?)? else {
+        return Ok(None);
+    };
+    let tagged = probe.colorspace == Some(header.color.colorspace)
+        && probe.color_range == Some(header.color.range);
+    Ok((!tagged).then_some(header.color))
+}
+
+/// Write decoded data to stdout or the output file, or print it if it is a message,
+/// followed by the summary of the run. Archives are unpacked into the output directory.
+///
+/// # Arguments
+/// * `args` - Command line arguments of the run.
+/// * `report` - Report of the decoded video.
+/// * `data` - Decoded data, after decryption and decompression.
+/// * `start` - When decoding began.
+/// * `expected_hash_match` - If `data` matched the hash passed with `--expect-sha256`.
+fn write_decoded_data(
+    args: &Args,
+    report: &FileReport,
+    data: &[u8],
+    start: time::Instant,
+    expected_hash_match: Option<bool>,
+) -> Result<()> {
+    if args.output_is_stdout() {
+        eprintln!("Writing {} to stdout.", format_bytes(data.len() as u64));
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(data)
+            .and_then(|()| stdout.flush())
+            .context("Unable to write data to stdout.")?;
+        return Ok(());
+    }
+    if report.header.is_message {
+        // The message is part of the JSON summary instead.
+        if !args.json {
+            println!("Message: {}", String::from_utf8_lossy(data));
+        }
+        if args.outputfile.is_none() {
+            return print_decode_summary(args, report, data, start, expected_hash_match);
+        }
+    }
+    if report.header.is_archive {
+        let output_dir = args.output_file()?;
+        if output_dir.exists() && !output_dir.is_dir() {
+            bail!(
+                "Video holds an archive to unpack into a directory, but {} is a file.",
+                output_dir.display()
+            );
+        }
+        let files = unpack_archive(data, output_dir, args.overwrite)?;
+        eprintln!(
+            "Unpacked {} files with {} into {}.",
+            files.len(),
+            format_bytes(data.len() as u64),
+            output_dir.display()
+        );
+        return print_decode_summary(args, report, data, start, expected_hash_match);
+    }
+    let output_file = resolve_output_file(args, &report.header)?;
+    eprintln!(
+        "Writing {} to {}.",
+        format_bytes(data.len() as u64),
+        output_file.display()
+    );
+    write_output_file(&output_file, data)?;
+    print_decode_summary(args, report, data, start, expected_hash_match)
+}
+
+#[derive(Debug)]
+/// Error returned when the decoded data does not match the hash passed with `--expect-sha256`,
+/// so the process exits with `EXPECTED_HASH_MISMATCH_EXIT_CODE`.
+pub struct ExpectedHashMismatch;
+
+impl std::fmt::Display for ExpectedHashMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Decoded data does not match the hash passed with --expect-sha256."
+        )
+    }
+}
+
+impl std::error::Error for ExpectedHashMismatch {}
+
+/// Print what decoding found out beyond the data itself:
+/// the copy used, frame statistics, the error report if requested,
+/// a quantization bias suggestion and the note stored in the header.
+///
+/// # Arguments
+/// * `args` - Command line arguments of the run.
+/// * `converter` - Converter the video was decoded with.
+/// * `report` - Report of the decoded video.
+fn print_decode_diagnostics(args: &Args, converter: &Converter, report: &FileReport) -> Result<()> {
+    if report.copy > 1 {
+        eprintln!("Decoded from copy {}.", report.copy);
+    }
+    if let Some(stats_csv) = &args.stats_csv {
+        write_stats_csv(stats_csv, &report.frame_stats, args.overwrite)?;
+        eprintln!(
+            "Wrote statistics of {} frames to {}.",
+            report.frame_stats.len(),
+            stats_csv.display()
+        );
+    }
+    if args.error_report {
+        print_error_report(
+            &report.frame_stats,
+            &report.corrected_offsets,
+            &report.uncorrected_offsets,
+        );
+    }
+    let suggested_bias = converter.suggested_quantization_bias(report);
+    if suggested_bias != report.header.quantization_bias {
+        let [red, green, blue] = suggested_bias;
+        eprintln!(
+            "Levels were read shifted from their encoded values. Encoding with --quantization-bias {red},{green},{blue} may reduce errors on this platform."
+        );
+    }
+    if !report.header.note.is_empty() {
+        eprintln!("Note: {}", report.header.note);
+    }
+    Ok(())
+}
+
+/// Print the summary of a decoded video as JSON if `args.json` is set.
+///
+/// # Arguments
+/// * `args` - Command line arguments of the run.
+/// * `report` - Report of the decoded video.
+/// * `data` - Decoded data, after decryption and decompression.
+/// * `start` - When decoding began.
+/// * `expected_hash_match` - If `data` matched the hash passed with `--expect-sha256`.
+fn print_decode_summary(
+    args: &Args,
+    report: &FileReport,
+    data: &[u8],
+    start: time::Instant,
+    expected_hash_match: Option<bool>,
+) -> Result<()> {
+    if !args.json {
+        return Ok(());
+    }
+    RunSummary {
+        mode: "vtd",
+        bytes_read: fs::metadata(args.input_file()?)
+            .context("Unable to read size of the decoded video.")?
+            .len(),
+        bytes_written: data.len() as u64,
+        frames: report.frame_stats.len(),
+        video_seconds: None,
+        elapsed_seconds: start.elapsed().as_secs_f64(),
+        message: report
+            .header
+            .is_message
+            .then(|| String::from_utf8_lossy(data).into_owned()),
+        report: Some(report),
+        expected_hash_match,
+    }
+    .print()
+}
+
+/// Decode the video again with the converter settings recorded in its header,
+/// as described in `decode_video_with`.
+///
+/// # Arguments
+/// * `args` - Decoding options not stored in the header.
+/// * `converter_config` - Converter settings stored in the header.
+/// * `downsample_scaler` - Downsample scaler stored in the header, zero if not stored.
+/// * `start` - When decoding began.
+fn decode_with_header_settings(
+    args: &Args,
+    converter_config: &[u8],
+    downsample_scaler: u32,
+    start: time::Instant,
+) -> Result<()> {
+    let header_converter = args
+        .header_converter(converter_config, downsample_scaler)
+        .context("Video header holds invalid converter settings.")?;
+    eprintln!(
+        "Video was encoded with different converter settings, decoding again with the settings from its header:"
+    );
+    eprintln!("{header_converter}");
+    decode_video_with(args, &header_converter, false, start)
+}
+
+/// Split and decode the video at `args.inputfile`, trying every scale algorithm
+/// if `args.auto_filter` is set.
+/// Returns the used scale algorithm with the decoded data and report.
+fn decode_best(
+    args: &Args,
+    converter: &Converter,
+    backend: &dyn VideoBackend,
+) -> Result<(&'static str, Vec<u8>, FileReport)> {
+    let frame_buffer = args.frame_buffer()?;
+    let mut best: Option<(&'static str, Vec<u8>, FileReport)> = None;
+    for scale_algorithm in args.split_scale_algorithms() {
+        timed_block!(format!("video splitting ({scale_algorithm})"), {
+            converter.split_video(
+                backend,
+                &frame_buffer,
+                args.input_file()?,
+                scale_algorithm,
+                &args.video_window(),
+                args.rotate,
+                args.split_color(),
+            )?;
+        });
+
+        eprintln!("Starting file reconstruction.");
+        let start_file_reconstruction = time::Instant::now();
+        let result = converter.reconstruct_to_vec(&frame_buffer, args.dump_frames.as_deref());
+        eprintln!(
+            "Finished file reconstruction after: {}",
+            format_duration(start_file_reconstruction.elapsed())
+        );
+        let (data, report) = match result {
+            Ok(decoded) => decoded,
+            Err(err)
+                if args.auto_filter
+                    && !err.is::<Interrupted>()
+                    && !err.is::<DataLayoutDiffers>() =>
+            {
+                eprintln!("Decoding with scale algorithm {scale_algorithm} failed: {err:#}");
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        eprintln!(
+            "Header copies found: {}  Agreeing: {}",
+            report.header_copies, report.agreeing_header_copies
+        );
+        eprintln!(
+            "Frames recovered from parity: {}  Lost: {}  Discarded: {}",
+            report.recovered_frames, report.lost_frames, report.discarded_frames
+        );
+        if let Some(crc_failed_frames) = report.crc_failed_frames {
+            eprintln!("Frames failing their CRC: {crc_failed_frames}");
+        }
+        if let Some(expected_frames) = report.expected_data_frames {
+            eprintln!(
+                "Data frames read: {}  Expected: {expected_frames}",
+                report.read_data_frames
+            );
+        }
+        eprintln!(
+            "Errors during file reconstruction: Corrected: {}  Uncorrectable: {}",
+            report.corrected_errors, report.uncorrected_errors
+        );
+        let hash_match = report.hash_match;
+        if best
+            .as_ref()
+            .is_none_or(|(_, _, best_report)| report.is_better_than(best_report))
+        {
+            best = Some((scale_algorithm, data, report));
+        }
+        if hash_match {
+            break;
+        }
+    }
+
+    best.context("Decoding failed with every scale algorithm.")
+}
+
+/// Search the start of the video for the header with the converters from `Args::auto_candidates`,
+/// splitting it once for every data pixel size.
+/// Returns the first converter the header is found with.
+///
+/// # Errors
+/// Fails if the video can not be split or no candidate finds the header.
+fn find_auto_converter(args: &Args, backend: &dyn VideoBackend) -> Result<Converter> {
+    let frame_buffer = args.frame_buffer()?;
+    // The header follows the prebuffer frames, so the start of the video is enough.
+    let mut window = args.video_window();
+    window
+        .duration
+        .get_or_insert_with(|| AUTO_PROBE_SECONDS.to_string());
+    let candidates = args.auto_candidates();
+    eprintln!(
+        "Searching the first {} seconds for the header with {} combinations of data pixel size and color bits.",
+        window.duration.as_deref().unwrap_or_default(),
+        candidates.len()
+    );
+    let mut split_pixel_size = None;
+    for candidate in &candidates {
+        if split_pixel_size != Some(candidate.data_pixel_size) {
+            candidate.converter.split_video(
+                backend,
+                &frame_buffer,
+                args.input_file()?,
+                args.split_scale_algorithm(),
+                &window,
+                args.rotate,
+                args.split_color(),
+            )?;
+            split_pixel_size = Some(candidate.data_pixel_size);
+        }
+        if candidate.converter.inspect_header(&frame_buffer).is_ok() {
+            let [red, green, blue] = candidate.color_bits;
+            eprintln!(
+                "Header found with --data-pixel-size {} --bits {red},{green},{blue}.",
+                candidate.data_pixel_size
+            );
+            return Ok(candidate.converter);
+        }
+    }
+    bail!(
+        "No header found with any of the {} combinations of data pixel size and color bits tried.",
+        candidates.len()
+    )
+}
+
+/// Path to write decoded data to. If the output path is a directory,
+/// the data is restored in it under the file name stored in `header`.
+fn resolve_output_file(args: &Args, header: &HeaderData) -> Result<PathBuf> {
+    let output_file = args.output_file()?;
+    if !output_file.is_dir() {
+        return Ok(output_file.to_path_buf());
+    }
+    let Some(file_name) = header.safe_file_name() else {
+        bail!(
+            "Output path {} is a directory, but the video does not store a usable file name.",
+            output_file.display()
+        );
+    };
+    let output_file = output_file.join(file_name);
+    if !args.overwrite && output_file.exists() {
+        bail!(
+            "File at {} exists and overwrite is not enabled.",
+            output_file.display()
+        );
+    }
+    Ok(output_file)
+}
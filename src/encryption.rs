@@ -0,0 +1,129 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Optional passphrase based encryption of the data before it is encoded.
+//! The key is derived from the passphrase with Argon2id using its default parameters,
+//! the data is encrypted with AES-256-GCM.
+//! Encryption happens after compression and before the Hamming encoding,
+//! so the header hash covers the ciphertext and corruption is detected before decrypting.
+
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, rand_core::RngCore},
+};
+use anyhow::{Result, anyhow};
+use argon2::Argon2;
+
+/// Length of the key derivation salt in bytes.
+pub const SALT_LEN: usize = 16;
+
+/// Length of the AES-GCM nonce in bytes.
+pub const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Random values needed besides the passphrase to decrypt the data.
+/// Stored in the header.
+pub struct EncryptionParams {
+    /// Salt the key was derived with.
+    pub salt: [u8; SALT_LEN],
+    /// Nonce the data was encrypted with.
+    pub nonce: [u8; NONCE_LEN],
+}
+
+/// Derives the AES-256 key from `passphrase`.
+///
+/// # Arguments
+/// * `passphrase` - Passphrase given by the user.
+/// * `salt` - Random salt stored alongside the data.
+fn derive_key(passphrase: &str, salt: &[u8; SALT_LEN]) -> Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("Unable to derive key from passphrase: {err}"))?;
+    Ok(key)
+}
+
+/// Encrypts `data` with a key derived from `passphrase`,
+/// using a fresh random salt and nonce.
+///
+/// # Arguments
+/// * `data` - Data to encrypt.
+/// * `passphrase` - Passphrase given by the user.
+///
+/// # Errors
+/// Fails if the key can not be derived or encryption fails.
+pub fn encrypt(data: &[u8], passphrase: &str) -> Result<(Vec<u8>, EncryptionParams)> {
+    let mut params = EncryptionParams {
+        salt: [0; SALT_LEN],
+        nonce: [0; NONCE_LEN],
+    };
+    OsRng.fill_bytes(&mut params.salt);
+    OsRng.fill_bytes(&mut params.nonce);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &params.salt)?);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&params.nonce), data)
+        .map_err(|_| anyhow!("Unable to encrypt data."))?;
+    Ok((ciphertext, params))
+}
+
+/// Decrypts data encrypted by `encrypt`.
+/// Fails if the passphrase is wrong or the data was altered.
+///
+/// # Arguments
+/// * `data` - Ciphertext read from the video.
+/// * `passphrase` - Passphrase given by the user.
+/// * `params` - Salt and nonce read from the header.
+///
+/// # Errors
+/// Fails if the passphrase is wrong or the data was altered.
+pub fn decrypt(data: &[u8], passphrase: &str, params: &EncryptionParams) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &params.salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(&params.nonce), data)
+        .map_err(|_| {
+            anyhow!("Unable to decrypt data. The passphrase is wrong or the data is corrupted.")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"Data compression resistant video generator.";
+
+    #[test]
+    fn encrypted_data_round_trips() -> Result<()> {
+        let (ciphertext, params) = encrypt(DATA, "correct horse")?;
+        assert_ne!(&ciphertext[..DATA.len()], DATA);
+        assert_eq!(decrypt(&ciphertext, "correct horse", &params)?, DATA);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_passphrase_fails_to_decrypt() -> Result<()> {
+        let (ciphertext, params) = encrypt(DATA, "correct horse")?;
+        assert!(decrypt(&ciphertext, "battery staple", &params).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails_to_decrypt() -> Result<()> {
+        let (ciphertext, params) = encrypt(DATA, "correct horse")?;
+        // A flipped data bit and a flipped bit of the GCM tag at the end.
+        for index in [0, ciphertext.len() - 1] {
+            let mut tampered = ciphertext.clone();
+            tampered[index] ^= 1;
+            assert!(decrypt(&tampered, "correct horse", &params).is_err());
+        }
+        let mut params = params;
+        params.nonce[0] ^= 1;
+        assert!(decrypt(&ciphertext, "correct horse", &params).is_err());
+        Ok(())
+    }
+}
@@ -9,13 +9,189 @@
 
 //! Error correction
 
+use std::sync::OnceLock;
+
 use anyhow::{Result, bail};
+use clap::ValueEnum;
 
 use crate::constants::{
-    BIT_MASK_26, BIT_MASK_31, BYTES_U32, HAMMING_CHUNK_BYTES_31_26, HAMMING_CHUNK_BYTES_TOAL_31_26,
-    HAMMING_DATA_BITS_31_26, HAMMING_DATA_POSITIONS_31_26, HAMMING_PARITY_POSITIONS_31_26,
+    BYTES_U64, RS_CHUNK_BYTES_255_223, RS_CHUNK_BYTES_TOTAL_255_223, RS_PARITY_BYTES_255_223,
+    RS_PRIMITIVE_POLYNOMIAL,
 };
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Forward error correction code protecting the data stream.
+pub enum FecKind {
+    #[value(name = "hamming-7-4")]
+    /// Hamming(7, 4) with an additional parity bit.
+    /// Corrects a single bit error in every 4 data bits.
+    Hamming7_4,
+    #[value(name = "hamming-15-11")]
+    /// Hamming(15, 11) with an additional parity bit.
+    /// Corrects a single bit error in every 11 data bits.
+    Hamming15_11,
+    #[default]
+    #[value(name = "hamming", alias = "hamming-31-26")]
+    /// Hamming(31, 26) with an additional parity bit.
+    /// Corrects a single bit error in every 26 data bits.
+    Hamming,
+    #[value(name = "hamming-63-57")]
+    /// Hamming(63, 57) with an additional parity bit.
+    /// Corrects a single bit error in every 57 data bits.
+    Hamming63_57,
+    #[value(name = "rs")]
+    /// Reed-Solomon RS(255, 223) over GF(256).
+    /// Corrects up to 16 damaged bytes in every 223 data bytes,
+    /// no matter how many bits of a byte are wrong.
+    Rs,
+}
+
+impl FecKind {
+    #[must_use]
+    /// Parameters of the Hamming code variant, `None` for other codes.
+    pub fn hamming_params(self) -> Option<HammingParams> {
+        match self {
+            FecKind::Hamming7_4 => Some(HAMMING_7_4),
+            FecKind::Hamming15_11 => Some(HAMMING_15_11),
+            FecKind::Hamming => Some(HAMMING_31_26),
+            FecKind::Hamming63_57 => Some(HAMMING_63_57),
+            FecKind::Rs => None,
+        }
+    }
+
+    #[must_use]
+    /// Number of data bytes encoded at once. Data is padded to a multiple of it.
+    pub fn chunk_bytes(self) -> usize {
+        match self.hamming_params() {
+            Some(params) => params.chunk_bytes(),
+            None => RS_CHUNK_BYTES_255_223,
+        }
+    }
+
+    #[must_use]
+    /// Number of bytes a chunk of `chunk_bytes` data bytes is encoded into.
+    pub fn chunk_bytes_total(self) -> usize {
+        match self.hamming_params() {
+            Some(params) => params.chunk_bytes_total(),
+            None => RS_CHUNK_BYTES_TOTAL_255_223,
+        }
+    }
+
+    #[must_use]
+    /// Number of symbols in a code word, the units errors are corrected in.
+    pub fn word_symbols(self) -> usize {
+        match self.hamming_params() {
+            Some(params) => params.code_bits as usize + 1,
+            None => RS_CHUNK_BYTES_TOTAL_255_223,
+        }
+    }
+
+    #[must_use]
+    /// Bits in a symbol: Hamming codes correct single bits, Reed-Solomon whole bytes.
+    pub fn symbol_bits(self) -> usize {
+        match self.hamming_params() {
+            Some(_) => 1,
+            None => u8::BITS as usize,
+        }
+    }
+
+    #[must_use]
+    /// Length of the encoded stream for `data_len` bytes of data, including padding.
+    ///
+    /// # Arguments
+    /// * `data_len` - Number of data bytes.
+    pub fn encoded_len(self, data_len: usize) -> usize {
+        data_len.div_ceil(self.chunk_bytes()) * self.chunk_bytes_total()
+    }
+
+    #[must_use]
+    /// Value identifying the code in the header.
+    pub fn to_code(self) -> u8 {
+        match self {
+            FecKind::Hamming => 0,
+            FecKind::Rs => 1,
+            FecKind::Hamming7_4 => 2,
+            FecKind::Hamming15_11 => 3,
+            FecKind::Hamming63_57 => 4,
+        }
+    }
+
+    /// Reads the code identified by `code` as written by `to_code`.
+    ///
+    /// # Arguments
+    /// * `code` - Value read from the header.
+    ///
+    /// # Errors
+    /// Fails if `code` does not name a known error correction code.
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(FecKind::Hamming),
+            1 => Ok(FecKind::Rs),
+            2 => Ok(FecKind::Hamming7_4),
+            3 => Ok(FecKind::Hamming15_11),
+            4 => Ok(FecKind::Hamming63_57),
+            _ => bail!("Unknown error correction code {code}."),
+        }
+    }
+
+    /// Adds error correction to `data`, which must be a multiple of `chunk_bytes` long.
+    ///
+    /// # Arguments
+    /// * `data` - The bytes to calculate parity for.
+    ///
+    /// # Errors
+    /// Fails if `data` is not a multiple of `chunk_bytes` long.
+    pub fn encode(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.hamming_params() {
+            Some(params) => encode_with_hamming(data, params),
+            None => encode_with_reed_solomon_255_223(data),
+        }
+    }
+
+    /// Adds error correction to `data` of any length,
+    /// padding the last chunk with zeros like `encoded_len` expects.
+    /// Only the last partial chunk is copied, `data` itself is encoded in place.
+    ///
+    /// # Arguments
+    /// * `data` - The bytes to calculate parity for.
+    ///
+    /// # Errors
+    /// Fails if the data can not be encoded.
+    pub fn encode_padded(self, data: &[u8]) -> Result<Vec<u8>> {
+        let whole_len = data.len() / self.chunk_bytes() * self.chunk_bytes();
+        let (whole, rest) = data.split_at(whole_len);
+        let mut encoded = self.encode(whole)?;
+        if !rest.is_empty() {
+            let mut last_chunk = rest.to_vec();
+            last_chunk.resize(self.chunk_bytes(), 0);
+            encoded.extend(self.encode(&last_chunk)?);
+        }
+        Ok(encoded)
+    }
+
+    /// Corrects and strips the error correction added by `encode`,
+    /// counting the errors separately for every block of `block_len` bytes of `data`.
+    ///
+    /// # Arguments
+    /// * `data` - The bytes to evaluate.
+    /// * `block_len` - Length of a block in bytes.
+    /// * `start_offset` - Position of the first byte of `data` within the first block.
+    ///
+    /// # Errors
+    /// Fails if `data` is not a multiple of `chunk_bytes_total` long or `block_len` is zero.
+    pub fn decode_blocks(
+        self,
+        data: &[u8],
+        block_len: usize,
+        start_offset: usize,
+    ) -> Result<(Vec<u8>, HammingReport, Vec<HammingReport>)> {
+        match self.hamming_params() {
+            Some(params) => decode_with_hamming_blocks(data, block_len, start_offset, params),
+            None => decode_with_reed_solomon_255_223_blocks(data, block_len, start_offset),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 /// Reports if error was detected and if it could be corrected
 /// when evaluating hamming code parity.
@@ -29,33 +205,249 @@ pub enum HammingStatus {
     Uncorrectable,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 /// Number of correctable and uncorrectable errors
 /// found when decoding data with `decode_with_hamming_31_26`.
+/// Reed-Solomon decoding counts corrected bytes and uncorrectable code words instead.
 pub struct HammingReport {
     /// Single bit errors found and corrected.
     pub corrected_errors: u32,
     /// Double bit errors found and unable to be corrected.
     pub uncorrected_errors: u32,
+    /// Byte offsets within the decoded data of the code words errors were corrected in.
+    /// Only collected for the whole data, not for every block.
+    pub corrected_offsets: Vec<usize>,
+    /// Byte offsets within the decoded data of the code words with uncorrectable errors.
+    /// Only collected for the whole data, not for every block.
+    pub uncorrected_offsets: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Parameters of an extended Hamming code:
+/// a Hamming(n, k) code word plus a parity bit over the entire word,
+/// stored in `n + 1` bits.
+pub struct HammingParams {
+    /// Bits of a Hamming code word `n`, not counting the overall parity bit.
+    pub code_bits: u32,
+    /// Data bits `k` in a code word.
+    pub data_bits: u32,
+    /// Zero based positions of the data bits within the code word.
+    pub data_positions: &'static [u32],
+    /// One based positions of the parity bits within the code word, the powers of two.
+    pub parity_positions: &'static [u32],
+}
+
+/// Hamming(7, 4), the most robust variant with 100% overhead.
+pub const HAMMING_7_4: HammingParams = HammingParams {
+    code_bits: 7,
+    data_bits: 4,
+    data_positions: &hamming_data_positions::<4>(),
+    parity_positions: &[1, 2, 4],
+};
+
+/// Hamming(15, 11), 45% overhead.
+pub const HAMMING_15_11: HammingParams = HammingParams {
+    code_bits: 15,
+    data_bits: 11,
+    data_positions: &hamming_data_positions::<11>(),
+    parity_positions: &[1, 2, 4, 8],
+};
+
+/// Hamming(31, 26), 23% overhead.
+pub const HAMMING_31_26: HammingParams = HammingParams {
+    code_bits: 31,
+    data_bits: 26,
+    data_positions: &hamming_data_positions::<26>(),
+    parity_positions: &[1, 2, 4, 8, 16],
+};
+
+/// Hamming(63, 57), the least overhead at 12%, correcting one bit in 57.
+pub const HAMMING_63_57: HammingParams = HammingParams {
+    code_bits: 63,
+    data_bits: 57,
+    data_positions: &hamming_data_positions::<57>(),
+    parity_positions: &[1, 2, 4, 8, 16, 32],
+};
+
+/// All supported Hamming code variants.
+pub const HAMMING_VARIANTS: [HammingParams; 4] =
+    [HAMMING_7_4, HAMMING_15_11, HAMMING_31_26, HAMMING_63_57];
+
+/// Zero based positions of the `K` data bits of a Hamming code word:
+/// every one based position that is not a power of two.
+const fn hamming_data_positions<const K: usize>() -> [u32; K] {
+    let mut positions = [0; K];
+    let mut index = 0;
+    let mut position: u32 = 1;
+    while index < K {
+        if !position.is_power_of_two() {
+            positions[index] = position - 1;
+            index += 1;
+        }
+        position += 1;
+    }
+    positions
+}
+
+#[derive(Debug)]
+/// Lookup tables of a Hamming code variant, so decoding needs no loop over every bit.
+struct HammingTables {
+    /// For every parity bit, mask over the code word bits it covers.
+    parity_masks: Vec<u64>,
+    /// Code word bit to flip for every syndrome, zero for syndrome zero.
+    /// The syndrome is the one based position of the damaged bit.
+    error_masks: Vec<u64>,
+}
+
+impl HammingTables {
+    /// Builds the tables of `params`.
+    ///
+    /// # Arguments
+    /// * `params` - Hamming code variant to build the tables for.
+    fn new(params: HammingParams) -> Self {
+        let parity_masks = params
+            .parity_positions
+            .iter()
+            .map(|&p| {
+                (0..params.code_bits)
+                    .filter(|bit| (bit + 1) & p != 0)
+                    .fold(0, |mask, bit| mask | (1 << bit))
+            })
+            .collect();
+        let error_masks = (0..=params.code_bits)
+            .map(|syndrome| {
+                if syndrome == 0 {
+                    0
+                } else {
+                    1 << (syndrome - 1)
+                }
+            })
+            .collect();
+        Self {
+            parity_masks,
+            error_masks,
+        }
+    }
+}
+
+impl HammingParams {
+    /// Lookup tables of this variant, built on first use.
+    fn tables(self) -> &'static HammingTables {
+        static TABLES: [OnceLock<HammingTables>; HAMMING_VARIANTS.len()] =
+            [const { OnceLock::new() }; HAMMING_VARIANTS.len()];
+        // Every variant has a different number of parity bits, starting at three.
+        TABLES[self.parity_positions.len() - HAMMING_7_4.parity_positions.len()]
+            .get_or_init(|| HammingTables::new(self))
+    }
+
+    /// Mask over the data bits of a code word.
+    fn data_mask(self) -> u64 {
+        (1 << self.data_bits) - 1
+    }
+
+    /// Mask over the Hamming code bits of a code word, excluding the overall parity bit.
+    fn code_mask(self) -> u64 {
+        (1 << self.code_bits) - 1
+    }
+
+    /// Bytes a code word including the overall parity bit is stored in.
+    fn word_bytes(self) -> usize {
+        (self.code_bits as usize + 1) / u8::BITS as usize
+    }
+
+    #[must_use]
+    /// How many bytes in a data chunk, the smallest whole number of bytes
+    /// made up of whole code words: lcm(8 bits/byte, k bits) / 8 bits/byte.
+    pub fn chunk_bytes(self) -> usize {
+        let mut lcm = self.data_bits as usize;
+        while !lcm.is_multiple_of(u8::BITS as usize) {
+            lcm += self.data_bits as usize;
+        }
+        lcm / u8::BITS as usize
+    }
+
+    #[must_use]
+    /// How many bytes in a chunk when parity is added.
+    pub fn chunk_bytes_total(self) -> usize {
+        self.chunk_bytes() * u8::BITS as usize / self.data_bits as usize * self.word_bytes()
+    }
 }
 
 /// Splits data into 26 bit chunks and calculates Hamming(31, 26) code
-/// using `hamming_31_26_encode`.
+/// using `hamming_encode`.
 /// The data must be provided as a multiple of 13 bytes, since lcm(8,26)/8.
 /// This function does not provide any padding.
 ///
 /// # Arguments
 /// * `data` - The bytes to calculate parity for.
-pub fn encode_with_hamming_31_26(data: &Vec<u8>) -> Result<Vec<u8>> {
-    if data.len() % HAMMING_CHUNK_BYTES_31_26 != 0 {
+///
+/// # Errors
+/// Fails if `data` is not a multiple of the Hamming(31, 26) chunk length.
+pub fn encode_with_hamming_31_26(data: &[u8]) -> Result<Vec<u8>> {
+    encode_with_hamming(data, HAMMING_31_26)
+}
+
+/// Takes a data containing Hamming(31, 26) error correction
+/// generated using the `encode_with_hamming_31_26` function.
+/// And evaluates the code, correcting errors where possible
+/// returning the original data or as close to the original data
+/// as error correction permits.  
+/// The number of corrected and uncorrected errors is reported
+/// in a `HammingReport` struct.
+///
+/// # Arguments
+/// * `data` - The bytes to evaluate.
+///
+/// # Errors
+/// Fails if `data` is not a multiple of the Hamming(31, 26) code word chunk length.
+pub fn decode_with_hamming_31_26(data: &[u8]) -> Result<(Vec<u8>, HammingReport)> {
+    let (output, report, _) = decode_with_hamming_31_26_blocks(data, data.len().max(1), 0)?;
+    Ok((output, report))
+}
+
+/// Decodes like `decode_with_hamming_31_26`, additionally counting
+/// the errors separately for every block of `block_len` bytes of `data`,
+/// e.g. for every frame the data was read from.
+/// A code word is counted towards the block its first byte lies in.
+///
+/// # Arguments
+/// * `data` - The bytes to evaluate.
+/// * `block_len` - Length of a block in bytes.
+/// * `start_offset` - Position of the first byte of `data` within the first block.
+///
+/// # Errors
+/// Fails if `data` is not a multiple of the Hamming(31, 26) code word chunk length or `block_len` is zero.
+pub fn decode_with_hamming_31_26_blocks(
+    data: &[u8],
+    block_len: usize,
+    start_offset: usize,
+) -> Result<(Vec<u8>, HammingReport, Vec<HammingReport>)> {
+    decode_with_hamming_blocks(data, block_len, start_offset, HAMMING_31_26)
+}
+
+/// Splits data into `params.data_bits` bit chunks and calculates their Hamming code
+/// using `hamming_encode`. Code words are stored little-endian.
+/// The data must be provided as a multiple of `params.chunk_bytes()` bytes.
+/// This function does not provide any padding.
+///
+/// # Arguments
+/// * `data` - The bytes to calculate parity for.
+/// * `params` - Hamming code variant to use.
+///
+/// # Errors
+/// Fails if `data` is not a multiple of `params.chunk_bytes()` long.
+pub fn encode_with_hamming(data: &[u8], params: HammingParams) -> Result<Vec<u8>> {
+    if !data.len().is_multiple_of(params.chunk_bytes()) {
         bail!(
             "Data length must be a multiple of {} bytes.",
-            HAMMING_CHUNK_BYTES_31_26
+            params.chunk_bytes()
         );
     }
+    let data_bits = params.data_bits as usize;
+    let word_bytes = params.word_bytes();
     // Calculate number of total bytes in number of hamming groups
     // needed to encode `data`.
-    let output_bytes = ((data.len() / HAMMING_DATA_BITS_31_26) * BYTES_U32) / u8::BITS as usize;
+    let output_bytes = data.len() / params.chunk_bytes() * params.chunk_bytes_total();
     let mut encoded = Vec::with_capacity(output_bytes);
     let mut bit_buffer: u64 = 0;
     let mut bit_counter: usize = 0;
@@ -64,58 +456,79 @@ pub fn encode_with_hamming_31_26(data: &Vec<u8>) -> Result<Vec<u8>> {
         bit_buffer = (bit_buffer << u8::BITS) | (*byte as u64);
         bit_counter += u8::BITS as usize;
 
-        while bit_counter >= HAMMING_DATA_BITS_31_26 {
-            #[allow(clippy::cast_possible_truncation)]
-            let data_bits: u32 =
-                ((bit_buffer >> (bit_counter - HAMMING_DATA_BITS_31_26)) as u32) & BIT_MASK_26;
-            bit_counter -= HAMMING_DATA_BITS_31_26;
-            let code = hamming_31_26_encode(data_bits);
-            encoded.extend_from_slice(&code.to_le_bytes());
+        while bit_counter >= data_bits {
+            let word = (bit_buffer >> (bit_counter - data_bits)) & params.data_mask();
+            bit_counter -= data_bits;
+            let code = hamming_encode(word, params);
+            encoded.extend_from_slice(&code.to_le_bytes()[..word_bytes]);
         }
     }
     Ok(encoded)
 }
 
-/// Takes a data containing Hamming(31, 26) error correction
-/// generated using the `encode_with_hamming_31_26` function.
-/// And evaluates the code, correcting errors where possible
-/// returning the original data or as close to the original data
-/// as error correction permits.  
-/// The number of corrected and uncorrected errors is reported
-/// in a `HammingReport` struct.
+/// Decodes data encoded with `encode_with_hamming`, correcting errors where possible
+/// and counting them in total and separately for every block of `block_len` bytes of `data`.
+/// A code word is counted towards the block its first byte lies in.
 ///
 /// # Arguments
 /// * `data` - The bytes to evaluate.
-pub fn decode_with_hamming_31_26(data: &[u8]) -> Result<(Vec<u8>, HammingReport)> {
-    if data.len() % HAMMING_CHUNK_BYTES_TOAL_31_26 != 0 {
+/// * `block_len` - Length of a block in bytes.
+/// * `start_offset` - Position of the first byte of `data` within the first block.
+/// * `params` - Hamming code variant the data was encoded with.
+///
+/// # Errors
+/// Fails if `data` is not a multiple of `params.chunk_bytes_total()` long or `block_len` is zero.
+pub fn decode_with_hamming_blocks(
+    data: &[u8],
+    block_len: usize,
+    start_offset: usize,
+    params: HammingParams,
+) -> Result<(Vec<u8>, HammingReport, Vec<HammingReport>)> {
+    if !data.len().is_multiple_of(params.chunk_bytes_total()) {
         bail!(
             "Data length must be a multiple of {} bytes.",
-            HAMMING_CHUNK_BYTES_TOAL_31_26
+            params.chunk_bytes_total()
         );
     }
+    if block_len == 0 {
+        bail!("Block length must not be zero.");
+    }
+    let data_bits = params.data_bits as usize;
+    let word_bytes = params.word_bytes();
     // Calculate number of data bytes encoded in number of hamming groups
     // encoded in `data`.
-    let output_bytes = ((data.len() / BYTES_U32) * HAMMING_DATA_BITS_31_26) / u8::BITS as usize;
+    let output_bytes = data.len() / params.chunk_bytes_total() * params.chunk_bytes();
     let mut output: Vec<u8> = Vec::with_capacity(output_bytes);
     let mut bit_buffer: u64 = 0;
     let mut bit_counter: usize = 0;
-    let mut corrected_errors = 0;
-    let mut uncorrected_errors = 0;
-
-    // Go over every group of four bytes since they
-    // are the result of a single Hamming(31, 26) group.
-    for chunk in data.chunks_exact(BYTES_U32) {
-        let chunk_bytes: [u8; BYTES_U32] = chunk.try_into()?;
-        let (data_bits, hamming_code) = hamming_31_26_decode(u32::from_le_bytes(chunk_bytes));
-        // Keep track of number of errors.
+    let mut report = HammingReport::default();
+    let mut block_reports =
+        vec![HammingReport::default(); (start_offset + data.len()).div_ceil(block_len)];
+
+    // Go over every code word, each the result of a single Hamming group.
+    for (i, chunk) in data.chunks_exact(word_bytes).enumerate() {
+        let mut word_bytes_le = [0u8; BYTES_U64];
+        word_bytes_le[..word_bytes].copy_from_slice(chunk);
+        let (word, hamming_code) = hamming_decode(u64::from_le_bytes(word_bytes_le), params);
+        // Keep track of number and position of errors.
+        let offset = i * word_bytes;
+        let block_report = &mut block_reports[(start_offset + offset) / block_len];
         match hamming_code {
-            HammingStatus::CorrectedSingle => corrected_errors += 1,
-            HammingStatus::Uncorrectable => uncorrected_errors += 1,
+            HammingStatus::CorrectedSingle => {
+                report.corrected_errors += 1;
+                report.corrected_offsets.push(offset);
+                block_report.corrected_errors += 1;
+            }
+            HammingStatus::Uncorrectable => {
+                report.uncorrected_errors += 1;
+                report.uncorrected_offsets.push(offset);
+                block_report.uncorrected_errors += 1;
+            }
             HammingStatus::NoError => (),
         }
-        // Buffer the extracted 26 data bits.
-        bit_counter += HAMMING_DATA_BITS_31_26;
-        bit_buffer = (bit_buffer << HAMMING_DATA_BITS_31_26) | (data_bits as u64);
+        // Buffer the extracted data bits.
+        bit_counter += data_bits;
+        bit_buffer = (bit_buffer << params.data_bits) | word;
 
         // When more than 8 data bits are available, extract them from `bit_buffer`.
         while bit_counter >= u8::BITS as usize {
@@ -125,64 +538,101 @@ pub fn decode_with_hamming_31_26(data: &[u8]) -> Result<(Vec<u8>, HammingReport)
             bit_counter -= u8::BITS as usize;
         }
     }
-    Ok((
-        output,
-        HammingReport {
-            corrected_errors,
-            uncorrected_errors,
-        },
-    ))
+    Ok((output, report, block_reports))
 }
 
-/// Takes 26 bits in and encodes them as 32 bits with a Hamming(31, 26) code
+/// Takes `params.data_bits` bits in and encodes them with a Hamming code
 /// including an additional parity bit over all other bits.
 ///
 /// # Arguments
-/// * `data_bits` - The 26 data bits to calculate parity for.
-fn hamming_31_26_encode(data_bits: u32) -> u32 {
-    let data_bits = data_bits & BIT_MASK_26; // Mask to 26 bits
-    let mut code_word = 0u32;
+/// * `data_bits` - The data bits to calculate parity for.
+/// * `params` - Hamming code variant to use.
+fn hamming_encode(data_bits: u64, params: HammingParams) -> u64 {
+    let data_bits = data_bits & params.data_mask();
+    let mut code_word = 0u64;
 
     // Place data bits into code_word
-    for (i, &offset) in HAMMING_DATA_POSITIONS_31_26.iter().enumerate() {
+    for (i, &offset) in params.data_positions.iter().enumerate() {
         let bit = (data_bits >> i) & 1;
         code_word |= bit << offset;
     }
 
     // Compute parity bits
-    for &p in &HAMMING_PARITY_POSITIONS_31_26 {
-        let mut parity = 0;
-        for bit in 0u32..31 {
-            let code_word_pos = bit + 1;
-            if (code_word_pos & p) != 0 {
-                parity ^= (code_word >> bit) & 1;
-            }
-        }
+    let tables = params.tables();
+    for (&p, &mask) in params.parity_positions.iter().zip(&tables.parity_masks) {
+        let parity = u64::from((code_word & mask).count_ones() % 2);
         code_word |= parity << (p - 1);
     }
 
-    // Add overall parity bit (bit 31)
+    // Add overall parity bit after the Hamming code
     let overall_parity = code_word.count_ones() % 2;
-    code_word | (overall_parity << 31)
+    code_word | ((overall_parity as u64) << params.code_bits)
 }
 
-/// Takes in 32 bits that include 26 data bits, 5 parity bits as
-/// defined by a Hamming(31, 26) code and a parity bit over the entire group.
+/// Takes in a code word that includes data and parity bits as
+/// defined by a Hamming code and a parity bit over the entire group.
 /// Evaluates these and performs error correction when possible.
 /// If an error was found and if it could be corrected is reported
 /// using a `HammingStatus`.
 ///
 /// # Arguments
-/// * `code_word` - The 32 bits to evaluate parity for.
-fn hamming_31_26_decode(code_word: u32) -> (u32, HammingStatus) {
-    let hamming_code = code_word & BIT_MASK_31;
-    let original_parity = code_word.count_ones() % 2;
+/// * `code_word` - The bits to evaluate parity for.
+/// * `params` - Hamming code variant the code word was encoded with.
+fn hamming_decode(code_word: u64, params: HammingParams) -> (u64, HammingStatus) {
+    let tables = params.tables();
+    let hamming_code = code_word & params.code_mask();
+    let original_parity = (code_word & ((params.code_mask() << 1) | 1)).count_ones() % 2;
+
+    // Each syndrome bit is the parity over the bits covered by a parity bit.
+    let syndrome = tables
+        .parity_masks
+        .iter()
+        .enumerate()
+        .fold(0, |syndrome, (i, &mask)| {
+            syndrome | (((hamming_code & mask).count_ones() as usize % 2) << i)
+        });
+
+    // Determine error type and apply corrections
+    let (corrected_hamming, status) = match (syndrome != 0, original_parity != 0) {
+        // No errors detected
+        (false, false) => (hamming_code, HammingStatus::NoError),
+        // Single-bit error in the overall parity bit, not included in hamming_code
+        (false, true) => (hamming_code, HammingStatus::CorrectedSingle),
+        // Single-bit error in Hamming code, flip the bit the syndrome points at
+        (true, true) => (
+            hamming_code ^ tables.error_masks[syndrome],
+            HammingStatus::CorrectedSingle,
+        ),
+        // Uncorrectable multi-bit error
+        (true, false) => (hamming_code, HammingStatus::Uncorrectable),
+    };
+
+    // Extract data bits from corrected Hamming code
+    let mut data = 0;
+    for (i, &offset) in params.data_positions.iter().enumerate() {
+        data |= ((corrected_hamming >> offset) & 1) << i;
+    }
+
+    (data, status)
+}
+
+#[cfg(test)]
+/// Decodes like `hamming_decode`, but recomputes every parity bit bit by bit
+/// instead of using the lookup tables.
+/// Kept as reference to verify the tables against.
+///
+/// # Arguments
+/// * `code_word` - The bits to evaluate parity for.
+/// * `params` - Hamming code variant the code word was encoded with.
+fn hamming_decode_bitwise(code_word: u64, params: HammingParams) -> (u64, HammingStatus) {
+    let hamming_code = code_word & params.code_mask();
+    let original_parity = (code_word & ((params.code_mask() << 1) | 1)).count_ones() % 2;
     let mut syndrome: u32 = 0;
 
     // Calculate syndrome for Hamming code
-    for (i, &p) in HAMMING_PARITY_POSITIONS_31_26.iter().enumerate() {
+    for (i, &p) in params.parity_positions.iter().enumerate() {
         let mut parity = 0;
-        for bit in 0u32..31 {
+        for bit in 0..params.code_bits {
             let code_word_pos = bit + 1;
             if (code_word_pos & p) != 0 {
                 parity ^= (hamming_code >> bit) & 1;
@@ -200,7 +650,7 @@ fn hamming_31_26_decode(code_word: u32) -> (u32, HammingStatus) {
 
         // Single-bit error in parity bit
         (false, true) => {
-            // Flip overall parity bit (bit 31 not included in hamming_code)
+            // Flip overall parity bit (not included in hamming_code)
             (hamming_code, HammingStatus::CorrectedSingle)
         }
 
@@ -220,9 +670,498 @@ fn hamming_31_26_decode(code_word: u32) -> (u32, HammingStatus) {
 
     // Extract data bits from corrected Hamming code
     let mut data = 0;
-    for (i, &offset) in HAMMING_DATA_POSITIONS_31_26.iter().enumerate() {
+    for (i, &offset) in params.data_positions.iter().enumerate() {
         data |= ((corrected_hamming >> offset) & 1) << i;
     }
 
     (data, status)
 }
+
+/// Exponential and logarithm tables of GF(256) generated by `RS_PRIMITIVE_POLYNOMIAL`.
+/// The exponential table is stored twice, so sums of two logarithms need no reduction.
+const GF_TABLES: ([u8; 512], [u8; 256]) = gf_tables();
+
+/// Generator polynomial of RS(255, 223), highest degree first:
+/// the product of `(x - a^i)` for `i` in `0..32` with `a` the primitive element.
+const RS_GENERATOR_255_223: [u8; RS_PARITY_BYTES_255_223 + 1] = rs_generator_255_223();
+
+/// Builds the tables of `GF_TABLES`.
+const fn gf_tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut value: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            exp[i] = value as u8;
+            log[value as usize] = i as u8;
+        }
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= RS_PRIMITIVE_POLYNOMIAL;
+        }
+        i += 1;
+    }
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    (exp, log)
+}
+
+/// Builds `RS_GENERATOR_255_223`.
+const fn rs_generator_255_223() -> [u8; RS_PARITY_BYTES_255_223 + 1] {
+    let mut generator = [0u8; RS_PARITY_BYTES_255_223 + 1];
+    generator[0] = 1;
+    let mut degree = 0;
+    while degree < RS_PARITY_BYTES_255_223 {
+        // Multiply by (x + a^degree), subtraction is addition in GF(256).
+        let root = GF_TABLES.0[degree];
+        let mut i = degree + 1;
+        while i > 0 {
+            generator[i] ^= gf_mul(generator[i - 1], root);
+            i -= 1;
+        }
+        degree += 1;
+    }
+    generator
+}
+
+/// Multiplies two elements of GF(256).
+///
+/// # Arguments
+/// * `a` - First factor.
+/// * `b` - Second factor.
+const fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    GF_TABLES.0[GF_TABLES.1[a as usize] as usize + GF_TABLES.1[b as usize] as usize]
+}
+
+/// Divides two elements of GF(256).
+///
+/// # Arguments
+/// * `a` - Dividend.
+/// * `b` - Divisor, must not be zero.
+fn gf_div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        return 0;
+    }
+    GF_TABLES.0[GF_TABLES.1[a as usize] as usize + 255 - GF_TABLES.1[b as usize] as usize]
+}
+
+/// Returns `a^exponent` with `a` the primitive element of GF(256).
+///
+/// # Arguments
+/// * `exponent` - Power to raise the primitive element to.
+fn gf_alpha_pow(exponent: usize) -> u8 {
+    GF_TABLES.0[exponent % 255]
+}
+
+/// Evaluates a polynomial over GF(256) at `x`.
+///
+/// # Arguments
+/// * `poly` - Coefficients, lowest degree first.
+/// * `x` - Point to evaluate at.
+fn gf_poly_eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter()
+        .rev()
+        .fold(0, |acc, &coefficient| gf_mul(acc, x) ^ coefficient)
+}
+
+/// Splits data into 223 byte chunks and appends 32 Reed-Solomon parity bytes to each,
+/// giving RS(255, 223) code words that correct up to 16 damaged bytes.
+/// The data must be provided as a multiple of 223 bytes.
+/// This function does not provide any padding.
+///
+/// # Arguments
+/// * `data` - The bytes to calculate parity for.
+///
+/// # Errors
+/// Fails if `data` is not a multiple of 223 bytes long.
+pub fn encode_with_reed_solomon_255_223(data: &[u8]) -> Result<Vec<u8>> {
+    if !data.len().is_multiple_of(RS_CHUNK_BYTES_255_223) {
+        bail!("Data length must be a multiple of {RS_CHUNK_BYTES_255_223} bytes.");
+    }
+    let mut encoded = Vec::with_capacity(FecKind::Rs.encoded_len(data.len()));
+    for message in data.chunks_exact(RS_CHUNK_BYTES_255_223) {
+        encoded.extend_from_slice(message);
+        encoded.extend_from_slice(&rs_255_223_parity(message));
+    }
+    Ok(encoded)
+}
+
+/// Decodes data encoded with `encode_with_reed_solomon_255_223`, correcting errors
+/// where possible. Counts corrected bytes and uncorrectable code words in total
+/// and separately for every block of `block_len` bytes of `data`.
+/// A code word is counted towards the block its first byte lies in.
+/// The data bytes of uncorrectable code words are returned as read.
+///
+/// # Arguments
+/// * `data` - The bytes to evaluate.
+/// * `block_len` - Length of a block in bytes.
+/// * `start_offset` - Position of the first byte of `data` within the first block.
+///
+/// # Errors
+/// Fails if `data` is not a multiple of 255 bytes long or `block_len` is zero.
+pub fn decode_with_reed_solomon_255_223_blocks(
+    data: &[u8],
+    block_len: usize,
+    start_offset: usize,
+) -> Result<(Vec<u8>, HammingReport, Vec<HammingReport>)> {
+    if !data.len().is_multiple_of(RS_CHUNK_BYTES_TOTAL_255_223) {
+        bail!("Data length must be a multiple of {RS_CHUNK_BYTES_TOTAL_255_223} bytes.");
+    }
+    if block_len == 0 {
+        bail!("Block length must not be zero.");
+    }
+    let mut output =
+        Vec::with_capacity(data.len() / RS_CHUNK_BYTES_TOTAL_255_223 * RS_CHUNK_BYTES_255_223);
+    let mut report = HammingReport::default();
+    let mut block_reports =
+        vec![HammingReport::default(); (start_offset + data.len()).div_ceil(block_len)];
+
+    for (i, chunk) in data.chunks_exact(RS_CHUNK_BYTES_TOTAL_255_223).enumerate() {
+        let offset = i * RS_CHUNK_BYTES_TOTAL_255_223;
+        let block_report = &mut block_reports[(start_offset + offset) / block_len];
+        if let Some((code_word, corrected)) = rs_255_223_decode(chunk) {
+            if corrected > 0 {
+                report.corrected_offsets.push(offset);
+            }
+            report.corrected_errors += corrected;
+            block_report.corrected_errors += corrected;
+            output.extend_from_slice(&code_word[..RS_CHUNK_BYTES_255_223]);
+        } else {
+            report.uncorrected_errors += 1;
+            report.uncorrected_offsets.push(offset);
+            block_report.uncorrected_errors += 1;
+            output.extend_from_slice(&chunk[..RS_CHUNK_BYTES_255_223]);
+        }
+    }
+    Ok((output, report, block_reports))
+}
+
+/// Calculates the 32 parity bytes of a RS(255, 223) code word,
+/// the remainder of dividing the message shifted by 32 bytes by the generator polynomial.
+///
+/// # Arguments
+/// * `message` - The 223 data bytes.
+fn rs_255_223_parity(message: &[u8]) -> [u8; RS_PARITY_BYTES_255_223] {
+    let mut parity = [0u8; RS_PARITY_BYTES_255_223];
+    for &byte in message {
+        let feedback = byte ^ parity[0];
+        parity.copy_within(1.., 0);
+        parity[RS_PARITY_BYTES_255_223 - 1] = 0;
+        if feedback != 0 {
+            for (parity_byte, &coefficient) in parity.iter_mut().zip(&RS_GENERATOR_255_223[1..]) {
+                *parity_byte ^= gf_mul(coefficient, feedback);
+            }
+        }
+    }
+    parity
+}
+
+/// Syndromes of a RS(255, 223) code word, its value at the roots of the generator polynomial.
+/// All zero if no error is detected.
+///
+/// # Arguments
+/// * `code_word` - The 255 bytes to evaluate, first byte as highest degree coefficient.
+fn rs_255_223_syndromes(code_word: &[u8]) -> [u8; RS_PARITY_BYTES_255_223] {
+    let mut syndromes = [0u8; RS_PARITY_BYTES_255_223];
+    for (i, syndrome) in syndromes.iter_mut().enumerate() {
+        let root = gf_alpha_pow(i);
+        *syndrome = code_word
+            .iter()
+            .fold(0, |acc, &byte| gf_mul(acc, root) ^ byte);
+    }
+    syndromes
+}
+
+/// Finds the error locator polynomial for `syndromes` using the Berlekamp-Massey algorithm.
+/// Returns the polynomial, lowest degree first, and the number of errors it locates.
+///
+/// # Arguments
+/// * `syndromes` - Syndromes as returned by `rs_255_223_syndromes`.
+fn berlekamp_massey(syndromes: &[u8]) -> (Vec<u8>, usize) {
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let mut previous_discrepancy = 1u8;
+    let mut error_count = 0;
+    let mut shift = 1;
+    for n in 0..syndromes.len() {
+        let mut discrepancy = syndromes[n];
+        for i in 1..locator.len().min(n + 1) {
+            discrepancy ^= gf_mul(locator[i], syndromes[n - i]);
+        }
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = gf_div(discrepancy, previous_discrepancy);
+        let mut updated = locator.clone();
+        updated.resize(updated.len().max(previous.len() + shift), 0);
+        for (i, &coefficient) in previous.iter().enumerate() {
+            updated[i + shift] ^= gf_mul(scale, coefficient);
+        }
+        if 2 * error_count <= n {
+            previous = locator;
+            previous_discrepancy = discrepancy;
+            error_count = n + 1 - error_count;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        locator = updated;
+    }
+    while locator.len() > 1 && locator.last() == Some(&0) {
+        locator.pop();
+    }
+    (locator, error_count)
+}
+
+/// Corrects up to 16 damaged bytes of a RS(255, 223) code word.
+/// Returns the corrected code word and the number of corrected bytes,
+/// or `None` if the errors can not be corrected.
+///
+/// # Arguments
+/// * `received` - The 255 bytes read.
+fn rs_255_223_decode(received: &[u8]) -> Option<([u8; RS_CHUNK_BYTES_TOTAL_255_223], u32)> {
+    let mut code_word: [u8; RS_CHUNK_BYTES_TOTAL_255_223] = received.try_into().ok()?;
+    let syndromes = rs_255_223_syndromes(&code_word);
+    if syndromes.iter().all(|&syndrome| syndrome == 0) {
+        return Some((code_word, 0));
+    }
+    let (locator, error_count) = berlekamp_massey(&syndromes);
+    if error_count > RS_PARITY_BYTES_255_223 / 2 || locator.len() != error_count + 1 {
+        return None;
+    }
+
+    // Byte `k` is the coefficient of x^(254 - k), its locator a^(254 - k).
+    // Chien search: the locator polynomial is zero at the inverse of every error locator.
+    let last = RS_CHUNK_BYTES_TOTAL_255_223 - 1;
+    let positions: Vec<usize> = (0..RS_CHUNK_BYTES_TOTAL_255_223)
+        .filter(|&k| gf_poly_eval(&locator, gf_alpha_pow(255 - (last - k))) == 0)
+        .collect();
+    if positions.len() != error_count {
+        return None;
+    }
+
+    // Forney algorithm: error evaluator is the syndrome polynomial
+    // times the locator polynomial, modulo x^32.
+    let mut evaluator = [0u8; RS_PARITY_BYTES_255_223];
+    for (i, &syndrome) in syndromes.iter().enumerate() {
+        for (j, &coefficient) in locator.iter().enumerate() {
+            if i + j < RS_PARITY_BYTES_255_223 {
+                evaluator[i + j] ^= gf_mul(syndrome, coefficient);
+            }
+        }
+    }
+    // The formal derivative only keeps odd powers in characteristic two.
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &coefficient)| if i % 2 == 1 { coefficient } else { 0 })
+        .collect();
+    for &k in &positions {
+        let locator_value = gf_alpha_pow(last - k);
+        let inverse = gf_alpha_pow(255 - (last - k));
+        let denominator = gf_poly_eval(&derivative, inverse);
+        if denominator == 0 {
+            return None;
+        }
+        let magnitude = gf_div(gf_poly_eval(&evaluator, inverse), denominator);
+        code_word[k] ^= gf_mul(locator_value, magnitude);
+    }
+    if rs_255_223_syndromes(&code_word)
+        .iter()
+        .any(|&syndrome| syndrome != 0)
+    {
+        return None;
+    }
+    Some((code_word, u32::try_from(error_count).ok()?))
+}
+
+/// Checks the Reed-Solomon RS(255, 223) implementation for consistency.
+/// Encodes known messages and verifies that they decode unchanged
+/// and that 16 damaged bytes, the most the code can correct, are corrected
+/// wherever they are in the code word.
+/// Fails if any check does not hold, since error correction would
+/// then silently produce wrong data.
+///
+/// # Errors
+/// Fails if the tables or the decoder are inconsistent.
+pub fn verify_reed_solomon_255_223() -> Result<()> {
+    #[allow(clippy::cast_possible_truncation)]
+    let messages: [Vec<u8>; 3] = [
+        vec![0; RS_CHUNK_BYTES_255_223],
+        vec![0xFF; RS_CHUNK_BYTES_255_223],
+        (0..RS_CHUNK_BYTES_255_223)
+            .map(|i| (i * 37 + 11) as u8)
+            .collect(),
+    ];
+    let max_errors = RS_PARITY_BYTES_255_223 / 2;
+    for (message_index, message) in messages.iter().enumerate() {
+        let code_word = encode_with_reed_solomon_255_223(message)?;
+        match rs_255_223_decode(&code_word) {
+            Some((decoded, 0)) if decoded.as_slice() == code_word.as_slice() => {}
+            _ => bail!("Reed-Solomon inconsistent: Message {message_index} does not round trip."),
+        }
+        for first in [0, 100, RS_CHUNK_BYTES_TOTAL_255_223 - max_errors] {
+            let mut damaged = code_word.clone();
+            for (i, byte) in damaged[first..first + max_errors].iter_mut().enumerate() {
+                #[allow(clippy::cast_possible_truncation)]
+                {
+                    *byte ^= (i + 1) as u8;
+                }
+            }
+            match rs_255_223_decode(&damaged) {
+                Some((corrected, count))
+                    if corrected.as_slice() == code_word.as_slice()
+                        && count as usize == max_errors => {}
+                _ => bail!(
+                    "Reed-Solomon inconsistent: {max_errors} errors from byte {first} in message {message_index} not corrected."
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the hand written Hamming(31, 26) tables for consistency.
+/// See `verify_hamming_tables`.
+///
+/// # Errors
+/// Fails if the tables are inconsistent.
+pub fn verify_hamming_31_26_tables() -> Result<()> {
+    verify_hamming_tables(HAMMING_31_26)
+}
+
+/// Checks the tables of a Hamming code variant for consistency.
+/// Encodes a set of known data words and verifies that they decode
+/// unchanged, that every single bit error is corrected
+/// and that double bit errors are detected as uncorrectable.
+/// Fails if any check does not hold, since error correction would
+/// then silently produce wrong data.
+///
+/// # Arguments
+/// * `params` - Hamming code variant to check.
+///
+/// # Errors
+/// Fails if the tables are inconsistent.
+pub fn verify_hamming_tables(params: HammingParams) -> Result<()> {
+    let test_vectors: [u64; 7] = [
+        0,
+        params.data_mask(),
+        0xAAAA_AAAA_AAAA_AAAA & params.data_mask(),
+        0x5555_5555_5555_5555 & params.data_mask(),
+        1,
+        1 << (params.data_bits - 1),
+        0x0123_4567_89AB_CDEF & params.data_mask(),
+    ];
+    let name = format!("Hamming({}, {})", params.code_bits, params.data_bits);
+    let word_bits = params.code_bits + 1;
+
+    for data_bits in test_vectors {
+        let code_word = hamming_encode(data_bits, params);
+        if hamming_decode(code_word, params) != (data_bits, HammingStatus::NoError) {
+            bail!("{name} tables inconsistent: {data_bits:#x} does not round trip.");
+        }
+        for error_bit in 0..word_bits {
+            let damaged = code_word ^ (1 << error_bit);
+            if hamming_decode(damaged, params) != (data_bits, HammingStatus::CorrectedSingle) {
+                bail!(
+                    "{name} tables inconsistent: Single bit error at bit {error_bit} in {data_bits:#x} not corrected."
+                );
+            }
+            let second_bit = (error_bit + 1) % word_bits;
+            let (_, status) = hamming_decode(damaged ^ (1 << second_bit), params);
+            if status != HammingStatus::Uncorrectable {
+                bail!(
+                    "{name} tables inconsistent: Double bit error at bits {error_bit} and {second_bit} in {data_bits:#x} not detected."
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hamming_tables_are_consistent() -> Result<()> {
+        for params in HAMMING_VARIANTS {
+            verify_hamming_tables(params)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reed_solomon_255_223_is_consistent() -> Result<()> {
+        verify_reed_solomon_255_223()
+    }
+
+    /// Data words swept for a variant: every word of the small variants,
+    /// a fixed sample of the larger ones.
+    fn sweep_data_words(params: HammingParams) -> Vec<u64> {
+        if params.data_bits <= 11 {
+            return (0..=params.data_mask()).collect();
+        }
+        let mut words = vec![0, params.data_mask(), 1, 1 << (params.data_bits - 1)];
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        for _ in 0..64 {
+            // xorshift64, any spread of bit patterns will do.
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            words.push(state & params.data_mask());
+        }
+        words
+    }
+
+    #[test]
+    fn syndrome_decoder_matches_bitwise_decoder() {
+        for params in HAMMING_VARIANTS {
+            let word_bits = params.code_bits + 1;
+            for data_bits in sweep_data_words(params) {
+                let code_word = hamming_encode(data_bits, params);
+                assert_eq!(
+                    hamming_decode(code_word, params),
+                    (data_bits, HammingStatus::NoError)
+                );
+                assert_eq!(
+                    hamming_decode_bitwise(code_word, params),
+                    (data_bits, HammingStatus::NoError)
+                );
+                for first in 0..word_bits {
+                    let damaged = code_word ^ (1 << first);
+                    let decoded = hamming_decode(damaged, params);
+                    assert_eq!(decoded, (data_bits, HammingStatus::CorrectedSingle));
+                    assert_eq!(
+                        decoded,
+                        hamming_decode_bitwise(damaged, params),
+                        "Hamming({}, {}) bit {first} of {data_bits:#x}",
+                        params.code_bits,
+                        params.data_bits
+                    );
+                    for second in first + 1..word_bits {
+                        let double_damaged = damaged ^ (1 << second);
+                        let decoded = hamming_decode(double_damaged, params);
+                        assert_eq!(decoded.1, HammingStatus::Uncorrectable);
+                        assert_eq!(
+                            decoded,
+                            hamming_decode_bitwise(double_damaged, params),
+                            "Hamming({}, {}) bits {first} and {second} of {data_bits:#x}",
+                            params.code_bits,
+                            params.data_bits
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
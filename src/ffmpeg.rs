@@ -0,0 +1,1010 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! ffmpeg video backend.
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use image::DynamicImage;
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    backend::{
+        ColorRange, Colorspace, EncodeSettings, EncoderOptions, EncoderPreset, FrameWriter,
+        HwAccel, Rotation, SplitSettings, VideoBackend, VideoCodec, VideoProbe,
+    },
+    constants::{
+        FFMPEG_EXCUTABLE_PATH, FFMPEG_PATH_ENV, FFPROBE_EXECUTABLE_PATH, FFPROBE_PATH_ENV,
+        VAAPI_DEVICE,
+    },
+    filesys::{FrameBuffer, path_to_str, resolve_executable},
+    interrupt::{Interrupted, is_interrupted},
+};
+
+/// How often a wait on ffmpeg checks if the run was interrupted.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Most lines of ffmpeg's error output included in the error of a failed run.
+const FFMPEG_ERROR_LINES: usize = 10;
+
+#[derive(Debug, Clone)]
+/// Video backend calling the ffmpeg executable.
+pub struct FfmpegBackend {
+    /// Path to the ffmpeg executable.
+    executable: PathBuf,
+    /// Path to the ffprobe executable, if available.
+    ffprobe_executable: Option<PathBuf>,
+    /// Codec and quality settings used when encoding videos.
+    encoder: EncoderOptions,
+    /// Longest a single ffmpeg run may take before it is killed, unlimited if `None`.
+    timeout: Option<Duration>,
+}
+
+impl FfmpegBackend {
+    /// Creates a backend using the resolved ffmpeg and ffprobe executables.
+    /// ffmpeg is required while ffprobe is optional,
+    /// features relying on it are skipped if it can not be found.
+    ///
+    /// # Arguments
+    /// * `ffmpeg_path` - ffmpeg path explicitly requested by the user.
+    /// * `ffprobe_path` - ffprobe path explicitly requested by the user.
+    /// * `encoder` - Codec and quality settings used when encoding videos.
+    /// * `timeout` - Longest a single ffmpeg run may take, unlimited if `None`.
+    pub fn new(
+        ffmpeg_path: Option<&Path>,
+        ffprobe_path: Option<&Path>,
+        encoder: EncoderOptions,
+        timeout: Option<Duration>,
+    ) -> Result<Self> {
+        let executable = resolve_executable(
+            "ffmpeg",
+            ffmpeg_path,
+            FFMPEG_PATH_ENV,
+            FFMPEG_EXCUTABLE_PATH,
+        )?;
+        check_version(&executable, timeout)?;
+        let ffprobe_executable = match resolve_executable(
+            "ffprobe",
+            ffprobe_path,
+            FFPROBE_PATH_ENV,
+            FFPROBE_EXECUTABLE_PATH,
+        ) {
+            Ok(ffprobe_executable) => Some(ffprobe_executable),
+            // A path the user asked for explicitly must be valid.
+            Err(err) if ffprobe_path.is_some() => return Err(err),
+            Err(_) => None,
+        };
+        Ok(Self {
+            executable,
+            ffprobe_executable,
+            encoder,
+            timeout,
+        })
+    }
+
+    /// Run ffmpeg with the supplied arguments and check its exit status.
+    ///
+    /// # Arguments
+    /// * `args` - Arguments passed to ffmpeg.
+    fn run(&self, args: &[&str]) -> Result<()> {
+        let mut child = Command::new(&self.executable)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Unable to run ffmpeg at {}.", self.executable.display()))?;
+        let stderr = capture_stderr(&mut child);
+        let status = wait_for_exit(&mut child, Instant::now(), self.timeout)?;
+        if !status.success() {
+            bail!(failure_message(
+                &format!("ffmpeg returned nonzero exit status ({status})."),
+                stderr
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Waits for `child` to exit. It is killed instead if the run is interrupted,
+/// `timeout` passes or waiting fails, so no ffmpeg outlives a failed run.
+///
+/// # Arguments
+/// * `child` - The running ffmpeg process.
+/// * `started` - When `child` was started.
+/// * `timeout` - Longest `child` may run, unlimited if `None`.
+fn wait_for_exit(
+    child: &mut Child,
+    started: Instant,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus> {
+    wait_for_exit_with(child, started, timeout, || Ok(()))
+}
+
+/// Waits for `child` to exit like `wait_for_exit`, calling `on_poll` every time
+/// it checks on `child` in the meantime. `child` is killed if `on_poll` fails.
+///
+/// # Arguments
+/// * `child` - The running ffmpeg process.
+/// * `started` - When `child` was started.
+/// * `timeout` - Longest `child` may run, unlimited if `None`.
+/// * `on_poll` - Work done while `child` runs.
+fn wait_for_exit_with(
+    child: &mut Child,
+    started: Instant,
+    timeout: Option<Duration>,
+    mut on_poll: impl FnMut() -> Result<()>,
+) -> Result<ExitStatus> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status),
+            Ok(None) => {}
+            Err(err) => {
+                kill_child(child);
+                return Err(err).context("Unable to wait for ffmpeg.");
+            }
+        }
+        if is_interrupted() {
+            kill_child(child);
+            return Err(Interrupted.into());
+        }
+        check_timeout(child, started, timeout)?;
+        if let Err(err) = on_poll() {
+            kill_child(child);
+            return Err(err);
+        }
+        thread::sleep(INTERRUPT_POLL_INTERVAL);
+    }
+}
+
+/// Kills `child` if it has been running longer than `timeout`.
+///
+/// # Arguments
+/// * `child` - The running ffmpeg process.
+/// * `started` - When `child` was started.
+/// * `timeout` - Longest `child` may run, unlimited if `None`.
+///
+/// # Errors
+/// Fails if `child` was killed.
+fn check_timeout(child: &mut Child, started: Instant, timeout: Option<Duration>) -> Result<()> {
+    if let Some(timeout) = timeout
+        && started.elapsed() > timeout
+    {
+        kill_child(child);
+        bail!("ffmpeg was stopped after running for more than {timeout:?}, see --ffmpeg-timeout.");
+    }
+    Ok(())
+}
+
+/// Reads the error output of `child` on a separate thread until it exits,
+/// so ffmpeg never blocks on a full pipe while it is being waited for.
+///
+/// # Arguments
+/// * `child` - The ffmpeg process, started with its error output piped.
+fn capture_stderr(child: &mut Child) -> Option<JoinHandle<Vec<u8>>> {
+    child.stderr.take().map(read_on_thread)
+}
+
+/// Reads the standard output of `child` on a separate thread until it exits,
+/// like `capture_stderr` for the output ffmpeg and ffprobe print their results to.
+///
+/// # Arguments
+/// * `child` - The process, started with its standard output piped.
+fn capture_stdout(child: &mut Child) -> Option<JoinHandle<Vec<u8>>> {
+    child.stdout.take().map(read_on_thread)
+}
+
+/// Reads `pipe` to its end on a separate thread.
+///
+/// # Arguments
+/// * `pipe` - Output pipe of a child process.
+fn read_on_thread(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        output
+    })
+}
+
+/// Output captured by `capture_stdout` or `capture_stderr`, empty if there is none.
+///
+/// # Arguments
+/// * `handle` - Thread reading the output.
+fn join_output(handle: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    handle
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default()
+}
+
+/// Error message of a failed ffmpeg run: `message` followed by the last
+/// `FFMPEG_ERROR_LINES` lines ffmpeg printed, which usually name the cause.
+///
+/// # Arguments
+/// * `message` - What failed.
+/// * `stderr` - Error output captured by `capture_stderr`, if any.
+fn failure_message(message: &str, stderr: Option<JoinHandle<Vec<u8>>>) -> String {
+    let output = join_output(stderr);
+    let output = String::from_utf8_lossy(&output);
+    let lines: Vec<&str> = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.is_empty() {
+        return message.to_string();
+    }
+    format!(
+        "{message} ffmpeg output:\n{}",
+        lines[lines.len().saturating_sub(FFMPEG_ERROR_LINES)..].join("\n")
+    )
+}
+
+/// Kills `child` and waits for it, so it does not linger as a zombie.
+/// Errors are ignored since the process may already have exited.
+///
+/// # Arguments
+/// * `child` - The ffmpeg process to stop.
+fn kill_child(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Checks the encoder selected by `encoder` is built into the ffmpeg at `executable`.
+/// Hardware encoders are only part of some ffmpeg builds,
+/// so they are checked before any frames are generated.
+///
+/// # Arguments
+/// * `executable` - Path to the ffmpeg executable.
+/// * `encoder` - Encoder settings to check.
+/// * `timeout` - Longest ffmpeg may run, unlimited if `None`.
+fn check_encoder(
+    executable: &Path,
+    encoder: EncoderOptions,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let name = encoder_name(encoder)?;
+    let Some(hwaccel) = encoder.hwaccel else {
+        return Ok(());
+    };
+    let mut child = Command::new(executable)
+        .args(["-hide_banner", "-encoders"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Unable to run ffmpeg at {}.", executable.display()))?;
+    let stdout = capture_stdout(&mut child);
+    let status = wait_for_exit(&mut child, Instant::now(), timeout)?;
+    // Every encoder is listed as a line of capability flags followed by its name.
+    let available = status.success()
+        && String::from_utf8_lossy(&join_output(stdout))
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(name));
+    if !available {
+        bail!(
+            "ffmpeg at {} has no {name} encoder, it was built without {} support. Drop --hwaccel to encode in software.",
+            executable.display(),
+            hwaccel.name()
+        );
+    }
+    Ok(())
+}
+
+/// Checks the executable at `executable` runs `-version` successfully,
+/// so a broken installation is reported before any frames are generated.
+///
+/// # Arguments
+/// * `executable` - Path to the ffmpeg executable.
+/// * `timeout` - Longest ffmpeg may run, unlimited if `None`.
+fn check_version(executable: &Path, timeout: Option<Duration>) -> Result<()> {
+    let mut child = Command::new(executable)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Unable to run ffmpeg at {}.", executable.display()))?;
+    let status = wait_for_exit(&mut child, Instant::now(), timeout)?;
+    if !status.success() {
+        bail!(
+            "ffmpeg at {} failed to run -version. Check the installation or pass --ffmpeg-path.",
+            executable.display()
+        );
+    }
+    Ok(())
+}
+
+/// Prefixes `path` with the `file:` protocol, so ffmpeg does not mistake
+/// a file name containing a colon for a protocol or one starting with `-` for an option.
+///
+/// # Arguments
+/// * `path` - Path of a video file read or written by ffmpeg.
+fn file_url(path: &Path) -> Result<String> {
+    Ok(format!("file:{}", path_to_str(path)?))
+}
+
+/// ffmpeg filter undoing `rotation`, applied before scaling the frames.
+///
+/// # Arguments
+/// * `rotation` - Clockwise rotation the video underwent.
+fn rotation_filter(rotation: Rotation) -> Option<&'static str> {
+    match rotation {
+        Rotation::None => None,
+        // transpose=2 rotates counterclockwise, transpose=1 clockwise.
+        Rotation::Clockwise90 => Some("transpose=2"),
+        Rotation::Clockwise180 => Some("hflip,vflip"),
+        Rotation::Clockwise270 => Some("transpose=1"),
+    }
+}
+
+/// Name of the ffmpeg encoder for the codec and hardware acceleration of `encoder`.
+///
+/// # Arguments
+/// * `encoder` - Codec and hardware acceleration of the encoder.
+fn encoder_name(encoder: EncoderOptions) -> Result<&'static str> {
+    let name = match (encoder.hwaccel, encoder.codec) {
+        (None, VideoCodec::H264) => "libx264",
+        (None, VideoCodec::Vp9) => "libvpx-vp9",
+        (None, VideoCodec::Av1) => "libaom-av1",
+        (Some(HwAccel::Nvenc), VideoCodec::H264) => "h264_nvenc",
+        (Some(HwAccel::Nvenc), VideoCodec::Vp9) => {
+            bail!("NVENC can not encode VP9, use h264 or av1 or drop --hwaccel.")
+        }
+        (Some(HwAccel::Nvenc), VideoCodec::Av1) => "av1_nvenc",
+        (Some(HwAccel::Vaapi), VideoCodec::H264) => "h264_vaapi",
+        (Some(HwAccel::Vaapi), VideoCodec::Vp9) => "vp9_vaapi",
+        (Some(HwAccel::Vaapi), VideoCodec::Av1) => "av1_vaapi",
+        (Some(HwAccel::Qsv), VideoCodec::H264) => "h264_qsv",
+        (Some(HwAccel::Qsv), VideoCodec::Vp9) => "vp9_qsv",
+        (Some(HwAccel::Qsv), VideoCodec::Av1) => "av1_qsv",
+    };
+    Ok(name)
+}
+
+/// Name of the NVENC preset closest to `preset`, from p1 for the fastest to p7 for the slowest.
+///
+/// # Arguments
+/// * `preset` - Requested encoder preset.
+fn nvenc_preset(preset: EncoderPreset) -> &'static str {
+    match preset {
+        EncoderPreset::Ultrafast | EncoderPreset::Superfast => "p1",
+        EncoderPreset::Veryfast => "p2",
+        EncoderPreset::Faster => "p3",
+        EncoderPreset::Fast | EncoderPreset::Medium => "p4",
+        EncoderPreset::Slow => "p5",
+        EncoderPreset::Slower => "p6",
+        EncoderPreset::Veryslow => "p7",
+    }
+}
+
+/// Codec specific encoder options.
+///
+/// # Arguments
+/// * `encoder` - Codec, CRF, preset and hardware acceleration of the encoder.
+/// * `output_file` - Path the video is written to, its extension selects the container.
+fn codec_args(encoder: EncoderOptions, output_file: &Path) -> Result<Vec<String>> {
+    if encoder.codec == VideoCodec::H264
+        && output_file
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("webm"))
+    {
+        bail!("WebM only holds VP9 and AV1 videos, use an .mp4 or .mkv file for H.264.");
+    }
+    let crf = format!("{}", encoder.crf);
+    let mut args = vec!["-c:v".to_string(), encoder_name(encoder)?.to_string()];
+    match (encoder.hwaccel, encoder.codec) {
+        (None, VideoCodec::H264) => args.extend([
+            "-preset".to_string(),
+            encoder.preset.name().to_string(),
+            "-crf".to_string(),
+            crf,
+        ]),
+        // A bitrate of zero makes the CRF the only quality target.
+        (None, VideoCodec::Vp9) => args.extend([
+            "-crf".to_string(),
+            crf,
+            "-b:v".to_string(),
+            "0".to_string(),
+            "-deadline".to_string(),
+            "good".to_string(),
+            // libvpx only accepts speeds up to 5 for the good deadline.
+            "-cpu-used".to_string(),
+            format!("{}", encoder.preset.speed().min(5)),
+            "-row-mt".to_string(),
+            "1".to_string(),
+        ]),
+        (None, VideoCodec::Av1) => args.extend([
+            "-crf".to_string(),
+            crf,
+            "-b:v".to_string(),
+            "0".to_string(),
+            "-cpu-used".to_string(),
+            format!("{}", encoder.preset.speed()),
+            "-row-mt".to_string(),
+            "1".to_string(),
+        ]),
+        // Hardware encoders have no CRF, their constant quality modes are used instead.
+        (Some(HwAccel::Nvenc), _) => args.extend([
+            "-preset".to_string(),
+            nvenc_preset(encoder.preset).to_string(),
+            "-rc".to_string(),
+            "vbr".to_string(),
+            "-cq".to_string(),
+            crf,
+            "-b:v".to_string(),
+            "0".to_string(),
+        ]),
+        (Some(HwAccel::Vaapi), _) => args.extend([
+            "-rc_mode".to_string(),
+            "CQP".to_string(),
+            "-qp".to_string(),
+            crf,
+        ]),
+        (Some(HwAccel::Qsv), _) => args.extend([
+            // Quick Sync has no presets faster than veryfast.
+            "-preset".to_string(),
+            match encoder.preset {
+                EncoderPreset::Ultrafast | EncoderPreset::Superfast => "veryfast",
+                preset => preset.name(),
+            }
+            .to_string(),
+            "-global_quality".to_string(),
+            crf,
+        ]),
+    }
+    if encoder.codec == VideoCodec::H264 {
+        args.extend(["-profile:v".to_string(), "high".to_string()]);
+    }
+    Ok(args)
+}
+
+/// Checks the encoder and container keep the alpha channel of the frames.
+/// Only VP9 encoded by libvpx stores alpha, as an extra plane in `.webm` and `.mkv` files.
+/// Everything else silently discards it, losing the data it holds.
+///
+/// # Arguments
+/// * `encoder` - Codec and hardware acceleration of the encoder.
+/// * `output_file` - Path the video is written to, its extension selects the container.
+fn check_alpha_support(encoder: EncoderOptions, output_file: &Path) -> Result<()> {
+    if encoder.codec != VideoCodec::Vp9 || encoder.hwaccel.is_some() {
+        bail!(
+            "Only VP9 encoded in software keeps the alpha channel, use --codec vp9 without --hwaccel."
+        );
+    }
+    let keeps_alpha = output_file.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("webm") || extension.eq_ignore_ascii_case("mkv")
+    });
+    if !keeps_alpha {
+        bail!("Only WebM and Matroska store the alpha channel of VP9, use a .webm or .mkv file.");
+    }
+    Ok(())
+}
+
+/// Output options shared by both ways of encoding a video.
+///
+/// # Arguments
+/// * `settings` - Framerates and output resolution.
+/// * `encoder` - Codec, CRF, preset and upscaling algorithm of the encoder.
+/// * `output_file` - Path the video is written to.
+fn encode_output_args(
+    settings: &EncodeSettings,
+    encoder: EncoderOptions,
+    output_file: &Path,
+) -> Result<Vec<String>> {
+    // Encoding parameters choosed as per youtube reccomendation:
+    // https://support.google.com/youtube/answer/1722171
+    // - mp4 Containter
+    // - H.264, VP9 and AV1 if selected
+    // - Profile: High
+    // - CABAC enabled
+    // - bt709 colorspace, unless another one is selected
+    // - Chroma subsampling: 4:2:0
+    // Hardware encoders take NV12 frames, VA-API also needs them uploaded to the GPU.
+    // Data in the alpha channel needs the 4:2:0 format with a full resolution alpha plane.
+    if settings.alpha {
+        check_alpha_support(encoder, output_file)?;
+    }
+    let pixel_format = match encoder.hwaccel {
+        None if settings.alpha => "format=yuva420p",
+        None | Some(HwAccel::Nvenc) => "format=yuv420p",
+        Some(HwAccel::Vaapi) => "format=nv12,hwupload",
+        Some(HwAccel::Qsv) => "format=nv12",
+    };
+    let mut args = Vec::new();
+    if encoder.hwaccel == Some(HwAccel::Vaapi) {
+        // Global option, applies regardless of its position.
+        args.extend(["-vaapi_device".to_string(), VAAPI_DEVICE.to_string()]);
+    }
+    // The scale filter converts the RGB frames to YUV with the same matrix and range
+    // the video is tagged with, so decoders converting back by the tags restore the levels.
+    let color = encoder.color;
+    args.extend([
+        "-vf".to_string(),
+        format!(
+            "scale={}:{}:flags={}:out_color_matrix={}:out_range={},{pixel_format}",
+            settings.frame_width,
+            settings.frame_height,
+            encoder.scale_algorithm,
+            color.colorspace.scale_matrix(),
+            color.range.name()
+        ),
+    ]);
+    args.extend(codec_args(encoder, output_file)?);
+    args.extend([
+        "-colorspace:v".to_string(),
+        color.colorspace.matrix().to_string(),
+        "-color_primaries:v".to_string(),
+        color.colorspace.primaries().to_string(),
+        "-color_trc:v".to_string(),
+        color.colorspace.transfer().to_string(),
+        "-color_range:v".to_string(),
+        color.range.name().to_string(),
+        "-r".to_string(),
+        format!("{}", settings.video_fps),
+        // Leave out the encoder version, creation time and copied input metadata,
+        // so encoding the same data with the same settings gives identical files.
+        "-fflags".to_string(),
+        "+bitexact".to_string(),
+        "-flags:v".to_string(),
+        "+bitexact".to_string(),
+        "-map_metadata".to_string(),
+        "-1".to_string(),
+        // Refuse to replace an existing file unless overwriting was requested.
+        if settings.overwrite { "-y" } else { "-n" }.to_string(),
+        file_url(output_file)?,
+    ]);
+    Ok(args)
+}
+
+#[derive(Debug)]
+/// Streams raw RGB or RGBA frames to a running ffmpeg process.
+struct FfmpegFrameWriter {
+    /// The ffmpeg process encoding the video.
+    child: Child,
+    /// Standard input of `child`, `None` once closed.
+    stdin: Option<ChildStdin>,
+    /// When `child` was started.
+    started: Instant,
+    /// Longest `child` may run, unlimited if `None`.
+    timeout: Option<Duration>,
+    /// Error output of `child`, see `capture_stderr`.
+    stderr: Option<JoinHandle<Vec<u8>>>,
+}
+
+impl FrameWriter for FfmpegFrameWriter {
+    fn write_frame(&mut self, frame: &DynamicImage) -> Result<()> {
+        check_timeout(&mut self.child, self.started, self.timeout)?;
+        let stdin = self
+            .stdin
+            .as_mut()
+            .context("ffmpeg input already closed.")?;
+        if let Err(err) = stdin.write_all(frame.as_bytes()) {
+            // ffmpeg closes its input when it fails, its output says why.
+            drop(self.stdin.take());
+            kill_child(&mut self.child);
+            return Err(err).context(failure_message(
+                "Unable to write frame to ffmpeg, it exited early.",
+                self.stderr.take(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        // Closing stdin signals the end of the video.
+        drop(self.stdin.take());
+        let status = wait_for_exit(&mut self.child, self.started, self.timeout)?;
+        if !status.success() {
+            bail!(failure_message(
+                &format!("ffmpeg returned nonzero exit status ({status})."),
+                self.stderr.take()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FfmpegFrameWriter {
+    fn drop(&mut self) {
+        // Stop an encode that was abandoned before `finish`,
+        // instead of leaving ffmpeg writing to a file about to be discarded.
+        if self.stdin.take().is_some() {
+            kill_child(&mut self.child);
+        }
+    }
+}
+
+/// Reads a combine frame for `VideoBackend::encode`, in the pixel layout
+/// `FfmpegFrameWriter` passes on to ffmpeg.
+///
+/// # Arguments
+/// * `path` - Path of the frame.
+/// * `settings` - Data frame resolution and if frames carry an alpha channel.
+fn read_frame(path: &Path, settings: &EncodeSettings) -> Result<DynamicImage> {
+    let frame =
+        image::open(path).with_context(|| format!("Unable to read frame {}.", path.display()))?;
+    if (frame.width(), frame.height()) != (settings.data_width, settings.data_height) {
+        bail!(
+            "Frame {} is {}x{}, expected the data resolution {}x{}.",
+            path.display(),
+            frame.width(),
+            frame.height(),
+            settings.data_width,
+            settings.data_height
+        );
+    }
+    Ok(if settings.alpha {
+        DynamicImage::ImageRgba8(frame.into_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(frame.into_rgb8())
+    })
+}
+
+#[derive(Debug, Deserialize)]
+/// Fields read from the JSON output of ffprobe.
+struct FfprobeOutput {
+    /// Selected video streams, empty if the file has none.
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    /// Container level properties.
+    format: Option<FfprobeFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Video stream properties reported by ffprobe.
+/// ffprobe reports rates and counts as strings.
+struct FfprobeStream {
+    /// Width in pixels.
+    width: Option<u32>,
+    /// Height in pixels.
+    height: Option<u32>,
+    /// Framerate as a fraction, e.g. `30000/1001`.
+    r_frame_rate: Option<String>,
+    /// Number of frames, missing for containers that do not store it.
+    nb_frames: Option<String>,
+    /// YUV matrix the stream is tagged with, e.g. `bt709`.
+    color_space: Option<String>,
+    /// Color range the stream is tagged with, `tv` or `pc`.
+    color_range: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Container properties reported by ffprobe.
+struct FfprobeFormat {
+    /// Duration in seconds.
+    duration: Option<String>,
+}
+
+/// Parses a fraction such as `30000/1001` as printed by ffprobe.
+/// Returns `None` for malformed input and the `0/0` ffprobe prints for unknown rates.
+///
+/// # Arguments
+/// * `rational` - Fraction to parse.
+fn parse_rational(rational: &str) -> Option<f64> {
+    let (numerator, denominator) = rational.split_once('/')?;
+    let numerator: f64 = numerator.trim().parse().ok()?;
+    let denominator: f64 = denominator.trim().parse().ok()?;
+    (numerator > 0.0 && denominator > 0.0).then(|| numerator / denominator)
+}
+
+/// Reads the properties of the first video stream from the JSON output of ffprobe.
+/// The frame count is estimated from the duration if the container does not store it.
+///
+/// # Arguments
+/// * `output` - JSON printed by ffprobe.
+/// * `input_file` - Probed video, named in errors.
+fn parse_probe(output: &[u8], input_file: &Path) -> Result<VideoProbe> {
+    let probe: FfprobeOutput =
+        serde_json::from_slice(output).context("Unable to parse ffprobe output.")?;
+    let Some(stream) = probe.streams.first() else {
+        bail!("{} contains no video stream.", input_file.display());
+    };
+    let (Some(width), Some(height)) = (stream.width, stream.height) else {
+        bail!("Unable to read video resolution from ffprobe output.");
+    };
+    let fps = stream.r_frame_rate.as_deref().and_then(parse_rational);
+    // Matroska and WebM do not store a frame count, estimate it from the duration instead.
+    let duration = probe
+        .format
+        .and_then(|format| format.duration)
+        .and_then(|duration| duration.parse::<f64>().ok());
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let frame_count = stream
+        .nb_frames
+        .as_deref()
+        .and_then(|frames| frames.parse().ok())
+        .or_else(|| Some((duration? * fps?).round() as u64));
+    Ok(VideoProbe {
+        width,
+        height,
+        fps,
+        frame_count,
+        colorspace: stream
+            .color_space
+            .as_deref()
+            .and_then(Colorspace::from_matrix),
+        color_range: stream.color_range.as_deref().and_then(ColorRange::from_tag),
+    })
+}
+
+impl VideoBackend for FfmpegBackend {
+    fn encode(
+        &self,
+        settings: &EncodeSettings,
+        frame_buffer: &FrameBuffer,
+        output_file: &Path,
+    ) -> Result<()> {
+        // An image2 sequence is read from a single directory, but the frames are spread
+        // over several, and ffmpeg builds for Windows lack glob support.
+        // The frames are streamed to ffmpeg instead, read in parallel a batch at a time.
+        let frame_paths = frame_buffer.list_combine_frames()?;
+        let mut writer = self.encode_stream(settings, output_file)?;
+        for batch in frame_paths.chunks(rayon::current_num_threads()) {
+            let frames = batch
+                .par_iter()
+                .map(|path| read_frame(path, settings))
+                .collect::<Result<Vec<_>>>()?;
+            for frame in &frames {
+                writer.write_frame(frame)?;
+            }
+        }
+        writer.finish()
+    }
+
+    fn encode_stream(
+        &self,
+        settings: &EncodeSettings,
+        output_file: &Path,
+    ) -> Result<Box<dyn FrameWriter>> {
+        check_encoder(&self.executable, self.encoder, self.timeout)?;
+        let data_fps = format!("{}", settings.data_fps);
+        let frame_size = format!("{}x{}", settings.data_width, settings.data_height);
+        let mut args = vec![
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            if settings.alpha { "rgba" } else { "rgb24" },
+            "-s",
+            &frame_size,
+            "-framerate",
+            &data_fps,
+            "-i",
+            "pipe:0",
+        ];
+        let output_args = encode_output_args(settings, self.encoder, output_file)?;
+        args.extend(output_args.iter().map(String::as_str));
+        let mut child = Command::new(&self.executable)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Unable to run ffmpeg at {}.", self.executable.display()))?;
+        let Some(stdin) = child.stdin.take() else {
+            kill_child(&mut child);
+            bail!("Unable to open ffmpeg stdin.");
+        };
+        let stderr = capture_stderr(&mut child);
+        Ok(Box::new(FfmpegFrameWriter {
+            child,
+            stdin: Some(stdin),
+            started: Instant::now(),
+            timeout: self.timeout,
+            stderr,
+        }))
+    }
+
+    fn split(
+        &self,
+        settings: &SplitSettings,
+        input_file: &Path,
+        frame_buffer: &FrameBuffer,
+    ) -> Result<()> {
+        let mut args = vec!["-hide_banner", "-loglevel", "error"];
+        // Given as input options, so ffmpeg seeks in the input
+        // instead of decoding and discarding everything before the window.
+        if let Some(seek) = &settings.window.seek {
+            args.extend(["-ss", seek]);
+        }
+        if let Some(duration) = &settings.window.duration {
+            args.extend(["-t", duration]);
+        }
+        let input_url = file_url(input_file)?;
+        // Overrides the tags of videos whose colors were stripped or changed.
+        let color_options = settings
+            .color
+            .map(|color| {
+                format!(
+                    ":in_color_matrix={}:in_range={}",
+                    color.colorspace.scale_matrix(),
+                    color.range.name()
+                )
+            })
+            .unwrap_or_default();
+        let scale_filter = format!(
+            "scale={}:{}:flags={}{color_options}",
+            settings.split_width, settings.split_height, settings.scale_algorithm,
+        );
+        let filter = match rotation_filter(settings.rotation) {
+            Some(rotation_filter) => format!("{rotation_filter},{scale_filter}"),
+            None => scale_filter,
+        };
+        let data_fps = format!("{}", settings.data_fps);
+        if settings.alpha {
+            // The native VP9 decoder of ffmpeg drops the alpha plane, libvpx keeps it.
+            args.extend(["-c:v", "libvpx-vp9"]);
+        }
+        args.extend(["-i", &input_url, "-vf", &filter, "-r", &data_fps]);
+        if settings.alpha {
+            args.extend(["-pix_fmt", "rgba"]);
+        }
+        let frame_pattern = frame_buffer.frame_path_pattern_split();
+        args.push(path_to_str(&frame_pattern)?);
+        let mut child = Command::new(&self.executable)
+            .args(&args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Unable to run ffmpeg at {}.", self.executable.display()))?;
+        let stderr = capture_stderr(&mut child);
+        // An image2 sequence is written to a single directory, so the frames are moved
+        // into the subdirectories of the framebuffer as ffmpeg finishes them.
+        let mut next_frame = 1;
+        let status = wait_for_exit_with(&mut child, Instant::now(), self.timeout, || {
+            next_frame = frame_buffer.shard_split_frames(next_frame, false)?;
+            Ok(())
+        })?;
+        if !status.success() {
+            bail!(failure_message(
+                &format!("ffmpeg returned nonzero exit status ({status})."),
+                stderr
+            ));
+        }
+        frame_buffer.shard_split_frames(next_frame, true)?;
+        Ok(())
+    }
+
+    fn recompress(&self, input_file: &Path, output_file: &Path, bitrate_kbps: u32) -> Result<()> {
+        let bitrate = format!("{bitrate_kbps}k");
+        // A buffer of two seconds, as platforms cap the rate over short windows.
+        let buffer_size = format!("{}k", 2 * bitrate_kbps);
+        // Platforms deliver H.264 to every client, independent of the uploaded codec.
+        self.run(&[
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-i",
+            &file_url(input_file)?,
+            "-c:v",
+            "libx264",
+            "-preset",
+            EncoderPreset::Medium.name(),
+            "-b:v",
+            &bitrate,
+            "-maxrate",
+            &bitrate,
+            "-bufsize",
+            &buffer_size,
+            "-pix_fmt",
+            "yuv420p",
+            "-an",
+            "-y",
+            &file_url(output_file)?,
+        ])
+    }
+
+    fn probe(&self, input_file: &Path) -> Result<Option<VideoProbe>> {
+        let Some(ffprobe_executable) = &self.ffprobe_executable else {
+            return Ok(None);
+        };
+        let mut child = Command::new(ffprobe_executable)
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=width,height,r_frame_rate,nb_frames,color_space,color_range:format=duration",
+                "-of",
+                "json",
+                &file_url(input_file)?,
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| {
+                format!("Unable to run ffprobe at {}.", ffprobe_executable.display())
+            })?;
+        let stdout = capture_stdout(&mut child);
+        let status = wait_for_exit(&mut child, Instant::now(), self.timeout)?;
+        if !status.success() {
+            bail!("ffprobe returned nonzero exit status.");
+        }
+        parse_probe(&join_output(stdout), input_file).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `json` as if ffprobe printed it for `video.mp4`.
+    fn parse(json: &str) -> Result<VideoProbe> {
+        parse_probe(json.as_bytes(), Path::new("video.mp4"))
+    }
+
+    #[test]
+    fn rationals_are_parsed() {
+        assert_eq!(parse_rational("30/1"), Some(30.0));
+        assert_eq!(parse_rational("30000/1001"), Some(30_000.0 / 1001.0));
+        assert_eq!(parse_rational(" 25 / 1 "), Some(25.0));
+        // ffprobe prints 0/0 for unknown rates.
+        assert_eq!(parse_rational("0/0"), None);
+        assert_eq!(parse_rational("30"), None);
+        assert_eq!(parse_rational("a/1"), None);
+    }
+
+    #[test]
+    fn stored_frame_count_is_read() -> Result<()> {
+        let probe = parse(
+            r#"{"streams": [{"width": 1920, "height": 1080, "r_frame_rate": "30/1", "nb_frames": "450",
+                             "color_space": "bt470bg", "color_range": "pc"}],
+                "format": {"duration": "15.000000"}}"#,
+        )?;
+        assert_eq!(
+            probe,
+            VideoProbe {
+                width: 1920,
+                height: 1080,
+                fps: Some(30.0),
+                frame_count: Some(450),
+                colorspace: Some(Colorspace::Bt601),
+                color_range: Some(ColorRange::Pc),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_frame_count_is_estimated_from_the_duration() -> Result<()> {
+        // Matroska and WebM do not store nb_frames.
+        let probe = parse(
+            r#"{"streams": [{"width": 1280, "height": 720, "r_frame_rate": "30000/1001"}],
+                "format": {"duration": "10.010000"}}"#,
+        )?;
+        assert_eq!(probe.frame_count, Some(300));
+        // Untagged videos leave the colorspace to the caller.
+        assert_eq!(probe.colorspace, None);
+        assert_eq!(probe.color_range, None);
+        // Without a known framerate there is nothing to estimate from.
+        let unknown_rate = parse(
+            r#"{"streams": [{"width": 1280, "height": 720, "r_frame_rate": "0/0"}],
+                "format": {"duration": "10.0"}}"#,
+        )?;
+        assert_eq!(unknown_rate.fps, None);
+        assert_eq!(unknown_rate.frame_count, None);
+        Ok(())
+    }
+
+    #[test]
+    fn files_without_a_video_stream_are_rejected() {
+        let err = parse(r#"{"streams": [], "format": {"duration": "3.0"}}"#).unwrap_err();
+        assert!(err.to_string().contains("no video stream"), "{err}");
+        assert!(parse(r#"{"format": {}}"#).is_err());
+        assert!(parse(r#"{"streams": [{"r_frame_rate": "30/1"}]}"#).is_err());
+        assert!(parse("not json").is_err());
+    }
+}
@@ -10,53 +10,629 @@
 //! File system access.
 
 use std::{
-    env, fs,
+    env,
+    ffi::OsString,
+    fs, io,
+    ops::Deref,
     path::{Path, PathBuf},
+    process,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use glob::{Pattern, glob};
+use memmap2::Mmap;
 
-use crate::constants::FRAME_DIR;
+use crate::{
+    constants::{FRAME_DIR, FRAMES_PER_SHARD, WORKDIR_ENV},
+    utils::generate_unique_timestamp_dir,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// Image format of the frames kept in a `FrameBuffer`.
+/// The frames only live until ffmpeg or the decoder has read them,
+/// so the uncompressed formats trade disk space for less time spent compressing.
+pub enum FrameFormat {
+    #[default]
+    /// PNG, compressed and the only format keeping the alpha channel.
+    Png,
+    /// Uncompressed BMP.
+    Bmp,
+    /// Uncompressed binary PPM, the fastest to write and read.
+    Ppm,
+}
+
+impl FrameFormat {
+    #[must_use]
+    /// File extension of frames in this format, also selecting it for ffmpeg and `image`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Png => "png",
+            FrameFormat::Bmp => "bmp",
+            FrameFormat::Ppm => "ppm",
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Directory where frames are stored before being combined into a video file
+/// or after being extracted from a video file.
+/// Frames are spread over subdirectories of `FRAMES_PER_SHARD` frames each.
+/// Only a handle, see `FrameBufferGuard` for directories deleted after the run.
+pub struct FrameBuffer {
+    /// Path of the directory.
+    dir: PathBuf,
+    /// Image format the frames are saved in.
+    format: FrameFormat,
+}
+
+#[derive(Debug)]
+/// Framebuffer used by a single run, whose directory is deleted when the guard is dropped,
+/// including when the run fails or is interrupted, unless it is kept.
+/// Dereferences to the `FrameBuffer` it guards.
+pub struct FrameBufferGuard {
+    /// The guarded framebuffer.
+    frame_buffer: FrameBuffer,
+    /// If the directory is left in place for inspection or later runs.
+    keep: bool,
+}
+
+impl FrameBufferGuard {
+    /// Keeps the directory after the guard is dropped, once its frames are needed by a later run.
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+}
+
+impl Deref for FrameBufferGuard {
+    type Target = FrameBuffer;
 
-/// Gets the directory where frames are stored before
-/// being combined into a video file or after being
-/// extracted from a video file.
-/// Creates directory if it doesnt exit.
-pub fn get_framebuffer_folder() -> Result<PathBuf> {
-    let temp_dir = env::temp_dir();
-    let frame_dir = temp_dir.join(FRAME_DIR);
+    fn deref(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+}
 
-    if !frame_dir.exists() {
-        fs::create_dir(&frame_dir).context("Unable to crate frame directory.")?;
+impl Drop for FrameBufferGuard {
+    fn drop(&mut self) {
+        let dir = self.frame_buffer.path();
+        if self.keep {
+            eprintln!("Kept frames in {}.", dir.display());
+        } else if let Err(err) = fs::remove_dir_all(dir) {
+            // Dropping may happen while unwinding, so the error is reported instead of returned.
+            eprintln!("Unable to delete frame directory {}: {err}", dir.display());
+        }
     }
-    Ok(frame_dir)
 }
 
-/// Clears the directory specified by `get_framebuffer_folder()`
-/// by deleting and recreating it.
-pub fn clear_framebuffer_folder() -> Result<()> {
-    let frame_dir = get_framebuffer_folder()?;
-    // No existence check needed, `get_framebuffer_folder()` creates `frame_dir`
-    // if it doesnt exist.
-    fs::remove_dir_all(&frame_dir).context("Unable to delete frame directory.")?;
-    fs::create_dir(frame_dir).context("Unable to crate frame directory.")?;
-    Ok(())
+/// Creates a new framebuffer in `workdir` used by this run only,
+/// so concurrently running processes do not clear each other's frames.
+/// The directory is deleted when the returned guard is dropped, unless `keep` is set.
+///
+/// # Arguments
+/// * `workdir` - Existing directory the framebuffer is created in.
+/// * `format` - Image format of the frames.
+/// * `keep` - If the directory is kept after the run, see `--keep-frames`.
+///
+/// # Errors
+/// Fails if the directory can not be created.
+pub fn scoped_framebuffer(
+    workdir: &Path,
+    format: FrameFormat,
+    keep: bool,
+) -> Result<FrameBufferGuard> {
+    let base_dir = workdir.join(format!("{FRAME_DIR}_{}_", process::id()));
+    let dir = loop {
+        let dir = PathBuf::from(generate_unique_timestamp_dir(path_to_str(&base_dir)?));
+        // Another thread of this process may have created the same directory in the meantime.
+        match fs::create_dir(&dir) {
+            Ok(()) => break dir,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err).context("Unable to crate frame directory."),
+        }
+    };
+    Ok(FrameBufferGuard {
+        frame_buffer: FrameBuffer { dir, format },
+        keep,
+    })
+}
+
+impl FrameBuffer {
+    /// Opens an existing frame directory, such as one kept by split for a later reheader.
+    /// The directory is not deleted afterwards.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory holding the frames.
+    ///
+    /// # Errors
+    /// Fails if `dir` is not a directory.
+    pub fn open(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            bail!("Frame directory {} does not exist.", dir.display());
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            format: FrameFormat::Png,
+        })
+    }
+
+    #[must_use]
+    /// Saves the frames in `format` instead of PNG.
+    ///
+    /// # Arguments
+    /// * `format` - Image format of the frames.
+    pub fn with_format(mut self, format: FrameFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    #[must_use]
+    /// Path of the framebuffer directory.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Clears the framebuffer directory by deleting and recreating it.
+    ///
+    /// # Errors
+    /// Fails if the directory can not be deleted or created.
+    pub fn clear(&self) -> Result<()> {
+        fs::remove_dir_all(&self.dir).context("Unable to delete frame directory.")?;
+        fs::create_dir(&self.dir).context("Unable to crate frame directory.")?;
+        Ok(())
+    }
+
+    /// Returns a file path inside the framebuffer used to save a frame
+    /// of the video to be created, creating its subdirectory if needed.
+    ///
+    /// # Arguments
+    /// * `index` - Number of the frame in the video to be created.
+    ///
+    /// # Errors
+    /// Fails if the subdirectory can not be created.
+    pub fn frame_path_combine(&self, index: usize) -> Result<PathBuf> {
+        self.frame_path("combine", index)
+    }
+
+    /// Returns a file path inside the framebuffer used to save a frame
+    /// extracted from a video, creating its subdirectory if needed.
+    ///
+    /// # Arguments
+    /// * `index` - Number of the frame in the video, starting at zero.
+    ///
+    /// # Errors
+    /// Fails if the subdirectory can not be created.
+    pub fn frame_path_split(&self, index: usize) -> Result<PathBuf> {
+        self.frame_path("split", index)
+    }
+
+    #[must_use]
+    /// Returns a printf style path pattern in the framebuffer directory itself,
+    /// which ffmpeg writes split frames to, numbered from one.
+    /// `shard_split_frames` moves them to `frame_path_split` as they are finished.
+    pub fn frame_path_pattern_split(&self) -> PathBuf {
+        self.dir
+            .join(format!("split%09d.{}", self.format.extension()))
+    }
+
+    /// Moves the frames written to `frame_path_pattern_split` into their subdirectories,
+    /// starting with number `next`, and returns the number of the first frame not moved.
+    /// Frames are written in order, so a frame is finished once the next one exists.
+    /// Unless the writer `finished`, the last frame is left in place for that reason.
+    ///
+    /// # Arguments
+    /// * `next` - Number of the first frame not moved yet, one at the start.
+    /// * `finished` - If the writer exited and every frame present is complete.
+    ///
+    /// # Errors
+    /// Fails if a frame can not be moved.
+    pub fn shard_split_frames(&self, mut next: usize, finished: bool) -> Result<usize> {
+        let written_path = |number: usize| {
+            self.dir
+                .join(format!("split{number:09}.{}", self.format.extension()))
+        };
+        while written_path(next).exists() && (finished || written_path(next + 1).exists()) {
+            fs::rename(written_path(next), self.frame_path_split(next - 1)?)
+                .context("Unable to move split frame into its subdirectory.")?;
+            next += 1;
+        }
+        Ok(next)
+    }
+
+    /// Returns the path of frame `index` named with `prefix`,
+    /// inside the subdirectory holding its `FRAMES_PER_SHARD` frames.
+    /// Names and subdirectories are zero padded, so they sort in frame order.
+    ///
+    /// # Arguments
+    /// * `prefix` - Start of the file name, telling combine and split frames apart.
+    /// * `index` - Number of the frame.
+    fn frame_path(&self, prefix: &str, index: usize) -> Result<PathBuf> {
+        let shard_dir = self
+            .dir
+            .join(format!("shard{:0>8}", index / FRAMES_PER_SHARD));
+        fs::create_dir_all(&shard_dir).context("Unable to create frame subdirectory.")?;
+        Ok(shard_dir.join(format!("{prefix}{index:0>12}.{}", self.format.extension())))
+    }
+
+    /// Returns the paths of all split frames in the framebuffer
+    /// in the order they appear in the video.
+    /// Collecting them up front tells the decoder how many frames to expect.
+    ///
+    /// # Errors
+    /// Fails if the framebuffer path is not valid UTF-8 or can not be read.
+    pub fn list_split_frames(&self) -> Result<Vec<PathBuf>> {
+        list_frames(
+            &self
+                .escaped_dir()?
+                .join("shard*")
+                .join(format!("split*.{}", self.format.extension())),
+        )
+    }
+
+    /// Returns the paths of all combine frames in the framebuffer
+    /// in the order they appear in the video.
+    ///
+    /// # Errors
+    /// Fails if the framebuffer path is not valid UTF-8 or can not be read.
+    pub fn list_combine_frames(&self) -> Result<Vec<PathBuf>> {
+        list_frames(
+            &self
+                .escaped_dir()?
+                .join("shard*")
+                .join(format!("combine*.{}", self.format.extension())),
+        )
+    }
+
+    /// Returns the framebuffer directory with glob metacharacters escaped,
+    /// so a temp directory containing `*`, `?` or `[` is matched literally.
+    fn escaped_dir(&self) -> Result<PathBuf> {
+        Ok(PathBuf::from(Pattern::escape(path_to_str(&self.dir)?)))
+    }
 }
 
-/// Returns a file path inside the framebuffer folder used to save a frame.
+#[must_use]
+/// Returns a file path inside `dump_dir` used to save a decoded frame for inspection.
 ///
 /// # Arguments
-/// * `index` - Number of the frame in the video to be created.
-pub fn frame_path_combine(index: usize) -> Result<PathBuf> {
-    Ok(get_framebuffer_folder()?.join(format!("combine{index:0>12}.png")))
+/// * `dump_dir` - Directory where dumped frames are saved.
+/// * `index` - Number of the frame in the decoded video.
+pub fn frame_path_dump(dump_dir: &Path, index: usize) -> PathBuf {
+    dump_dir.join(format!("dump{index:0>12}.png"))
+}
+
+#[derive(Debug)]
+/// Contents of an input file, either mapped into memory or read into a buffer.
+pub enum InputData {
+    /// File mapped into memory, its pages are read from disk as they are accessed.
+    Mapped(Mmap),
+    /// File read completely into memory.
+    Read(Vec<u8>),
+}
+
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            InputData::Mapped(map) => map,
+            InputData::Read(data) => data,
+        }
+    }
 }
 
-/// Returns glob wildcard over all split frames in the frambuffer directory.
-pub fn frame_path_wildcard_split() -> Result<PathBuf> {
-    Ok(get_framebuffer_folder()?.join(Path::new("split*.png")))
+/// Reads the file at `path`, mapping it into memory instead if `map` is set.
+/// Mapping skips copying the whole file up front, keeping large inputs out of the heap.
+/// Falls back to reading the file if it can not be mapped, e.g. on some network filesystems.
+///
+/// # Arguments
+/// * `path` - Path of the file to read.
+/// * `map` - If the file should be mapped into memory.
+///
+/// # Errors
+/// Fails if the file can not be opened or read.
+pub fn read_input_file(path: &Path, map: bool) -> Result<InputData> {
+    if map {
+        let file = fs::File::open(path)
+            .with_context(|| format!("Unable to open source file {}.", path.display()))?;
+        // SAFETY: The mapping is only ever read. Another process truncating the file while it
+        // is mapped makes these reads fault, which is why mapping is opt-in with `--mmap`.
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => return Ok(InputData::Mapped(map)),
+            Err(err) => eprintln!(
+                "Unable to map {} into memory ({err}), reading it instead.",
+                path.display()
+            ),
+        }
+    }
+    fs::read(path)
+        .map(InputData::Read)
+        .with_context(|| format!("Unable to read source file {}.", path.display()))
 }
 
-/// Returns glob wildcard over all combine frames in the frambuffer directory.
-pub fn frame_path_wildcard_combine() -> Result<PathBuf> {
-    Ok(get_framebuffer_folder()?.join(Path::new("combine*.png")))
+/// Returns all paths matching `wildcard` in frame order.
+///
+/// # Arguments
+/// * `wildcard` - Glob wildcard matching the frames, differing only in the frame index.
+fn list_frames(wildcard: &Path) -> Result<Vec<PathBuf>> {
+    let mut frame_paths = glob(&glob_path_string(wildcard)?)?
+        .collect::<Result<Vec<PathBuf>, _>>()
+        .context("Unable to read frame path.")?;
+    // Frame indices and subdirectories are zero padded to a minimum width,
+    // indices past that width have more digits. Sorting shorter paths first keeps
+    // frame order for those, paths of the same length sort lexically.
+    frame_paths.sort_by(|a, b| {
+        a.as_os_str()
+            .len()
+            .cmp(&b.as_os_str().len())
+            .then_with(|| a.cmp(b))
+    });
+    Ok(frame_paths)
+}
+
+/// Converts a glob wildcard path to the string passed to the `glob` crate.
+/// Uses forward slashes as separators on Windows, which the crate expects.
+///
+/// # Arguments
+/// * `wildcard` - Glob wildcard path.
+fn glob_path_string(wildcard: &Path) -> Result<String> {
+    let wildcard = path_to_str(wildcard)?;
+    #[cfg(windows)]
+    let wildcard = wildcard.replace('\\', "/");
+    #[cfg(not(windows))]
+    let wildcard = wildcard.to_string();
+    Ok(wildcard)
+}
+
+/// Converts `path` to a string for use in external command arguments or glob patterns.
+/// Fails instead of converting lossily, as a replaced character would
+/// silently point at a different file.
+///
+/// # Arguments
+/// * `path` - Path to convert.
+///
+/// # Errors
+/// Fails if `path` is not valid UTF-8.
+pub fn path_to_str(path: &Path) -> Result<&str> {
+    path.to_str().with_context(|| {
+        format!(
+            "Path {} is not valid UTF-8 and can not be passed on safely.",
+            path.display()
+        )
+    })
+}
+
+/// Returns the path a file is written to before being moved to `target`
+/// using `commit_partial_output()`.
+/// The partial file lives in the same directory as `target`, so the final rename
+/// is atomic, and keeps its file name as suffix so the extension is preserved.
+///
+/// # Arguments
+/// * `target` - Path where the finished file should end up.
+///
+/// # Errors
+/// Fails if `target` has no file name.
+pub fn partial_output_path(target: &Path) -> Result<PathBuf> {
+    let file_name = target
+        .file_name()
+        .context("Output path does not contain a file name.")?;
+    let mut partial_name = OsString::from(".vortexkey-partial-");
+    partial_name.push(file_name);
+    Ok(target.with_file_name(partial_name))
+}
+
+/// Atomically moves a finished partial file to its target path.
+///
+/// # Arguments
+/// * `partial` - Path of the completely written partial file.
+/// * `target` - Path where the file should end up.
+///
+/// # Errors
+/// Fails if the file can not be moved.
+pub fn commit_partial_output(partial: &Path, target: &Path) -> Result<()> {
+    fs::rename(partial, target).with_context(|| {
+        format!(
+            "Unable to move finished output from {} to {}.",
+            partial.display(),
+            target.display()
+        )
+    })
+}
+
+/// Removes a partial file left behind by a failed write.
+/// Errors are ignored since the partial file may never have been created.
+///
+/// # Arguments
+/// * `partial` - Path of the partial file to remove.
+pub fn discard_partial_output(partial: &Path) {
+    let _ = fs::remove_file(partial);
+}
+
+/// Writes `data` to `path` through a partial file,
+/// so an interrupted write never leaves a truncated file at `path`.
+///
+/// # Arguments
+/// * `path` - Path where the file will be stored.
+/// * `data` - Content to write.
+///
+/// # Errors
+/// Fails if the file can not be written or moved into place.
+pub fn write_output_file(path: &Path, data: &[u8]) -> Result<()> {
+    let partial_path = partial_output_path(path)?;
+    if let Err(err) = fs::write(&partial_path, data) {
+        discard_partial_output(&partial_path);
+        return Err(err).context("Unable to write output file.");
+    }
+    commit_partial_output(&partial_path, path)
+}
+
+#[must_use]
+/// Searches the directories listed in the `PATH` environment variable
+/// for an executable file called `name`, with `.exe` appended on Windows.
+///
+/// # Arguments
+/// * `name` - File name of the executable.
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path_var = env::var_os("PATH")?;
+    let file_name = format!("{name}{}", env::consts::EXE_SUFFIX);
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Resolves the work directory framebuffers are created in.
+/// In order of precedence uses `override_path`, the environment variable `WORKDIR_ENV`
+/// or the temp directory. Configured directories must exist and be writable,
+/// which is checked by creating and deleting a file in them.
+///
+/// # Arguments
+/// * `override_path` - Directory explicitly requested by the user.
+///
+/// # Errors
+/// Fails if a configured directory does not exist or can not be written to.
+pub fn resolve_workdir(override_path: Option<&Path>) -> Result<PathBuf> {
+    let Some(workdir) = override_path
+        .map(Path::to_path_buf)
+        .or_else(|| env::var_os(WORKDIR_ENV).map(PathBuf::from))
+    else {
+        return Ok(env::temp_dir());
+    };
+    if !workdir.is_dir() {
+        bail!(
+            "Configured work directory {} does not exist.",
+            workdir.display()
+        );
+    }
+    let probe_file = workdir.join(format!(".{FRAME_DIR}_{}", process::id()));
+    fs::write(&probe_file, []).with_context(|| {
+        format!(
+            "Configured work directory {} is not writable.",
+            workdir.display()
+        )
+    })?;
+    // Failing to remove the empty file does not keep the directory from being used.
+    let _ = fs::remove_file(&probe_file);
+    Ok(workdir)
+}
+
+/// Resolves the path of an external tool.
+/// In order of precedence uses `override_path`, the environment variable `env_var`,
+/// `default_path` if it exists or the first match on `PATH`.
+/// Explicitly configured paths are used as is, but must exist.
+///
+/// # Arguments
+/// * `name` - File name of the executable, used for the `PATH` search.
+/// * `override_path` - Path explicitly requested by the user.
+/// * `env_var` - Environment variable that may contain the path.
+/// * `default_path` - Path used if nothing was configured.
+///
+/// # Errors
+/// Fails if a configured path does not exist or the executable can not be found.
+pub fn resolve_executable(
+    name: &str,
+    override_path: Option<&Path>,
+    env_var: &str,
+    default_path: &str,
+) -> Result<PathBuf> {
+    let configured = override_path
+        .map(Path::to_path_buf)
+        .or_else(|| env::var_os(env_var).map(PathBuf::from));
+    if let Some(configured) = configured {
+        if !configured.is_file() {
+            bail!(
+                "Configured {name} executable {} does not exist.",
+                configured.display()
+            );
+        }
+        return Ok(configured);
+    }
+
+    let default_path = PathBuf::from(default_path);
+    if default_path.is_file() {
+        return Ok(default_path);
+    }
+    find_in_path(name).with_context(|| {
+        format!(
+            "{name} not found at {} or on PATH. Install it, pass --{name}-path or set {env_var}.",
+            default_path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn high_frame_indices_glob_in_order() -> Result<()> {
+        // Glob metacharacters in the work directory must be matched literally.
+        let workdir = env::temp_dir().join(format!("vortexkey_test_[glob]_{}", process::id()));
+        fs::create_dir_all(&workdir)?;
+        let frame_buffer = scoped_framebuffer(&workdir, FrameFormat::Png, false)?;
+        let indices = [
+            0,
+            9,
+            10,
+            999_999_999,
+            1_000_000_000,
+            999_999_999_999,
+            1_000_000_000_000,
+            12_345_678_901_234,
+        ];
+        // Written out of order, so directory order does not give the right result by chance.
+        for &index in indices.iter().rev() {
+            fs::write(frame_buffer.frame_path_combine(index)?, [])?;
+            fs::write(frame_buffer.frame_path_split(index)?, [])?;
+        }
+        let combine_frames = frame_buffer.list_combine_frames()?;
+        let expected = indices
+            .iter()
+            .map(|&index| frame_buffer.frame_path_combine(index))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(combine_frames, expected);
+        let split_frames = frame_buffer.list_split_frames()?;
+        let expected = indices
+            .iter()
+            .map(|&index| frame_buffer.frame_path_split(index))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(split_frames, expected);
+        drop(frame_buffer);
+        fs::remove_dir(workdir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn written_split_frames_are_moved_once_finished() -> Result<()> {
+        let frame_buffer = scoped_framebuffer(&env::temp_dir(), FrameFormat::Png, false)?;
+        let written_path =
+            |number: usize| frame_buffer.path().join(format!("split{number:09}.png"));
+        for number in 1..=3 {
+            fs::write(written_path(number), [])?;
+        }
+        // The last frame may still be being written.
+        assert_eq!(frame_buffer.shard_split_frames(1, false)?, 3);
+        assert!(written_path(3).exists());
+        assert_eq!(frame_buffer.shard_split_frames(3, true)?, 4);
+        let expected = (0..3)
+            .map(|index| frame_buffer.frame_path_split(index))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(frame_buffer.list_split_frames()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn frames_are_sharded_into_subdirectories() -> Result<()> {
+        let frame_buffer = scoped_framebuffer(&env::temp_dir(), FrameFormat::Png, false)?;
+        let shard = |index: usize| -> Result<PathBuf> {
+            Ok(frame_buffer
+                .frame_path_combine(index)?
+                .parent()
+                .context("Frame path has no directory.")?
+                .to_path_buf())
+        };
+        assert_eq!(shard(0)?, shard(FRAMES_PER_SHARD - 1)?);
+        assert_ne!(shard(FRAMES_PER_SHARD - 1)?, shard(FRAMES_PER_SHARD)?);
+        assert_eq!(shard(0)?.parent(), Some(frame_buffer.path()));
+        assert!(shard(FRAMES_PER_SHARD)?.is_dir());
+        Ok(())
+    }
 }
@@ -0,0 +1,531 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finder patterns locating the data grid in video filmed off a screen.
+//!
+//! Each corner of the data grid holds a QR code style finder pattern:
+//! a dark ring, a light ring and a dark 3x3 center, seven data units across.
+//! It is surrounded by a light ring of one data unit, separating it
+//! from the data and from whatever lies beyond the edge of the frame.
+//!
+//! Scanning a captured frame for the 1:1:3:1:1 dark and light runs crossing
+//! the center of a pattern finds all four corners, no matter the scale.
+//! The homography mapping the grid onto them undoes the perspective
+//! of the camera, so every data unit can be sampled where it ended up.
+
+use std::array;
+
+use anyhow::{Result, bail};
+use image::RgbImage;
+
+use crate::constants::COLOR_CHANNELS;
+
+/// Edge length of a finder pattern in data units.
+pub const FINDER_SIZE: u32 = 7;
+
+/// Edge length of the square of data units reserved in each corner of the grid,
+/// the finder pattern with the light ring around it.
+pub const FINDER_RESERVED: u32 = FINDER_SIZE + 2;
+
+/// Pixel value of every channel of dark finder pattern units.
+const DARK: u8 = 0;
+
+/// Pixel value of every channel of light finder pattern units.
+const LIGHT: u8 = u8::MAX;
+
+/// Pixel offsets within a data unit, as fractions of its edge length,
+/// sampled in both directions when reading a captured frame.
+/// Staying clear of the edges avoids the blur towards neighbouring units.
+const SAMPLE_OFFSETS: [f64; 3] = [0.35, 0.5, 0.65];
+
+/// Hits scanning rows through a finder pattern needed to accept it,
+/// so random data does not pass as a pattern by chance.
+const MIN_FINDER_HITS: usize = 2;
+
+#[must_use]
+/// Returns the pixel value of the finder pattern unit at (`x`, `y`) of the data grid,
+/// or `None` if the unit is not reserved for a finder pattern and holds data.
+///
+/// # Arguments
+/// * `x` - Column of the data unit.
+/// * `y` - Row of the data unit.
+/// * `grid` - (Width, Height) Number of data units in each frame.
+pub fn finder_unit(x: u32, y: u32, grid: [u32; 2]) -> Option<u8> {
+    // Distance from the closest edge of the frame, if within a reserved corner.
+    let corner_distance = |position: u32, size: u32| {
+        let distance = position.min(size - 1 - position);
+        (distance < FINDER_RESERVED).then_some(distance)
+    };
+    let distance_x = corner_distance(x, grid[0])?;
+    let distance_y = corner_distance(y, grid[1])?;
+    if [distance_x, distance_y].contains(&0)
+        || [distance_x, distance_y].contains(&(FINDER_SIZE + 1))
+    {
+        return Some(LIGHT);
+    }
+    // Ring of the pattern counted from its outside, the center counting as ring 2.
+    let (pattern_x, pattern_y) = (distance_x - 1, distance_y - 1);
+    let ring = pattern_x
+        .min(pattern_y)
+        .min(FINDER_SIZE - 1 - pattern_x)
+        .min(FINDER_SIZE - 1 - pattern_y);
+    Some(if ring == 1 { LIGHT } else { DARK })
+}
+
+#[must_use]
+/// Number of data units reserved for finder patterns in every frame.
+pub fn reserved_units() -> usize {
+    4 * (FINDER_RESERVED * FINDER_RESERVED) as usize
+}
+
+/// Locates the finder patterns in a captured frame and samples every data unit of the grid
+/// through the homography they define.
+/// Returns the sampled values of all units including the finder patterns,
+/// one byte per channel, in the order `average_image` returns them.
+///
+/// # Arguments
+/// * `img` - Captured frame at any resolution.
+/// * `grid` - (Width, Height) Number of data units in each frame.
+///
+/// # Errors
+/// Fails if the four finder patterns can not be found.
+pub fn sample_grid(img: &RgbImage, grid: [u32; 2]) -> Result<Vec<u8>> {
+    let [top_left, top_right, bottom_left, bottom_right] = locate_finders(img)?;
+    // Centers of the finder patterns in data units, measured from the top left of the grid.
+    let near = f64::from(FINDER_RESERVED) / 2.0;
+    let [width, height] = grid.map(f64::from);
+    let homography = Homography::from_points(
+        [
+            [near, near],
+            [width - near, near],
+            [near, height - near],
+            [width - near, height - near],
+        ],
+        [top_left, top_right, bottom_left, bottom_right],
+    )?;
+
+    let (img_width, img_height) = img.dimensions();
+    let mut output = Vec::with_capacity((grid[0] * grid[1]) as usize * COLOR_CHANNELS);
+    for y in 0..grid[1] {
+        for x in 0..grid[0] {
+            let mut sums = [0u32; COLOR_CHANNELS];
+            let mut samples = 0u32;
+            for offset_y in SAMPLE_OFFSETS {
+                for offset_x in SAMPLE_OFFSETS {
+                    let [img_x, img_y] =
+                        homography.map([f64::from(x) + offset_x, f64::from(y) + offset_y]);
+                    if !(0.0..f64::from(img_width)).contains(&img_x)
+                        || !(0.0..f64::from(img_height)).contains(&img_y)
+                    {
+                        continue;
+                    }
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let pixel = img.get_pixel(img_x as u32, img_y as u32);
+                    for (sum, &value) in sums.iter_mut().zip(&pixel.0) {
+                        *sum += u32::from(value);
+                    }
+                    samples += 1;
+                }
+            }
+            for sum in sums {
+                #[allow(clippy::cast_possible_truncation)]
+                output.push(sum.checked_div(samples).unwrap_or(0) as u8);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Finds the centers of the four finder patterns in a captured frame,
+/// in pixel coordinates. Order: top left, top right, bottom left, bottom right
+///
+/// # Arguments
+/// * `img` - Captured frame.
+fn locate_finders(img: &RgbImage) -> Result<[[f64; 2]; 4]> {
+    let (width, height) = img.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    let dark = dark_pixels(img);
+    let is_dark = |x: usize, y: usize| dark[y * width + x];
+
+    // Candidates found scanning rows, as (x, y, module size) in pixels.
+    let mut candidates = Vec::new();
+    for y in 0..height {
+        let runs = runs((0..width).map(|x| is_dark(x, y)));
+        for window in runs.windows(5) {
+            let lengths: [usize; 5] = array::from_fn(|run| window[run].1);
+            if !window[0].0.0 || !finder_ratio(lengths) {
+                continue;
+            }
+            let center_start = window[2].0.1;
+            let center_x = center_start + window[2].1 / 2;
+            let column = runs_through(|row| is_dark(center_x, row), height, y);
+            if let Some((center_y, vertical_total)) = column {
+                let horizontal_total: usize = lengths.iter().sum();
+                // The pattern is square, so both directions cross a similar width.
+                if vertical_total * 2 > horizontal_total && horizontal_total * 2 > vertical_total {
+                    #[allow(clippy::cast_precision_loss)]
+                    candidates.push([
+                        center_start as f64 + window[2].1 as f64 / 2.0,
+                        center_y,
+                        horizontal_total as f64 / f64::from(FINDER_SIZE),
+                    ]);
+                }
+            }
+        }
+    }
+
+    // Merges the hits of every row crossing the same pattern.
+    let mut clusters: Vec<([f64; 3], usize)> = Vec::new();
+    for candidate in candidates {
+        let found = clusters.iter_mut().find(|(center, hits)| {
+            #[allow(clippy::cast_precision_loss)]
+            let mean = center.map(|sum| sum / *hits as f64);
+            (mean[0] - candidate[0]).hypot(mean[1] - candidate[1]) < 2.0 * mean[2].max(1.0)
+        });
+        match found {
+            Some((center, hits)) => {
+                for (sum, value) in center.iter_mut().zip(candidate) {
+                    *sum += value;
+                }
+                *hits += 1;
+            }
+            None => clusters.push((candidate, 1)),
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let centers: Vec<[f64; 2]> = clusters
+        .into_iter()
+        .filter(|&(_, hits)| hits >= MIN_FINDER_HITS)
+        .map(|(center, hits)| [center[0] / hits as f64, center[1] / hits as f64])
+        .collect();
+
+    // The patterns sit in the outermost corners of the grid.
+    let extreme = |score: fn(&[f64; 2]) -> f64| {
+        (0..centers.len()).max_by(|&a, &b| score(&centers[a]).total_cmp(&score(&centers[b])))
+    };
+    let corners = [
+        extreme(|[x, y]| -x - y),
+        extreme(|[x, y]| x - y),
+        extreme(|[x, y]| y - x),
+        extreme(|[x, y]| x + y),
+    ];
+    let [
+        Some(top_left),
+        Some(top_right),
+        Some(bottom_left),
+        Some(bottom_right),
+    ] = corners
+    else {
+        bail!("Unable to locate the finder patterns in the frame.");
+    };
+    let corners = [top_left, top_right, bottom_left, bottom_right];
+    if (0..4).any(|a| (a + 1..4).any(|b| corners[a] == corners[b])) {
+        bail!(
+            "Found only {} finder patterns in the frame, all four corners must be visible.",
+            centers.len()
+        );
+    }
+    Ok(corners.map(|corner| centers[corner]))
+}
+
+/// Classifies every pixel of a frame as dark or light by its luma,
+/// cutting halfway between the darkest and lightest values
+/// after ignoring the most extreme two percent on either side.
+///
+/// # Arguments
+/// * `img` - Captured frame.
+fn dark_pixels(img: &RgbImage) -> Vec<bool> {
+    let luma: Vec<u8> = img
+        .pixels()
+        .map(|pixel| {
+            let [red, green, blue] = pixel.0.map(u32::from);
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                ((red * 299 + green * 587 + blue * 114) / 1000) as u8
+            }
+        })
+        .collect();
+    let mut histogram = [0usize; 256];
+    for &value in &luma {
+        histogram[value as usize] += 1;
+    }
+    let percentile = |fraction: usize| {
+        let target = luma.len() * fraction / 100;
+        let mut seen = 0;
+        (0..=u8::MAX)
+            .find(|&value| {
+                seen += histogram[value as usize];
+                seen > target
+            })
+            .unwrap_or(u8::MAX)
+    };
+    let threshold = u8::midpoint(percentile(2), percentile(98));
+    luma.into_iter().map(|value| value < threshold).collect()
+}
+
+/// Splits a line of pixels into runs of equal darkness,
+/// returned as ((dark, start), length).
+///
+/// # Arguments
+/// * `pixels` - Darkness of every pixel along the line.
+fn runs(pixels: impl Iterator<Item = bool>) -> Vec<((bool, usize), usize)> {
+    let mut runs: Vec<((bool, usize), usize)> = Vec::new();
+    for (position, dark) in pixels.enumerate() {
+        match runs.last_mut() {
+            Some(((run_dark, _), length)) if *run_dark == dark => *length += 1,
+            _ => runs.push(((dark, position), 1)),
+        }
+    }
+    runs
+}
+
+/// Checks five alternating runs, starting dark, have the 1:1:3:1:1 widths
+/// of a line crossing the center of a finder pattern.
+///
+/// # Arguments
+/// * `lengths` - Lengths of the runs in pixels.
+fn finder_ratio(lengths: [usize; 5]) -> bool {
+    let total: usize = lengths.iter().sum();
+    if total < FINDER_SIZE as usize {
+        return false;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let module = total as f64 / f64::from(FINDER_SIZE);
+    lengths
+        .iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&length, modules)| {
+            #[allow(clippy::cast_precision_loss)]
+            let deviation = (length as f64 - modules * module).abs();
+            deviation < modules * module / 2.0
+        })
+}
+
+/// Walks a line of pixels up and down from a pixel in the dark center of a finder pattern.
+/// Returns the position of the center and the width of the whole pattern along the line,
+/// if the runs crossed have the widths of a finder pattern.
+///
+/// # Arguments
+/// * `is_dark` - Darkness of the pixel at each position along the line.
+/// * `len` - Number of pixels in the line.
+/// * `start` - Position of the pixel in the center of the pattern.
+fn runs_through(is_dark: impl Fn(usize) -> bool, len: usize, start: usize) -> Option<(f64, usize)> {
+    if !is_dark(start) {
+        return None;
+    }
+    // Lengths of the center, light and outer dark runs in one direction.
+    let walk = |step: isize| {
+        let mut lengths = [0usize; 3];
+        let mut position = start;
+        for (run, length) in lengths.iter_mut().enumerate() {
+            let dark = run != 1;
+            loop {
+                if is_dark(position) != dark {
+                    break;
+                }
+                *length += 1;
+                match position.checked_add_signed(step).filter(|&next| next < len) {
+                    Some(next) => position = next,
+                    // Only the outer ring may end at the border.
+                    None if run == 2 => break,
+                    None => return None,
+                }
+            }
+        }
+        Some(lengths)
+    };
+    let [center_up, light_up, dark_up] = walk(-1)?;
+    let [center_down, light_down, dark_down] = walk(1)?;
+    let lengths = [
+        dark_up,
+        light_up,
+        center_up + center_down - 1,
+        light_down,
+        dark_down,
+    ];
+    if !finder_ratio(lengths) {
+        return None;
+    }
+    let center_start = start + 1 - center_up;
+    #[allow(clippy::cast_precision_loss)]
+    Some((
+        center_start as f64 + lengths[2] as f64 / 2.0,
+        lengths.iter().sum(),
+    ))
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Projective transformation between two planes,
+/// mapping (u, v) to (h0 u + h1 v + h2, h3 u + h4 v + h5) / (h6 u + h7 v + 1).
+struct Homography {
+    /// Coefficients h0 to h7.
+    coefficients: [f64; 8],
+}
+
+impl Homography {
+    /// Computes the homography mapping each of `from` onto the point at the same index of `to`.
+    ///
+    /// # Arguments
+    /// * `from` - Four points in the source plane, no three on a line.
+    /// * `to` - The four points they map to.
+    fn from_points(from: [[f64; 2]; 4], to: [[f64; 2]; 4]) -> Result<Self> {
+        // Two linear equations per point pair, solved by Gaussian elimination.
+        let mut rows = [[0.0f64; 9]; 8];
+        for (index, ([u, v], [x, y])) in from.into_iter().zip(to).enumerate() {
+            rows[2 * index] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x];
+            rows[2 * index + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y];
+        }
+        for column in 0..8 {
+            let pivot = (column..8)
+                .max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))
+                .unwrap_or(column);
+            if rows[pivot][column].abs() < f64::EPSILON {
+                bail!("Finder patterns are degenerate, unable to compute the perspective.");
+            }
+            rows.swap(column, pivot);
+            let pivot_row = rows[column];
+            for (index, row) in rows.iter_mut().enumerate() {
+                if index != column {
+                    let factor = row[column] / pivot_row[column];
+                    for (entry, pivot_entry) in row.iter_mut().zip(pivot_row).skip(column) {
+                        *entry -= factor * pivot_entry;
+                    }
+                }
+            }
+        }
+        let mut coefficients = [0.0; 8];
+        for (index, coefficient) in coefficients.iter_mut().enumerate() {
+            *coefficient = rows[index][8] / rows[index][index];
+        }
+        Ok(Self { coefficients })
+    }
+
+    /// Maps a point of the source plane into the target plane.
+    ///
+    /// # Arguments
+    /// * `point` - (u, v) Point in the source plane.
+    fn map(&self, [u, v]: [f64; 2]) -> [f64; 2] {
+        let h = &self.coefficients;
+        let scale = h[6] * u + h[7] * v + 1.0;
+        [
+            (h[0] * u + h[1] * v + h[2]) / scale,
+            (h[3] * u + h[4] * v + h[5]) / scale,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (Width, Height) of the data grid drawn into the test frames.
+    const GRID: [u32; 2] = [36, 27];
+
+    /// Corners of the data grid in the captured frame, as filmed at an angle.
+    /// Order: top left, top right, bottom left, bottom right
+    const CAPTURED_CORNERS: [[f64; 2]; 4] =
+        [[40.0, 30.0], [430.0, 62.0], [58.0, 331.0], [452.0, 298.0]];
+
+    /// Channel values of the data unit at (`x`, `y`), finder patterns in the corners
+    /// and dark or light channels picked by a fixed pseudo random sequence elsewhere.
+    fn unit_value(x: u32, y: u32) -> [u8; COLOR_CHANNELS] {
+        if let Some(value) = finder_unit(x, y, GRID) {
+            return [value; COLOR_CHANNELS];
+        }
+        let index = y * GRID[0] + x;
+        array::from_fn(|channel| {
+            #[allow(clippy::cast_possible_truncation)]
+            let seed = index
+                .wrapping_mul(2_654_435_761)
+                .rotate_left(channel as u32 * 7);
+            if seed & 0x100 == 0 { DARK } else { LIGHT }
+        })
+    }
+
+    /// Corners of the data grid in data units. Order: as `CAPTURED_CORNERS`
+    fn grid_corners() -> [[f64; 2]; 4] {
+        let [width, height] = GRID.map(f64::from);
+        [[0.0, 0.0], [width, 0.0], [0.0, height], [width, height]]
+    }
+
+    /// Draws the data grid into a 480x360 frame through the perspective of `CAPTURED_CORNERS`,
+    /// on a gray background.
+    fn captured_frame() -> RgbImage {
+        let to_grid = Homography::from_points(CAPTURED_CORNERS, grid_corners()).unwrap();
+        RgbImage::from_fn(480, 360, |x, y| {
+            let [u, v] = to_grid.map([f64::from(x) + 0.5, f64::from(y) + 0.5]);
+            let [width, height] = GRID.map(f64::from);
+            if (0.0..width).contains(&u) && (0.0..height).contains(&v) {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                image::Rgb(unit_value(u as u32, v as u32))
+            } else {
+                image::Rgb([128; COLOR_CHANNELS])
+            }
+        })
+    }
+
+    #[test]
+    fn homography_maps_its_points() {
+        let homography = Homography::from_points(grid_corners(), CAPTURED_CORNERS).unwrap();
+        for (from, to) in grid_corners().into_iter().zip(CAPTURED_CORNERS) {
+            let [x, y] = homography.map(from);
+            assert!((x - to[0]).abs() < 1e-6 && (y - to[1]).abs() < 1e-6);
+        }
+        let line = [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [0.0, 1.0]];
+        assert!(Homography::from_points(line, CAPTURED_CORNERS).is_err());
+    }
+
+    #[test]
+    fn finders_are_located_in_a_warped_frame() {
+        let to_frame = Homography::from_points(grid_corners(), CAPTURED_CORNERS).unwrap();
+        let near = f64::from(FINDER_RESERVED) / 2.0;
+        let [width, height] = GRID.map(f64::from);
+        let expected = [
+            [near, near],
+            [width - near, near],
+            [near, height - near],
+            [width - near, height - near],
+        ]
+        .map(|center| to_frame.map(center));
+        let found = locate_finders(&captured_frame()).unwrap();
+        for (found, expected) in found.into_iter().zip(expected) {
+            let distance = (found[0] - expected[0]).hypot(found[1] - expected[1]);
+            assert!(distance < 2.0, "found {found:?}, expected {expected:?}");
+        }
+    }
+
+    #[test]
+    fn warped_frame_is_sampled_back_to_its_grid() {
+        let sampled = sample_grid(&captured_frame(), GRID).unwrap();
+        let expected: Vec<u8> = (0..GRID[1])
+            .flat_map(|y| (0..GRID[0]).flat_map(move |x| unit_value(x, y)))
+            .collect();
+        assert_eq!(sampled.len(), expected.len());
+        let misread = sampled
+            .iter()
+            .zip(&expected)
+            .filter(|&(&sampled, &expected)| sampled.abs_diff(expected) > 64)
+            .count();
+        assert_eq!(misread, 0);
+    }
+
+    #[test]
+    fn frame_without_finders_is_rejected() {
+        let frame = RgbImage::from_fn(480, 360, |x, y| {
+            image::Rgb(
+                [if (x / 12 + y / 12) % 2 == 0 {
+                    DARK
+                } else {
+                    LIGHT
+                }; COLOR_CHANNELS],
+            )
+        });
+        assert!(sample_grid(&frame, GRID).is_err());
+    }
+}
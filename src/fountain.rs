@@ -0,0 +1,329 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Systematic Luby transform (fountain) code across data frames.
+//!
+//! The payloads of the data frames are the source blocks and are sent unchanged.
+//! They are followed by droplets, each the XOR of a random set of source blocks.
+//! The degree of a droplet follows the robust soliton distribution and its neighbours
+//! are drawn from a generator seeded with its index, so the decoder derives them
+//! without anything being stored in the frame.
+//! Decoding peels droplets with a single unknown neighbour until no progress is made,
+//! so any large enough subset of frames rebuilds the lost ones,
+//! no matter where in the video they were dropped.
+
+use std::cmp::Ordering;
+
+use crate::framing::xor_into;
+
+/// Robust soliton parameter scaling the number of expected degree one droplets.
+const SOLITON_C: f64 = 0.1;
+
+/// Robust soliton bound on the probability of decoding failing.
+const SOLITON_DELTA: f64 = 0.5;
+
+/// Fractional part of the golden ratio, stepping the first neighbour of consecutive droplets.
+const GOLDEN_RATIO_FRACTION: f64 = 0.618_033_988_749_895;
+
+/// Number of droplets sent for `source_blocks` data frames with `overhead_percent` overhead.
+/// At least one droplet is sent if the overhead is not zero.
+///
+/// # Arguments
+/// * `source_blocks` - Number of data frames.
+/// * `overhead_percent` - Droplets sent per hundred data frames.
+pub fn droplet_count(source_blocks: usize, overhead_percent: u16) -> usize {
+    if overhead_percent == 0 || source_blocks == 0 {
+        return 0;
+    }
+    (source_blocks * overhead_percent as usize).div_ceil(100)
+}
+
+/// Creates `count` droplets over the source blocks.
+///
+/// # Arguments
+/// * `blocks` - Source blocks, all of the same length.
+/// * `count` - Number of droplets to create.
+pub fn encode_droplets(blocks: &[Vec<u8>], count: usize) -> Vec<Vec<u8>> {
+    let block_len = blocks.first().map_or(0, Vec::len);
+    let distribution = robust_soliton_cdf(blocks.len());
+    (0..count)
+        .map(|index| {
+            let mut droplet = vec![0u8; block_len];
+            for neighbour in droplet_neighbours(index, blocks.len(), &distribution) {
+                xor_into(&mut droplet, &blocks[neighbour]);
+            }
+            droplet
+        })
+        .collect()
+}
+
+/// Rebuilds missing source blocks from the received droplets by peeling:
+/// the XOR of the known blocks is removed from every droplet, and a droplet left with a single
+/// unknown neighbour is that block, which may in turn free further droplets.
+/// Returns the number of rebuilt blocks.
+///
+/// # Arguments
+/// * `blocks` - Source blocks, `None` where the data frame was lost.
+///   Rebuilt blocks are filled in.
+/// * `droplets` - Received droplets with their index.
+pub fn decode_blocks(blocks: &mut [Option<Vec<u8>>], droplets: &[(usize, &[u8])]) -> usize {
+    if blocks.iter().all(Option::is_some) {
+        return 0;
+    }
+    let distribution = robust_soliton_cdf(blocks.len());
+    let mut pending: Vec<(Vec<usize>, Vec<u8>)> = droplets
+        .iter()
+        .map(|&(index, data)| {
+            (
+                droplet_neighbours(index, blocks.len(), &distribution),
+                data.to_vec(),
+            )
+        })
+        .collect();
+    let mut rebuilt = 0;
+    loop {
+        let rebuilt_before = rebuilt;
+        pending.retain_mut(|(neighbours, data)| {
+            neighbours.retain(|&neighbour| match &blocks[neighbour] {
+                Some(block) => {
+                    xor_into(data, block);
+                    false
+                }
+                None => true,
+            });
+            match neighbours.as_slice() {
+                [] => false,
+                &[neighbour] => {
+                    blocks[neighbour] = Some(std::mem::take(data));
+                    rebuilt += 1;
+                    false
+                }
+                _ => true,
+            }
+        });
+        if rebuilt == rebuilt_before {
+            return rebuilt;
+        }
+    }
+}
+
+/// Cumulative robust soliton distribution over the degrees 1 to `source_blocks`,
+/// the entry at index `d - 1` being the probability of a degree of at most `d`.
+///
+/// # Arguments
+/// * `source_blocks` - Number of source blocks.
+fn robust_soliton_cdf(source_blocks: usize) -> Vec<f64> {
+    #[allow(clippy::cast_precision_loss)]
+    let k = source_blocks as f64;
+    let r = SOLITON_C * (k / SOLITON_DELTA).ln() * k.sqrt();
+    // Degree of the spike added by the robust part of the distribution.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let spike = if r > 0.0 { (k / r).floor() as usize } else { 0 };
+    let weights: Vec<f64> = (1..=source_blocks)
+        .map(|degree| {
+            #[allow(clippy::cast_precision_loss)]
+            let d = degree as f64;
+            let ideal = if degree == 1 {
+                1.0 / k
+            } else {
+                1.0 / (d * (d - 1.0))
+            };
+            let robust = match degree.cmp(&spike) {
+                Ordering::Less => r / (d * k),
+                Ordering::Equal => r * (r / SOLITON_DELTA).ln() / k,
+                Ordering::Greater => 0.0,
+            };
+            ideal + robust.max(0.0)
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    let mut cumulative = 0.0;
+    weights
+        .iter()
+        .map(|weight| {
+            cumulative += weight / total;
+            cumulative
+        })
+        .collect()
+}
+
+/// Source blocks combined into the droplet with the given index,
+/// derived the same way when encoding and decoding.
+///
+/// # Arguments
+/// * `index` - Index of the droplet, counting only droplets.
+/// * `source_blocks` - Number of source blocks.
+/// * `distribution` - Degree distribution as returned by `robust_soliton_cdf`.
+fn droplet_neighbours(index: usize, source_blocks: usize, distribution: &[f64]) -> Vec<usize> {
+    let mut generator = SplitMix64(((source_blocks as u64) << 32) ^ index as u64);
+    #[allow(clippy::cast_precision_loss)]
+    let sample = (generator.next() >> 11) as f64 / (1u64 << 53) as f64;
+    let degree = distribution
+        .iter()
+        .position(|&cumulative| sample < cumulative)
+        .map_or(source_blocks, |position| position + 1);
+    // The first neighbour follows a Weyl sequence rather than the generator,
+    // spreading the droplets evenly over the data frames even when only a few are sent.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    let first = ((index as f64 * GOLDEN_RATIO_FRACTION).fract() * source_blocks as f64) as usize;
+    let mut neighbours = Vec::with_capacity(degree);
+    neighbours.push(first.min(source_blocks - 1));
+    while neighbours.len() < degree {
+        #[allow(clippy::cast_possible_truncation)]
+        let neighbour = (generator.next() % source_blocks as u64) as usize;
+        if !neighbours.contains(&neighbour) {
+            neighbours.push(neighbour);
+        }
+    }
+    neighbours
+}
+
+/// `SplitMix64` pseudo random generator, small and fully determined by its seed,
+/// so droplet neighbours do not depend on the platform or a library version.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    /// Advances the state and returns the next value.
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of source blocks in the tests.
+    const SOURCE_BLOCKS: usize = 40;
+
+    /// Pseudo random source blocks of 32 bytes each.
+    fn source_blocks() -> Vec<Vec<u8>> {
+        let mut generator = SplitMix64(7);
+        (0..SOURCE_BLOCKS)
+            .map(|_| generator.next().to_le_bytes().repeat(4))
+            .collect()
+    }
+
+    /// Source blocks with `lost` of them spread over the video missing.
+    fn received_blocks(blocks: &[Vec<u8>], lost: usize) -> Vec<Option<Vec<u8>>> {
+        let mut received: Vec<_> = blocks.iter().cloned().map(Some).collect();
+        for loss in 0..lost {
+            received[loss * blocks.len() / lost] = None;
+        }
+        received
+    }
+
+    #[test]
+    fn droplet_count_rounds_up() {
+        assert_eq!(droplet_count(40, 0), 0);
+        assert_eq!(droplet_count(0, 25), 0);
+        assert_eq!(droplet_count(40, 25), 10);
+        assert_eq!(droplet_count(41, 25), 11);
+        assert_eq!(droplet_count(1, 1), 1);
+    }
+
+    #[test]
+    fn robust_soliton_cdf_is_a_distribution() {
+        for source_blocks in [1, 2, 10, 40, 1000] {
+            let cdf = robust_soliton_cdf(source_blocks);
+            assert_eq!(cdf.len(), source_blocks);
+            assert!(cdf.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!((cdf[source_blocks - 1] - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn droplet_neighbours_are_distinct_and_in_range() {
+        let distribution = robust_soliton_cdf(SOURCE_BLOCKS);
+        for index in 0..1000 {
+            let neighbours = droplet_neighbours(index, SOURCE_BLOCKS, &distribution);
+            assert!(!neighbours.is_empty());
+            assert!(
+                neighbours
+                    .iter()
+                    .all(|&neighbour| neighbour < SOURCE_BLOCKS)
+            );
+            for (position, neighbour) in neighbours.iter().enumerate() {
+                assert!(!neighbours[position + 1..].contains(neighbour));
+            }
+            assert_eq!(
+                neighbours,
+                droplet_neighbours(index, SOURCE_BLOCKS, &distribution)
+            );
+        }
+    }
+
+    #[test]
+    fn lost_blocks_are_rebuilt_at_every_overhead() {
+        let blocks = source_blocks();
+        // Lost data frames that a quarter of the droplets going missing as well still leaves
+        // rebuildable.
+        for (overhead, lost) in [(25, 4), (50, 8), (100, 12)] {
+            let droplets = encode_droplets(&blocks, droplet_count(SOURCE_BLOCKS, overhead));
+            let received_droplets: Vec<(usize, &[u8])> = droplets
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| index % 4 != 3)
+                .map(|(index, droplet)| (index, droplet.as_slice()))
+                .collect();
+            let mut received = received_blocks(&blocks, lost);
+            let rebuilt = decode_blocks(&mut received, &received_droplets);
+            assert_eq!(rebuilt, lost, "{overhead}% overhead");
+            for (received, block) in received.iter().zip(&blocks) {
+                assert_eq!(received.as_ref(), Some(block), "{overhead}% overhead");
+            }
+        }
+    }
+
+    #[test]
+    fn complete_blocks_need_no_droplets() {
+        let blocks = source_blocks();
+        let mut received = received_blocks(&blocks, 0);
+        assert_eq!(decode_blocks(&mut received, &[]), 0);
+        assert!(received.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn too_few_droplets_leave_blocks_missing() {
+        let blocks = source_blocks();
+        let droplets = encode_droplets(&blocks, droplet_count(SOURCE_BLOCKS, 25));
+        let all_droplets: Vec<(usize, &[u8])> = droplets
+            .iter()
+            .enumerate()
+            .map(|(index, droplet)| (index, droplet.as_slice()))
+            .collect();
+
+        // More data frames lost than droplets sent can never be rebuilt.
+        let lost = droplets.len() + 1;
+        let mut received = received_blocks(&blocks, lost);
+        let rebuilt = decode_blocks(&mut received, &all_droplets);
+        assert!(rebuilt < lost);
+        assert_eq!(
+            received.iter().filter(|block| block.is_none()).count(),
+            lost - rebuilt
+        );
+        // Whatever was rebuilt is correct.
+        for (received, block) in received.iter().zip(&blocks) {
+            assert!(received.as_ref().is_none_or(|received| received == block));
+        }
+
+        // Without droplets nothing is rebuilt.
+        let mut received = received_blocks(&blocks, 1);
+        assert_eq!(decode_blocks(&mut received, &[]), 0);
+        assert!(received.iter().any(Option::is_none));
+    }
+}
@@ -0,0 +1,467 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Layout of the encoded data stream across data frames.
+//!
+//! The stream is split into frame sized chunks, each starting with a sequence number
+//! stored in several copies. The decoder places frames by their sequence number,
+//! so a dropped or duplicated frame only damages its own part of the stream,
+//! which error correction may then repair. Videos encoded before sequence numbers
+//! were added and without parity frames hold just the chunks, placed in read order.
+//!
+//! Newly encoded frames end with a CRC-32 of the rest of the frame.
+//! It is not used to place or repair anything, but tells the decoder
+//! which frames arrived damaged without waiting for the error correction.
+//!
+//! With parity frames, after every `parity_interval` data frames a parity frame is inserted,
+//! containing the XOR of the preceding data frames.
+//! A single lost or destroyed data frame per group can be rebuilt from the others.
+//!
+//! With a fountain overhead, the data frames are followed by droplet frames
+//! instead, see `fountain`. Any lost data frames can be rebuilt
+//! as long as enough frames survive overall.
+
+use anyhow::{Result, bail};
+
+use crate::{
+    constants::BYTES_U32,
+    fountain::{decode_blocks, droplet_count, encode_droplets},
+};
+
+/// Number of redundant copies of the sequence number in each frame.
+const SEQUENCE_COPIES: usize = 3;
+
+/// Bytes reserved at the start of each frame for its sequence number.
+pub const SEQUENCE_LEN: usize = SEQUENCE_COPIES * (u32::BITS / u8::BITS) as usize;
+
+/// Bytes reserved at the end of each frame for its CRC-32.
+pub const FRAME_CRC_LEN: usize = BYTES_U32;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Frame level problems found while reassembling the data stream.
+pub struct FramingReport {
+    /// Number of data frames neither read nor rebuilt, replaced by zeros.
+    pub lost: usize,
+    /// Number of data frames rebuilt from their parity group.
+    pub recovered: usize,
+    /// Number of frames read more than once or with an unreadable sequence number.
+    pub discarded: usize,
+    /// Number of frames read that are not blank, including discarded ones.
+    pub read: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Position of a frame within the data stream.
+pub enum FramePosition {
+    /// Data frame with the given index, counting only data frames.
+    Data(usize),
+    /// Parity frame of the given group.
+    Parity(usize),
+    /// Fountain droplet with the given index, counting only droplets.
+    Droplet(usize),
+}
+
+/// Number of data frame payload bytes available in a frame.
+///
+/// # Arguments
+/// * `frame_bytes` - Number of bytes encoded in each frame.
+/// * `sequenced` - If every frame starts with a sequence number.
+pub fn payload_len(frame_bytes: usize, sequenced: bool) -> Result<usize> {
+    if !sequenced {
+        return Ok(frame_bytes);
+    }
+    if frame_bytes <= SEQUENCE_LEN {
+        bail!(
+            "Frames ({frame_bytes} bytes) are too small to hold a sequence number ({SEQUENCE_LEN} bytes)."
+        );
+    }
+    Ok(frame_bytes - SEQUENCE_LEN)
+}
+
+/// Total number of data, parity and droplet frames used for a stream.
+///
+/// # Arguments
+/// * `stream_len` - Length of the encoded data stream in bytes.
+/// * `frame_bytes` - Number of bytes encoded in each frame.
+/// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+/// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+/// * `sequenced` - If every frame starts with a sequence number.
+pub fn frame_count(
+    stream_len: usize,
+    frame_bytes: usize,
+    parity_interval: usize,
+    fountain_overhead: u16,
+    sequenced: bool,
+) -> Result<usize> {
+    let data_frames = stream_len.div_ceil(payload_len(frame_bytes, sequenced)?);
+    if fountain_overhead > 0 {
+        return Ok(data_frames + droplet_count(data_frames, fountain_overhead));
+    }
+    if parity_interval == 0 {
+        return Ok(data_frames);
+    }
+    Ok(data_frames + data_frames.div_ceil(parity_interval))
+}
+
+/// Splits the data stream into frames, adding sequence numbers
+/// and parity or droplet frames if enabled.
+/// Every returned frame is exactly `frame_bytes` long.
+///
+/// # Arguments
+/// * `stream` - Encoded data stream.
+/// * `frame_bytes` - Number of bytes encoded in each frame.
+/// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+/// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+/// * `sequenced` - If every frame starts with a sequence number.
+pub fn build_frames(
+    stream: &[u8],
+    frame_bytes: usize,
+    parity_interval: usize,
+    fountain_overhead: u16,
+    sequenced: bool,
+) -> Result<Vec<Vec<u8>>> {
+    if (parity_interval > 0 || fountain_overhead > 0) && !sequenced {
+        bail!("Parity and droplet frames can only be placed by their sequence number.");
+    }
+    if parity_interval > 0 && fountain_overhead > 0 {
+        bail!("Parity frames and fountain droplets can not be combined.");
+    }
+    let payload_len = payload_len(frame_bytes, sequenced)?;
+    let mut frames = Vec::with_capacity(frame_count(
+        stream.len(),
+        frame_bytes,
+        parity_interval,
+        fountain_overhead,
+        sequenced,
+    )?);
+    if fountain_overhead > 0 {
+        let blocks: Vec<Vec<u8>> = stream
+            .chunks(payload_len)
+            .map(|chunk| {
+                let mut block = chunk.to_vec();
+                block.resize(payload_len, 0);
+                block
+            })
+            .collect();
+        let droplets = encode_droplets(&blocks, droplet_count(blocks.len(), fountain_overhead));
+        for payload in blocks.iter().chain(&droplets) {
+            frames.push(sequenced_frame(frames.len(), payload, frame_bytes)?);
+        }
+        return Ok(frames);
+    }
+    if parity_interval == 0 {
+        for chunk in stream.chunks(payload_len) {
+            frames.push(if sequenced {
+                sequenced_frame(frames.len(), chunk, frame_bytes)?
+            } else {
+                let mut frame = chunk.to_vec();
+                frame.resize(frame_bytes, 0);
+                frame
+            });
+        }
+        return Ok(frames);
+    }
+
+    for group in stream.chunks(payload_len * parity_interval) {
+        let mut parity = vec![0u8; payload_len];
+        for chunk in group.chunks(payload_len) {
+            xor_into(&mut parity, chunk);
+            frames.push(sequenced_frame(frames.len(), chunk, frame_bytes)?);
+        }
+        frames.push(sequenced_frame(frames.len(), &parity, frame_bytes)?);
+    }
+    Ok(frames)
+}
+
+/// Reassembles the data stream from the frames read from a video.
+/// Frames are placed by their sequence number, so dropped or duplicated frames
+/// do not shift the data. A single missing data frame per parity group is rebuilt,
+/// or as many as the received droplets allow with a fountain overhead,
+/// any others are filled with zeros.
+/// Frames without sequence numbers are simply concatenated in read order.
+/// Bytes following the first `frame_bytes` of a frame, such as its CRC, are left out.
+///
+/// # Arguments
+/// * `frames` - Frames read from the video following the header.
+/// * `stream_len` - Expected length of the encoded data stream in bytes.
+/// * `frame_bytes` - Number of bytes encoded in each frame.
+/// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+/// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+/// * `sequenced` - If every frame starts with a sequence number.
+pub fn reassemble_stream(
+    frames: &[Vec<u8>],
+    stream_len: usize,
+    frame_bytes: usize,
+    parity_interval: usize,
+    fountain_overhead: u16,
+    sequenced: bool,
+) -> Result<(Vec<u8>, FramingReport)> {
+    let payload_len = payload_len(frame_bytes, sequenced)?;
+    let mut report = FramingReport::default();
+    if !sequenced {
+        report.read = frames
+            .iter()
+            .filter(|frame| frame.iter().any(|&x| x != 0))
+            .count();
+        let mut stream: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| &frame[..frame_bytes.min(frame.len())])
+            .copied()
+            .collect();
+        stream.truncate(stream_len);
+        return Ok((stream, report));
+    }
+
+    let total_frames = frame_count(
+        stream_len,
+        frame_bytes,
+        parity_interval,
+        fountain_overhead,
+        sequenced,
+    )?;
+    let mut slots: Vec<Option<&[u8]>> = vec![None; total_frames];
+    for frame in frames {
+        match read_sequence_number(frame) {
+            // Sequence numbers are stored offset by one, zero marks a blank frame.
+            Some(0) => continue,
+            Some(sequence) if sequence <= total_frames && slots[sequence - 1].is_none() => {
+                slots[sequence - 1] = Some(&frame[SEQUENCE_LEN..frame_bytes.min(frame.len())]);
+            }
+            _ => report.discarded += 1,
+        }
+        report.read += 1;
+    }
+
+    if fountain_overhead > 0 {
+        let data_frames = stream_len.div_ceil(payload_len);
+        let mut stream = reassemble_fountain(&slots, data_frames, payload_len, &mut report);
+        stream.truncate(stream_len);
+        return Ok((stream, report));
+    }
+    let mut stream = Vec::with_capacity(total_frames * payload_len);
+    // Without parity frames the whole stream is a single group without parity.
+    let group_len = if parity_interval == 0 {
+        total_frames.max(1)
+    } else {
+        parity_interval + 1
+    };
+    for group in slots.chunks(group_len) {
+        let (parity, data) = if parity_interval == 0 {
+            (&None, group)
+        } else {
+            group.split_last().unwrap_or((&None, &[]))
+        };
+        let missing: Vec<usize> = (0..data.len()).filter(|&i| data[i].is_none()).collect();
+        let rebuilt = if let ([_], Some(parity)) = (missing.as_slice(), parity) {
+            // XOR of the parity frame and all present data frames is the missing frame.
+            let mut rebuilt = parity.to_vec();
+            for frame in data.iter().flatten() {
+                xor_into(&mut rebuilt, frame);
+            }
+            report.recovered += 1;
+            Some(rebuilt)
+        } else {
+            report.lost += missing.len();
+            None
+        };
+        for frame in data {
+            match frame {
+                Some(frame) => stream.extend_from_slice(frame),
+                None => match &rebuilt {
+                    Some(rebuilt) => stream.extend_from_slice(rebuilt),
+                    None => stream.resize(stream.len() + payload_len, 0),
+                },
+            }
+        }
+    }
+    stream.truncate(stream_len);
+    Ok((stream, report))
+}
+
+/// Concatenates the data frames placed in `slots`, rebuilding missing ones
+/// from the droplet frames following them. Frames that can not be rebuilt are filled with zeros.
+///
+/// # Arguments
+/// * `slots` - Payloads placed by their sequence number, data frames first.
+/// * `data_frames` - Number of data frames.
+/// * `payload_len` - Number of payload bytes in each frame.
+/// * `report` - Report the rebuilt and lost frames are counted in.
+fn reassemble_fountain(
+    slots: &[Option<&[u8]>],
+    data_frames: usize,
+    payload_len: usize,
+    report: &mut FramingReport,
+) -> Vec<u8> {
+    let (sources, droplet_slots) = slots.split_at(data_frames);
+    let mut blocks: Vec<Option<Vec<u8>>> = sources
+        .iter()
+        .map(|source| source.map(<[u8]>::to_vec))
+        .collect();
+    let droplets: Vec<(usize, &[u8])> = droplet_slots
+        .iter()
+        .enumerate()
+        .filter_map(|(index, droplet)| droplet.map(|droplet| (index, droplet)))
+        .collect();
+    report.recovered += decode_blocks(&mut blocks, &droplets);
+    report.lost += blocks.iter().filter(|block| block.is_none()).count();
+
+    let mut stream = Vec::with_capacity(data_frames * payload_len);
+    for block in blocks {
+        match block {
+            Some(block) => stream.extend_from_slice(&block),
+            None => stream.resize(stream.len() + payload_len, 0),
+        }
+    }
+    stream
+}
+
+/// Finds the position of a frame within the data stream.
+/// Returns `None` for frames that are not part of it, e.g. buffer frames.
+///
+/// # Arguments
+/// * `frame` - Frame read from the video.
+/// * `offset` - Index of the frame counted from the first frame following the header.
+///   Only used if frames are not sequenced, otherwise the sequence number is used.
+/// * `stream_len` - Expected length of the encoded data stream in bytes.
+/// * `frame_bytes` - Number of bytes encoded in each frame.
+/// * `parity_interval` - Number of data frames per parity frame, zero if disabled.
+/// * `fountain_overhead` - Droplet frames per hundred data frames, zero if disabled.
+/// * `sequenced` - If every frame starts with a sequence number.
+pub fn frame_position(
+    frame: &[u8],
+    offset: usize,
+    stream_len: usize,
+    frame_bytes: usize,
+    parity_interval: usize,
+    fountain_overhead: u16,
+    sequenced: bool,
+) -> Result<Option<FramePosition>> {
+    let total_frames = frame_count(
+        stream_len,
+        frame_bytes,
+        parity_interval,
+        fountain_overhead,
+        sequenced,
+    )?;
+    if !sequenced {
+        return Ok((offset < total_frames).then_some(FramePosition::Data(offset)));
+    }
+    let slot = match read_sequence_number(frame) {
+        Some(sequence) if (1..=total_frames).contains(&sequence) => sequence - 1,
+        _ => return Ok(None),
+    };
+    let data_frames = stream_len.div_ceil(payload_len(frame_bytes, sequenced)?);
+    if fountain_overhead > 0 {
+        return Ok(Some(match slot.checked_sub(data_frames) {
+            Some(droplet) => FramePosition::Droplet(droplet),
+            None => FramePosition::Data(slot),
+        }));
+    }
+    if parity_interval == 0 {
+        return Ok(Some(FramePosition::Data(slot)));
+    }
+    let group = slot / (parity_interval + 1);
+    let index_in_group = slot % (parity_interval + 1);
+    // The last group may hold fewer data frames, its parity frame directly follows them.
+    let group_data_frames = parity_interval.min(data_frames - group * parity_interval);
+    Ok(Some(if index_in_group == group_data_frames {
+        FramePosition::Parity(group)
+    } else {
+        FramePosition::Data(group * parity_interval + index_in_group)
+    }))
+}
+
+/// Creates a frame starting with the sequence number for `index`
+/// followed by `payload`, zero padded to `frame_bytes`.
+fn sequenced_frame(index: usize, payload: &[u8], frame_bytes: usize) -> Result<Vec<u8>> {
+    // Offset by one so a blank frame never carries a valid sequence number.
+    let sequence = u32::try_from(index + 1)?.to_le_bytes();
+    let mut frame = Vec::with_capacity(frame_bytes);
+    for _ in 0..SEQUENCE_COPIES {
+        frame.extend_from_slice(&sequence);
+    }
+    frame.extend_from_slice(payload);
+    frame.resize(frame_bytes, 0);
+    Ok(frame)
+}
+
+/// Reads the sequence number at the start of a frame.
+/// Returns `None` if no two of its copies agree.
+fn read_sequence_number(frame: &[u8]) -> Option<usize> {
+    let copies: Vec<u32> = frame
+        .get(..SEQUENCE_LEN)?
+        .chunks_exact(SEQUENCE_LEN / SEQUENCE_COPIES)
+        .map(|copy| u32::from_le_bytes(copy.try_into().unwrap_or_default()))
+        .collect();
+    let sequence = copies
+        .iter()
+        .find(|&&candidate| copies.iter().filter(|&&x| x == candidate).count() >= 2)?;
+    usize::try_from(*sequence).ok()
+}
+
+/// Appends the CRC-32 of `frame` to it.
+pub fn append_frame_crc(frame: &mut Vec<u8>) {
+    let crc = crc32fast::hash(frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Checks the CRC-32 following the first `frame_bytes` of a frame read from a video.
+/// Returns `false` if it does not match or the frame is too short to hold it.
+///
+/// # Arguments
+/// * `frame` - Frame read from the video.
+/// * `frame_bytes` - Number of bytes covered by the CRC, the sequence number and payload.
+pub fn frame_crc_matches(frame: &[u8], frame_bytes: usize) -> bool {
+    frame
+        .get(frame_bytes..frame_bytes + FRAME_CRC_LEN)
+        .is_some_and(|stored| stored == crc32fast::hash(&frame[..frame_bytes]).to_le_bytes())
+}
+
+/// XORs `source` into `target` byte by byte.
+pub fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (target_byte, source_byte) in target.iter_mut().zip(source) {
+        *target_byte ^= source_byte;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_standard_check_value() {
+        let mut frame = b"123456789".to_vec();
+        append_frame_crc(&mut frame);
+        assert_eq!(frame[9..], 0xCBF4_3926_u32.to_le_bytes());
+    }
+
+    #[test]
+    fn every_single_bit_error_fails_the_crc() {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut frame: Vec<u8> = (0..64usize).map(|i| (i * 37) as u8).collect();
+        let frame_bytes = frame.len();
+        append_frame_crc(&mut frame);
+        assert_eq!(frame.len(), frame_bytes + FRAME_CRC_LEN);
+        assert!(frame_crc_matches(&frame, frame_bytes));
+        // Errors in the CRC itself are caught as well.
+        for bit in 0..frame.len() * 8 {
+            let mut damaged = frame.clone();
+            damaged[bit / 8] ^= 1 << (bit % 8);
+            assert!(!frame_crc_matches(&damaged, frame_bytes), "bit {bit}");
+        }
+    }
+
+    #[test]
+    fn frame_without_room_for_a_crc_fails() {
+        let mut frame = vec![1, 2, 3];
+        append_frame_crc(&mut frame);
+        assert!(!frame_crc_matches(&frame[..frame.len() - 1], 3));
+        assert!(!frame_crc_matches(&[], 0));
+    }
+}
@@ -0,0 +1,117 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Hashes checking the decoded data against the data that was encoded.
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+
+/// Bytes the header reserves for the hash of the data.
+/// Shorter hashes are stored in the first bytes, followed by zeros.
+pub const HASH_LEN: usize = 32;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Hash of the data stored in the header and checked after decoding.
+pub enum HashAlgorithm {
+    #[default]
+    #[value(name = "sha256")]
+    /// SHA-256, the only hash of videos encoded before the algorithm was selectable.
+    Sha256,
+    #[value(name = "blake3")]
+    /// BLAKE3, as secure as SHA-256 and several times faster on large files.
+    Blake3,
+    #[value(name = "crc32")]
+    /// CRC-32, catching accidental damage but not deliberate changes.
+    Crc32,
+    #[value(name = "none")]
+    /// No hash, the decoded data is not checked.
+    None,
+}
+
+impl HashAlgorithm {
+    #[must_use]
+    /// Hash of `data`, zero padded to `HASH_LEN` bytes. All zero for `None`.
+    ///
+    /// # Arguments
+    /// * `data` - The data to hash.
+    pub fn digest(self, data: &[u8]) -> [u8; HASH_LEN] {
+        let mut hash = [0; HASH_LEN];
+        match self {
+            HashAlgorithm::Sha256 => hash = Sha256::digest(data).into(),
+            HashAlgorithm::Blake3 => {
+                hash = blake3::Hasher::new().update_rayon(data).finalize().into();
+            }
+            HashAlgorithm::Crc32 => hash[..4].copy_from_slice(&crc32fast::hash(data).to_le_bytes()),
+            HashAlgorithm::None => {}
+        }
+        hash
+    }
+
+    #[must_use]
+    /// Value identifying the algorithm in the header.
+    /// SHA-256 is zero, so headers written before the algorithm was stored read as SHA-256.
+    pub fn to_code(self) -> u8 {
+        match self {
+            HashAlgorithm::Sha256 => 0,
+            HashAlgorithm::Blake3 => 1,
+            HashAlgorithm::Crc32 => 2,
+            HashAlgorithm::None => 3,
+        }
+    }
+
+    /// Reads the algorithm identified by `code` as written by `to_code`.
+    ///
+    /// # Arguments
+    /// * `code` - Value read from the header.
+    ///
+    /// # Errors
+    /// Fails if `code` does not name a known hash algorithm.
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(HashAlgorithm::Sha256),
+            1 => Ok(HashAlgorithm::Blake3),
+            2 => Ok(HashAlgorithm::Crc32),
+            3 => Ok(HashAlgorithm::None),
+            _ => bail!("Unknown hash algorithm {code}."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bytes_to_hex_string;
+
+    #[test]
+    fn digests_match_published_values() {
+        assert_eq!(
+            bytes_to_hex_string(&HashAlgorithm::Sha256.digest(b"")),
+            "0xe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            bytes_to_hex_string(&HashAlgorithm::Blake3.digest(b"")),
+            "0xaf1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        let crc = HashAlgorithm::Crc32.digest(b"123456789");
+        assert_eq!(crc[..4], 0xCBF4_3926_u32.to_le_bytes());
+        assert!(crc[4..].iter().all(|&byte| byte == 0));
+        assert_eq!(HashAlgorithm::None.digest(b"123456789"), [0; HASH_LEN]);
+    }
+
+    #[test]
+    fn codes_round_trip() -> Result<()> {
+        for &algorithm in HashAlgorithm::value_variants() {
+            assert_eq!(HashAlgorithm::from_code(algorithm.to_code())?, algorithm);
+        }
+        assert_eq!(HashAlgorithm::from_code(0)?, HashAlgorithm::Sha256);
+        assert!(HashAlgorithm::from_code(4).is_err());
+        Ok(())
+    }
+}
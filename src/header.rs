@@ -0,0 +1,1073 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Redundant header written in front of the encoded data.
+//!
+//! A header block has the following structure:
+//!
+//! - Bytes 0-143:   Three copies of the base header
+//! - Bytes 144-1673: Two copies of the base header followed by the header extension,
+//!   each Reed-Solomon RS(255,223) encoded (Version 3 and later)
+//! - Bytes 1674-1679: Zero
+//!
+//! Version 2 blocks hold three plain copies of the header extension from byte 144 on,
+//! decoded by bitwise majority vote. The base copies in front stay plain in every version,
+//! so the version code is found without knowing the layout that follows.
+//!
+//! Base header:
+//!
+//! - Bytes 0-7:     `VERSION_CODE`
+//! - Bytes 8-15:    Data length in bytes (little-endian)
+//! - Bytes 16-47:   Hash of the data, see `HashAlgorithm`
+//!
+//! Header extension:
+//!
+//! - Bytes 0-3:     Flags (little-endian), see `FLAG_*` constants
+//! - Bytes 4-5:     Parity frame interval (little-endian), zero if disabled
+//! - Bytes 6-33:    Converter settings, see `Converter::config_bytes`. Zero if unknown
+//! - Bytes 34-49:   Encryption salt, zero if not encrypted
+//! - Bytes 50-61:   Encryption nonce, zero if not encrypted
+//! - Byte 62:       Error correction code of the data, see `FecKind::to_code`
+//! - Bytes 63-64:   Interleave depth (little-endian), zero or one if not interleaved
+//! - Bytes 65-66:   Prebuffer frame count (little-endian), only if `FLAG_BUFFER_FRAMES` is set
+//! - Bytes 67-68:   Postbuffer frame count (little-endian), only if `FLAG_BUFFER_FRAMES` is set
+//! - Bytes 69-72:   Data and parity frame count of each copy (little-endian), zero if unknown
+//! - Bytes 73-75:   Quantization bias the data frames were encoded with, one signed byte per channel (RGB)
+//! - Byte 76:       Bits in the alpha channel of every data unit, only if `FLAG_ALPHA_CHANNEL` is set
+//! - Bytes 77-78:   Fountain droplet frames per hundred data frames (little-endian), zero if disabled
+//! - Byte 79:       Downsample scaler the video is split with, zero if not stored
+//! - Bytes 80-81:   Data frames between repeated header blocks (little-endian), zero if not repeated
+//! - Byte 82:       Hash algorithm of the data, see `HashAlgorithm::to_code`
+//! - Byte 83:       Colorspace of the video, see `Colorspace::to_code`
+//! - Byte 84:       Color range of the video, see `ColorRange::to_code`
+//! - Bytes 85-127:  Reserved, zero
+//! - Byte 128:      Length of the note in bytes
+//! - Bytes 129-255: Note, UTF-8 text
+//! - Byte 256:      Length of the original file name in bytes, zero if not stored
+//! - Bytes 257-511: Original file name, UTF-8 text
+//!
+//! Reserved bytes are written as zero, so new fields can be added
+//! without changing the block layout as long as zero keeps the old behaviour.
+//!
+//! Since version 4 the header frames use a fixed layout of `HEADER_GRID` units
+//! holding one bit per color channel, independent of the color bits and data pixel size,
+//! so a video encoded with forgotten settings still shows them in its header.
+//! Earlier versions wrote the header frames like data frames with `LevelMapping::Midpoint`.
+
+use std::path::{Component, Path};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    backend::{ColorRange, Colorspace, VideoColor},
+    constants::{
+        COLOR_CHANNELS, CONVERTER_CONFIG_LEN, POSTBUFFER_FRAMES, PREBUFFER_FRAMES,
+        RS_CHUNK_BYTES_255_223, RS_CHUNK_BYTES_TOTAL_255_223,
+    },
+    encryption::{EncryptionParams, NONCE_LEN, SALT_LEN},
+    error_correction::{
+        FecKind, decode_with_reed_solomon_255_223_blocks, encode_with_reed_solomon_255_223,
+    },
+    finder::FINDER_RESERVED,
+    hashing::{HASH_LEN, HashAlgorithm},
+    levels::LevelMapping,
+};
+
+/// Identifying what version the converter is.
+/// Also used as a "magic" number to identify the beginnig of
+/// the first header frame.
+pub const VERSION_CODE: [u8; 8] = [68, 65, 67, 79, 0, 255, 0, 4];
+
+/// Version code of videos with the header blocks of the current version,
+/// whose header frames are laid out like data frames instead of in `HEADER_GRID`.
+pub const VERSION_CODE_V3: [u8; 8] = [68, 65, 67, 79, 0, 255, 0, 3];
+
+/// Version code of videos whose header blocks hold three plain copies of the header extension,
+/// with the same data frames as the current version.
+pub const VERSION_CODE_V2: [u8; 8] = [68, 65, 67, 79, 0, 255, 0, 2];
+
+/// Version code of videos where the header is only made up of the base header
+/// and shares the first data frame with the encoded data.
+pub const VERSION_CODE_V1: [u8; 8] = [68, 65, 67, 79, 0, 255, 0, 1];
+
+/// Marks the beginning of a calibration frame, see `FLAG_CALIBRATION_FRAME`.
+/// The level ramp starts with the first data unit following it.
+pub const CALIBRATION_MAGIC: [u8; 8] = [67, 65, 76, 73, 0, 255, 0, 1];
+
+/// Number of redundant copies of each header part in a header block.
+pub const HEADER_COPIES: usize = 3;
+
+/// Lenght in bytes of a single copy of the base header.
+pub const BASE_LEN: usize = 48;
+
+/// Lenght in bytes of a single copy of the header extension.
+pub const EXTENSION_LEN: usize = 512;
+
+/// Lenght in bytes of a version 1 header block.
+pub const BLOCK_LEN_V1: usize = BASE_LEN * HEADER_COPIES;
+
+/// Lenght in bytes of a header block.
+/// Version 3 and later blocks leave the last bytes zero to keep the length of version 2 blocks,
+/// so both span the same number of frames.
+pub const BLOCK_LEN: usize = (BASE_LEN + EXTENSION_LEN) * HEADER_COPIES;
+
+/// (Width, Height) Number of units in the fixed layout of header frames.
+/// Each unit spans the data units closest to it, so every data grid
+/// at least this fine can show the header frames.
+pub const HEADER_GRID: [u32; 2] = [64, 36];
+
+/// Bytes in each header frame, one bit per color channel of every unit of `HEADER_GRID`
+/// outside the corners reserved for finder patterns.
+pub const HEADER_FRAME_BYTES: usize = (HEADER_GRID[0] * HEADER_GRID[1]
+    - 4 * FINDER_RESERVED * FINDER_RESERVED) as usize
+    * COLOR_CHANNELS
+    / u8::BITS as usize;
+
+/// Number of frames spanned by a header block in the fixed header layout.
+pub const HEADER_BLOCK_FRAMES: usize = BLOCK_LEN.div_ceil(HEADER_FRAME_BYTES);
+
+/// Number of Reed-Solomon encoded copies of the header in a version 3 and later header block.
+pub const HEADER_RS_COPIES: usize = 2;
+
+/// Lenght in bytes of a single Reed-Solomon encoded copy of the base header and extension.
+const HEADER_RS_LEN: usize =
+    (BASE_LEN + EXTENSION_LEN).div_ceil(RS_CHUNK_BYTES_255_223) * RS_CHUNK_BYTES_TOTAL_255_223;
+
+/// Header flag set if the data is a text message passed on the command line
+/// instead of a file, so the decoder prints it instead of writing a file.
+pub const FLAG_MESSAGE: u32 = 1;
+
+/// Header flag set if the data frames use `LevelMapping::Even`
+/// instead of `LevelMapping::Midpoint`. Header frames always use the latter.
+pub const FLAG_EVEN_LEVELS: u32 = 1 << 1;
+
+/// Header flag set if the data was compressed with zstd before encoding,
+/// so the decoder decompresses it after error correction.
+/// Data length and hash describe the compressed data.
+pub const FLAG_COMPRESSED: u32 = 1 << 2;
+
+/// Header flag set if the data was encrypted before encoding.
+/// Data length and hash describe the encrypted data.
+pub const FLAG_ENCRYPTED: u32 = 1 << 3;
+
+/// Header flag set if the buffer frame counts are stored in the header extension.
+/// Videos without it were encoded with `PREBUFFER_FRAMES` and `POSTBUFFER_FRAMES`.
+pub const FLAG_BUFFER_FRAMES: u32 = 1 << 4;
+
+/// Header flag set if every data frame starts with its sequence number,
+/// so the decoder can place frames even if some were dropped or duplicated.
+/// Videos with parity frames always carry sequence numbers, even without the flag.
+pub const FLAG_SEQUENCE_NUMBERS: u32 = 1 << 5;
+
+/// Header flag set if the data values of each color channel in the data frames
+/// are the Gray code of the level they are shown as. Header frames are never Gray coded.
+pub const FLAG_GRAY_CODE: u32 = 1 << 6;
+
+/// Header flag set if a calibration frame showing every level of each color channel
+/// follows the header frames of every copy, preceding the data frames.
+pub const FLAG_CALIBRATION_FRAME: u32 = 1 << 7;
+
+/// Header flag set if the corners of every frame hold finder patterns instead of data.
+/// Only informational, the decoder has to know the layout to read the header.
+pub const FLAG_FINDER_PATTERNS: u32 = 1 << 8;
+
+/// Header flag set if the frames hold data in the alpha channel as a fourth channel,
+/// with the number of bits stored in the header extension.
+/// Only informational, the decoder has to know the layout to read the header.
+pub const FLAG_ALPHA_CHANNEL: u32 = 1 << 9;
+
+/// Header flag set if the data frames are gray, with every unit carrying its bits
+/// in the luminance. The decoder then reads the luma of the data units
+/// instead of their green channel.
+pub const FLAG_LUMA_ONLY: u32 = 1 << 10;
+
+/// Header flag set if the data is an archive of several files,
+/// to be unpacked into a directory when decoding, see `archive`.
+pub const FLAG_ARCHIVE: u32 = 1 << 11;
+
+/// Header flag set if every data, parity and droplet frame ends with a CRC-32
+/// of the rest of the frame, see `framing`. The decoder reports which frames fail it.
+pub const FLAG_FRAME_CRC: u32 = 1 << 12;
+
+/// Offset of the encryption salt in the header extension, directly followed by the nonce.
+const ENCRYPTION_OFFSET: usize = 34;
+
+/// Offset of the converter settings in the header extension.
+const CONVERTER_CONFIG_OFFSET: usize = 6;
+
+/// Offset of the error correction code in the header extension.
+const FEC_OFFSET: usize = 62;
+
+/// Offset of the interleave depth in the header extension.
+const INTERLEAVE_OFFSET: usize = 63;
+
+/// Offset of the prebuffer frame count in the header extension,
+/// directly followed by the postbuffer frame count.
+const BUFFER_FRAMES_OFFSET: usize = 65;
+
+/// Offset of the data frame count in the header extension.
+const DATA_FRAMES_OFFSET: usize = 69;
+
+/// Offset of the quantization bias in the header extension.
+const QUANTIZATION_BIAS_OFFSET: usize = 73;
+
+/// Offset of the number of bits encoded in the alpha channel in the header extension.
+const ALPHA_BITS_OFFSET: usize = 76;
+
+/// Offset of the fountain overhead in the header extension.
+const FOUNTAIN_OVERHEAD_OFFSET: usize = 77;
+
+/// Offset of the downsample scaler in the header extension.
+const DOWNSAMPLE_SCALER_OFFSET: usize = 79;
+
+/// Offset of the header interval in the header extension.
+const HEADER_INTERVAL_OFFSET: usize = 80;
+
+/// Offset of the hash algorithm in the header extension.
+const HASH_ALGORITHM_OFFSET: usize = 82;
+
+/// Offset of the colorspace in the header extension, directly followed by the color range.
+const COLOR_OFFSET: usize = 83;
+
+/// Offset of the note length in the header extension.
+const NOTE_OFFSET: usize = 128;
+
+/// Maximum length of the note in bytes.
+pub const NOTE_MAX_LEN: usize = 127;
+
+/// Offset of the file name length in the header extension.
+const FILE_NAME_OFFSET: usize = 256;
+
+/// Maximum length of the stored file name in bytes.
+pub const FILE_NAME_MAX_LEN: usize = 255;
+
+/// Device names Windows reserves in every directory, whatever extension follows them.
+const RESERVED_FILE_NAMES: [&str; 24] = [
+    "CON", "PRN", "AUX", "NUL", "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
+    "COM8", "COM9", "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+/// Data stored in a videos header.
+pub struct HeaderData {
+    /// Identifying what version the used converter is.
+    /// Also used as a "magic" number to identify the beginnig of
+    /// the first header frame.
+    pub version_code: [u8; 8],
+    /// Number of bytes that were encoded into the video.
+    pub data_len: usize,
+    /// Hash over the data, zero padded if shorter.
+    pub data_hash: [u8; HASH_LEN],
+    /// Algorithm `data_hash` was calculated with.
+    pub hash_algorithm: HashAlgorithm,
+    /// A parity frame follows every `parity_interval` data frames.
+    /// Zero if no parity frames are used.
+    pub parity_interval: u16,
+    /// Fountain droplet frames sent per hundred data frames, see `fountain`.
+    /// Zero if no droplets are used.
+    pub fountain_overhead: u16,
+    /// If every data frame starts with its sequence number, see `FLAG_SEQUENCE_NUMBERS`.
+    pub sequence_numbers: bool,
+    /// If every data frame ends with a CRC-32, see `FLAG_FRAME_CRC`.
+    pub frame_crc: bool,
+    /// If the data is a text message instead of a file.
+    pub is_message: bool,
+    /// If the data is an archive of several files, see `FLAG_ARCHIVE`.
+    pub is_archive: bool,
+    /// If the data was compressed with zstd before encoding.
+    pub is_compressed: bool,
+    /// Salt and nonce if the data was encrypted before encoding.
+    pub encryption: Option<EncryptionParams>,
+    /// Free text note, e.g. instructions on how to decode the video.
+    /// Empty if not set.
+    pub note: String,
+    /// How channel levels are mapped to pixel values in the data frames.
+    pub level_mapping: LevelMapping,
+    /// Colorspace and range the video was encoded with.
+    pub color: VideoColor,
+    /// If channel data values are the Gray code of the level they are shown as,
+    /// so a level misread as its neighbour flips a single bit.
+    pub gray_code: bool,
+    /// If a calibration frame precedes the data frames, see `FLAG_CALIBRATION_FRAME`.
+    /// Filled in by the converter when generating header frames.
+    pub calibration_frame: bool,
+    /// If the corners of every frame hold finder patterns, see `FLAG_FINDER_PATTERNS`.
+    /// Filled in by the converter when generating header frames.
+    pub finder_patterns: bool,
+    /// Bits encoded in the alpha channel of every data unit, see `FLAG_ALPHA_CHANNEL`.
+    /// Zero if the frames only hold data in the color channels.
+    /// Filled in by the converter when generating header frames.
+    pub alpha_bits: u32,
+    /// If the frames are gray with all data in the luminance, see `FLAG_LUMA_ONLY`.
+    /// Filled in by the converter when generating header frames.
+    pub luma_only: bool,
+    /// Multiple of the data resolution frames are scaled to when splitting the video,
+    /// see `Converter::with_downsample_scaler`. Zero if not stored.
+    /// Filled in by the converter when generating header frames.
+    pub downsample_scaler: u32,
+    /// A header block is repeated after every `header_interval` data, parity and droplet frames,
+    /// see `Converter::with_header_interval`. Zero if the header is only at the start.
+    /// Filled in by the converter when generating header frames.
+    pub header_interval: u16,
+    /// Error correction code protecting the data frames.
+    pub fec: FecKind,
+    /// Number of error correction code words interleaved with each other,
+    /// one if not interleaved.
+    pub interleave_depth: u16,
+    /// Number of blank frames before the first header frame.
+    pub prebuffer_frames: u16,
+    /// Number of blank frames after the last data frame.
+    pub postbuffer_frames: u16,
+    /// Number of data and parity frames following the header frames of each copy.
+    /// Filled in by the converter when generating header frames,
+    /// as it depends on the frame size. `None` if not stored.
+    pub data_frames: Option<usize>,
+    /// Offset the encoder added to the pixel value of every level. Order: RGB
+    /// Filled in by the converter when generating header frames.
+    /// Only informational, used to calibrate the bias of later encodes.
+    pub quantization_bias: [i8; COLOR_CHANNELS],
+    /// Name of the encoded file without any directories.
+    /// Empty if not stored, e.g. for messages.
+    pub file_name: String,
+    /// Settings of the converter that encoded the video, as returned by
+    /// `Converter::config_bytes`. `None` for videos encoded before they were stored.
+    pub converter_config: Option<[u8; CONVERTER_CONFIG_LEN]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Outcome of the majority vote over all header copies.
+pub struct HeaderVote {
+    /// Number of redundant header copies read.
+    pub copies: usize,
+    /// Number of header copies identical to the majority voted header.
+    pub agreeing_copies: usize,
+}
+
+impl HeaderData {
+    #[must_use]
+    /// Create the header describing `data`, hashed with SHA-256.
+    ///
+    /// # Arguments
+    /// * `data` - The data to generate a header for.
+    /// * `parity_interval` - Number of data frames covered by each parity frame.
+    pub fn new(data: &[u8], parity_interval: u16) -> Self {
+        Self::with_hash_algorithm(data, parity_interval, HashAlgorithm::Sha256)
+    }
+
+    #[must_use]
+    /// Create the header describing `data`, hashed with `hash_algorithm`.
+    ///
+    /// # Arguments
+    /// * `data` - The data to generate a header for.
+    /// * `parity_interval` - Number of data frames covered by each parity frame.
+    /// * `hash_algorithm` - Hash the decoded data is checked with.
+    pub fn with_hash_algorithm(
+        data: &[u8],
+        parity_interval: u16,
+        hash_algorithm: HashAlgorithm,
+    ) -> Self {
+        Self {
+            version_code: VERSION_CODE,
+            data_len: data.len(),
+            data_hash: hash_algorithm.digest(data),
+            hash_algorithm,
+            parity_interval,
+            fountain_overhead: 0,
+            sequence_numbers: true,
+            frame_crc: true,
+            is_message: false,
+            is_archive: false,
+            is_compressed: false,
+            encryption: None,
+            note: String::new(),
+            level_mapping: LevelMapping::Midpoint,
+            color: VideoColor::default(),
+            gray_code: true,
+            calibration_frame: false,
+            finder_patterns: false,
+            alpha_bits: 0,
+            luma_only: false,
+            downsample_scaler: 0,
+            header_interval: 0,
+            fec: FecKind::Hamming,
+            interleave_depth: 1,
+            prebuffer_frames: PREBUFFER_FRAMES,
+            postbuffer_frames: POSTBUFFER_FRAMES,
+            data_frames: None,
+            quantization_bias: [0; COLOR_CHANNELS],
+            file_name: String::new(),
+            converter_config: None,
+        }
+    }
+
+    #[must_use]
+    /// Number of blank frames before and after the data, as passed to
+    /// `Converter::encoded_frame_count`.
+    pub fn buffer_frames(&self) -> [u16; 2] {
+        [self.prebuffer_frames, self.postbuffer_frames]
+    }
+
+    /// Sets the note stored in the header.
+    ///
+    /// # Arguments
+    /// * `note` - Free text note, at most `NOTE_MAX_LEN` bytes.
+    ///
+    /// # Errors
+    /// Fails if `note` is longer than `NOTE_MAX_LEN` bytes.
+    pub fn with_note(mut self, note: &str) -> Result<Self> {
+        if note.len() > NOTE_MAX_LEN {
+            bail!(
+                "Note is {} bytes long, at most {NOTE_MAX_LEN} bytes fit into the header.",
+                note.len()
+            );
+        }
+        self.note = note.to_string();
+        Ok(self)
+    }
+
+    /// Sets the original file name stored in the header.
+    ///
+    /// # Arguments
+    /// * `file_name` - Name of the encoded file, at most `FILE_NAME_MAX_LEN` bytes.
+    ///
+    /// # Errors
+    /// Fails if `file_name` is longer than `FILE_NAME_MAX_LEN` bytes.
+    pub fn with_file_name(mut self, file_name: &str) -> Result<Self> {
+        if file_name.len() > FILE_NAME_MAX_LEN {
+            bail!(
+                "File name is {} bytes long, at most {FILE_NAME_MAX_LEN} bytes fit into the header.",
+                file_name.len()
+            );
+        }
+        self.file_name = file_name.to_string();
+        Ok(self)
+    }
+
+    #[must_use]
+    /// File name to restore the decoded data as, safe to join onto an output directory.
+    /// Path separators and drive or stream colons are replaced,
+    /// so a crafted header can not write outside the directory.
+    /// `None` if no usable name is stored, or it is a device name reserved on Windows.
+    pub fn safe_file_name(&self) -> Option<String> {
+        let file_name: String = self
+            .file_name
+            .chars()
+            .map(|c| {
+                if matches!(c, '/' | '\\' | ':' | '\0') {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        // Windows ignores the extension and trailing spaces, `nul .txt` is the device too.
+        let stem = file_name.split('.').next().unwrap_or_default().trim_end();
+        if RESERVED_FILE_NAMES
+            .iter()
+            .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        {
+            return None;
+        }
+        let mut components = Path::new(&file_name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Some(file_name),
+            _ => None,
+        }
+    }
+
+    /// Flags stored in the header extension.
+    fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.is_message {
+            flags |= FLAG_MESSAGE;
+        }
+        if self.is_archive {
+            flags |= FLAG_ARCHIVE;
+        }
+        if self.level_mapping == LevelMapping::Even {
+            flags |= FLAG_EVEN_LEVELS;
+        }
+        if self.is_compressed {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.encryption.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if self.sequence_numbers {
+            flags |= FLAG_SEQUENCE_NUMBERS;
+        }
+        if self.frame_crc {
+            flags |= FLAG_FRAME_CRC;
+        }
+        if self.gray_code {
+            flags |= FLAG_GRAY_CODE;
+        }
+        if self.calibration_frame {
+            flags |= FLAG_CALIBRATION_FRAME;
+        }
+        if self.finder_patterns {
+            flags |= FLAG_FINDER_PATTERNS;
+        }
+        if self.alpha_bits > 0 {
+            flags |= FLAG_ALPHA_CHANNEL;
+        }
+        if self.luma_only {
+            flags |= FLAG_LUMA_ONLY;
+        }
+        flags | FLAG_BUFFER_FRAMES
+    }
+
+    /// Serializes a single copy of the base header.
+    fn base_bytes(&self) -> [u8; BASE_LEN] {
+        let mut base = [0u8; BASE_LEN];
+        base[0..8].copy_from_slice(&self.version_code);
+        base[8..16].copy_from_slice(&(self.data_len as u64).to_le_bytes());
+        base[16..48].copy_from_slice(&self.data_hash);
+        base
+    }
+
+    /// Serializes a single copy of the header extension.
+    fn extension_bytes(&self) -> [u8; EXTENSION_LEN] {
+        let mut extension = [0u8; EXTENSION_LEN];
+        extension[0..4].copy_from_slice(&self.flags().to_le_bytes());
+        extension[4..6].copy_from_slice(&self.parity_interval.to_le_bytes());
+        if let Some(converter_config) = &self.converter_config {
+            extension[CONVERTER_CONFIG_OFFSET..CONVERTER_CONFIG_OFFSET + CONVERTER_CONFIG_LEN]
+                .copy_from_slice(converter_config);
+        }
+        if let Some(encryption) = &self.encryption {
+            let nonce_offset = ENCRYPTION_OFFSET + SALT_LEN;
+            extension[ENCRYPTION_OFFSET..nonce_offset].copy_from_slice(&encryption.salt);
+            extension[nonce_offset..nonce_offset + NONCE_LEN].copy_from_slice(&encryption.nonce);
+        }
+        extension[FEC_OFFSET] = self.fec.to_code();
+        extension[HASH_ALGORITHM_OFFSET] = self.hash_algorithm.to_code();
+        extension[COLOR_OFFSET] = self.color.colorspace.to_code();
+        extension[COLOR_OFFSET + 1] = self.color.range.to_code();
+        extension[INTERLEAVE_OFFSET..INTERLEAVE_OFFSET + 2]
+            .copy_from_slice(&self.interleave_depth.to_le_bytes());
+        extension[BUFFER_FRAMES_OFFSET..BUFFER_FRAMES_OFFSET + 2]
+            .copy_from_slice(&self.prebuffer_frames.to_le_bytes());
+        extension[BUFFER_FRAMES_OFFSET + 2..BUFFER_FRAMES_OFFSET + 4]
+            .copy_from_slice(&self.postbuffer_frames.to_le_bytes());
+        // Counts too large to store are left unknown.
+        let data_frames = self
+            .data_frames
+            .and_then(|data_frames| u32::try_from(data_frames).ok())
+            .unwrap_or_default();
+        extension[DATA_FRAMES_OFFSET..DATA_FRAMES_OFFSET + 4]
+            .copy_from_slice(&data_frames.to_le_bytes());
+        extension[QUANTIZATION_BIAS_OFFSET..QUANTIZATION_BIAS_OFFSET + COLOR_CHANNELS]
+            .copy_from_slice(&self.quantization_bias.map(i8::to_le_bytes).concat());
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            extension[ALPHA_BITS_OFFSET] = self.alpha_bits as u8;
+        }
+        extension[FOUNTAIN_OVERHEAD_OFFSET..FOUNTAIN_OVERHEAD_OFFSET + 2]
+            .copy_from_slice(&self.fountain_overhead.to_le_bytes());
+        // Scalers too large to store are left unknown.
+        extension[DOWNSAMPLE_SCALER_OFFSET] = u8::try_from(self.downsample_scaler).unwrap_or(0);
+        extension[HEADER_INTERVAL_OFFSET..HEADER_INTERVAL_OFFSET + 2]
+            .copy_from_slice(&self.header_interval.to_le_bytes());
+        let note = self.note.as_bytes();
+        let note_len = note.len().min(NOTE_MAX_LEN);
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            extension[NOTE_OFFSET] = note_len as u8;
+        }
+        extension[NOTE_OFFSET + 1..NOTE_OFFSET + 1 + note_len].copy_from_slice(&note[..note_len]);
+        let file_name = self.file_name.as_bytes();
+        let file_name_len = file_name.len().min(FILE_NAME_MAX_LEN);
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            extension[FILE_NAME_OFFSET] = file_name_len as u8;
+        }
+        extension[FILE_NAME_OFFSET + 1..FILE_NAME_OFFSET + 1 + file_name_len]
+            .copy_from_slice(&file_name[..file_name_len]);
+        extension
+    }
+
+    /// Generates a header block containing three copies of the base header
+    /// followed by two Reed-Solomon encoded copies of the base header and extension.
+    ///
+    /// # Errors
+    /// Fails if the header can not be Reed-Solomon encoded.
+    pub fn to_block(&self) -> Result<[u8; BLOCK_LEN]> {
+        let mut block = [0u8; BLOCK_LEN];
+        let (base_part, rs_part) = block.split_at_mut(BLOCK_LEN_V1);
+        let base = self.base_bytes();
+        for copy in base_part.chunks_exact_mut(BASE_LEN) {
+            copy.copy_from_slice(&base);
+        }
+        let mut header = base.to_vec();
+        header.extend_from_slice(&self.extension_bytes());
+        header.resize(header.len().next_multiple_of(RS_CHUNK_BYTES_255_223), 0);
+        let encoded = encode_with_reed_solomon_255_223(&header)?;
+        for copy in rs_part
+            .chunks_exact_mut(HEADER_RS_LEN)
+            .take(HEADER_RS_COPIES)
+        {
+            copy.copy_from_slice(&encoded);
+        }
+        Ok(block)
+    }
+
+    /// Reads the version code of a header block by majority vote over its base copies.
+    /// Works for all header versions since the base copies are always at the start.
+    ///
+    /// # Arguments
+    /// * `block` - At least the first `BLOCK_LEN_V1` bytes of a header block.
+    ///
+    /// # Errors
+    /// Fails if `block` is shorter than `BLOCK_LEN_V1` bytes.
+    pub fn read_version_code(block: &[u8]) -> Result<[u8; 8]> {
+        let base_part = block
+            .get(..BLOCK_LEN_V1)
+            .context("Not enough data to read header version.")?;
+        let copies: Vec<&[u8]> = base_part
+            .chunks_exact(BASE_LEN)
+            .map(|copy| &copy[0..8])
+            .collect();
+        let (version_code, _) = majority_vote(&copies);
+        Ok(version_code.as_slice().try_into()?)
+    }
+
+    /// Decodes the header from all available header blocks,
+    /// see `vote_rs_copies` and `vote_plain_copies`.
+    ///
+    /// # Arguments
+    /// * `blocks` - Header blocks read from the video. All must have the same version.
+    /// * `version_code` - Version of the blocks, as returned by `read_version_code`.
+    ///
+    /// # Errors
+    /// Fails if no block is given, the version is unknown, a block is too short
+    /// or the voted header holds invalid values.
+    pub fn from_blocks(blocks: &[&[u8]], version_code: [u8; 8]) -> Result<(Self, HeaderVote)> {
+        if blocks.is_empty() {
+            bail!("No complete header block found.");
+        }
+        let (majority, vote) = match version_code {
+            VERSION_CODE | VERSION_CODE_V3 => vote_rs_copies(blocks)?,
+            VERSION_CODE_V2 => vote_plain_copies(blocks, BLOCK_LEN, EXTENSION_LEN)?,
+            VERSION_CODE_V1 => vote_plain_copies(blocks, BLOCK_LEN_V1, 0)?,
+            _ => bail!("Unknown header version {version_code:?}."),
+        };
+
+        let data_len: usize = u64::from_le_bytes(majority[8..16].try_into()?)
+            .try_into()
+            .context("Read data lenght wont fit into pointer type.")?;
+        let extension = &majority[BASE_LEN..];
+        let flags = match extension.get(0..4) {
+            Some(bytes) => u32::from_le_bytes(bytes.try_into()?),
+            None => 0,
+        };
+        let parity_interval = match extension.get(4..6) {
+            Some(bytes) => u16::from_le_bytes(bytes.try_into()?),
+            None => 0,
+        };
+        let fountain_overhead = read_u16(extension, FOUNTAIN_OVERHEAD_OFFSET);
+        let encryption = read_encryption(extension, flags)?;
+        // Zero selects Hamming, which protected the data before the code was stored.
+        let fec = match extension.get(FEC_OFFSET) {
+            Some(&code) => FecKind::from_code(code)?,
+            None => FecKind::Hamming,
+        };
+        // Zero selects SHA-256, the only hash before the algorithm was stored.
+        let hash_algorithm = match extension.get(HASH_ALGORITHM_OFFSET) {
+            Some(&code) => HashAlgorithm::from_code(code)?,
+            None => HashAlgorithm::Sha256,
+        };
+        // Zero selects BT.709 in limited range, the only colors before they were stored.
+        let color = VideoColor {
+            colorspace: match extension.get(COLOR_OFFSET) {
+                Some(&code) => Colorspace::from_code(code)?,
+                None => Colorspace::Bt709,
+            },
+            range: match extension.get(COLOR_OFFSET + 1) {
+                Some(&code) => ColorRange::from_code(code)?,
+                None => ColorRange::Tv,
+            },
+        };
+        let interleave_depth = match extension.get(INTERLEAVE_OFFSET..INTERLEAVE_OFFSET + 2) {
+            Some(bytes) => u16::from_le_bytes(bytes.try_into()?).max(1),
+            None => 1,
+        };
+        let [prebuffer_frames, postbuffer_frames] = read_buffer_frames(extension, flags)?;
+        let data_frames = extension
+            .get(DATA_FRAMES_OFFSET..DATA_FRAMES_OFFSET + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+            .filter(|&data_frames| data_frames != 0);
+        // The version byte of the settings is never zero, so zero means they were not stored.
+        let converter_config = extension
+            .get(CONVERTER_CONFIG_OFFSET..CONVERTER_CONFIG_OFFSET + CONVERTER_CONFIG_LEN)
+            .filter(|bytes| bytes[0] != 0)
+            .map(TryInto::try_into)
+            .transpose()?;
+        let note = read_text(extension, NOTE_OFFSET, NOTE_MAX_LEN);
+        let file_name = read_text(extension, FILE_NAME_OFFSET, FILE_NAME_MAX_LEN);
+        Ok((
+            Self {
+                version_code: majority[0..8].try_into()?,
+                data_len,
+                data_hash: majority[16..48].try_into()?,
+                hash_algorithm,
+                parity_interval,
+                fountain_overhead,
+                sequence_numbers: flags & FLAG_SEQUENCE_NUMBERS != 0
+                    || parity_interval > 0
+                    || fountain_overhead > 0,
+                frame_crc: flags & FLAG_FRAME_CRC != 0,
+                is_message: flags & FLAG_MESSAGE != 0,
+                is_archive: flags & FLAG_ARCHIVE != 0,
+                is_compressed: flags & FLAG_COMPRESSED != 0,
+                encryption,
+                note,
+                level_mapping: if flags & FLAG_EVEN_LEVELS == 0 {
+                    LevelMapping::Midpoint
+                } else {
+                    LevelMapping::Even
+                },
+                color,
+                gray_code: flags & FLAG_GRAY_CODE != 0,
+                calibration_frame: flags & FLAG_CALIBRATION_FRAME != 0,
+                finder_patterns: flags & FLAG_FINDER_PATTERNS != 0,
+                alpha_bits: read_alpha_bits(extension, flags),
+                luma_only: flags & FLAG_LUMA_ONLY != 0,
+                downsample_scaler: extension
+                    .get(DOWNSAMPLE_SCALER_OFFSET)
+                    .map_or(0, |&scaler| u32::from(scaler)),
+                header_interval: read_u16(extension, HEADER_INTERVAL_OFFSET),
+                fec,
+                interleave_depth,
+                prebuffer_frames,
+                postbuffer_frames,
+                data_frames,
+                quantization_bias: read_quantization_bias(extension),
+                file_name,
+                converter_config,
+            },
+            vote,
+        ))
+    }
+}
+
+/// Reads a little-endian u16 from a header extension, zero if the extension ends before it.
+///
+/// # Arguments
+/// * `extension` - Majority voted header extension.
+/// * `offset` - Offset of the value in the extension.
+fn read_u16(extension: &[u8], offset: usize) -> u16 {
+    extension
+        .get(offset..offset + 2)
+        .map_or(0, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads the salt and nonce from a header extension, `None` if the data is not encrypted.
+///
+/// # Arguments
+/// * `extension` - Majority voted header extension.
+/// * `flags` - Flags read from the extension.
+fn read_encryption(extension: &[u8], flags: u32) -> Result<Option<EncryptionParams>> {
+    if flags & FLAG_ENCRYPTED == 0 {
+        return Ok(None);
+    }
+    let nonce_offset = ENCRYPTION_OFFSET + SALT_LEN;
+    Ok(Some(EncryptionParams {
+        salt: extension[ENCRYPTION_OFFSET..nonce_offset].try_into()?,
+        nonce: extension[nonce_offset..nonce_offset + NONCE_LEN].try_into()?,
+    }))
+}
+
+/// Reads the buffer frame counts from a header extension,
+/// falling back to the fixed counts used before they were stored.
+///
+/// # Arguments
+/// * `extension` - Majority voted header extension, empty for version 1 headers.
+/// * `flags` - Flags read from `extension`.
+fn read_buffer_frames(extension: &[u8], flags: u32) -> Result<[u16; 2]> {
+    if flags & FLAG_BUFFER_FRAMES == 0 {
+        return Ok([PREBUFFER_FRAMES, POSTBUFFER_FRAMES]);
+    }
+    let bytes = extension
+        .get(BUFFER_FRAMES_OFFSET..BUFFER_FRAMES_OFFSET + 4)
+        .context("Header extension is too short to hold the buffer frame counts.")?;
+    Ok([
+        u16::from_le_bytes([bytes[0], bytes[1]]),
+        u16::from_le_bytes([bytes[2], bytes[3]]),
+    ])
+}
+
+/// Reads the number of bits encoded in the alpha channel from a header extension,
+/// zero if the frames only hold data in the color channels.
+///
+/// # Arguments
+/// * `extension` - Majority voted header extension, empty for version 1 headers.
+/// * `flags` - Flags read from `extension`.
+fn read_alpha_bits(extension: &[u8], flags: u32) -> u32 {
+    if flags & FLAG_ALPHA_CHANNEL == 0 {
+        return 0;
+    }
+    extension
+        .get(ALPHA_BITS_OFFSET)
+        .map_or(0, |&bits| u32::from(bits))
+}
+
+/// Reads the quantization bias the data frames were encoded with from a header extension,
+/// zero for every channel if not stored.
+///
+/// # Arguments
+/// * `extension` - Majority voted header extension, empty for version 1 headers.
+fn read_quantization_bias(extension: &[u8]) -> [i8; COLOR_CHANNELS] {
+    extension
+        .get(QUANTIZATION_BIAS_OFFSET..QUANTIZATION_BIAS_OFFSET + COLOR_CHANNELS)
+        .map_or([0; COLOR_CHANNELS], |bytes| {
+            [bytes[0], bytes[1], bytes[2]].map(|byte| i8::from_le_bytes([byte]))
+        })
+}
+
+/// Reads a length prefixed text field from a header extension.
+/// A corrupted text should not prevent decoding, so invalid UTF-8 is replaced.
+/// Returns an empty string for version 1 headers.
+///
+/// # Arguments
+/// * `extension` - Majority voted header extension, empty for version 1 headers.
+/// * `offset` - Offset of the length byte, directly followed by the text.
+/// * `max_len` - Maximum length of the text in bytes.
+fn read_text(extension: &[u8], offset: usize, max_len: usize) -> String {
+    match extension.get(offset) {
+        Some(&len) => {
+            let len = (len as usize).min(max_len);
+            String::from_utf8_lossy(&extension[offset + 1..offset + 1 + len]).into_owned()
+        }
+        None => String::new(),
+    }
+}
+
+/// Decodes the header from version 1 and 2 header blocks
+/// by bitwise majority vote over the plain copies they contain,
+/// each made up of a base and its matching extension copy.
+/// Returns the voted base header followed by the extension.
+///
+/// # Arguments
+/// * `blocks` - Header blocks read from the video.
+/// * `block_len` - Length of a header block of their version.
+/// * `extension_len` - Length of a header extension copy, zero for version 1.
+fn vote_plain_copies(
+    blocks: &[&[u8]],
+    block_len: usize,
+    extension_len: usize,
+) -> Result<(Vec<u8>, HeaderVote)> {
+    let mut copies: Vec<Vec<u8>> = Vec::with_capacity(blocks.len() * HEADER_COPIES);
+    for block in blocks {
+        let block = block
+            .get(..block_len)
+            .context("Header block is shorter than expected.")?;
+        let (base_part, extension_part) = block.split_at(BASE_LEN * HEADER_COPIES);
+        for i in 0..HEADER_COPIES {
+            let mut copy = base_part[i * BASE_LEN..(i + 1) * BASE_LEN].to_vec();
+            copy.extend_from_slice(&extension_part[i * extension_len..(i + 1) * extension_len]);
+            copies.push(copy);
+        }
+    }
+    let copy_refs: Vec<&[u8]> = copies.iter().map(Vec::as_slice).collect();
+    let (majority, agreeing_copies) = majority_vote(&copy_refs);
+    Ok((
+        majority,
+        HeaderVote {
+            copies: copies.len(),
+            agreeing_copies,
+        },
+    ))
+}
+
+/// Decodes the header from version 3 and later header blocks.
+/// Every Reed-Solomon code word is taken from the first candidate that decodes without
+/// uncorrectable errors: the bitwise majority over all copies if there are at least three,
+/// then every copy in turn. Up to 16 damaged bytes per code word are corrected,
+/// even if the same bits are damaged in several copies.
+/// If no candidate decodes, the data bytes of the first candidate are used as read.
+/// Returns the base header followed by the extension.
+///
+/// # Arguments
+/// * `blocks` - Header blocks read from the video.
+fn vote_rs_copies(blocks: &[&[u8]]) -> Result<(Vec<u8>, HeaderVote)> {
+    let mut copies: Vec<&[u8]> = Vec::with_capacity(blocks.len() * HEADER_RS_COPIES);
+    for block in blocks {
+        let rs_part = block
+            .get(BLOCK_LEN_V1..BLOCK_LEN_V1 + HEADER_RS_COPIES * HEADER_RS_LEN)
+            .context("Header block is shorter than expected.")?;
+        copies.extend(rs_part.chunks_exact(HEADER_RS_LEN));
+    }
+    let (majority, _) = majority_vote(&copies);
+    let mut candidates = Vec::with_capacity(copies.len() + 1);
+    if copies.len() >= HEADER_COPIES {
+        candidates.push(majority.as_slice());
+    }
+    candidates.extend(copies.iter().copied());
+    // Data bytes and whether each code word was decoded, for every candidate.
+    let decoded = candidates
+        .iter()
+        .map(|candidate| {
+            let (data, _, reports) = decode_with_reed_solomon_255_223_blocks(
+                candidate,
+                RS_CHUNK_BYTES_TOTAL_255_223,
+                0,
+            )?;
+            let valid: Vec<bool> = reports
+                .iter()
+                .map(|report| report.uncorrected_errors == 0)
+                .collect();
+            Ok((data, valid))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (fallback, _) = &decoded[0];
+    let mut header = Vec::with_capacity(fallback.len());
+    for (word, fallback_word) in fallback.chunks(RS_CHUNK_BYTES_255_223).enumerate() {
+        let word_data = decoded
+            .iter()
+            .find(|(_, valid)| valid[word])
+            .map_or(fallback_word, |(data, _)| {
+                &data[word * RS_CHUNK_BYTES_255_223..(word + 1) * RS_CHUNK_BYTES_255_223]
+            });
+        header.extend_from_slice(word_data);
+    }
+    // Only copies decoding to the chosen header in full agree with it.
+    let skipped = candidates.len() - copies.len();
+    let agreeing_copies = decoded[skipped..]
+        .iter()
+        .filter(|(data, valid)| valid.iter().all(|&valid| valid) && *data == header)
+        .count();
+    header.truncate(BASE_LEN + EXTENSION_LEN);
+    Ok((
+        header,
+        HeaderVote {
+            copies: copies.len(),
+            agreeing_copies,
+        },
+    ))
+}
+
+/// Bitwise majority vote over equally long copies.
+/// A bit is set if it is set in more than half of the copies.
+/// Also returns how many copies were identical to the result.
+///
+/// # Arguments
+/// * `copies` - Redundant copies to vote over.
+fn majority_vote(copies: &[&[u8]]) -> (Vec<u8>, usize) {
+    let len = copies.first().map_or(0, |copy| copy.len());
+    let mut majority = vec![0u8; len];
+    for (i, majority_byte) in majority.iter_mut().enumerate() {
+        for bit in 0..u8::BITS {
+            let set_count = copies
+                .iter()
+                .filter(|copy| (copy[i] >> bit) & 1 == 1)
+                .count();
+            if set_count * 2 > copies.len() {
+                *majority_byte |= 1 << bit;
+            }
+        }
+    }
+    let agreeing_copies = copies.iter().filter(|&&copy| copy == majority).count();
+    (majority, agreeing_copies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::RS_PARITY_BYTES_255_223;
+
+    /// Header of a small file with a name and note, filling part of the extension.
+    fn test_header() -> HeaderData {
+        let mut header = HeaderData::new(b"header test data", 0);
+        header.file_name = "header.bin".to_string();
+        header.note = "Header corruption test".to_string();
+        header
+    }
+
+    /// Flips every bit of `len` bytes from `start` in `copy`.
+    fn damage(copy: &mut [u8], start: usize, len: usize) {
+        for byte in &mut copy[start..start + len] {
+            *byte ^= 0xFF;
+        }
+    }
+
+    #[test]
+    fn header_block_round_trips() -> Result<()> {
+        let header = test_header();
+        let block = header.to_block()?;
+        assert_eq!(HeaderData::read_version_code(&block)?, VERSION_CODE);
+        let (decoded, vote) = HeaderData::from_blocks(&[&block], VERSION_CODE)?;
+        assert_eq!(decoded, header);
+        assert_eq!(vote.agreeing_copies, HEADER_RS_COPIES);
+        Ok(())
+    }
+
+    #[test]
+    fn header_survives_damage_defeating_majority_vote() -> Result<()> {
+        let header = test_header();
+        let mut block = header.to_block()?;
+        // The same bytes after the version code are damaged in two of the three base copies,
+        // so the majority vote over them is wrong.
+        for copy in block[..BLOCK_LEN_V1].chunks_exact_mut(BASE_LEN).take(2) {
+            damage(copy, 8, 24);
+        }
+        let base_copies: Vec<&[u8]> = block[..BLOCK_LEN_V1].chunks_exact(BASE_LEN).collect();
+        assert_ne!(majority_vote(&base_copies).0, header.base_bytes());
+
+        // Both Reed-Solomon copies are damaged alike, 16 bytes in every code word.
+        let rs_part = &mut block[BLOCK_LEN_V1..BLOCK_LEN_V1 + HEADER_RS_COPIES * HEADER_RS_LEN];
+        for copy in rs_part.chunks_exact_mut(HEADER_RS_LEN) {
+            for word in copy.chunks_exact_mut(RS_CHUNK_BYTES_TOTAL_255_223) {
+                damage(word, 20, RS_PARITY_BYTES_255_223 / 2);
+            }
+        }
+
+        let (decoded, vote) = HeaderData::from_blocks(&[&block], VERSION_CODE)?;
+        assert_eq!(decoded, header);
+        assert_eq!(vote.agreeing_copies, HEADER_RS_COPIES);
+        Ok(())
+    }
+
+    #[test]
+    fn safe_file_name_stays_inside_the_output_directory() {
+        let safe_name = |file_name: &str| {
+            let mut header = test_header();
+            header.file_name = file_name.to_string();
+            header.safe_file_name()
+        };
+        assert_eq!(safe_name("report.pdf").as_deref(), Some("report.pdf"));
+        assert_eq!(safe_name("../etc/passwd").as_deref(), Some(".._etc_passwd"));
+        assert_eq!(safe_name("C:\\boot.ini").as_deref(), Some("C__boot.ini"));
+        assert_eq!(
+            safe_name("file.txt:stream").as_deref(),
+            Some("file.txt_stream")
+        );
+        for unusable in [
+            "",
+            ".",
+            "..",
+            "CON",
+            "nul.txt",
+            "Com1.tar.gz",
+            "lpt9",
+            "aux .log",
+        ] {
+            assert_eq!(safe_name(unusable), None, "{unusable:?}");
+        }
+        assert_eq!(safe_name("console.txt").as_deref(), Some("console.txt"));
+    }
+
+    #[test]
+    fn header_without_blocks_fails() {
+        assert!(HeaderData::from_blocks(&[], VERSION_CODE).is_err());
+        assert!(HeaderData::read_version_code(&[0; BLOCK_LEN_V1 - 1]).is_err());
+    }
+}
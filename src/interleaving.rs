@@ -0,0 +1,166 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Block interleaving of error correction code words.
+//!
+//! A damaged region of a frame corrupts a contiguous run of the stream.
+//! Interleaving writes groups of `depth` code words symbol by symbol in turn,
+//! so such a burst is spread across many code words, each receiving
+//! few enough errors to be corrected.
+
+use anyhow::{Result, bail};
+
+/// Interleaves the code words in `data`.
+/// Every group of `depth` consecutive code words is written as its first symbols,
+/// followed by its second symbols and so on.
+/// The last group may hold fewer code words.
+///
+/// # Arguments
+/// * `data` - Error correction encoded stream, a whole number of code words.
+/// * `word_symbols` - Number of symbols in a code word.
+/// * `symbol_bits` - Bits in a symbol, one or eight.
+/// * `depth` - Number of code words interleaved with each other. One leaves `data` unchanged.
+pub fn interleave(
+    data: &[u8],
+    word_symbols: usize,
+    symbol_bits: usize,
+    depth: usize,
+) -> Result<Vec<u8>> {
+    permute(data, word_symbols, symbol_bits, depth, false)
+}
+
+/// Reverts `interleave`, restoring the original order of the code words.
+///
+/// # Arguments
+/// * `data` - Interleaved stream.
+/// * `word_symbols` - Number of symbols in a code word.
+/// * `symbol_bits` - Bits in a symbol, one or eight.
+/// * `depth` - Depth `data` was interleaved with.
+pub fn deinterleave(
+    data: &[u8],
+    word_symbols: usize,
+    symbol_bits: usize,
+    depth: usize,
+) -> Result<Vec<u8>> {
+    permute(data, word_symbols, symbol_bits, depth, true)
+}
+
+/// Moves every symbol of `data` between its position in code word order
+/// and its interleaved position.
+///
+/// # Arguments
+/// * `data` - Stream to permute.
+/// * `word_symbols` - Number of symbols in a code word.
+/// * `symbol_bits` - Bits in a symbol, one or eight.
+/// * `depth` - Number of code words interleaved with each other.
+/// * `inverse` - If interleaved positions are moved back to code word order.
+fn permute(
+    data: &[u8],
+    word_symbols: usize,
+    symbol_bits: usize,
+    depth: usize,
+    inverse: bool,
+) -> Result<Vec<u8>> {
+    if !matches!(symbol_bits, 1 | 8) {
+        bail!("Interleaving supports one or eight bit symbols, not {symbol_bits}.");
+    }
+    if depth == 0 || word_symbols == 0 {
+        bail!("Interleave depth and code word length must not be zero.");
+    }
+    let word_bits = word_symbols * symbol_bits;
+    let data_bits = data.len() * u8::BITS as usize;
+    if !data_bits.is_multiple_of(word_bits) {
+        bail!("Data is not a whole number of {word_bits} bit code words.");
+    }
+    if depth == 1 {
+        return Ok(data.to_vec());
+    }
+
+    let words = data_bits / word_bits;
+    let mut output = vec![0u8; data.len()];
+    for group_start in (0..words).step_by(depth) {
+        let group_words = depth.min(words - group_start);
+        let group_base = group_start * word_symbols;
+        for word in 0..group_words {
+            for symbol in 0..word_symbols {
+                let plain = group_base + word * word_symbols + symbol;
+                let interleaved = group_base + symbol * group_words + word;
+                let (from, to) = if inverse {
+                    (interleaved, plain)
+                } else {
+                    (plain, interleaved)
+                };
+                if symbol_bits == 8 {
+                    output[to] = data[from];
+                } else {
+                    // Bits are numbered from the most significant bit of each byte.
+                    let bit = (data[from / 8] >> (7 - from % 8)) & 1;
+                    output[to / 8] |= bit << (7 - to % 8);
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error_correction::FecKind;
+
+    /// Encodes `data` with `fec`, interleaves it with `depth`, damages `burst` bytes
+    /// from `burst_start` of the stream and decodes it again.
+    /// Returns if the data was recovered.
+    fn recovers_burst(
+        fec: FecKind,
+        data: &[u8],
+        depth: usize,
+        burst_start: usize,
+        burst_len: usize,
+    ) -> Result<bool> {
+        let encoded = fec.encode(data)?;
+        let mut stream = interleave(&encoded, fec.word_symbols(), fec.symbol_bits(), depth)?;
+        for byte in &mut stream[burst_start..burst_start + burst_len] {
+            *byte ^= 0xFF;
+        }
+        let stream = deinterleave(&stream, fec.word_symbols(), fec.symbol_bits(), depth)?;
+        let (decoded, _, _) = fec.decode_blocks(&stream, stream.len(), 0)?;
+        Ok(decoded[..data.len()] == *data)
+    }
+
+    #[test]
+    fn interleave_round_trips() -> Result<()> {
+        #[allow(clippy::cast_possible_truncation)]
+        let data: Vec<u8> = (0..1000_usize).map(|i| (i * 7) as u8).collect();
+        for (word_symbols, symbol_bits) in [(8, 1), (32, 1), (255, 8)] {
+            let len = data.len() / word_symbols * word_symbols;
+            for depth in [1, 3, 8] {
+                let interleaved = interleave(&data[..len], word_symbols, symbol_bits, depth)?;
+                let restored = deinterleave(&interleaved, word_symbols, symbol_bits, depth)?;
+                assert_eq!(restored, data[..len]);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn burst_is_recovered_only_with_interleaving() -> Result<()> {
+        #[allow(clippy::cast_possible_truncation)]
+        let data: Vec<u8> = (0..8 * 223_usize).map(|i| (i * 37 + 11) as u8).collect();
+        // 64 bytes hit at most two Reed-Solomon code words, more than the 16 bytes they correct,
+        // interleaved with depth 8 every code word gets 8 of them.
+        assert!(!recovers_burst(FecKind::Rs, &data, 1, 300, 64)?);
+        assert!(recovers_burst(FecKind::Rs, &data, 8, 300, 64)?);
+        // A damaged byte holds a whole Hamming(7, 4) code word,
+        // interleaved with depth 8 it is a single bit of 8 code words.
+        assert!(!recovers_burst(FecKind::Hamming7_4, &data, 1, 100, 1)?);
+        assert!(recovers_burst(FecKind::Hamming7_4, &data, 8, 100, 1)?);
+        Ok(())
+    }
+}
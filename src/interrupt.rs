@@ -0,0 +1,72 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Stopping a run cleanly when it is interrupted with Ctrl-C.
+//!
+//! The signal handler only raises a flag. Frame loops and waits on ffmpeg check it
+//! and fail with `Interrupted`, which unwinds like any other error:
+//! temporary framebuffers are deleted when dropped, partial output files are discarded
+//! and running ffmpeg processes are killed. A second Ctrl-C exits immediately.
+
+use std::{
+    fmt, process,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Context, Result};
+
+use crate::constants::INTERRUPTED_EXIT_CODE;
+
+/// Set once the process received Ctrl-C or a termination signal.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error returned by work stopped because the process was interrupted.
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interrupted.")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Installs the handler raising the interrupt flag on Ctrl-C, SIGTERM and SIGHUP.
+/// Without it the signals end the process right away, leaving its frames behind.
+///
+/// # Errors
+/// Fails if a handler was already installed or the signals can not be caught.
+pub fn install_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(i32::from(INTERRUPTED_EXIT_CODE));
+        }
+        eprintln!("Interrupted, cleaning up. Press Ctrl-C again to exit immediately.");
+    })
+    .context("Unable to install the Ctrl-C handler.")
+}
+
+#[must_use]
+/// If the process was interrupted.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fails with `Interrupted` if the process was interrupted,
+/// called between frames so long running work stops promptly.
+///
+/// # Errors
+/// Fails if the process was interrupted.
+pub fn check_interrupted() -> Result<()> {
+    if is_interrupted() {
+        return Err(Interrupted.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,386 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Mapping between the levels of a color channel and the pixel values encoding them.
+//!
+//! A channel encoding `bits` bits has `2^bits` levels.
+//! The further apart the pixel values of neighbouring levels are,
+//! the more the value can shift during compression before being misread.
+//!
+//! Data values can be Gray coded before being used as levels,
+//! so a value misread as a neighbouring level differs in a single bit,
+//! which error correction can repair.
+//!
+//! A calibration frame shows every level of each channel in turn,
+//! so the decoder can measure where the levels ended up after compression,
+//! gamma or white balance changes and classify values against those instead.
+
+use std::cmp::Reverse;
+
+use clap::ValueEnum;
+
+use crate::constants::{ALPHA_CHANNEL, COLOR_CHANNELS};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+/// How the levels of a color channel are mapped to pixel values.
+pub enum LevelMapping {
+    #[default]
+    /// Level in the upper bits with the next bit set, centering the value in its range.
+    /// For 2 bits: 0x20, 0x60, 0xA0, 0xE0
+    Midpoint,
+    /// Levels evenly spread across the full range: `level * 255 / (2^bits - 1)`
+    /// For 2 bits: 0x00, 0x55, 0xAA, 0xFF
+    Even,
+}
+
+impl LevelMapping {
+    #[must_use]
+    /// Returns the pixel value encoding `level`.
+    ///
+    /// # Arguments
+    /// * `level` - Level to encode, smaller than `2^bits`.
+    /// * `bits` - Number of bits encoded in the color channel.
+    pub fn encode(self, level: u8, bits: u32) -> u8 {
+        let level = level as u32;
+        let value = match self {
+            LevelMapping::Midpoint => {
+                let shift = u8::BITS - bits;
+                let marker = if bits < u8::BITS { 1 << (shift - 1) } else { 0 };
+                (level << shift) | marker
+            }
+            LevelMapping::Even => {
+                let max_level: u32 = (1 << bits) - 1;
+                // A channel without bits has a single level, placed in the middle.
+                (level * u8::MAX as u32)
+                    .checked_div(max_level)
+                    .unwrap_or(u8::MAX as u32 / 2 + 1)
+            }
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            value as u8
+        }
+    }
+
+    #[must_use]
+    /// Returns the pixel value every level of a color channel is encoded as.
+    ///
+    /// # Arguments
+    /// * `bits` - Number of bits encoded in the color channel.
+    pub fn level_values(self, bits: u32) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        (0..1u32 << bits)
+            .map(|level| self.encode(level as u8, bits))
+            .collect()
+    }
+}
+
+#[must_use]
+/// Returns the Gray code of `value`, differing from that of `value + 1` in exactly one bit.
+/// With Gray coding the data value read from a level is its Gray code,
+/// so a level read as its neighbour flips a single data bit.
+///
+/// # Arguments
+/// * `value` - Level read from a pixel value.
+pub fn gray_encode(value: u8) -> u8 {
+    value ^ (value >> 1)
+}
+
+#[must_use]
+/// Reverts `gray_encode`, returning the level a Gray coded data value is shown as.
+///
+/// # Arguments
+/// * `gray` - Data value to encode.
+pub fn gray_decode(gray: u8) -> u8 {
+    let mut value = gray;
+    let mut shifted = gray >> 1;
+    while shifted != 0 {
+        value ^= shifted;
+        shifted >>= 1;
+    }
+    value
+}
+
+#[must_use]
+/// Returns the level a channel shows in data unit `unit` of a calibration frame,
+/// cycling through all `2^bits` levels.
+///
+/// # Arguments
+/// * `unit` - Index of the data unit, counted from the first unit of the ramp.
+/// * `bits` - Number of bits encoded in the color channel.
+pub fn calibration_level(unit: usize, bits: u32) -> u8 {
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        (unit % (1 << bits)) as u8
+    }
+}
+
+#[must_use]
+/// Measures the pixel value of every level of each color channel
+/// as the mean of the data units showing it in a calibration frame.
+/// Levels no data unit shows keep their value from `expected`.
+///
+/// # Arguments
+/// * `ramp_units` - Averaged data units of the calibration ramp, one byte per channel,
+///   starting with the unit showing level zero.
+/// * `color_bits` - Number of bits encoded in each color channel. Order: RGB
+/// * `expected` - Pixel value every level was encoded as, per color channel.
+pub fn measure_level_values(
+    ramp_units: &[u8],
+    color_bits: [u32; COLOR_CHANNELS],
+    expected: [Vec<u8>; COLOR_CHANNELS],
+) -> [Vec<u8>; COLOR_CHANNELS] {
+    let mut sums = color_bits.map(|bits| vec![(0u64, 0u64); 1 << bits]);
+    for (unit, data_unit) in ramp_units.chunks_exact(COLOR_CHANNELS).enumerate() {
+        for (channel, &value) in data_unit.iter().enumerate() {
+            let level = calibration_level(unit, color_bits[channel]) as usize;
+            let (sum, count) = &mut sums[channel][level];
+            *sum += u64::from(value);
+            *count += 1;
+        }
+    }
+    let mut measured = expected;
+    for (levels, sums) in measured.iter_mut().zip(sums) {
+        for (level_value, (sum, count)) in levels.iter_mut().zip(sums) {
+            if let Some(mean) = (sum + count / 2).checked_div(count) {
+                #[allow(clippy::cast_possible_truncation)]
+                {
+                    *level_value = mean as u8;
+                }
+            }
+        }
+    }
+    measured
+}
+
+#[derive(Debug, Clone)]
+/// Classifies pixel values as the level whose encoded value is closest,
+/// instead of cutting the value range at fixed boundaries.
+/// A value shifted by compression is still read as the level it is nearest to.
+pub struct LevelClassifier {
+    /// Data value of the closest level for every pixel value,
+    /// per color channel followed by the alpha channel.
+    tables: [[u8; 256]; COLOR_CHANNELS + 1],
+    /// Difference between every pixel value and the value encoding its closest level,
+    /// per color channel followed by the alpha channel.
+    offsets: [[i16; 256]; COLOR_CHANNELS + 1],
+}
+
+impl LevelClassifier {
+    #[must_use]
+    /// Builds the lookup tables for all color channels.
+    ///
+    /// # Arguments
+    /// * `level_mapping` - How levels were mapped to pixel values.
+    /// * `color_bits` - Number of bits encoded in each color channel. Order: RGB
+    /// * `gray_code` - If data values are the Gray code of their level, see `gray_encode`.
+    pub fn new(
+        level_mapping: LevelMapping,
+        color_bits: [u32; COLOR_CHANNELS],
+        gray_code: bool,
+    ) -> Self {
+        Self::from_level_values(
+            color_bits.map(|bits| level_mapping.level_values(bits)),
+            gray_code,
+        )
+    }
+
+    #[must_use]
+    /// Builds the lookup tables for levels found at the given pixel values,
+    /// e.g. as measured by `measure_level_values`.
+    ///
+    /// # Arguments
+    /// * `level_values` - Pixel value of every level, per color channel. Order: RGB
+    /// * `gray_code` - If data values are the Gray code of their level, see `gray_encode`.
+    pub fn from_level_values(level_values: [Vec<u8>; COLOR_CHANNELS], gray_code: bool) -> Self {
+        let [red, green, blue] = level_values;
+        // The alpha channel holds no data unless set by `with_alpha_levels`.
+        let tables =
+            [red, green, blue, vec![0]].map(|level_values| Self::table(&level_values, gray_code));
+        Self {
+            tables: tables.map(|(table, _)| table),
+            offsets: tables.map(|(_, offsets)| offsets),
+        }
+    }
+
+    #[must_use]
+    /// Adds the lookup tables for data encoded in the alpha channel.
+    ///
+    /// # Arguments
+    /// * `level_mapping` - How levels were mapped to pixel values.
+    /// * `alpha_bits` - Number of bits encoded in the alpha channel.
+    /// * `gray_code` - If data values are the Gray code of their level, see `gray_encode`.
+    pub fn with_alpha_levels(
+        mut self,
+        level_mapping: LevelMapping,
+        alpha_bits: u32,
+        gray_code: bool,
+    ) -> Self {
+        let (table, offsets) = Self::table(&level_mapping.level_values(alpha_bits), gray_code);
+        self.tables[ALPHA_CHANNEL] = table;
+        self.offsets[ALPHA_CHANNEL] = offsets;
+        self
+    }
+
+    /// Builds the lookup tables for a single color channel,
+    /// returning the data value and the offset from it for every pixel value.
+    ///
+    /// # Arguments
+    /// * `level_values` - Pixel value of every level of the color channel.
+    /// * `gray_code` - If data values are the Gray code of their level.
+    fn table(level_values: &[u8], gray_code: bool) -> ([u8; 256], [i16; 256]) {
+        let mut table = [0u8; 256];
+        let mut offsets = [0i16; 256];
+        for value in 0..=u8::MAX {
+            let (closest, &level_value) = level_values
+                .iter()
+                .enumerate()
+                // Ties go to the higher level, matching the range boundaries
+                // of the midpoint mapping.
+                .min_by_key(|&(level, &level_value)| (value.abs_diff(level_value), Reverse(level)))
+                .unwrap_or((0, &0));
+            #[allow(clippy::cast_possible_truncation)]
+            let closest = closest as u8;
+            table[value as usize] = if gray_code {
+                gray_encode(closest)
+            } else {
+                closest
+            };
+            offsets[value as usize] = i16::from(value) - i16::from(level_value);
+        }
+        (table, offsets)
+    }
+
+    #[must_use]
+    /// Returns how far `value` lies from the value encoding its closest level
+    /// in color channel `channel`. Positive if it was read brighter than encoded.
+    ///
+    /// # Arguments
+    /// * `channel` - Index of the channel. Order: RGBA
+    /// * `value` - Pixel value read from a frame.
+    pub fn offset(&self, channel: usize, value: u8) -> i16 {
+        self.offsets[channel][value as usize]
+    }
+
+    #[must_use]
+    /// Returns the data value of the level closest to `value` in color channel `channel`.
+    ///
+    /// # Arguments
+    /// * `channel` - Index of the channel. Order: RGBA
+    /// * `value` - Pixel value read from a frame.
+    pub fn classify(&self, channel: usize, value: u8) -> u8 {
+        self.tables[channel][value as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Level read by cutting the value range into equal parts, the decoding replaced by
+    /// `LevelClassifier`.
+    fn truncate(value: u8, bits: u32) -> u8 {
+        value >> (u8::BITS - bits)
+    }
+
+    /// Pixel value of `level` shifted by `shift`, clamped to the valid range.
+    fn shifted(level_value: u8, shift: i16) -> u8 {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        {
+            (i16::from(level_value) + shift).clamp(0, 255) as u8
+        }
+    }
+
+    #[test]
+    fn nearest_level_beats_truncation_under_constant_shift() {
+        let bits = 2;
+        let classifier = LevelClassifier::new(LevelMapping::Even, [bits; COLOR_CHANNELS], false);
+        let (mut nearest_errors, mut truncated_errors) = (0, 0);
+        // Half the distance between two levels is 42.5, any smaller shift keeps the nearest level.
+        for shift in -42..=42 {
+            for level in 0..1u8 << bits {
+                let value = shifted(LevelMapping::Even.encode(level, bits), shift);
+                nearest_errors += usize::from(classifier.classify(0, value) != level);
+                truncated_errors += usize::from(truncate(value, bits) != level);
+            }
+        }
+        assert_eq!(nearest_errors, 0);
+        assert!(truncated_errors > 0);
+    }
+
+    #[test]
+    fn measured_levels_follow_a_shift_beyond_the_range_boundaries() {
+        let bits = 2;
+        let shift = 40;
+        let level_values = LevelMapping::Midpoint.level_values(bits);
+        let measured = level_values
+            .iter()
+            .map(|&level_value| shifted(level_value, shift))
+            .collect::<Vec<_>>();
+        let classifier = LevelClassifier::from_level_values(
+            [measured.clone(), measured.clone(), measured.clone()],
+            false,
+        );
+        for (level, &value) in measured.iter().enumerate() {
+            assert_eq!(classifier.classify(0, value) as usize, level);
+            assert_eq!(classifier.offset(0, value), 0);
+        }
+        // Cutting at fixed boundaries reads all but the top level one too high.
+        let truncated_errors = measured
+            .iter()
+            .enumerate()
+            .filter(|&(level, &value)| truncate(value, bits) as usize != level)
+            .count();
+        assert_eq!(truncated_errors, 3);
+    }
+
+    /// Bit errors after every data value of a channel is read one level too low and too high.
+    fn bit_errors_from_neighbour_levels(bits: u32, gray_code: bool) -> u32 {
+        let mapping = LevelMapping::Midpoint;
+        let classifier = LevelClassifier::new(mapping, [bits; COLOR_CHANNELS], gray_code);
+        let max_level = (1u8 << bits) - 1;
+        let mut bit_errors = 0;
+        for data in 0..=max_level {
+            let level = if gray_code { gray_decode(data) } else { data };
+            let neighbours = [level.checked_sub(1), (level < max_level).then(|| level + 1)];
+            for neighbour in neighbours.into_iter().flatten() {
+                let read = classifier.classify(0, mapping.encode(neighbour, bits));
+                bit_errors += (read ^ data).count_ones();
+            }
+        }
+        bit_errors
+    }
+
+    #[test]
+    fn gray_code_reduces_bit_errors_of_neighbour_levels() {
+        for bits in 2..=4 {
+            let gray = bit_errors_from_neighbour_levels(bits, true);
+            let plain = bit_errors_from_neighbour_levels(bits, false);
+            // A Gray coded level differs from its neighbours in a single bit.
+            let neighbour_reads = 2 * ((1 << bits) - 1);
+            assert_eq!(gray, neighbour_reads);
+            assert!(
+                gray < plain,
+                "{bits} bits: {gray} Gray coded, {plain} plain"
+            );
+        }
+    }
+
+    #[test]
+    fn gray_code_round_trips() {
+        for value in 0..=u8::MAX {
+            assert_eq!(gray_decode(gray_encode(value)), value);
+            assert_eq!(
+                (gray_encode(value) ^ gray_encode(value.wrapping_add(1))).count_ones(),
+                1
+            );
+        }
+    }
+}
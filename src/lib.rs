@@ -0,0 +1,104 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! vortexkey - Data compression resistant video generator.
+//! Encode arbitrary data as series of images and combining into video.
+//! Also does the reverse.
+//!
+//! The `vortexkey` binary is a command line interface over this library.
+//! The types needed to encode and decode are re-exported at the crate root,
+//! the modules hold the remaining building blocks.
+//!
+//! # Example
+//! Encode a file to a 1080p video with data units of 10x10 pixels,
+//! then decode it again:
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use vortexkey::{
+//!     BackendKind, Converter, EncoderOptions, FrameFormat, VideoWindow, backend::Rotation,
+//!     scoped_framebuffer,
+//! };
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     // 1 red, 2 green and 1 blue bit per data unit, one data frame per second of a 30 fps video.
+//!     let converter = Converter::builder()
+//!         .color_bits([1, 2, 1])
+//!         .data_fps(1)
+//!         .video_fps(30)
+//!         .resolution([1920, 1080])
+//!         .data_block_size(10)
+//!         .build()?;
+//!     let backend = BackendKind::Ffmpeg.create(None, None, EncoderOptions::default(), None)?;
+//!     // Deleted with its frames when it goes out of scope.
+//!     let frame_buffer = scoped_framebuffer(&std::env::temp_dir(), FrameFormat::Png, false)?;
+//!
+//!     // Three header frames, no parity frames and three blank frames at either end.
+//!     converter.deconstruct_file(&frame_buffer, Path::new("input.bin"), 3, 0, [3, 3])?;
+//!     converter.combine_frames(backend.as_ref(), &frame_buffer, "video.mp4", false)?;
+//!
+//!     converter.split_video(
+//!         backend.as_ref(),
+//!         &frame_buffer,
+//!         "video.mp4",
+//!         "area",
+//!         &VideoWindow::default(),
+//!         Rotation::None,
+//!         None,
+//!     )?;
+//!     let report = converter.reconstruct_file(&frame_buffer, "output.bin", false, None)?;
+//!     println!("Hash matched: {}", report.hash_match);
+//!     Ok(())
+//! }
+//! ```
+
+#![warn(
+    missing_docs,
+    missing_debug_implementations,
+    rust_2018_idioms,
+    clippy::missing_docs_in_private_items,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::pedantic,
+    clippy::redundant_clone,
+    clippy::needless_pass_by_value
+)]
+#![allow(clippy::cast_lossless, clippy::doc_comment_double_space_linebreaks)]
+
+pub mod archive;
+pub mod backend;
+pub mod compression;
+pub mod constants;
+pub mod converter;
+pub mod encryption;
+pub mod error_correction;
+mod ffmpeg;
+pub mod filesys;
+mod finder;
+mod fountain;
+mod framing;
+pub mod hashing;
+pub mod header;
+mod interleaving;
+pub mod interrupt;
+pub mod levels;
+pub mod stats;
+pub mod utils;
+
+pub use backend::{
+    BackendKind, EncoderOptions, EncoderPreset, HwAccel, VideoBackend, VideoCodec, VideoWindow,
+};
+pub use converter::{Converter, ConverterBuilder, FileReport};
+pub use error_correction::{
+    FecKind, HAMMING_VARIANTS, HammingParams, HammingReport, decode_with_hamming_31_26,
+    decode_with_hamming_blocks, decode_with_reed_solomon_255_223_blocks, encode_with_hamming,
+    encode_with_hamming_31_26, encode_with_reed_solomon_255_223,
+};
+pub use filesys::{FrameBuffer, FrameBufferGuard, FrameFormat, scoped_framebuffer};
+pub use header::HeaderData;
@@ -1,5 +1,5 @@
 // vortexkey - Data compression resistant video generator.
-// Copyright 2025 0verv0ltage 
+// Copyright 2025 0verv0ltage
 //
 // Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
 // https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
@@ -10,7 +10,8 @@
 //! vortexkey - Data compression resistant video generator.
 //! Encode arbitrary data as series of images and combining into video.
 //! Also does the reverse.
-//! NOTE: No effort has been undertaken to make this work on Windows. Probably wont. 🤷
+//! Command line interface over the `vortexkey` library.
+//! Runs on Linux, macOS and Windows, see the README for the Windows round trip check.
 
 // Youtube Recommended video bitrates for SDR uploads
 // Type    Standard Frame Rate (24-30), High Frame Rate (48-60)
@@ -23,7 +24,6 @@
 // 360p         1 Mbps          1.5 Mbps
 
 // TODO:
-// - Base 64 config representaion
 // - Split converter struct
 
 #![warn(
@@ -37,102 +37,94 @@
     clippy::redundant_clone,
     clippy::needless_pass_by_value
 )]
-#![allow(clippy::cast_lossless, dead_code)]
+#![allow(clippy::cast_lossless, clippy::doc_comment_double_space_linebreaks)]
 
-use std::time;
+use std::{process::ExitCode, time};
 
-use anyhow::{Result, bail};
-use clap::Parser;
+use anyhow::Result;
 
 use cli::{Args, OperatingMode};
-use utils::format_duration;
-
-mod cli;
-mod constants;
-mod converter;
-mod error_correction;
-mod filesys;
-mod utils;
+use commands::{
+    check_io_paths, compare_videos, encode_video, inspect_video, print_capacities, print_estimate,
+    reheader_video, run_bench, split_frames, validate_settings, verify_round_trip,
+};
+use decode::{ExpectedHashMismatch, decode_video};
+use vortexkey::{
+    constants::{EXPECTED_HASH_MISMATCH_EXIT_CODE, INTERRUPTED_EXIT_CODE},
+    interrupt::{Interrupted, install_handler, is_interrupted},
+    utils::format_duration,
+};
 
 /// Times the execution of `code` and
 /// prints out the measured time.
 macro_rules! timed_block {
     ($name:expr, $code:block) => {
-        println!("Starting {}", $name);
+        timed_block!($name, _elapsed, $code);
+    };
+    ($name:expr, $elapsed:ident, $code:block) => {
+        eprintln!("Starting {}", $name);
         let start = std::time::Instant::now();
         $code
-        println!(
-            "Finished {} after: {:?}",
-            $name,
-            start.elapsed()
-        );
+        let $elapsed = start.elapsed();
+        eprintln!("Finished {} after: {:?}", $name, $elapsed);
     };
 }
 
+mod cli;
+mod commands;
+mod decode;
+
 /// Read in command line args and execute program function as requested.
 fn execute_args() -> Result<()> {
-    let args = Args::parse();
+    let args = Args::parse_with_config_file()?;
+    check_io_paths(&args)?;
+    match args.mode {
+        OperatingMode::Capacities => return print_capacities(&args),
+        OperatingMode::Estimate => return print_estimate(&args),
+        OperatingMode::Validate => return validate_settings(&args),
+        _ => (),
+    }
     let main_converter = args.to_converter_config()?;
 
-    if !args.inputfile.exists() {
-        bail!(
-            "Provided input file at {:?} could not be found.",
-            args.inputfile
-        );
-    }
     match args.mode {
-        OperatingMode::Split => {
-            timed_block!("frame generation", {
-                main_converter.deconstruct_file(&args.inputfile)?;
-            });
-            Ok(())
-        }
-        OperatingMode::DataToVideo => {
-            timed_block!("frame generation", {
-                main_converter.deconstruct_file(&args.inputfile)?;
-            });
-
-            timed_block!("frame combination", {
-                main_converter.combine_frames(&args.outputfile, args.overwrite)?;
-            });
-            Ok(())
-        }
-        OperatingMode::VideoToData => {
-            timed_block!("video splitting", {
-                main_converter.split_video(&args.inputfile)?;
-            });
-            println!("Starting .");
-            let start_split_video = time::Instant::now();
-
-            println!(
-                "Finished video splitting after: {}",
-                format_duration(start_split_video.elapsed())
-            );
-
-            println!("Starting file reconstruction.");
-            let start_file_reconstruction = time::Instant::now();
-            let report = main_converter.reconstruct_file(&args.outputfile, args.overwrite)?;
-            println!(
-                "Errors during file reconstruction: Corrected: {}  Uncorrectable: {}",
-                report.corrected_errors, report.uncorrected_errors
-            );
-            println!(
-                "Finished file reconstruction after: {}",
-                format_duration(start_file_reconstruction.elapsed())
-            );
-            Ok(())
+        OperatingMode::Split => split_frames(&args, &main_converter),
+        OperatingMode::DataToVideo => encode_video(&args, &main_converter),
+        OperatingMode::VideoToData => decode_video(&args, &main_converter),
+        OperatingMode::Compare => compare_videos(&args, &main_converter),
+        OperatingMode::Inspect => inspect_video(&args, &main_converter),
+        OperatingMode::Verify => verify_round_trip(&args, &main_converter),
+        OperatingMode::Bench => run_bench(&args, &main_converter),
+        OperatingMode::Reheader => reheader_video(&args, &main_converter),
+        OperatingMode::Capacities | OperatingMode::Estimate | OperatingMode::Validate => {
+            unreachable!("Handled before reading the input file.")
         }
     }
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let main_start = time::Instant::now();
+    // Without the handler Ctrl-C still ends the run, just without cleaning up.
+    if let Err(err) = install_handler() {
+        eprintln!("Warning: {err:#}");
+    }
 
-    execute_args()?;
+    if let Err(err) = execute_args() {
+        // ffmpeg receives Ctrl-C as well and may fail before the interrupt is noticed.
+        if err.is::<Interrupted>() || is_interrupted() {
+            eprintln!("Interrupted, temporary frames and partial output were removed.");
+            return ExitCode::from(INTERRUPTED_EXIT_CODE);
+        }
+        eprintln!("Error: {err:?}");
+        return if err.is::<ExpectedHashMismatch>() {
+            ExitCode::from(EXPECTED_HASH_MISMATCH_EXIT_CODE)
+        } else {
+            ExitCode::FAILURE
+        };
+    }
 
-    println!(
+    eprintln!(
         "Total execution time: {}",
         format_duration(main_start.elapsed())
     );
-    Ok(())
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,384 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Per frame statistics collected while decoding,
+//! used to study how a platform degrades uploaded videos,
+//! and the summary of a run printed as JSON for scripts.
+
+use std::{fmt::Write, path::Path, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::{
+    constants::{COLOR_CHANNELS, ERROR_HISTOGRAM_WIDTH, ERROR_REPORT_MAX_OFFSETS},
+    converter::FileReport,
+    filesys::write_output_file,
+    levels::LevelClassifier,
+    utils::format_bytes,
+};
+
+#[derive(Debug, Serialize)]
+/// Summary of encoding or decoding a file, printed with `--json`.
+pub struct RunSummary<'a> {
+    /// Operating mode of the run, as given on the command line.
+    pub mode: &'static str,
+    /// Bytes read: the data encoded, or the size of the decoded video.
+    pub bytes_read: u64,
+    /// Bytes written: the size of the encoded video, or the decoded data.
+    pub bytes_written: u64,
+    /// Frames in the encoded video, or frames read from the decoded video.
+    pub frames: usize,
+    /// Playback length of the encoded video in seconds, `None` when decoding.
+    pub video_seconds: Option<f64>,
+    /// Time the run took in seconds.
+    pub elapsed_seconds: f64,
+    /// Message decoded from a video encoded with `--message`.
+    pub message: Option<String>,
+    /// Error correction report of the decoded video, `None` when encoding.
+    pub report: Option<&'a FileReport>,
+    /// If the decoded data matched the hash passed with `--expect-sha256`,
+    /// `None` when encoding or if no hash was passed.
+    pub expected_hash_match: Option<bool>,
+}
+
+impl RunSummary<'_> {
+    /// Prints the summary to stdout as a single line JSON object.
+    ///
+    /// # Errors
+    /// Fails if the summary can not be serialized.
+    pub fn print(&self) -> Result<()> {
+        let json = serde_json::to_string(self).context("Unable to serialize run summary.")?;
+        println!("{json}");
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// Throughput measured in bench mode, printed as a table or with `--json` as JSON.
+pub struct BenchSummary {
+    /// Operating mode of the run, always `bench`.
+    pub mode: &'static str,
+    /// Bytes of synthetic data encoded.
+    pub bytes: u64,
+    /// Frames in the encoded video.
+    pub frames: usize,
+    /// Size of the encoded video in bytes.
+    pub video_bytes: u64,
+    /// Time every phase took, in the order they ran.
+    pub phases: Vec<PhaseTiming>,
+    /// If the decoded data matched the encoded data, `None` if it was not decoded.
+    pub data_match: Option<bool>,
+}
+
+impl BenchSummary {
+    /// Prints the summary to stdout, as a single line JSON object if `json` is set
+    /// and as a table otherwise.
+    ///
+    /// # Arguments
+    /// * `json` - If the summary is printed as JSON.
+    ///
+    /// # Errors
+    /// Fails if the summary can not be serialized.
+    pub fn print(&self, json: bool) -> Result<()> {
+        if json {
+            let json = serde_json::to_string(self).context("Unable to serialize bench summary.")?;
+            println!("{json}");
+            return Ok(());
+        }
+        println!(
+            "Data: {}  Frames: {}  Video: {}",
+            format_bytes(self.bytes),
+            self.frames,
+            format_bytes(self.video_bytes)
+        );
+        for phase in &self.phases {
+            println!(
+                "{:<20} {:>9.3} s {:>10.2} MiB/s",
+                phase.phase, phase.seconds, phase.mib_per_second
+            );
+        }
+        if let Some(data_match) = self.data_match {
+            println!("Decoded data matches: {data_match}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// Time one phase of bench mode took.
+pub struct PhaseTiming {
+    /// Name of the phase, e.g. `frame generation`.
+    pub phase: &'static str,
+    /// Time the phase took in seconds.
+    pub seconds: f64,
+    /// Data processed per second in MiB, counting the synthetic data only.
+    pub mib_per_second: f64,
+}
+
+impl PhaseTiming {
+    #[must_use]
+    /// Timing of a phase that processed `bytes` bytes of data in `elapsed`.
+    ///
+    /// # Arguments
+    /// * `phase` - Name of the phase.
+    /// * `elapsed` - Time the phase took.
+    /// * `bytes` - Bytes of data the phase processed.
+    pub fn new(phase: &'static str, elapsed: Duration, bytes: u64) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        #[allow(clippy::cast_precision_loss)]
+        let mib = bytes as f64 / (1024.0 * 1024.0);
+        Self {
+            phase,
+            seconds,
+            mib_per_second: if seconds > 0.0 { mib / seconds } else { 0.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Role of a frame within the video.
+pub enum FrameKind {
+    /// Blank frame before or after the data, or a frame that could not be placed.
+    Buffer,
+    /// Header frame. In version 1 videos also contains the start of the data.
+    Header,
+    /// Data frame.
+    Data,
+    /// Parity frame, or fountain droplet frame.
+    Parity,
+    /// Calibration frame showing every level of each color channel.
+    Calibration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Statistics of a single frame read from a video.
+pub struct FrameStats {
+    /// Index of the frame in the video, counting every split frame.
+    pub index: usize,
+    /// Role of the frame.
+    pub kind: FrameKind,
+    /// Index of the data frame counting only data frames, if the frame holds data.
+    pub data_frame: Option<usize>,
+    /// Mean pixel value of each color channel. Order: RGB
+    pub mean_channels: [f64; COLOR_CHANNELS],
+    /// Mean difference between the pixel values of each color channel
+    /// and the values encoding the levels they were read as,
+    /// positive if the frame was brightened. Order: RGB
+    pub mean_level_offsets: [f64; COLOR_CHANNELS],
+    /// Single bit errors corrected in the data of this frame.
+    pub corrected_errors: u32,
+    /// Uncorrectable errors found in the data of this frame.
+    pub uncorrected_errors: u32,
+    /// If the CRC at the end of the frame matched,
+    /// `None` for frames without one, such as header and buffer frames.
+    pub crc_ok: Option<bool>,
+}
+
+#[must_use]
+/// Mean pixel value of each color channel of a frame.
+///
+/// # Arguments
+/// * `frame_data_units` - Averaged data units of the frame, one byte per channel.
+/// * `channels` - Channels of every data unit, four if the alpha channel follows the colors.
+pub fn mean_channels(frame_data_units: &[u8], channels: usize) -> [f64; COLOR_CHANNELS] {
+    let mut sums = [0u64; COLOR_CHANNELS];
+    for data_unit in frame_data_units.chunks_exact(channels) {
+        for (sum, &value) in sums.iter_mut().zip(data_unit) {
+            *sum += value as u64;
+        }
+    }
+    let unit_count = (frame_data_units.len() / channels).max(1);
+    #[allow(clippy::cast_precision_loss)]
+    sums.map(|sum| sum as f64 / unit_count as f64)
+}
+
+#[must_use]
+/// Mean difference between the pixel values of each color channel of a frame
+/// and the values encoding the levels they were read as.
+///
+/// # Arguments
+/// * `frame_data_units` - Averaged data units of the frame, one byte per channel.
+/// * `channels` - Channels of every data unit, four if the alpha channel follows the colors.
+/// * `classifier` - Classifier the frame was decoded with.
+pub fn mean_level_offsets(
+    frame_data_units: &[u8],
+    channels: usize,
+    classifier: &LevelClassifier,
+) -> [f64; COLOR_CHANNELS] {
+    let mut sums = [0i64; COLOR_CHANNELS];
+    for data_unit in frame_data_units.chunks_exact(channels) {
+        for (channel, (sum, &value)) in sums.iter_mut().zip(data_unit).enumerate() {
+            *sum += i64::from(classifier.offset(channel, value));
+        }
+    }
+    let unit_count = (frame_data_units.len() / channels).max(1);
+    #[allow(clippy::cast_precision_loss)]
+    sums.map(|sum| sum as f64 / unit_count as f64)
+}
+
+/// Writes frame statistics to a CSV file with a header row.
+///
+/// # Arguments
+/// * `path` - Path where the CSV file will be stored.
+/// * `stats` - Statistics of every frame.
+/// * `overwrite` - If the file should be overwritten if it exists.
+///
+/// # Errors
+/// Fails if the file exists and `overwrite` is not set, or it can not be written.
+pub fn write_stats_csv(path: &Path, stats: &[FrameStats], overwrite: bool) -> Result<()> {
+    if !overwrite && path.exists() {
+        bail!(
+            "File at {} exists and overwrite is not enabled.",
+            path.display()
+        );
+    }
+    let mut csv = String::from(
+        "frame,kind,data_frame,mean_red,mean_green,mean_blue,offset_red,offset_green,offset_blue,corrected_errors,uncorrected_errors,crc\n",
+    );
+    for frame in stats {
+        let data_frame = frame
+            .data_frame
+            .map_or(String::new(), |data_frame| data_frame.to_string());
+        let [red, green, blue] = frame.mean_channels;
+        let [offset_red, offset_green, offset_blue] = frame.mean_level_offsets;
+        let crc = match frame.crc_ok {
+            Some(true) => "ok",
+            Some(false) => "failed",
+            None => "",
+        };
+        let _ = writeln!(
+            csv,
+            "{},{:?},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{},{},{}",
+            frame.index,
+            frame.kind,
+            data_frame,
+            red,
+            green,
+            blue,
+            offset_red,
+            offset_green,
+            offset_blue,
+            frame.corrected_errors,
+            frame.uncorrected_errors,
+            crc
+        );
+    }
+    write_output_file(path, csv.as_bytes())
+}
+
+/// Prints the frames that failed their CRC and a histogram of the errors
+/// found in every frame that had any to stderr, followed by the byte offsets of the affected code words in the error corrected stream.
+/// Each bar shows corrected errors as `+` and uncorrectable ones as `#`,
+/// scaled to the frame with the most errors.
+///
+/// # Arguments
+/// * `stats` - Statistics of every frame.
+/// * `corrected_offsets` - Offsets of the code words errors were corrected in.
+/// * `uncorrected_offsets` - Offsets of the code words with uncorrectable errors.
+pub fn print_error_report(
+    stats: &[FrameStats],
+    corrected_offsets: &[usize],
+    uncorrected_offsets: &[usize],
+) {
+    print_crc_failures(stats);
+    let frames_with_errors: Vec<&FrameStats> = stats
+        .iter()
+        .filter(|frame| frame.corrected_errors + frame.uncorrected_errors > 0)
+        .collect();
+    if frames_with_errors.is_empty() {
+        eprintln!("No errors found in any frame.");
+        return;
+    }
+    let max_errors = frames_with_errors
+        .iter()
+        .map(|frame| (frame.corrected_errors + frame.uncorrected_errors) as usize)
+        .max()
+        .unwrap_or(1);
+    let bar_len = |errors: u32| (errors as usize * ERROR_HISTOGRAM_WIDTH).div_ceil(max_errors);
+
+    eprintln!("Errors per frame (+ corrected, # uncorrectable):");
+    for frame in &frames_with_errors {
+        let data_frame = frame.data_frame.map_or(String::new(), |data_frame| {
+            format!(" (data frame {data_frame})")
+        });
+        eprintln!(
+            "Frame {:>6}{data_frame}: {:>6} corrected {:>6} uncorrectable |{}{}",
+            frame.index,
+            frame.corrected_errors,
+            frame.uncorrected_errors,
+            "+".repeat(bar_len(frame.corrected_errors)),
+            "#".repeat(bar_len(frame.uncorrected_errors)),
+        );
+    }
+    eprintln!(
+        "{} of {} frames contained errors.",
+        frames_with_errors.len(),
+        stats.len()
+    );
+
+    print_offsets("Corrected", corrected_offsets);
+    print_offsets("Uncorrectable", uncorrected_offsets);
+}
+
+/// Prints the frames whose CRC did not match, up to `ERROR_REPORT_MAX_OFFSETS` of them.
+/// Nothing is printed if every frame matched or none carried a CRC.
+///
+/// # Arguments
+/// * `stats` - Statistics of every frame.
+fn print_crc_failures(stats: &[FrameStats]) {
+    let checked = stats.iter().filter(|frame| frame.crc_ok.is_some()).count();
+    let failed: Vec<usize> = stats
+        .iter()
+        .filter(|frame| frame.crc_ok == Some(false))
+        .map(|frame| frame.index)
+        .collect();
+    if failed.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{} of {checked} frames failed their CRC: {}",
+        failed.len(),
+        limited_list(&failed)
+    );
+}
+
+/// Prints up to `ERROR_REPORT_MAX_OFFSETS` code word offsets on one line.
+///
+/// # Arguments
+/// * `label` - Kind of error found in the code words.
+/// * `offsets` - Offsets of the code words.
+fn print_offsets(label: &str, offsets: &[usize]) {
+    if offsets.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{label} code words at stream offsets: {}",
+        limited_list(offsets)
+    );
+}
+
+/// Comma separated list of the first `ERROR_REPORT_MAX_OFFSETS` values,
+/// noting how many more were left out.
+///
+/// # Arguments
+/// * `values` - Values to list.
+fn limited_list(values: &[usize]) -> String {
+    let listed: Vec<String> = values
+        .iter()
+        .take(ERROR_REPORT_MAX_OFFSETS)
+        .map(ToString::to_string)
+        .collect();
+    let remaining = values.len().saturating_sub(ERROR_REPORT_MAX_OFFSETS);
+    if remaining > 0 {
+        format!("{} and {remaining} more", listed.join(", "))
+    } else {
+        listed.join(", ")
+    }
+}
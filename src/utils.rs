@@ -9,15 +9,27 @@
 
 //! General utility functions.
 
+use anyhow::{Result, bail};
 use chrono::Duration;
 use chrono::Local;
-use std::{path::Path, time};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    fmt::Write,
+    io::{self, IsTerminal},
+    path::Path,
+    time,
+};
 
+#[must_use]
 /// Generate a uniqe directory path based on the current ISO timestamp.
 /// If path exists tries prepending increasing number until available path is found.
-pub fn _generate_unique_timestamp_dir(base_dir: &str) -> String {
+/// The time is separated by dashes, as colons are not allowed in Windows file names.
+///
+/// # Arguments
+/// * `base_dir` - Prefix of the generated path.
+pub fn generate_unique_timestamp_dir(base_dir: &str) -> String {
     let now = Local::now();
-    let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let timestamp = now.format("%Y-%m-%dT%H-%M-%S").to_string();
 
     let mut counter: u64 = 1;
     let mut candidate = format!("{base_dir}{timestamp}");
@@ -31,17 +43,55 @@ pub fn _generate_unique_timestamp_dir(base_dir: &str) -> String {
     candidate
 }
 
+#[must_use]
 /// Return single hex representation of a byte slice.
 pub fn bytes_to_hex_string(bytes: &[u8]) -> String {
     let mut hex_string = String::with_capacity(2 + bytes.len() * 2);
     hex_string.push_str("0x");
 
     for byte in bytes {
-        hex_string.push_str(&format!("{byte:02x}"));
+        let _ = write!(hex_string, "{byte:02x}");
     }
     hex_string
 }
 
+/// Parse a hex string back into bytes, the inverse of `bytes_to_hex_string()`.
+/// The `0x` prefix is optional and both upper and lower case digits are accepted.
+///
+/// # Arguments
+/// * `hex_string` - Hex digits, two per byte.
+///
+/// # Errors
+/// Fails if the string has an odd number of digits or contains a non hex character.
+pub fn hex_string_to_bytes(hex_string: &str) -> Result<Vec<u8>> {
+    let digits = hex_string.strip_prefix("0x").unwrap_or(hex_string);
+    if !digits.len().is_multiple_of(2) {
+        bail!("Hex string has an odd number of digits ({}).", digits.len());
+    }
+    if let Some(invalid) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        bail!("Hex string contains non hex character {invalid:?}.");
+    }
+
+    // All characters are ASCII hex digits, so every pair is a valid byte.
+    Ok(digits
+        .as_bytes()
+        .chunks_exact(2)
+        .map(|pair| (hex_digit_value(pair[0]) << 4) | hex_digit_value(pair[1]))
+        .collect())
+}
+
+/// Value of a single ASCII hex digit, zero for any other character.
+///
+/// # Arguments
+/// * `digit` - ASCII hex digit.
+fn hex_digit_value(digit: u8) -> u8 {
+    char::from(digit)
+        .to_digit(16)
+        .and_then(|value| u8::try_from(value).ok())
+        .unwrap_or_default()
+}
+
+#[must_use]
 /// Format a duration to human readable form.
 pub fn format_duration(duration: time::Duration) -> String {
     let Ok(chrono_duration) = Duration::from_std(duration) else {
@@ -67,3 +117,312 @@ pub fn format_duration(duration: time::Duration) -> String {
         )
     }
 }
+
+#[must_use]
+/// Format a size in bytes to human readable form using binary prefixes,
+/// e.g. `1023 B`, `1.0 KiB` or `2.5 MiB`.
+///
+/// # Arguments
+/// * `bytes` - Size to format.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    // Compared after rounding to the printed decimal,
+    // so sizes just below the next unit are not shown as 1024.0 of this one.
+    while (size * 10.0).round() >= 10240.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[must_use]
+/// Creates a progress bar on stderr counting processed items, with an ETA.
+/// The bar is hidden if `visible` is false or stderr is not a terminal,
+/// so piped or redirected output is not filled with redraws.
+///
+/// # Arguments
+/// * `label` - What is being processed.
+/// * `total` - Total number of items to process.
+/// * `visible` - If the bar should be shown at all.
+pub fn progress_bar(label: &'static str, total: usize, visible: bool) -> ProgressBar {
+    if !visible || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(total as u64)
+        .with_style(
+            ProgressStyle::with_template("{msg}: [{wide_bar}] {pos}/{len} ETA {eta}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+        )
+        .with_message(label)
+}
+
+#[must_use]
+/// Compares two byte slices.
+/// Returns the offset of the first differing byte, if any,
+/// and the total number of differing bytes.
+/// Bytes past the end of the shorter slice count as differing.
+///
+/// # Arguments
+/// * `a` - First slice to compare.
+/// * `b` - Second slice to compare.
+pub fn compare_bytes(a: &[u8], b: &[u8]) -> (Option<usize>, usize) {
+    let first_difference = a
+        .iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())));
+    let differing_bytes =
+        a.iter().zip(b).filter(|(x, y)| x != y).count() + a.len().abs_diff(b.len());
+    (first_difference, differing_bytes)
+}
+
+/// Characters of the standard base64 alphabet, indexed by their 6 bit value.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[must_use]
+/// Encode bytes as padded standard base64.
+///
+/// # Arguments
+/// * `bytes` - Bytes to encode.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3F;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode padded standard base64.
+///
+/// # Arguments
+/// * `encoded` - Base64 text to decode.
+///
+/// # Errors
+/// Fails if `encoded` contains characters outside the base64 alphabet or has an invalid length.
+pub fn base64_decode(encoded: &str) -> Result<Vec<u8>> {
+    let encoded = encoded.trim().as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        bail!("Base64 length ({}) is not a multiple of 4.", encoded.len());
+    }
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    for (group_index, group) in encoded.chunks(4).enumerate() {
+        let is_last = group_index == encoded.len() / 4 - 1;
+        let padding = group.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            bail!("Invalid base64 padding.");
+        }
+        let mut bits: u32 = 0;
+        for &c in &group[..4 - padding] {
+            let Some(value) = BASE64_ALPHABET.iter().position(|&a| a == c) else {
+                bail!("Invalid base64 character {:?}.", c as char);
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                bits = (bits << 6) | value as u32;
+            }
+        }
+        bits <<= 6 * padding;
+        decoded.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_equal_bytes() {
+        assert_eq!(compare_bytes(b"", b""), (None, 0));
+        assert_eq!(compare_bytes(b"vortexkey", b"vortexkey"), (None, 0));
+    }
+
+    #[test]
+    fn compare_differing_bytes() {
+        assert_eq!(compare_bytes(b"abcdef", b"abXdef"), (Some(2), 1));
+        assert_eq!(compare_bytes(b"abcdef", b"Xbcdef"), (Some(0), 1));
+        assert_eq!(compare_bytes(b"abcdef", b"aXcXeX"), (Some(1), 3));
+    }
+
+    #[test]
+    fn compare_bytes_of_different_lengths() {
+        // Bytes missing from the shorter slice count as differing.
+        assert_eq!(compare_bytes(b"abc", b"abcde"), (Some(3), 2));
+        assert_eq!(compare_bytes(b"abcde", b"abc"), (Some(3), 2));
+        assert_eq!(compare_bytes(b"", b"ab"), (Some(0), 2));
+        assert_eq!(compare_bytes(b"aXc", b"abcd"), (Some(1), 2));
+    }
+
+    #[test]
+    fn base64_matches_rfc_4648_vectors() -> Result<()> {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (text, encoded) in vectors {
+            assert_eq!(base64_encode(text.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded)?, text.as_bytes());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn base64_round_trips_every_length_modulo_3() -> Result<()> {
+        let bytes: Vec<u8> = (0..=255).rev().collect();
+        for len in 0..=12 {
+            let encoded = base64_encode(&bytes[..len]);
+            assert_eq!(encoded.len(), len.div_ceil(3) * 4);
+            assert_eq!(base64_decode(&encoded)?, &bytes[..len], "{len} bytes");
+        }
+        assert_eq!(base64_decode(&base64_encode(&bytes))?, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn base64_with_invalid_character_fails() {
+        for invalid in ["Zm9v!A==", "Zm-v", "Zm_v", "Zm9 ", "Zé=="] {
+            assert!(base64_decode(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn base64_with_bad_padding_fails() {
+        // Wrong length, too much padding, and padding before the last group.
+        for invalid in ["Zm9", "Zm9vY", "Z===", "====", "Zg==Zm9v", "Zm=v", "Zg=A"] {
+            assert!(base64_decode(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn format_bytes_unit_boundaries() {
+        let cases = [
+            (0, "0 B"),
+            (1023, "1023 B"),
+            (1024, "1.0 KiB"),
+            (1535, "1.5 KiB"),
+            (10_188, "9.9 KiB"),
+            (1_048_524, "1023.9 KiB"),
+            (1_048_575, "1.0 MiB"),
+            (1_048_576, "1.0 MiB"),
+            (1_073_741_823, "1.0 GiB"),
+            (1_073_741_824, "1.0 GiB"),
+            (1 << 40, "1.0 TiB"),
+            (1 << 50, "1024.0 TiB"),
+            (u64::MAX, "16777216.0 TiB"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(format_bytes(bytes), expected, "{bytes} bytes");
+        }
+    }
+
+    #[test]
+    fn hex_string_round_trips() -> Result<()> {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(hex_string_to_bytes(&bytes_to_hex_string(&bytes))?, bytes);
+        assert_eq!(hex_string_to_bytes("")?, Vec::<u8>::new());
+        assert_eq!(hex_string_to_bytes("00ff7F")?, [0x00, 0xFF, 0x7F]);
+        Ok(())
+    }
+
+    #[test]
+    fn every_byte_pair_round_trips() -> Result<()> {
+        for pair in 0..=u16::MAX {
+            let bytes = pair.to_be_bytes();
+            let hex = bytes_to_hex_string(&bytes);
+            let digits = hex.trim_start_matches("0x");
+            assert_eq!(hex_string_to_bytes(&hex)?, bytes);
+            assert_eq!(hex_string_to_bytes(digits)?, bytes);
+            assert_eq!(hex_string_to_bytes(&digits.to_uppercase())?, bytes);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn random_bytes_of_every_length_round_trip() -> Result<()> {
+        // Fixed linear congruential sequence, so failures are reproducible.
+        let mut state = 0x2545_f491_u32;
+        for len in 0..512 {
+            let bytes: Vec<u8> = (0..len)
+                .map(|_| {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    state.to_be_bytes()[0]
+                })
+                .collect();
+            let hex = bytes_to_hex_string(&bytes);
+            assert_eq!(hex.len(), 2 + 2 * len);
+            assert_eq!(hex_string_to_bytes(&hex)?, bytes);
+            assert_eq!(
+                hex_string_to_bytes(&hex.to_uppercase().replacen('X', "x", 1))?,
+                bytes
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn every_ascii_pair_parses_only_if_both_are_hex_digits() {
+        for first in 0..=127u8 {
+            for second in 0..=127u8 {
+                let pair = String::from_utf8(vec![first, second]).unwrap();
+                let parsed = hex_string_to_bytes(&pair);
+                if pair == "0x" {
+                    assert_eq!(parsed.unwrap(), Vec::<u8>::new());
+                } else if first.is_ascii_hexdigit() && second.is_ascii_hexdigit() {
+                    let expected = u8::from_str_radix(&pair, 16).unwrap();
+                    assert_eq!(parsed.unwrap(), [expected], "{pair:?}");
+                } else {
+                    assert!(parsed.is_err(), "{pair:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hex_string_prefix_is_optional() -> Result<()> {
+        assert_eq!(hex_string_to_bytes("0xdeadBEEF")?, [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(hex_string_to_bytes("0x")?, Vec::<u8>::new());
+        // Only a single prefix is stripped.
+        assert!(hex_string_to_bytes("0x0xab").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn hex_string_with_odd_length_fails() {
+        for odd in ["abc", "0x1"] {
+            let err = hex_string_to_bytes(odd).unwrap_err();
+            assert!(err.to_string().contains("odd number"), "{odd:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn hex_string_with_non_hex_character_fails() {
+        for invalid in ["zz", "0g", "12 4", "+1", "éab"] {
+            let err = hex_string_to_bytes(invalid).unwrap_err();
+            assert!(err.to_string().contains("non hex"), "{invalid:?}: {err}");
+        }
+    }
+}
@@ -0,0 +1,558 @@
+// vortexkey - Data compression resistant video generator.
+// Copyright 2025 0verv0ltage
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runs of the command line program. Tests encoding or decoding videos need ffmpeg on PATH
+//! and are skipped if it is not installed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    thread,
+};
+
+use sha2::{Digest, Sha256};
+
+/// If ffmpeg can be run, otherwise the calling test is skipped.
+fn ffmpeg_available() -> bool {
+    let found = Command::new("ffmpeg")
+        .arg("-version")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !found {
+        eprintln!("ffmpeg not found, skipping.");
+    }
+    found
+}
+
+/// Empty directory for the files of a single test, removed by `TestDir::drop`.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates the directory, named after the test so parallel tests do not share one.
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("vortexkey_test_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// Path of `name` inside the directory.
+    fn file(&self, name: &str) -> String {
+        self.0.join(name).to_str().unwrap().to_owned()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs vortexkey with `args`, using `dir` as its temp directory,
+/// so the frames of parallel tests are kept apart.
+fn vortexkey(dir: &Path, args: &[&str]) -> Output {
+    vortexkey_with_env(dir, args, &[])
+}
+
+/// Runs vortexkey like `vortexkey` with the environment variables `envs` set.
+fn vortexkey_with_env(dir: &Path, args: &[&str], envs: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vortexkey"))
+        .args(args)
+        .env("TMPDIR", dir)
+        .env("TMP", dir)
+        .env("TEMP", dir)
+        .env_remove("VORTEXKEY_WORKDIR")
+        .env_remove("VORTEXKEY_PASSPHRASE")
+        .envs(envs.iter().copied())
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap()
+}
+
+/// Runs vortexkey like `vortexkey` and fails the test with its stderr if it fails.
+fn vortexkey_ok(dir: &Path, args: &[&str]) {
+    let output = vortexkey(dir, args);
+    assert!(
+        output.status.success(),
+        "vortexkey {args:?} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Deterministic test data of `len` bytes, differing with `seed`.
+fn test_data(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i.wrapping_mul(31) as u8) ^ seed.wrapping_mul(97))
+        .collect()
+}
+
+/// Frame directory split printed to `stderr` as kept.
+fn kept_frames_dir(stderr: &str) -> &str {
+    stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("Kept frames in "))
+        .expect("split did not print its frame directory")
+        .trim_end_matches('.')
+}
+
+/// Encodes `input` to `video` and decodes it to `output`.
+fn round_trip(dir: &Path, input: &str, video: &str, output: &str) {
+    vortexkey_ok(dir, &["-i", input, video]);
+    vortexkey_ok(dir, &["-m", "vtd", "-i", video, output]);
+}
+
+#[test]
+fn file_name_is_restored_into_a_directory() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("file_name");
+    let input = dir.file("foo.tar.gz");
+    let video = dir.file("video.mp4");
+    let restored = dir.file("restored");
+    fs::write(&input, test_data(5000, 0)).unwrap();
+    fs::create_dir(&restored).unwrap();
+    vortexkey_ok(&dir.0, &["-i", &input, &video]);
+    vortexkey_ok(&dir.0, &["-m", "vtd", "-i", &video, &restored]);
+    let restored_file = Path::new(&restored).join("foo.tar.gz");
+    assert_eq!(fs::read(restored_file).unwrap(), fs::read(&input).unwrap());
+}
+
+#[test]
+fn resolution_not_fitting_the_pixel_size_is_cut_unless_strict() {
+    let dir = TestDir::new("strict_dimensions");
+    let output = vortexkey(&dir.0, &["-m", "validate", "-d", "9"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("1908x1080"), "{stdout}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cutting it to 1908 pixels"));
+
+    let strict = vortexkey(
+        &dir.0,
+        &["-m", "validate", "-d", "9", "--strict-dimensions"],
+    );
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("3 pixels would be left over"));
+    vortexkey_ok(
+        &dir.0,
+        &["-m", "validate", "-d", "8", "--strict-dimensions"],
+    );
+}
+
+#[test]
+fn target_fps_can_not_be_given_with_video_fps() {
+    let dir = TestDir::new("target_fps");
+    let both = vortexkey(
+        &dir.0,
+        &["-m", "validate", "--target-fps", "25", "--video-fps", "30"],
+    );
+    assert!(!both.status.success());
+
+    // Either one on the command line replaces the other from the config file.
+    let config = dir.file("config.toml");
+    fs::write(&config, "video-fps = 24\n").unwrap();
+    let output = vortexkey(
+        &dir.0,
+        &[
+            "-m",
+            "validate",
+            "--config-file",
+            &config,
+            "--target-fps",
+            "25",
+        ],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("at 25 fps"));
+}
+
+#[test]
+fn parallel_round_trips_share_a_temp_dir() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("parallel");
+    let runs: Vec<_> = (0..2u8)
+        .map(|seed| {
+            let input = dir.file(&format!("input{seed}.bin"));
+            fs::write(&input, test_data(20_000, seed)).unwrap();
+            (
+                input,
+                dir.file(&format!("video{seed}.mp4")),
+                dir.file(&format!("output{seed}.bin")),
+            )
+        })
+        .collect();
+    thread::scope(|scope| {
+        for (input, video, output) in &runs {
+            let dir = &dir.0;
+            scope.spawn(move || round_trip(dir, input, video, output));
+        }
+    });
+    for (input, _, output) in &runs {
+        assert_eq!(fs::read(input).unwrap(), fs::read(output).unwrap());
+    }
+    // Both runs deleted their frame directories.
+    let leftover = fs::read_dir(&dir.0)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().is_dir())
+        .count();
+    assert_eq!(leftover, 0);
+}
+
+#[test]
+fn parallel_splits_reheader_their_own_frames() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("parallel_split");
+    let runs: Vec<_> = (0..2u8)
+        .map(|seed| {
+            let input = dir.file(&format!("input{seed}.bin"));
+            fs::write(&input, test_data(20_000, seed)).unwrap();
+            input
+        })
+        .collect();
+    let frame_dirs: Vec<String> = thread::scope(|scope| {
+        let splits: Vec<_> = runs
+            .iter()
+            .map(|input| scope.spawn(|| vortexkey(&dir.0, &["-m", "split", "-i", input])))
+            .collect();
+        splits
+            .into_iter()
+            .map(|split| {
+                let output = split.join().unwrap();
+                assert!(output.status.success());
+                kept_frames_dir(&String::from_utf8_lossy(&output.stderr)).to_owned()
+            })
+            .collect()
+    });
+    assert_ne!(frame_dirs[0], frame_dirs[1]);
+    for (seed, (input, frames)) in runs.iter().zip(&frame_dirs).enumerate() {
+        let video = dir.file(&format!("video{seed}.mp4"));
+        let output = dir.file(&format!("output{seed}.bin"));
+        vortexkey_ok(
+            &dir.0,
+            &[
+                "-m",
+                "reheader",
+                "-i",
+                input,
+                &video,
+                "--frames-dir",
+                frames,
+            ],
+        );
+        vortexkey_ok(&dir.0, &["-m", "vtd", "-i", &video, &output]);
+        assert_eq!(fs::read(input).unwrap(), fs::read(&output).unwrap());
+    }
+}
+
+#[test]
+fn frames_are_stored_in_the_workdir() {
+    let dir = TestDir::new("workdir");
+    let input = dir.file("input.bin");
+    let workdir = dir.file("work");
+    fs::write(&input, test_data(5000, 0)).unwrap();
+
+    let missing = vortexkey(
+        &dir.0,
+        &["-m", "split", "-i", &input, "--workdir", &workdir],
+    );
+    assert!(!missing.status.success());
+    assert!(
+        String::from_utf8_lossy(&missing.stderr).contains("does not exist"),
+        "{}",
+        String::from_utf8_lossy(&missing.stderr)
+    );
+
+    fs::create_dir(&workdir).unwrap();
+    let split = vortexkey(
+        &dir.0,
+        &["-m", "split", "-i", &input, "--workdir", &workdir],
+    );
+    assert!(split.status.success());
+    let stderr = String::from_utf8_lossy(&split.stderr);
+    assert_eq!(
+        Path::new(kept_frames_dir(&stderr)).parent(),
+        Some(Path::new(&workdir))
+    );
+}
+
+#[test]
+fn existing_output_is_only_overwritten_with_y() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("overwrite");
+    let input = dir.file("input.bin");
+    let video = dir.file("video.mp4");
+    fs::write(&input, test_data(5000, 0)).unwrap();
+    fs::write(&video, b"existing").unwrap();
+
+    let output = vortexkey(&dir.0, &["-i", &input, &video]);
+    assert!(!output.status.success());
+    assert_eq!(fs::read(&video).unwrap(), b"existing");
+    vortexkey_ok(&dir.0, &["-i", &input, &video, "-y"]);
+    assert_ne!(fs::read(&video).unwrap(), b"existing");
+
+    // Reheader combines the frames with a separate ffmpeg call.
+    let split = vortexkey(&dir.0, &["-m", "split", "-i", &input]);
+    let stderr = String::from_utf8_lossy(&split.stderr);
+    let frames = kept_frames_dir(&stderr);
+    let encoded = fs::read(&video).unwrap();
+    let reheader = [
+        "-m",
+        "reheader",
+        "-i",
+        &input,
+        &video,
+        "--frames-dir",
+        frames,
+    ];
+    assert!(!vortexkey(&dir.0, &reheader).status.success());
+    assert_eq!(fs::read(&video).unwrap(), encoded);
+    vortexkey_ok(&dir.0, &[&reheader[..], &["-y"]].concat());
+
+    let stats = dir.file("stats.csv");
+    fs::write(&stats, b"existing").unwrap();
+    let output = dir.file("output.bin");
+    let decode = ["-m", "vtd", "-i", &video, &output, "--stats-csv", &stats];
+    assert!(!vortexkey(&dir.0, &decode).status.success());
+    assert_eq!(fs::read(&stats).unwrap(), b"existing");
+    vortexkey_ok(&dir.0, &[&decode[..], &["-y"]].concat());
+    assert!(fs::read_to_string(&stats).unwrap().starts_with("frame,"));
+}
+
+#[test]
+fn empty_file_round_trips() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("empty");
+    let input = dir.file("empty.bin");
+    let output = dir.file("output.bin");
+    fs::write(&input, b"").unwrap();
+    round_trip(&dir.0, &input, &dir.file("video.mp4"), &output);
+    assert_eq!(fs::read(&output).unwrap(), b"");
+}
+
+#[test]
+fn video_decodes_with_other_settings_from_its_header() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("other_settings");
+    let input = dir.file("input.bin");
+    let video = dir.file("video.mp4");
+    let output = dir.file("output.bin");
+    fs::write(&input, test_data(5000, 0)).unwrap();
+    vortexkey_ok(
+        &dir.0,
+        &["-d", "20", "--bits", "1,1,1", "-i", &input, &video],
+    );
+    let decoded = vortexkey(&dir.0, &["-m", "vtd", "-i", &video, &output]);
+    let stderr = String::from_utf8_lossy(&decoded.stderr);
+    assert!(decoded.status.success(), "{stderr}");
+    assert!(stderr.contains("settings from its header"), "{stderr}");
+    assert_eq!(fs::read(&output).unwrap(), fs::read(&input).unwrap());
+}
+
+#[test]
+fn encoding_twice_gives_identical_videos() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("reproducible");
+    let input = dir.file("input.bin");
+    fs::write(&input, test_data(20_000, 0)).unwrap();
+    let [first, second] = ["first.mp4", "second.mp4"].map(|name| {
+        let video = dir.file(name);
+        vortexkey_ok(&dir.0, &["-i", &input, &video]);
+        Sha256::digest(fs::read(&video).unwrap())
+    });
+    assert_eq!(first, second);
+}
+
+#[test]
+fn bench_decodes_its_data_and_cleans_up() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("bench");
+    let workdir = dir.file("work");
+    fs::create_dir(&workdir).unwrap();
+    let output = vortexkey(
+        &dir.0,
+        &[
+            "-m",
+            "bench",
+            "--file-size",
+            "100000",
+            "--bench-decode",
+            "--json",
+            "--workdir",
+            &workdir,
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["bytes"], 100_000);
+    assert_eq!(summary["phases"].as_array().unwrap().len(), 4);
+    assert_eq!(summary["data_match"], true);
+    assert_eq!(fs::read_dir(&workdir).unwrap().count(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn hanging_ffprobe_is_stopped_by_the_timeout() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("hanging_ffprobe");
+    let ffprobe = dir.file("ffprobe");
+    fs::write(&ffprobe, "#!/bin/sh\nexec sleep 30\n").unwrap();
+    fs::set_permissions(&ffprobe, fs::Permissions::from_mode(0o755)).unwrap();
+    let video = dir.file("video.mp4");
+    fs::write(&video, b"not a video").unwrap();
+    let started = std::time::Instant::now();
+    let output = vortexkey(
+        &dir.0,
+        &[
+            "-m",
+            "vtd",
+            "-i",
+            &video,
+            &dir.file("output.bin"),
+            "--ffprobe-path",
+            &ffprobe,
+            "--ffmpeg-timeout",
+            "1",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--ffmpeg-timeout"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+}
+
+#[test]
+fn paths_with_spaces_and_unicode_round_trip() {
+    if !ffmpeg_available() {
+        return;
+    }
+    // Also the work directory, so the frames of split and reheader are in it too.
+    let dir = TestDir::new("spaces and ünïcödé 日本");
+    let input = dir.file("input file ä.bin");
+    let video = dir.file("vidéo with spaces.mp4");
+    let output = dir.file("output 出力.bin");
+    fs::write(&input, test_data(5000, 0)).unwrap();
+    round_trip(&dir.0, &input, &video, &output);
+    assert_eq!(fs::read(&output).unwrap(), fs::read(&input).unwrap());
+
+    let split = vortexkey(&dir.0, &["-m", "split", "-i", &input]);
+    let frames = kept_frames_dir(&String::from_utf8_lossy(&split.stderr)).to_owned();
+    let reheadered = dir.file("reheadered vidéo.mp4");
+    vortexkey_ok(
+        &dir.0,
+        &[
+            "-m",
+            "reheader",
+            "-i",
+            &input,
+            &reheadered,
+            "--frames-dir",
+            &frames,
+        ],
+    );
+    vortexkey_ok(&dir.0, &["-m", "vtd", "-i", &reheadered, &output, "-y"]);
+    assert_eq!(fs::read(&output).unwrap(), fs::read(&input).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_output_path_fails_clearly() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("non_utf8");
+    let input = dir.file("input.bin");
+    fs::write(&input, test_data(100, 0)).unwrap();
+    let video = dir.0.join(OsStr::from_bytes(b"video \xff.mp4"));
+    let output = Command::new(env!("CARGO_BIN_EXE_vortexkey"))
+        .arg("-i")
+        .arg(&input)
+        .arg(&video)
+        .arg("--workdir")
+        .arg(&dir.0)
+        .arg("-q")
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not valid UTF-8"));
+    assert!(!video.exists());
+}
+
+#[test]
+fn passphrase_is_read_from_the_environment_or_a_file() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = TestDir::new("passphrase");
+    let input = dir.file("input.bin");
+    let video = dir.file("video.mp4");
+    let output = dir.file("output.bin");
+    let passphrase_file = dir.file("passphrase.txt");
+    fs::write(&input, test_data(5000, 0)).unwrap();
+    fs::write(&passphrase_file, "correct horse\n").unwrap();
+    let env = [("VORTEXKEY_PASSPHRASE", "correct horse")];
+
+    let encode = vortexkey_with_env(&dir.0, &["-i", &input, &video, "--encrypt"], &env);
+    assert!(
+        encode.status.success(),
+        "{}",
+        String::from_utf8_lossy(&encode.stderr)
+    );
+    // Without a passphrase and no terminal to ask on, decoding fails instead of waiting.
+    let missing = vortexkey(&dir.0, &["-m", "vtd", "-i", &video, &output]);
+    assert!(!missing.status.success());
+    assert!(
+        String::from_utf8_lossy(&missing.stderr).contains("VORTEXKEY_PASSPHRASE"),
+        "{}",
+        String::from_utf8_lossy(&missing.stderr)
+    );
+    vortexkey_ok(
+        &dir.0,
+        &[
+            "-m",
+            "vtd",
+            "-i",
+            &video,
+            &output,
+            "-y",
+            "--passphrase-file",
+            &passphrase_file,
+        ],
+    );
+    assert_eq!(fs::read(&output).unwrap(), fs::read(&input).unwrap());
+
+    let wrong = [("VORTEXKEY_PASSPHRASE", "wrong horse")];
+    let decode = vortexkey_with_env(&dir.0, &["-m", "vtd", "-i", &video, &output, "-y"], &wrong);
+    assert!(!decode.status.success());
+}